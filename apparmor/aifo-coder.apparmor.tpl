@@ -2,6 +2,9 @@
 # Based on Docker's default (docker-default) profile with sane allowances
 # for typical developer workloads. It aims to preserve container isolation
 # without hindering normal agent operations (network, file IO, etc).
+#
+# The profile name and the container workspace, home and config staging paths
+# are placeholders; render with `aifo-coder apparmor render` or `make apparmor`.
 
 #include <tunables/global>
 
@@ -80,15 +83,18 @@ profile __PROFILE_NAME__ flags=(attach_disconnected,mediate_deleted) {
   /usr/local/lib/** mr,
 
   # Writable work areas (working tree, HOME, temp)
-  /workspace/** rwkmla,
-  /home/coder/** rwkml,
+  __WORKSPACE__/** rwkmla,
+  __HOME__/** rwkml,
   /tmp/** rwkml,
   /var/tmp/** rwkml,
   /var/log/host/** r,
 
-  # Explicit allowances for aifo-coder lock files (workspace-wide .git is already covered by /workspace/**)
-  /workspace/.aifo-coder.lock rwkml,
-  /home/coder/.aifo-coder.lock rwkml,
+  # Staged host config (mounted read-only; copied into __HOME__ by the entrypoint)
+  __STAGING__/** r,
+
+  # Explicit allowances for aifo-coder lock files (workspace-wide .git is already covered by __WORKSPACE__/**)
+  __WORKSPACE__/.aifo-coder.lock rwkml,
+  __HOME__/.aifo-coder.lock rwkml,
   /tmp/aifo-coder.lock rwkml,
   /run/user/[0-9]*/aifo-coder.lock rwkml,
 
@@ -1,9 +1,11 @@
 #![allow(clippy::module_name_repetitions)]
-//! AppArmor detection and profile selection helpers.
+//! AppArmor detection and profile selection helpers, plus rendering and installation of the
+//! shipped profile template (`aifo-coder apparmor render|install|status`).
 
 use std::env;
 use std::fs;
-use std::path::Path;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use crate::warn_print;
@@ -150,3 +152,165 @@ pub fn desired_apparmor_profile_quiet() -> Option<String> {
         None
     }
 }
+
+/// Profile template shipped in apparmor/aifo-coder.apparmor.tpl.
+pub const APPARMOR_TEMPLATE: &str = include_str!("../apparmor/aifo-coder.apparmor.tpl");
+
+/// Profile name preferred by desired_apparmor_profile() and used by `apparmor install`.
+pub const APPARMOR_PROFILE_NAME: &str = "aifo-coder";
+
+/// Directory `apparmor install` writes to unless AIFO_CODER_APPARMOR_DIR overrides it.
+pub const APPARMOR_INSTALL_DIR_DEFAULT: &str = "/etc/apparmor.d";
+
+/// Container paths substituted into the template; they mirror the launcher's mounts.
+const CONTAINER_WORKSPACE: &str = "/workspace";
+const CONTAINER_HOME: &str = "/home/coder";
+const CONTAINER_STAGING: &str = "/home/coder/.aifo-config-host";
+
+/// Validate an AppArmor profile name; it doubles as the installed file name.
+pub fn parse_apparmor_profile_name(s: &str) -> Result<String, String> {
+    let t = s.trim();
+    if !t.is_empty()
+        && !t.starts_with('.')
+        && t.chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        Ok(t.to_string())
+    } else {
+        Err(format!(
+            "invalid AppArmor profile name '{s}': use letters, digits, '-', '_' or '.'"
+        ))
+    }
+}
+
+/// Render the shipped template for profile `name` with the workspace, home and config staging
+/// paths the launcher mounts into agent containers.
+pub fn render_apparmor_profile(name: &str) -> String {
+    let body = APPARMOR_TEMPLATE
+        .replace("__PROFILE_NAME__", name)
+        .replace("__WORKSPACE__", CONTAINER_WORKSPACE)
+        .replace("__STAGING__", CONTAINER_STAGING)
+        .replace("__HOME__", CONTAINER_HOME);
+    let header = concat!(
+        "# Rendered by `aifo-coder apparmor render` from apparmor/aifo-coder.apparmor.tpl.\n",
+        "# Do not edit; re-run `aifo-coder apparmor install` after upgrading aifo-coder.\n",
+    );
+    format!("{header}{body}")
+}
+
+/// Directory holding installed profiles (AIFO_CODER_APPARMOR_DIR or /etc/apparmor.d).
+pub fn apparmor_install_dir() -> PathBuf {
+    env::var("AIFO_CODER_APPARMOR_DIR")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(APPARMOR_INSTALL_DIR_DEFAULT))
+}
+
+/// Locate apparmor_parser on PATH or in the sbin directories non-root PATHs often omit.
+pub fn apparmor_parser_path() -> Option<PathBuf> {
+    which::which("apparmor_parser").ok().or_else(|| {
+        ["/usr/sbin/apparmor_parser", "/sbin/apparmor_parser"]
+            .iter()
+            .map(PathBuf::from)
+            .find(|p| p.is_file())
+    })
+}
+
+/// Check a profile file with `apparmor_parser --skip-kernel-load` (nothing is loaded).
+/// Returns None when apparmor_parser is not installed.
+pub fn validate_apparmor_profile(path: &Path) -> Option<io::Result<()>> {
+    let parser = apparmor_parser_path()?;
+    let out = match Command::new(parser)
+        .args(["--skip-kernel-load", "--skip-cache", "--quiet"])
+        .arg(path)
+        .output()
+    {
+        Ok(o) => o,
+        Err(e) => return Some(Err(e)),
+    };
+    if out.status.success() {
+        Some(Ok(()))
+    } else {
+        Some(Err(io::Error::other(format!(
+            "apparmor_parser rejected {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&out.stderr).trim()
+        ))))
+    }
+}
+
+/// Load (or replace) a profile into the kernel with `apparmor_parser -r -W`.
+pub fn load_apparmor_profile(path: &Path) -> io::Result<()> {
+    let parser = apparmor_parser_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "apparmor_parser not found"))?;
+    let out = Command::new(parser)
+        .arg("-r")
+        .arg("-W")
+        .arg(path)
+        .output()?;
+    if out.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "apparmor_parser -r -W {} failed: {}",
+            path.display(),
+            String::from_utf8_lossy(&out.stderr).trim()
+        )))
+    }
+}
+
+/// Installed profile file compared with what `apparmor render` produces today.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppArmorInstallState {
+    Missing,
+    Current,
+    Outdated,
+}
+
+impl AppArmorInstallState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AppArmorInstallState::Missing => "not installed",
+            AppArmorInstallState::Current => "up to date",
+            AppArmorInstallState::Outdated => "outdated",
+        }
+    }
+}
+
+/// Host-side view of the shipped profile, as reported by `apparmor status` and doctor.
+#[derive(Debug, Clone)]
+pub struct AppArmorStatus {
+    pub name: String,
+    pub kernel_enabled: bool,
+    /// Whether the kernel lists the profile; None when that cannot be read (non-Linux, no access).
+    pub loaded: Option<bool>,
+    pub path: PathBuf,
+    pub installed: AppArmorInstallState,
+    pub parser: Option<PathBuf>,
+}
+
+/// Inspect kernel support, the loaded profile list and the installed file for profile `name`.
+pub fn apparmor_status(name: &str) -> AppArmorStatus {
+    let path = apparmor_install_dir().join(name);
+    let installed = match fs::read_to_string(&path) {
+        Ok(content) if content == render_apparmor_profile(name) => AppArmorInstallState::Current,
+        Ok(_) => AppArmorInstallState::Outdated,
+        Err(_) => AppArmorInstallState::Missing,
+    };
+    let loaded = if cfg!(target_os = "linux") {
+        fs::read_to_string("/sys/kernel/security/apparmor/profiles")
+            .ok()
+            .map(|_| apparmor_profile_available(name))
+    } else {
+        None
+    };
+    AppArmorStatus {
+        name: name.to_string(),
+        kernel_enabled: cfg!(target_os = "linux") && kernel_apparmor_enabled(),
+        loaded,
+        path,
+        installed,
+        parser: apparmor_parser_path(),
+    }
+}
@@ -1,4 +1,5 @@
-pub(crate) fn print_startup_banner() {
+/// Print the startup banner; AGENT (when known) adds its effective resource limits.
+pub(crate) fn print_startup_banner(agent: Option<&str>) {
     let version = env!("CARGO_PKG_VERSION");
     eprintln!();
     eprintln!("──────────────────────────────────────────────────────────────────────────────────────────────");
@@ -93,12 +94,33 @@ pub(crate) fn print_startup_banner() {
         "AppArmor=off".to_string()
     };
     eprintln!(
-        "    - Security: {}, Seccomp={}, cgroupns={}, rootless={}",
+        "    - Security: {}, Seccomp={}, cgroupns={}, rootless={}, hardened={}, egress={}, llm-gateway={}",
         aa,
         seccomp,
         cgroupns,
-        if rootless { "yes" } else { "no" }
+        if rootless { "yes" } else { "no" },
+        if aifo_coder::hardened_mode_enabled() {
+            "yes"
+        } else {
+            "no"
+        },
+        if aifo_coder::egress_allowlist_enabled() {
+            "allowlist"
+        } else {
+            "open"
+        },
+        if aifo_coder::llm_gateway_enabled() {
+            "yes"
+        } else {
+            "no"
+        }
     );
+    if let Some(agent) = agent {
+        eprintln!(
+            "    - Limits: {}",
+            aifo_coder::agent_resource_limits(agent, None).summary()
+        );
+    }
     eprintln!();
 
     // Safety highlights (concise, current capabilities)
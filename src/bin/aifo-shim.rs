@@ -14,6 +14,8 @@ use std::process::{self, Command, Stdio};
 use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use aifo_coder::SignedHeaders;
+
 const PROTO_VERSION: &str = "2";
 
 // Notification tools handled via /notify (extendable)
@@ -274,7 +276,55 @@ fn disconnect_wait(verbose: bool) {
     }
 }
 
+/// Forward a signal the shim received to the exec: repeated ^C escalates INT, TERM, KILL.
+///
+/// Returns None when no signal is pending, else the shim's exit code after the disconnect wait:
+/// 0, or 128+signal with AIFO_SHIM_EXIT_ZERO_ON_SIGINT=0.
+#[cfg(unix)]
+fn forward_pending_signal(url: &str, token: &str, exec_id: &str, verbose: bool) -> Option<i32> {
+    let (sig, code) = match SIGINT_COUNT.load(Ordering::SeqCst) {
+        0 if GOT_TERM.load(Ordering::SeqCst) => ("TERM", 143),
+        0 if GOT_HUP.load(Ordering::SeqCst) => ("HUP", 129),
+        0 => return None,
+        1 => ("INT", 130),
+        2 => ("TERM", 143),
+        _ => ("KILL", 137),
+    };
+    post_signal(url, token, exec_id, sig, verbose);
+    #[cfg(target_os = "linux")]
+    {
+        if sig != "KILL" {
+            kill_parent_shell_if_interactive();
+        }
+    }
+    disconnect_wait(verbose);
+    let zero_on_signal = env::var("AIFO_SHIM_EXIT_ZERO_ON_SIGINT")
+        .ok()
+        .as_deref()
+        .unwrap_or("1")
+        == "1";
+    Some(if zero_on_signal { 0 } else { code })
+}
+
+/// Exit code when the proxy went away without reporting one: 0 unless
+/// AIFO_SHIM_EXIT_ZERO_ON_DISCONNECT=0.
+fn disconnect_exit_code() -> i32 {
+    let zero_on_disconnect = env::var("AIFO_SHIM_EXIT_ZERO_ON_DISCONNECT")
+        .ok()
+        .map(|v| v.trim() != "0")
+        .unwrap_or(true);
+    if zero_on_disconnect {
+        0
+    } else {
+        1
+    }
+}
+
 fn post_signal(url: &str, token: &str, exec_id: &str, signal_name: &str, verbose: bool) {
+    let body = encode_form_parts(&[
+        ("exec_id".to_string(), exec_id.to_string()),
+        ("signal".to_string(), signal_name.to_string()),
+    ]);
     let mut args: Vec<String> = vec![
         "-sS".into(),
         "-X".into(),
@@ -285,11 +335,10 @@ fn post_signal(url: &str, token: &str, exec_id: &str, signal_name: &str, verbose
         "X-Aifo-Proto: 2".into(),
         "-H".into(),
         "Content-Type: application/x-www-form-urlencoded".into(),
-        "--data-urlencode".into(),
-        format!("exec_id={}", exec_id),
-        "--data-urlencode".into(),
-        format!("signal={}", signal_name),
     ];
+    push_signature_args(&mut args, "/signal", SignedHeaders::default(), &body);
+    args.push("--data-binary".into());
+    args.push(body);
     let mut final_url = url.to_string();
     if url.starts_with("unix://") {
         let sock = url.trim_start_matches("unix://").to_string();
@@ -302,6 +351,7 @@ fn post_signal(url: &str, token: &str, exec_id: &str, signal_name: &str, verbose
         }
         final_url.push_str("/signal");
     }
+    push_tls_args(&mut args, url);
     args.push(final_url);
     let mut cmd = Command::new("curl");
     cmd.args(&args);
@@ -315,6 +365,154 @@ fn post_signal(url: &str, token: &str, exec_id: &str, signal_name: &str, verbose
     let _ = cmd.status();
 }
 
+/// Session signing key from the launcher; requests go unsigned without it.
+fn signing_key() -> Option<String> {
+    env::var(aifo_coder::TOOLEXEC_SIGNING_KEY_ENV)
+        .ok()
+        .filter(|k| !k.trim().is_empty())
+}
+
+/// X-Aifo-Timestamp/Nonce/Signature header lines for a POST of BODY to PATH carrying SIGNED.
+fn signature_header_lines(path: &str, signed: SignedHeaders, body: &str) -> String {
+    let Some(key) = signing_key() else {
+        return String::new();
+    };
+    aifo_coder::toolexec_signature_headers(&key, path, signed, body.as_bytes())
+        .iter()
+        .map(|(k, v)| format!("{k}: {v}\r\n"))
+        .collect()
+}
+
+/// The signature headers as curl arguments.
+fn push_signature_args(args: &mut Vec<String>, path: &str, signed: SignedHeaders, body: &str) {
+    let Some(key) = signing_key() else {
+        return;
+    };
+    for (k, v) in aifo_coder::toolexec_signature_headers(&key, path, signed, body.as_bytes()) {
+        args.push("-H".to_string());
+        args.push(format!("{k}: {v}"));
+    }
+}
+
+/// Rotated tokens, shared by the shims of one agent container.
+fn token_cache_path() -> PathBuf {
+    let home = env::var("HOME").unwrap_or_else(|_| "/home/coder".to_string());
+    PathBuf::from(home).join(".aifo-exec").join("token")
+}
+
+/// Bearer token to use: AIFO_TOOLEEXEC_TOKEN, or a later rotation of it from the cache (the
+/// proxy revokes a token shortly after rotating it); scoped tokens are rotated via POST /token
+/// once two thirds of their lifetime have passed.
+fn resolve_token(url: &str, env_token: String, verbose: bool) -> String {
+    let Some(claims) = aifo_coder::token_claims(&env_token) else {
+        return env_token; // legacy session token: never expires
+    };
+    if claims.exp == 0 {
+        return env_token;
+    }
+    let same_grant =
+        |c: &aifo_coder::TokenClaims| c.scopes == claims.scopes && c.kinds == claims.kinds;
+    let (token, claims) = fs::read_to_string(token_cache_path())
+        .ok()
+        .map(|s| s.trim().to_string())
+        .and_then(|t| aifo_coder::token_claims(&t).map(|c| (t, c)))
+        .filter(|(_, c)| same_grant(c) && c.iat >= claims.iat && c.id != claims.id)
+        .unwrap_or((env_token, claims));
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    if !claims.needs_rotation(now) {
+        return token;
+    }
+    let Some(fresh) = rotate_token(url, &token) else {
+        if verbose {
+            eprintln!("aifo-shim: token rotation failed; using the current token");
+        }
+        return token;
+    };
+    // Write-then-rename keeps concurrent shims from reading a partial token.
+    let path = token_cache_path();
+    let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    let mut opts = fs::OpenOptions::new();
+    opts.create(true).write(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    if opts
+        .open(&tmp)
+        .and_then(|mut f| f.write_all(fresh.as_bytes()))
+        .is_ok()
+    {
+        let _ = fs::rename(&tmp, &path);
+    }
+    if verbose {
+        eprintln!("aifo-shim: rotated proxy token");
+    }
+    fresh
+}
+
+/// POST /token with TOKEN; returns the fresh token on 200.
+fn rotate_token(url: &str, token: &str) -> Option<String> {
+    fn exchange<S: Read + Write>(mut s: S, req: &str) -> Option<Vec<u8>> {
+        s.write_all(req.as_bytes()).ok()?;
+        let mut resp = Vec::new();
+        s.read_to_end(&mut resp).ok()?;
+        Some(resp)
+    }
+    let timeout = Some(std::time::Duration::from_secs(5));
+    let tcp = if url.starts_with("unix://") {
+        None
+    } else {
+        Some(TcpConn::connect(url)?)
+    };
+    let host = tcp.as_ref().map_or("localhost", |(_, h, _)| h.as_str());
+    let req = format!(
+        concat!(
+            "POST /token HTTP/1.1\r\n",
+            "Host: {host}\r\n",
+            "Authorization: Bearer {tok}\r\n",
+            "X-Aifo-Proto: 2\r\n",
+            "{sig}",
+            "Content-Type: application/x-www-form-urlencoded\r\n",
+            "Content-Length: 0\r\n",
+            "Connection: close\r\n",
+            "\r\n"
+        ),
+        host = host,
+        tok = token,
+        sig = signature_header_lines("/token", SignedHeaders::default(), "")
+    );
+    let resp = if let Some(sock) = url.strip_prefix("unix://") {
+        #[cfg(target_os = "linux")]
+        {
+            let s = UnixStream::connect(sock).ok()?;
+            let _ = s.set_read_timeout(timeout);
+            exchange(s, &req)?
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = sock;
+            return None;
+        }
+    } else {
+        let (s, _, _) = tcp?;
+        s.set_timeouts(timeout);
+        exchange(s, &req)?
+    };
+    let idx = aifo_coder::find_header_end(&resp)?;
+    if !resp.starts_with(b"HTTP/1.1 200") {
+        return None;
+    }
+    let v: serde_json::Value = serde_json::from_slice(&resp[idx..]).ok()?;
+    v.get("token")?.as_str().map(String::from)
+}
+
 // Best-effort native POST /signal over TCP or Linux UDS; silent on errors.
 fn send_signal_native(url: &str, token: &str, exec_id: &str, signal_name: &str) {
     let body = format!("exec_id={}&signal={}", exec_id, signal_name);
@@ -335,12 +533,14 @@ fn send_signal_native(url: &str, token: &str, exec_id: &str, signal_name: &str)
                     "Host: localhost\r\n",
                     "Authorization: Bearer {tok}\r\n",
                     "X-Aifo-Proto: 2\r\n",
+                    "{sig}",
                     "Content-Type: application/x-www-form-urlencoded\r\n",
                     "Content-Length: {len}\r\n",
                     "Connection: close\r\n",
                     "\r\n"
                 ),
                 tok = token,
+                sig = signature_header_lines("/signal", SignedHeaders::default(), &body),
                 len = body.len()
             );
             let _ = stream.write_all(req.as_bytes());
@@ -351,29 +551,18 @@ fn send_signal_native(url: &str, token: &str, exec_id: &str, signal_name: &str)
         return;
     }
 
-    // Default TCP http://host:port/…
-    let rest = url.trim_start_matches("http://").to_string();
-    let path_idx = rest.find('/').unwrap_or(rest.len());
-    let (host_port, _path) = rest.split_at(path_idx);
-    let (host, port) = if let Some((h, p)) = host_port.split_once(':') {
-        let pn = p.parse::<u16>().unwrap_or(80);
-        (h.to_string(), pn)
-    } else {
-        (host_port.to_string(), 80u16)
-    };
-    let addr = format!("{}:{}", host, port);
-    let mut stream = match TcpStream::connect(&addr) {
-        Ok(s) => s,
-        Err(_) => return,
+    // Default TCP http(s)://host:port/…
+    let Some((mut stream, host, _path)) = TcpConn::connect(url) else {
+        return;
     };
-    let _ = stream.set_read_timeout(Some(std::time::Duration::from_millis(300)));
-    let _ = stream.set_write_timeout(Some(std::time::Duration::from_millis(300)));
+    stream.set_timeouts(Some(std::time::Duration::from_millis(300)));
     let req = format!(
         concat!(
             "POST /signal HTTP/1.1\r\n",
             "Host: {host}\r\n",
             "Authorization: Bearer {tok}\r\n",
             "X-Aifo-Proto: 2\r\n",
+            "{sig}",
             "Content-Type: application/x-www-form-urlencoded\r\n",
             "Content-Length: {len}\r\n",
             "Connection: close\r\n",
@@ -381,12 +570,13 @@ fn send_signal_native(url: &str, token: &str, exec_id: &str, signal_name: &str)
         ),
         host = host,
         tok = token,
+        sig = signature_header_lines("/signal", SignedHeaders::default(), &body),
         len = body.len()
     );
     let _ = stream.write_all(req.as_bytes());
     let _ = stream.write_all(body.as_bytes());
     let _ = stream.flush();
-    let _ = stream.shutdown(std::net::Shutdown::Both);
+    stream.shutdown();
 }
 
 // Best-effort proactive escalation during disconnect wait: INT -> TERM -> KILL.
@@ -408,36 +598,24 @@ fn proactive_disconnect_escalation(url: &str, token: &str, exec_id: &str) {
 
 // Native HTTP/1.1 client (Phase 3): TCP + Linux UDS, chunked request, trailer parsing.
 // Returns Some(exit_code) when native path is taken; None to fall back to curl.
-fn try_run_native(
-    url: &str,
-    token: &str,
-    exec_id: &str,
-    form_parts: &[(String, String)],
-    verbose: bool,
-) -> Option<i32> {
-    // Default enabled; set AIFO_SHIM_NATIVE_HTTP=0 to force curl fallback
-    if std::env::var("AIFO_SHIM_NATIVE_HTTP").ok().as_deref() == Some("0") {
-        return None;
-    }
-
-    // Percent-encode a single component for application/x-www-form-urlencoded
-    fn urlencode_component(s: &str) -> String {
-        let mut out = String::with_capacity(s.len());
-        for b in s.bytes() {
-            match b {
-                b' ' => out.push('+'),
-                b'-' | b'_' | b'.' | b'~' => out.push(b as char),
-                b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z' => out.push(b as char),
-                _ => {
-                    out.push('%');
-                    out.push_str(&format!("{:02X}", b));
-                }
+// Percent-encode a single component for application/x-www-form-urlencoded
+fn urlencode_component(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b' ' => out.push('+'),
+            b'-' | b'_' | b'.' | b'~' => out.push(b as char),
+            b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z' => out.push(b as char),
+            _ => {
+                out.push('%');
+                out.push_str(&format!("{:02X}", b));
             }
         }
-        out
     }
+    out
+}
 
-    // Build the urlencoded body from provided form parts (tool, cwd, arg=...)
+fn encode_form_parts(form_parts: &[(String, String)]) -> String {
     let mut body = String::new();
     for (i, (k, v)) in form_parts.iter().enumerate() {
         if i > 0 {
@@ -447,10 +625,143 @@ fn try_run_native(
         body.push('=');
         body.push_str(&urlencode_component(v));
     }
+    body
+}
+
+/// TCP connection to the proxy: plain HTTP, or mutual TLS for https:// URLs using the session
+/// certificates in AIFO_TOOLEEXEC_TLS_DIR.
+enum TcpConn {
+    Plain(TcpStream),
+    Tls(Box<aifo_coder::ToolexecTlsStream>),
+}
+
+impl TcpConn {
+    /// Connect to URL; returns the connection, Host header and request path (default /exec).
+    fn connect(url: &str) -> Option<(TcpConn, String, String)> {
+        let (tls, rest) = match url.strip_prefix("https://") {
+            Some(rest) => (true, rest),
+            None => (false, url.trim_start_matches("http://")),
+        };
+        let path_idx = rest.find('/').unwrap_or(rest.len());
+        let (host_port, path) = rest.split_at(path_idx);
+        let path = if path.is_empty() { "/exec" } else { path };
+        let (host, port) = match host_port.split_once(':') {
+            Some((h, p)) => (h, p.parse::<u16>().unwrap_or(if tls { 443 } else { 80 })),
+            None => (host_port, if tls { 443 } else { 80 }),
+        };
+        let conn = if tls {
+            let dir = env::var("AIFO_TOOLEEXEC_TLS_DIR")
+                .ok()
+                .filter(|s| !s.trim().is_empty())?;
+            let s = aifo_coder::toolexec_tls_connect(host, port, Path::new(&dir)).ok()?;
+            TcpConn::Tls(Box::new(s))
+        } else {
+            TcpConn::Plain(TcpStream::connect((host, port)).ok()?)
+        };
+        Some((conn, host.to_string(), path.to_string()))
+    }
+
+    fn set_timeouts(&self, d: Option<std::time::Duration>) {
+        let _ = match self {
+            TcpConn::Plain(s) => s.set_read_timeout(d).and(s.set_write_timeout(d)),
+            TcpConn::Tls(s) => s.set_read_timeout(d).and(s.set_write_timeout(d)),
+        };
+    }
+
+    #[cfg(target_os = "linux")]
+    fn set_read_timeout(&self, d: Option<std::time::Duration>) {
+        let _ = match self {
+            TcpConn::Plain(s) => s.set_read_timeout(d),
+            TcpConn::Tls(s) => s.set_read_timeout(d),
+        };
+    }
+
+    #[cfg(target_os = "linux")]
+    /// Second handle to the same connection; a TLS session cannot be shared between handles.
+    fn try_clone(&self) -> std::io::Result<TcpConn> {
+        match self {
+            TcpConn::Plain(s) => s.try_clone().map(TcpConn::Plain),
+            TcpConn::Tls(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "TLS connections cannot be cloned",
+            )),
+        }
+    }
+
+    fn shutdown(&mut self) {
+        match self {
+            TcpConn::Plain(s) => {
+                let _ = s.shutdown(std::net::Shutdown::Both);
+            }
+            TcpConn::Tls(s) => s.shutdown(),
+        }
+    }
+}
+
+impl Read for TcpConn {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            TcpConn::Plain(s) => s.read(buf),
+            TcpConn::Tls(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for TcpConn {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            TcpConn::Plain(s) => s.write(buf),
+            TcpConn::Tls(s) => s.write(buf),
+        }
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            TcpConn::Plain(s) => s.flush(),
+            TcpConn::Tls(s) => s.flush(),
+        }
+    }
+}
+
+/// curl options for an https:// proxy URL: trust the session CA and present the client
+/// certificate.
+fn push_tls_args(args: &mut Vec<String>, url: &str) {
+    if !url.starts_with("https://") {
+        return;
+    }
+    if let Some(dir) = env::var("AIFO_TOOLEEXEC_TLS_DIR")
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+    {
+        let dir = Path::new(&dir);
+        for (flag, file) in [
+            ("--cacert", aifo_coder::TLS_CA_FILE),
+            ("--cert", aifo_coder::TLS_CLIENT_CERT_FILE),
+            ("--key", aifo_coder::TLS_CLIENT_KEY_FILE),
+        ] {
+            args.push(flag.to_string());
+            args.push(dir.join(file).to_string_lossy().into_owned());
+        }
+    }
+}
+
+fn try_run_native(
+    url: &str,
+    token: &str,
+    exec_id: &str,
+    form_parts: &[(String, String)],
+    verbose: bool,
+) -> Option<i32> {
+    // Default enabled; set AIFO_SHIM_NATIVE_HTTP=0 to force curl fallback
+    if std::env::var("AIFO_SHIM_NATIVE_HTTP").ok().as_deref() == Some("0") {
+        return None;
+    }
+
+    // Build the urlencoded body from provided form parts (tool, cwd, arg=...)
+    let body = encode_form_parts(form_parts);
 
     // Connection abstraction over TCP/UDS
     enum Conn {
-        Tcp(TcpStream, String, String), // stream, host header, path
+        Tcp(TcpConn, String, String), // stream, host header, path
         #[cfg(target_os = "linux")]
         Uds(UnixStream, String), // stream, path (Host: localhost)
     }
@@ -475,29 +786,13 @@ fn try_run_native(
             return None;
         }
     } else {
-        // Expect http://host:port/path
-        let rest = url.trim_start_matches("http://").to_string();
-        let path_idx = rest.find('/').unwrap_or(rest.len());
-        let (host_port, path) = rest.split_at(path_idx);
-        let path = if path.is_empty() {
-            "/exec".to_string()
-        } else {
-            path.to_string()
-        };
-        let (host, port) = if let Some((h, p)) = host_port.split_once(':') {
-            let pn = p.parse::<u16>().unwrap_or(80);
-            (h.to_string(), pn)
-        } else {
-            (host_port.to_string(), 80u16)
-        };
-        let addr = format!("{}:{}", host, port);
-        match TcpStream::connect(&addr) {
-            Ok(stream) => {
-                let _ = stream.set_read_timeout(Some(std::time::Duration::from_millis(1000)));
-                let _ = stream.set_write_timeout(Some(std::time::Duration::from_millis(1000)));
+        // Expect http(s)://host:port/path
+        match TcpConn::connect(url) {
+            Some((stream, host, path)) => {
+                stream.set_timeouts(Some(std::time::Duration::from_millis(1000)));
                 Conn::Tcp(stream, host, path)
             }
-            Err(_) => return None, // fall back to curl on connect error
+            None => return None, // fall back to curl on connect error
         }
     };
 
@@ -518,12 +813,21 @@ fn try_run_native(
             "Content-Type: application/x-www-form-urlencoded\r\n",
             "Transfer-Encoding: chunked\r\n",
             "X-Aifo-Exec-Id: {eid}\r\n",
+            "{sig}",
             "Connection: close\r\n",
             "\r\n"
         ),
         host = host_header,
         tok = token,
-        eid = exec_id
+        eid = exec_id,
+        sig = signature_header_lines(
+            &path,
+            SignedHeaders {
+                exec_id,
+                ..SignedHeaders::default()
+            },
+            &body
+        )
     );
 
     // Write request line + headers (best-effort; tolerate early write errors)
@@ -586,79 +890,9 @@ fn try_run_native(
                     || e.kind() == std::io::ErrorKind::TimedOut
                     || e.kind() == std::io::ErrorKind::Interrupted =>
             {
-                // Check signals
                 #[cfg(unix)]
                 {
-                    let cnt = SIGINT_COUNT.load(Ordering::SeqCst);
-                    if cnt >= 1 {
-                        let sig = if cnt == 1 {
-                            "INT"
-                        } else if cnt == 2 {
-                            "TERM"
-                        } else {
-                            "KILL"
-                        };
-                        post_signal(url, token, exec_id, sig, verbose);
-                        #[cfg(target_os = "linux")]
-                        {
-                            if sig != "KILL" {
-                                kill_parent_shell_if_interactive();
-                            }
-                        }
-                        let code = if std::env::var("AIFO_SHIM_EXIT_ZERO_ON_SIGINT")
-                            .ok()
-                            .as_deref()
-                            .unwrap_or("1")
-                            == "1"
-                        {
-                            0
-                        } else {
-                            match sig {
-                                "INT" => 130,
-                                "TERM" => 143,
-                                _ => 137,
-                            }
-                        };
-                        disconnect_wait(verbose);
-                        eprint!("\n\r");
-                        return Some(code);
-                    }
-                    if GOT_TERM.load(Ordering::SeqCst) {
-                        post_signal(url, token, exec_id, "TERM", verbose);
-                        #[cfg(target_os = "linux")]
-                        {
-                            kill_parent_shell_if_interactive();
-                        }
-                        let code = if std::env::var("AIFO_SHIM_EXIT_ZERO_ON_SIGINT")
-                            .ok()
-                            .as_deref()
-                            .unwrap_or("1")
-                            == "1"
-                        {
-                            0
-                        } else {
-                            143
-                        };
-                        eprint!("\n\r");
-                        return Some(code);
-                    }
-                    if GOT_HUP.load(Ordering::SeqCst) {
-                        post_signal(url, token, exec_id, "HUP", verbose);
-                        #[cfg(target_os = "linux")]
-                        {
-                            kill_parent_shell_if_interactive();
-                        }
-                        let code = if std::env::var("AIFO_SHIM_EXIT_ZERO_ON_SIGINT")
-                            .ok()
-                            .as_deref()
-                            .unwrap_or("1")
-                            == "1"
-                        {
-                            0
-                        } else {
-                            129
-                        };
-                        disconnect_wait(verbose);
+                    if let Some(code) = forward_pending_signal(url, token, exec_id, verbose) {
                         eprint!("\n\r");
                         return Some(code);
                     }
@@ -715,16 +949,8 @@ fn try_run_native(
                 .unwrap_or_else(|| "/tmp".to_string());
             let tmp_dir = format!("{}/aifo-shim.{}", tmp_base, std::process::id());
             let _ = fs::remove_dir_all(&tmp_dir);
-            // Honor override for non-zero on disconnect (default zero)
-            let zero_on_disconnect = std::env::var("AIFO_SHIM_EXIT_ZERO_ON_DISCONNECT")
-                .ok()
-                .map(|v| v.trim().to_string())
-                .filter(|s| !s.is_empty())
-                .map(|s| s != "0")
-                .unwrap_or(true);
-            let code = if zero_on_disconnect { 0 } else { 1 };
             eprint!("\n\r");
-            return Some(code);
+            return Some(disconnect_exit_code());
         }
     };
 
@@ -734,11 +960,14 @@ fn try_run_native(
     let header_text = String::from_utf8_lossy(header_bytes);
     let mut is_chunked = false;
     let mut header_exit_code: Option<i32> = None;
+    let mut policy_denied = false;
     for line in header_text.lines() {
         let l = line.trim();
         let ll = l.to_ascii_lowercase();
         if ll.starts_with("transfer-encoding:") && ll.contains("chunked") {
             is_chunked = true;
+        } else if ll.starts_with("x-aifo-policy:") {
+            policy_denied = true;
         } else if let Some(v) = l.strip_prefix("X-Exit-Code:") {
             if let Ok(n) = v.trim().parse::<i32>() {
                 header_exit_code = Some(n);
@@ -802,77 +1031,7 @@ fn try_run_native(
                 // Signal checks during blocking reads
                 #[cfg(unix)]
                 {
-                    let cnt = SIGINT_COUNT.load(Ordering::SeqCst);
-                    if cnt >= 1 {
-                        let sig = if cnt == 1 {
-                            "INT"
-                        } else if cnt == 2 {
-                            "TERM"
-                        } else {
-                            "KILL"
-                        };
-                        post_signal(url, token, exec_id, sig, verbose);
-                        #[cfg(target_os = "linux")]
-                        {
-                            if sig != "KILL" {
-                                kill_parent_shell_if_interactive();
-                            }
-                        }
-                        let code = if std::env::var("AIFO_SHIM_EXIT_ZERO_ON_SIGINT")
-                            .ok()
-                            .as_deref()
-                            .unwrap_or("1")
-                            == "1"
-                        {
-                            0
-                        } else {
-                            match sig {
-                                "INT" => 130,
-                                "TERM" => 143,
-                                _ => 137,
-                            }
-                        };
-                        disconnect_wait(verbose);
-                        signal_exit = Some(code);
-                        return Some("__signal__".to_string());
-                    }
-                    if GOT_TERM.load(Ordering::SeqCst) {
-                        post_signal(url, token, exec_id, "TERM", verbose);
-                        #[cfg(target_os = "linux")]
-                        {
-                            kill_parent_shell_if_interactive();
-                        }
-                        let code = if std::env::var("AIFO_SHIM_EXIT_ZERO_ON_SIGINT")
-                            .ok()
-                            .as_deref()
-                            .unwrap_or("1")
-                            == "1"
-                        {
-                            0
-                        } else {
-                            143
-                        };
-                        disconnect_wait(verbose);
-                        signal_exit = Some(code);
-                        return Some("__signal__".to_string());
-                    }
-                    if GOT_HUP.load(Ordering::SeqCst) {
-                        post_signal(url, token, exec_id, "HUP", verbose);
-                        #[cfg(target_os = "linux")]
-                        {
-                            kill_parent_shell_if_interactive();
-                        }
-                        let code = if std::env::var("AIFO_SHIM_EXIT_ZERO_ON_SIGINT")
-                            .ok()
-                            .as_deref()
-                            .unwrap_or("1")
-                            == "1"
-                        {
-                            0
-                        } else {
-                            129
-                        };
-                        disconnect_wait(verbose);
+                    if let Some(code) = forward_pending_signal(url, token, exec_id, verbose) {
                         signal_exit = Some(code);
                         return Some("__signal__".to_string());
                     }
@@ -991,16 +1150,22 @@ fn try_run_native(
             return Some(code);
         }
     } else {
-        // Not chunked: write remaining body bytes and drain to EOF
+        // Not chunked: write remaining body bytes and drain to EOF.
+        // Policy denials go to stderr so they never mix into piped tool output.
+        let mut out: Box<dyn Write> = if policy_denied {
+            Box::new(std::io::stderr())
+        } else {
+            Box::new(stdout)
+        };
         if !body_after.is_empty() {
-            let _ = stdout.write_all(body_after);
+            let _ = out.write_all(body_after);
         }
         let mut tmpb = [0u8; 8192];
         loop {
             match reader_box.read(&mut tmpb) {
                 Ok(0) => break,
                 Ok(n) => {
-                    let _ = stdout.write_all(&tmpb[..n]);
+                    let _ = out.write_all(&tmpb[..n]);
                 }
                 Err(ref e)
                     if e.kind() == std::io::ErrorKind::WouldBlock
@@ -1030,12 +1195,7 @@ fn try_run_native(
         let home_rm = std::env::var("HOME").unwrap_or_else(|_| "/home/coder".to_string());
         let d_rm = PathBuf::from(&home_rm).join(".aifo-exec").join(exec_id);
         let _ = fs::remove_dir_all(&d_rm);
-        // Honor override for non-zero on disconnect (default: zero)
-        let zero_on_disconnect = std::env::var("AIFO_SHIM_EXIT_ZERO_ON_DISCONNECT")
-            .ok()
-            .map(|v| v.trim() != "0")
-            .unwrap_or(true);
-        exit_code = if zero_on_disconnect { 0 } else { 1 };
+        exit_code = disconnect_exit_code();
     }
     // Best-effort tmp dir cleanup created by caller naming scheme
     let tmp_base = std::env::var("TMPDIR")
@@ -1050,6 +1210,391 @@ fn try_run_native(
     Some(exit_code)
 }
 
+#[cfg(target_os = "linux")]
+static GOT_WINCH: AtomicBool = AtomicBool::new(false);
+
+#[cfg(target_os = "linux")]
+extern "C" fn handle_winch(_sig: i32) {
+    GOT_WINCH.store(true, Ordering::SeqCst);
+}
+
+/// Interactive mode: both stdin and stdout are terminals (AIFO_SHIM_TTY=0 disables).
+#[cfg(target_os = "linux")]
+fn pty_mode_wanted() -> bool {
+    env::var("AIFO_SHIM_TTY").ok().as_deref() != Some("0")
+        && atty::is(atty::Stream::Stdin)
+        && atty::is(atty::Stream::Stdout)
+}
+
+/// Piped input: stdin is a pipe, file or socket rather than a terminal or /dev/null
+/// (AIFO_SHIM_STDIN=0 disables).
+#[cfg(target_os = "linux")]
+fn stdin_stream_wanted() -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    if env::var("AIFO_SHIM_STDIN").ok().as_deref() == Some("0") || atty::is(atty::Stream::Stdin) {
+        return false;
+    }
+    fs::metadata("/proc/self/fd/0")
+        .map(|m| {
+            let t = m.file_type();
+            t.is_fifo() || t.is_file() || t.is_socket()
+        })
+        .unwrap_or(false)
+}
+
+/// How try_run_upgraded attaches the local stdin to the remote command.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UpgradeMode {
+    /// Remote pseudo-terminal (`Upgrade: aifo-pty`).
+    Pty,
+    /// `docker exec -i` fed from redirected stdin (`Upgrade: aifo-stdin`).
+    Stdin,
+}
+
+#[cfg(target_os = "linux")]
+fn terminal_size() -> Option<(u16, u16)> {
+    // SAFETY: TIOCGWINSZ writes a winsize struct through a valid pointer.
+    let mut ws: libc::winsize = unsafe { std::mem::zeroed() };
+    let rc = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut ws) };
+    (rc == 0 && ws.ws_col > 0 && ws.ws_row > 0).then_some((ws.ws_col, ws.ws_row))
+}
+
+/// Puts the local terminal in raw mode; restores the saved settings on drop.
+#[cfg(target_os = "linux")]
+struct RawModeGuard(Option<nix::sys::termios::Termios>);
+
+#[cfg(target_os = "linux")]
+impl RawModeGuard {
+    fn enable() -> Self {
+        use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg};
+        let stdin = std::io::stdin();
+        let Ok(orig) = tcgetattr(&stdin) else {
+            return RawModeGuard(None);
+        };
+        let mut raw = orig.clone();
+        cfmakeraw(&mut raw);
+        if tcsetattr(&stdin, SetArg::TCSANOW, &raw).is_err() {
+            return RawModeGuard(None);
+        }
+        RawModeGuard(Some(orig))
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        if let Some(orig) = self.0.take() {
+            let _ = nix::sys::termios::tcsetattr(
+                std::io::stdin(),
+                nix::sys::termios::SetArg::TCSADRAIN,
+                &orig,
+            );
+        }
+    }
+}
+
+/// Proxy connection used by upgraded execs; stdin is forwarded on a second handle where the
+/// connection has one.
+#[cfg(target_os = "linux")]
+enum PtyConn {
+    Tcp(TcpConn),
+    Uds(UnixStream),
+}
+
+#[cfg(target_os = "linux")]
+impl PtyConn {
+    /// Connect to AIFO_TOOLEEXEC_URL; returns the connection, Host header and request path.
+    fn connect(url: &str) -> Option<(PtyConn, String, String)> {
+        if let Some(sock) = url.strip_prefix("unix://") {
+            let s = UnixStream::connect(sock).ok()?;
+            return Some((
+                PtyConn::Uds(s),
+                "localhost".to_string(),
+                "/exec".to_string(),
+            ));
+        }
+        let (conn, host, path) = TcpConn::connect(url)?;
+        Some((PtyConn::Tcp(conn), host, path))
+    }
+
+    fn try_clone(&self) -> std::io::Result<PtyConn> {
+        match self {
+            PtyConn::Tcp(s) => s.try_clone().map(PtyConn::Tcp),
+            PtyConn::Uds(s) => s.try_clone().map(PtyConn::Uds),
+        }
+    }
+
+    fn set_read_timeout(&self, d: Option<std::time::Duration>) {
+        match self {
+            PtyConn::Tcp(s) => s.set_read_timeout(d),
+            PtyConn::Uds(s) => {
+                let _ = s.set_read_timeout(d);
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Read for PtyConn {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            PtyConn::Tcp(s) => s.read(buf),
+            PtyConn::Uds(s) => s.read(buf),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Write for PtyConn {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            PtyConn::Tcp(s) => s.write(buf),
+            PtyConn::Uds(s) => s.write(buf),
+        }
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            PtyConn::Tcp(s) => s.flush(),
+            PtyConn::Uds(s) => s.flush(),
+        }
+    }
+}
+
+/// Run the tool with the local stdin attached over an upgraded connection.
+///
+/// UpgradeMode::Pty runs it on a remote pseudo-terminal (interactive REPLs, prompts, progress
+/// bars): after `101 Switching Protocols` the local terminal is put in raw mode and window size
+/// changes are forwarded too. UpgradeMode::Stdin streams redirected input (`cat f | tool`) to
+/// `docker exec -i` and closes it at EOF. Output frames are written to stdout. Any other
+/// response is a buffered (v1) reply and is printed as such.
+/// Returns None only when the proxy cannot be reached.
+#[cfg(target_os = "linux")]
+fn try_run_upgraded(
+    url: &str,
+    token: &str,
+    exec_id: &str,
+    form_parts: &[(String, String)],
+    mode: UpgradeMode,
+    verbose: bool,
+) -> Option<i32> {
+    use aifo_coder::{
+        pty_resize_payload, pty_write_frame, PtyFrameReader, PTY_FRAME_EXIT, PTY_FRAME_OUTPUT,
+        PTY_FRAME_RESIZE, PTY_FRAME_STDIN, PTY_FRAME_STDIN_EOF, PTY_SIZE_HEADER, PTY_UPGRADE,
+        STDIN_UPGRADE,
+    };
+
+    let (mut conn, host, path) = PtyConn::connect(url)?;
+    let (cols, rows) = terminal_size().unwrap_or((80, 24));
+    let body = encode_form_parts(form_parts);
+    let (upgrade, tty_headers) = match mode {
+        UpgradeMode::Pty => {
+            let term = env::var("TERM")
+                .ok()
+                .filter(|t| !t.is_empty() && !t.contains(['\r', '\n']))
+                .map(|t| format!("X-Aifo-Term: {t}\r\n"))
+                .unwrap_or_default();
+            (
+                PTY_UPGRADE,
+                format!("{PTY_SIZE_HEADER}: {cols}x{rows}\r\n{term}"),
+            )
+        }
+        UpgradeMode::Stdin => (STDIN_UPGRADE, String::new()),
+    };
+    // Proto 1: a proxy without upgrade support answers with a buffered response.
+    let req = format!(
+        concat!(
+            "POST {path} HTTP/1.1\r\n",
+            "Host: {host}\r\n",
+            "Authorization: Bearer {tok}\r\n",
+            "X-Aifo-Proto: 1\r\n",
+            "X-Aifo-Exec-Id: {eid}\r\n",
+            "Connection: Upgrade\r\n",
+            "Upgrade: {upgrade}\r\n",
+            "{tty_headers}",
+            "{sig}",
+            "Content-Type: application/x-www-form-urlencoded\r\n",
+            "Content-Length: {len}\r\n",
+            "\r\n",
+            "{body}"
+        ),
+        path = path,
+        host = host,
+        tok = token,
+        eid = exec_id,
+        upgrade = upgrade,
+        tty_headers = tty_headers,
+        sig = signature_header_lines(&path, SignedHeaders { upgrade, exec_id }, &body),
+        len = body.len(),
+        body = body
+    );
+    if conn.write_all(req.as_bytes()).is_err() {
+        return None;
+    }
+    if verbose {
+        match mode {
+            UpgradeMode::Pty => eprintln!("aifo-shim: requesting pty exec size={}x{}", cols, rows),
+            UpgradeMode::Stdin => eprintln!("aifo-shim: requesting exec with streamed stdin"),
+        }
+    }
+
+    // Response headers (the proxy may need a moment to route the tool).
+    conn.set_read_timeout(Some(std::time::Duration::from_millis(250)));
+    let mut hdr_buf: Vec<u8> = Vec::with_capacity(1024);
+    let mut tmp = [0u8; 1024];
+    let idx = loop {
+        if let Some(i) = aifo_coder::find_header_end(&hdr_buf) {
+            break i;
+        }
+        match conn.read(&mut tmp) {
+            Ok(0) => return Some(1),
+            Ok(n) => hdr_buf.extend_from_slice(&tmp[..n]),
+            Err(ref e)
+                if e.kind() == std::io::ErrorKind::WouldBlock
+                    || e.kind() == std::io::ErrorKind::TimedOut
+                    || e.kind() == std::io::ErrorKind::Interrupted =>
+            {
+                if let Some(code) = forward_pending_signal(url, token, exec_id, verbose) {
+                    eprint!("\n\r");
+                    return Some(code);
+                }
+            }
+            Err(_) => return Some(1),
+        }
+    };
+    let header_text = String::from_utf8_lossy(&hdr_buf[..idx]).to_string();
+    let after = hdr_buf[idx..].to_vec();
+    let switched = header_text
+        .lines()
+        .next()
+        .is_some_and(|l| l.split_whitespace().nth(1) == Some("101"));
+
+    let mut stdout = std::io::stdout();
+    if !switched {
+        let mut code = 1;
+        let mut policy_denied = false;
+        for line in header_text.lines() {
+            if let Some((k, v)) = line.split_once(':') {
+                if k.trim().eq_ignore_ascii_case("x-exit-code") {
+                    code = v.trim().parse::<i32>().unwrap_or(1);
+                } else if k.trim().eq_ignore_ascii_case("x-aifo-policy") {
+                    policy_denied = true;
+                }
+            }
+        }
+        let mut out: Box<dyn Write> = if policy_denied {
+            Box::new(std::io::stderr())
+        } else {
+            Box::new(stdout)
+        };
+        let _ = out.write_all(&after);
+        conn.set_read_timeout(None);
+        let _ = std::io::copy(&mut conn, &mut out);
+        let _ = out.flush();
+        return Some(code);
+    }
+
+    let raw = (mode == UpgradeMode::Pty).then(|| {
+        let winch = SigAction::new(
+            SigHandler::Handler(handle_winch),
+            SaFlags::SA_RESTART,
+            SigSet::empty(),
+        );
+        unsafe {
+            let _ = signal::sigaction(Signal::SIGWINCH, &winch);
+        }
+        RawModeGuard::enable()
+    });
+
+    // Forward stdin until EOF; the thread ends with the process. A connection without a second
+    // handle (TLS) gets the input through a channel and this thread writes the frames.
+    let mut stdin_writer = conn.try_clone().ok();
+    let single_stream = stdin_writer.is_none();
+    let (input_tx, input_rx) = std::sync::mpsc::channel::<(u8, Vec<u8>)>();
+    std::thread::spawn(move || {
+        let mut send = |kind: u8, data: &[u8]| match stdin_writer.as_mut() {
+            Some(w) => pty_write_frame(w, kind, data).is_ok(),
+            None => input_tx.send((kind, data.to_vec())).is_ok(),
+        };
+        let mut stdin = std::io::stdin();
+        let mut buf = [0u8; 8192];
+        loop {
+            match stdin.read(&mut buf) {
+                Ok(0) | Err(_) => {
+                    send(PTY_FRAME_STDIN_EOF, b"");
+                    break;
+                }
+                Ok(n) => {
+                    if !send(PTY_FRAME_STDIN, &buf[..n]) {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+    let mut resize_writer = conn.try_clone().ok();
+
+    // Poll faster when this thread also writes the input.
+    let poll = if single_stream { 20 } else { 100 };
+    conn.set_read_timeout(Some(std::time::Duration::from_millis(poll)));
+    let mut frames = PtyFrameReader::with_initial(&after);
+    let mut exit_code: Option<i32> = None;
+    let mut interrupted = false;
+    loop {
+        for (kind, data) in input_rx.try_iter() {
+            let _ = pty_write_frame(&mut conn, kind, &data);
+        }
+        match frames.read_frame(&mut conn) {
+            Ok(Some((PTY_FRAME_OUTPUT, data))) => {
+                let _ = stdout.write_all(&data);
+                let _ = stdout.flush();
+            }
+            Ok(Some((PTY_FRAME_EXIT, payload))) => {
+                exit_code = payload
+                    .get(..4)
+                    .and_then(|b| b.try_into().ok())
+                    .map(i32::from_be_bytes);
+                break;
+            }
+            Ok(Some(_)) => {}
+            Err(ref e)
+                if e.kind() == std::io::ErrorKind::WouldBlock
+                    || e.kind() == std::io::ErrorKind::TimedOut =>
+            {
+                if GOT_WINCH.swap(false, Ordering::SeqCst) {
+                    if let Some((c, r)) = terminal_size() {
+                        let w = match resize_writer.as_mut() {
+                            Some(w) => w,
+                            None => &mut conn,
+                        };
+                        let _ = pty_write_frame(w, PTY_FRAME_RESIZE, &pty_resize_payload(c, r));
+                    }
+                }
+                // In raw mode ^C travels as input; with piped stdin it reaches the shim.
+                if SIGINT_COUNT.load(Ordering::SeqCst) > 0
+                    || GOT_TERM.load(Ordering::SeqCst)
+                    || GOT_HUP.load(Ordering::SeqCst)
+                {
+                    interrupted = true;
+                    break;
+                }
+            }
+            Ok(None) | Err(_) => break,
+        }
+    }
+    // Restore the terminal before the signal reaches the parent shell.
+    drop(raw);
+    if interrupted {
+        exit_code = forward_pending_signal(url, token, exec_id, verbose);
+        drop(conn);
+        eprint!("\n\r");
+    }
+
+    let home = env::var("HOME").unwrap_or_else(|_| "/home/coder".to_string());
+    let _ = fs::remove_dir_all(PathBuf::from(&home).join(".aifo-exec").join(exec_id));
+    Some(exit_code.unwrap_or_else(disconnect_exit_code))
+}
+
 fn try_notify_native(
     url: &str,
     token: &str,
@@ -1088,7 +1633,7 @@ fn try_notify_native(
     }
 
     enum Conn {
-        Tcp(TcpStream, String), // stream, host header
+        Tcp(TcpConn, String), // stream, host header
         #[cfg(target_os = "linux")]
         Uds(UnixStream),
     }
@@ -1112,23 +1657,12 @@ fn try_notify_native(
             return None;
         }
     } else {
-        let rest = url.trim_start_matches("http://").to_string();
-        let path_idx = rest.find('/').unwrap_or(rest.len());
-        let (host_port, _path) = rest.split_at(path_idx);
-        let (host, port) = if let Some((h, p)) = host_port.split_once(':') {
-            let pn = p.parse::<u16>().unwrap_or(80);
-            (h.to_string(), pn)
-        } else {
-            (host_port.to_string(), 80u16)
-        };
-        let addr = format!("{}:{}", host, port);
-        match TcpStream::connect(&addr) {
-            Ok(stream) => {
-                let _ = stream.set_read_timeout(Some(std::time::Duration::from_millis(1000)));
-                let _ = stream.set_write_timeout(Some(std::time::Duration::from_millis(1000)));
+        match TcpConn::connect(url) {
+            Some((stream, host, _path)) => {
+                stream.set_timeouts(Some(std::time::Duration::from_millis(1000)));
                 Conn::Tcp(stream, host)
             }
-            Err(_) => return None,
+            None => return None,
         }
     };
 
@@ -1145,6 +1679,7 @@ fn try_notify_native(
             "Authorization: Bearer {tok}\r\n",
             "X-Aifo-Proto: 2\r\n",
             "X-Aifo-Client: rust-shim-native\r\n",
+            "{sig}",
             "Content-Type: application/x-www-form-urlencoded\r\n",
             "Content-Length: {len}\r\n",
             "Connection: close\r\n",
@@ -1152,6 +1687,7 @@ fn try_notify_native(
         ),
         host = host_header,
         tok = token,
+        sig = signature_header_lines("/notify", SignedHeaders::default(), &body),
         len = body.len()
     );
     // Best-effort writes: proceed to read response even if peer closed early.
@@ -1481,7 +2017,7 @@ fn main() {
     }
 
     let url = url_opt.unwrap();
-    let token = token_opt.unwrap();
+    let token = resolve_token(&url, token_opt.unwrap(), verbose);
 
     if verbose {
         eprintln!(
@@ -1533,6 +2069,9 @@ fn main() {
             );
         }
         let args_vec: Vec<String> = std::env::args().skip(1).collect();
+        let mut notify_parts = vec![("cmd".to_string(), invoked_tool.clone())];
+        notify_parts.extend(args_vec.iter().map(|a| ("arg".to_string(), a.clone())));
+        let notify_body = encode_form_parts(&notify_parts);
         let async_mode =
             !verbose && std::env::var("AIFO_SHIM_NOTIFY_ASYNC").ok().as_deref() != Some("0");
         if async_mode {
@@ -1550,12 +2089,14 @@ fn main() {
             curl_args.push("X-Aifo-Client: rust-shim-curl".to_string());
             curl_args.push("-H".to_string());
             curl_args.push("Content-Type: application/x-www-form-urlencoded".to_string());
-            curl_args.push("--data-urlencode".to_string());
-            curl_args.push(format!("cmd={}", invoked_tool));
-            for a in &args_vec {
-                curl_args.push("--data-urlencode".to_string());
-                curl_args.push(format!("arg={}", a));
-            }
+            push_signature_args(
+                &mut curl_args,
+                "/notify",
+                SignedHeaders::default(),
+                &notify_body,
+            );
+            curl_args.push("--data-binary".to_string());
+            curl_args.push(notify_body.clone());
             if final_url.starts_with("unix://") {
                 let sock_path = final_url.trim_start_matches("unix://").to_string();
                 curl_args.push("--unix-socket".to_string());
@@ -1567,6 +2108,7 @@ fn main() {
                 }
                 final_url.push_str("/notify");
             }
+            push_tls_args(&mut curl_args, &url);
             curl_args.push(final_url);
             let mut cmd = Command::new("curl");
             cmd.args(&curl_args)
@@ -1622,13 +2164,9 @@ fn main() {
         args.push("X-Aifo-Client: rust-shim-curl".to_string());
         args.push("-H".to_string());
         args.push("Content-Type: application/x-www-form-urlencoded".to_string());
-
-        args.push("--data-urlencode".to_string());
-        args.push(format!("cmd={}", invoked_tool));
-        for a in &args_vec {
-            args.push("--data-urlencode".to_string());
-            args.push(format!("arg={}", a));
-        }
+        push_signature_args(&mut args, "/notify", SignedHeaders::default(), &notify_body);
+        args.push("--data-binary".to_string());
+        args.push(notify_body);
 
         let mut final_url = url.clone();
         if url.starts_with("unix://") {
@@ -1642,6 +2180,7 @@ fn main() {
             }
             final_url.push_str("/notify");
         }
+        push_tls_args(&mut args, &url);
         args.push(final_url);
 
         let status_success = Command::new("curl")
@@ -1742,11 +2281,40 @@ fn main() {
     for a in std::env::args().skip(1) {
         form_parts.push(("arg".to_string(), a));
     }
+    // Agent variables the sidecar exec should see (the proxy filters them again)
+    let (forward_env, _) = aifo_coder::toolexec_env_filter(
+        env::vars_os().filter_map(|(k, v)| Some((k.into_string().ok()?, v.into_string().ok()?))),
+    );
+    if verbose && !forward_env.is_empty() {
+        let names: Vec<&str> = forward_env.iter().map(|(k, _)| k.as_str()).collect();
+        eprintln!("aifo-shim: forwarding env {}", names.join(","));
+    }
+    for (k, v) in forward_env {
+        form_parts.push(("env".to_string(), format!("{k}={v}")));
+    }
 
     // Install Linux signal handlers before entering native path so Ctrl-C is trapped properly
     #[cfg(target_os = "linux")]
     install_signal_handlers();
 
+    // Interactive terminal: run the tool on a remote PTY; piped input: stream stdin to the tool.
+    #[cfg(target_os = "linux")]
+    {
+        let mode = if pty_mode_wanted() {
+            Some(UpgradeMode::Pty)
+        } else if stdin_stream_wanted() {
+            Some(UpgradeMode::Stdin)
+        } else {
+            None
+        };
+        if let Some(mode) = mode {
+            if let Some(code) = try_run_upgraded(&url, &token, &exec_id, &form_parts, mode, verbose)
+            {
+                process::exit(code);
+            }
+        }
+    }
+
     // Try native HTTP client (Phase 3); fall back to curl when disabled or on error
     if let Some(code) = try_run_native(&url, &token, &exec_id, &form_parts, verbose) {
         process::exit(code);
@@ -1784,10 +2352,14 @@ fn main() {
     args.push("-H".to_string());
     args.push(format!("X-Aifo-Exec-Id: {}", exec_id));
 
-    for (k, v) in &form_parts {
-        args.push("--data-urlencode".to_string());
-        args.push(format!("{}={}", k, v));
-    }
+    let body = encode_form_parts(&form_parts);
+    let signed = SignedHeaders {
+        exec_id: &exec_id,
+        ..SignedHeaders::default()
+    };
+    push_signature_args(&mut args, "/exec", signed, &body);
+    args.push("--data-binary".to_string());
+    args.push(body);
 
     let mut final_url = url.clone();
     if url.starts_with("unix://") {
@@ -1797,6 +2369,7 @@ fn main() {
         args.push(sock_path);
         final_url = "http://localhost/exec".to_string();
     }
+    push_tls_args(&mut args, &url);
     args.push(final_url);
 
     #[cfg(target_os = "linux")]
@@ -1821,88 +2394,11 @@ fn main() {
         if let Ok(Some(_st)) = child.try_wait() {
             break;
         }
-        // Handle signals (Unix)
+        // Handle signals (Unix); keep markers for proxy cleanup
         #[cfg(unix)]
         {
-            let cnt = SIGINT_COUNT.load(Ordering::SeqCst);
-            if cnt >= 1 {
-                let sig = if cnt == 1 {
-                    "INT"
-                } else if cnt == 2 {
-                    "TERM"
-                } else {
-                    "KILL"
-                };
-                post_signal(&url, &token, &exec_id, sig, verbose);
-                #[cfg(target_os = "linux")]
-                {
-                    if sig != "KILL" {
-                        kill_parent_shell_if_interactive();
-                    }
-                }
+            if let Some(code) = forward_pending_signal(&url, &token, &exec_id, verbose) {
                 let _ = child.kill();
-                // Determine exit code mapping
-                let code = if std::env::var("AIFO_SHIM_EXIT_ZERO_ON_SIGINT")
-                    .ok()
-                    .as_deref()
-                    .unwrap_or("1")
-                    == "1"
-                {
-                    0
-                } else {
-                    match sig {
-                        "INT" => 130,
-                        "TERM" => 143,
-                        _ => 137,
-                    }
-                };
-                // Inform user and wait briefly so proxy logs can flush cleanly
-                disconnect_wait(verbose);
-                // Keep markers for proxy cleanup
-                let _ = child.wait();
-                let _ = fs::remove_dir_all(&tmp_dir);
-                eprint!("\n\r");
-                process::exit(code);
-            }
-            if GOT_TERM.load(Ordering::SeqCst) {
-                post_signal(&url, &token, &exec_id, "TERM", verbose);
-                #[cfg(target_os = "linux")]
-                {
-                    kill_parent_shell_if_interactive();
-                }
-                let _ = child.kill();
-                let code = if std::env::var("AIFO_SHIM_EXIT_ZERO_ON_SIGINT")
-                    .ok()
-                    .as_deref()
-                    .unwrap_or("1")
-                    == "1"
-                {
-                    0
-                } else {
-                    143
-                };
-                let _ = child.wait();
-                let _ = fs::remove_dir_all(&tmp_dir);
-                eprint!("\n\r");
-                process::exit(code);
-            }
-            if GOT_HUP.load(Ordering::SeqCst) {
-                post_signal(&url, &token, &exec_id, "HUP", verbose);
-                #[cfg(target_os = "linux")]
-                {
-                    kill_parent_shell_if_interactive();
-                }
-                let _ = child.kill();
-                let code = if std::env::var("AIFO_SHIM_EXIT_ZERO_ON_SIGINT")
-                    .ok()
-                    .as_deref()
-                    .unwrap_or("1")
-                    == "1"
-                {
-                    0
-                } else {
-                    129
-                };
                 let _ = child.wait();
                 let _ = fs::remove_dir_all(&tmp_dir);
                 eprint!("\n\r");
@@ -1938,11 +2434,7 @@ fn main() {
         let _ = fs::remove_dir_all(&base_dir);
     } else {
         // Optional default: zero exit on disconnect unless opted out
-        if env::var("AIFO_SHIM_EXIT_ZERO_ON_DISCONNECT")
-            .ok()
-            .as_deref()
-            != Some("0")
-        {
+        if disconnect_exit_code() == 0 {
             exit_code = 0;
         }
         // Proactively drive escalation on the proxy while we wait.
@@ -2359,4 +2851,83 @@ mod tests {
         send_signal_native(&url, "t", "e1", "INT");
         let _ = handle.join();
     }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_pty_exec_upgrade_relays_output_and_exit_code() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut s, _a) = listener.accept().expect("accept");
+            let buf = read_until_header_end(&mut s, 2000);
+            let head = String::from_utf8_lossy(&buf).to_string();
+            let _ = s.write_all(
+                b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: aifo-pty\r\nConnection: Upgrade\r\n\r\n",
+            );
+            aifo_coder::pty_write_frame(&mut s, aifo_coder::PTY_FRAME_OUTPUT, b"hi\r\n").unwrap();
+            aifo_coder::pty_write_frame(&mut s, aifo_coder::PTY_FRAME_EXIT, &7i32.to_be_bytes())
+                .unwrap();
+            head
+        });
+        let url = format!("http://127.0.0.1:{}/exec", port);
+        let parts = vec![
+            ("tool".to_string(), "python".to_string()),
+            ("cwd".to_string(), "/workspace".to_string()),
+        ];
+        let code =
+            try_run_upgraded(&url, "t", "e-pty", &parts, UpgradeMode::Pty, false).expect("pty");
+        assert_eq!(code, 7);
+        let head = server.join().unwrap();
+        assert!(head.contains("Upgrade: aifo-pty"), "{head}");
+        assert!(head.contains("X-Aifo-Tty-Size: "), "{head}");
+        assert!(head.contains("X-Aifo-Proto: 1"), "{head}");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_stdin_exec_requests_stdin_upgrade_without_tty_headers() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut s, _a) = listener.accept().expect("accept");
+            let buf = read_until_header_end(&mut s, 2000);
+            let head = String::from_utf8_lossy(&buf).to_string();
+            let _ = s.write_all(
+                b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: aifo-stdin\r\nConnection: Upgrade\r\n\r\n",
+            );
+            aifo_coder::pty_write_frame(&mut s, aifo_coder::PTY_FRAME_OUTPUT, b"OK\n").unwrap();
+            aifo_coder::pty_write_frame(&mut s, aifo_coder::PTY_FRAME_EXIT, &3i32.to_be_bytes())
+                .unwrap();
+            head
+        });
+        let url = format!("http://127.0.0.1:{}/exec", port);
+        let parts = vec![("tool".to_string(), "python".to_string())];
+        let code = try_run_upgraded(&url, "t", "e-in", &parts, UpgradeMode::Stdin, false)
+            .expect("stdin exec");
+        assert_eq!(code, 3);
+        let head = server.join().unwrap();
+        assert!(head.contains("Upgrade: aifo-stdin"), "{head}");
+        assert!(!head.contains("X-Aifo-Tty-Size"), "{head}");
+        assert!(!head.contains("X-Aifo-Term"), "{head}");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_pty_exec_without_upgrade_uses_buffered_reply() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).expect("bind");
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            if let Ok((mut s, _a)) = listener.accept() {
+                let _ = read_until_header_end(&mut s, 2000);
+                let _ = s.write_all(
+                    b"HTTP/1.1 403 Forbidden\r\nX-Exit-Code: 86\r\nContent-Length: 10\r\nConnection: close\r\n\r\nforbidden\n",
+                );
+            }
+        });
+        let url = format!("http://127.0.0.1:{}/exec", port);
+        let parts = vec![("tool".to_string(), "python".to_string())];
+        let code =
+            try_run_upgraded(&url, "t", "e-pty2", &parts, UpgradeMode::Pty, false).expect("reply");
+        assert_eq!(code, 86);
+    }
 }
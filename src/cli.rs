@@ -1,4 +1,7 @@
-use clap::{Parser, Subcommand};
+use std::ffi::OsString;
+
+use clap::error::ErrorKind;
+use clap::{Args, Parser, Subcommand};
 
 /// Validate tmux layout flag value
 fn validate_layout(s: &str) -> Result<String, String> {
@@ -165,6 +168,88 @@ pub(crate) enum ForkCmd {
     },
 }
 
+#[derive(Subcommand, Debug, Clone)]
+pub(crate) enum ConfigCmd {
+    /// Print every effective launcher setting and where its value came from
+    Show {
+        /// Emit machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Explain how one setting was resolved across config layers, env and CLI
+    Explain {
+        /// Setting key as printed by `config show` (e.g., proxy.max_secs)
+        #[arg(value_name = "KEY")]
+        key: String,
+        /// Emit machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub(crate) enum SecurityCmd {
+    /// Print the generated seccomp profile (or write it to a file)
+    ExportSeccomp {
+        /// Export the relaxed variant used for --toolchain-seccomp-relaxed sidecars
+        #[arg(long)]
+        relaxed: bool,
+        /// Write to FILE instead of stdout
+        #[arg(long, short = 'o', value_name = "FILE")]
+        output: Option<std::path::PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub(crate) enum AuditCmd {
+    /// Print the toolexec proxy audit log of a session (default: the most recent one)
+    Show {
+        /// Toolchain session id (file name under the audit directory)
+        #[arg(long, value_name = "SID")]
+        session: Option<String>,
+        /// Only records of this tool or notification command
+        #[arg(long, value_name = "TOOL")]
+        tool: Option<String>,
+        /// Only records of this kind: exec|notify|denied
+        #[arg(long, value_name = "EVENT", value_parser = ["exec", "notify", "denied"])]
+        event: Option<String>,
+        /// Only denials and execs that failed, timed out or were interrupted
+        #[arg(long)]
+        failed: bool,
+        /// Emit the matching records as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub(crate) enum ApparmorCmd {
+    /// Print the AppArmor profile rendered from the shipped template (or write it to a file)
+    Render {
+        /// Profile name
+        #[arg(long, default_value = aifo_coder::APPARMOR_PROFILE_NAME, value_parser = aifo_coder::parse_apparmor_profile_name)]
+        name: String,
+        /// Write to FILE instead of stdout
+        #[arg(long, short = 'o', value_name = "FILE")]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Render, validate and install the profile into /etc/apparmor.d, then load it
+    Install {
+        /// Profile name
+        #[arg(long, default_value = aifo_coder::APPARMOR_PROFILE_NAME, value_parser = aifo_coder::parse_apparmor_profile_name)]
+        name: String,
+        /// Write the profile file but do not load it into the kernel
+        #[arg(long = "no-load")]
+        no_load: bool,
+    },
+    /// Report whether the profile is installed, up to date and loaded
+    Status {
+        /// Profile name
+        #[arg(long, default_value = aifo_coder::APPARMOR_PROFILE_NAME, value_parser = aifo_coder::parse_apparmor_profile_name)]
+        name: String,
+    },
+}
+
 #[derive(Subcommand, Debug, Clone)]
 pub(crate) enum Agent {
     /// Run diagnostics to check environment and configuration
@@ -190,6 +275,42 @@ pub(crate) enum Agent {
     /// Show effective image references (including flavor/registry)
     Images,
 
+    /// Show effective configuration (files, env, CLI) with value sources
+    #[command(
+        after_long_help = "Examples:\n  aifo-coder config show\n  aifo-coder config show --json\n  aifo-coder config explain proxy.max_secs\n"
+    )]
+    Config {
+        #[command(subcommand)]
+        cmd: ConfigCmd,
+    },
+
+    /// Security profiles applied to agent containers and sidecars
+    #[command(
+        after_long_help = "Examples:\n  aifo-coder security export-seccomp > aifo-seccomp.json\n  aifo-coder security export-seccomp --relaxed -o relaxed.json\n"
+    )]
+    Security {
+        #[command(subcommand)]
+        cmd: SecurityCmd,
+    },
+
+    /// Inspect the audit log of proxied tool executions and notifications
+    #[command(
+        after_long_help = "Examples:\n  aifo-coder audit show\n  aifo-coder audit show --session abc123 --tool cargo\n  aifo-coder audit show --failed --json\n"
+    )]
+    Audit {
+        #[command(subcommand)]
+        cmd: AuditCmd,
+    },
+
+    /// Render, install and check the AppArmor profile shipped with aifo-coder
+    #[command(
+        after_long_help = "Examples:\n  aifo-coder apparmor render -o aifo-coder.apparmor\n  sudo aifo-coder apparmor install\n  aifo-coder apparmor status\n"
+    )]
+    Apparmor {
+        #[command(subcommand)]
+        cmd: ApparmorCmd,
+    },
+
     /// Clear on-disk caches (e.g., registry probe cache)
     CacheClear,
 
@@ -255,6 +376,19 @@ pub(crate) enum Agent {
         args: Vec<String>,
     },
 
+    /// Run an agent by name, including custom agents declared under [agents.NAME] in config
+    #[command(
+        after_long_help = "Examples:\n  aifo-coder run my-agent -- --help\n  aifo-coder --toolchain rust run my-agent\n"
+    )]
+    Run {
+        /// Agent name (built-in or from [agents.NAME] in the config files)
+        #[arg(value_name = "NAME")]
+        name: String,
+        /// Additional arguments passed through to the agent
+        #[arg(trailing_var_arg = true)]
+        args: Vec<String>,
+    },
+
     /// Fork maintenance commands
     #[command(
         after_long_help = "Examples:\n  aifo-coder fork list --json\n  aifo-coder fork clean --session abc123 --dry-run --json\n  aifo-coder fork clean --older-than 30 --yes --keep-dirty\n  aifo-coder fork merge --session abc123 --strategy octopus --autoclean\n"
@@ -298,6 +432,18 @@ pub(crate) struct Cli {
     #[arg(long = "docker-network-isolate")]
     pub(crate) docker_network_isolate: bool,
 
+    /// Only allow egress to the LLM endpoint, toolchain registries and --egress-allow hosts (AIFO_CODER_EGRESS_ALLOWLIST=1)
+    #[arg(long = "egress-allowlist")]
+    pub(crate) egress_allowlist: bool,
+
+    /// Extra host the egress allowlist permits (repeatable; *.example.com matches subdomains)
+    #[arg(long = "egress-allow", value_name = "HOST", value_parser = aifo_coder::parse_egress_host)]
+    pub(crate) egress_allow: Vec<String>,
+
+    /// Keep the API key on the host: agents get a session key and a base URL for the credential gateway (AIFO_CODER_LLM_GATEWAY=1)
+    #[arg(long = "llm-gateway")]
+    pub(crate) llm_gateway: bool,
+
     /// Disable named cache volumes for toolchain sidecars
     #[arg(long = "no-toolchain-cache")]
     pub(crate) no_toolchain_cache: bool,
@@ -310,6 +456,21 @@ pub(crate) struct Cli {
     #[arg(long = "toolchain-bootstrap")]
     pub(crate) toolchain_bootstrap: Vec<String>,
 
+    #[command(flatten)]
+    pub(crate) limits: LimitArgs,
+
+    /// Harden containers: drop capabilities, no-new-privileges, read-only root (AIFO_CODER_HARDENED=1)
+    #[arg(long)]
+    pub(crate) hardened: bool,
+
+    /// Give toolchain KIND's sidecar the relaxed seccomp profile (repeatable; "all" for every kind)
+    #[arg(long = "toolchain-seccomp-relaxed", value_name = "KIND")]
+    pub(crate) toolchain_seccomp_relaxed: Vec<String>,
+
+    /// Apply a named profile from the config files ([profiles.NAME]); explicit flags still win
+    #[arg(long, value_name = "NAME")]
+    pub(crate) profile: Option<String>,
+
     /// Print detailed execution info
     #[arg(long)]
     pub(crate) verbose: bool,
@@ -386,6 +547,364 @@ pub(crate) struct Cli {
 
     #[command(subcommand)]
     pub(crate) command: Agent,
+
+    /// Dotted config keys whose values were filled from config files (not a CLI flag).
+    #[arg(skip)]
+    pub(crate) config_applied: Vec<&'static str>,
+
+    /// Dotted config keys whose values were filled from the selected --profile (including
+    /// `env.NAME` for profile env entries).
+    #[arg(skip)]
+    pub(crate) profile_applied: Vec<String>,
+}
+
+/// Resource limit flags for the agent container and toolchain sidecars.
+#[derive(Args, Debug, Clone, Default)]
+pub(crate) struct LimitArgs {
+    /// CPU limit for the agent container, e.g. 2 or 1.5 (AIFO_CODER_CPUS)
+    #[arg(long, value_name = "N", value_parser = aifo_coder::parse_cpus_limit)]
+    pub(crate) cpus: Option<String>,
+
+    /// Memory limit for the agent container, e.g. 4g (AIFO_CODER_MEMORY)
+    #[arg(long, value_name = "SIZE", value_parser = aifo_coder::parse_memory_limit)]
+    pub(crate) memory: Option<String>,
+
+    /// Max processes in the agent container; -1 for unlimited (AIFO_CODER_PIDS_LIMIT)
+    #[arg(long = "pids-limit", value_name = "N", value_parser = aifo_coder::parse_pids_limit, allow_negative_numbers = true)]
+    pub(crate) pids_limit: Option<i64>,
+
+    /// Ulimit for the agent container (repeatable), e.g. nofile=4096:8192
+    #[arg(long, value_name = "NAME=SOFT[:HARD]", value_parser = aifo_coder::parse_ulimit)]
+    pub(crate) ulimit: Vec<String>,
+
+    /// CPU limit for each toolchain sidecar (AIFO_TOOLCHAIN_CPUS)
+    #[arg(long = "toolchain-cpus", value_name = "N", value_parser = aifo_coder::parse_cpus_limit)]
+    pub(crate) toolchain_cpus: Option<String>,
+
+    /// Memory limit for each toolchain sidecar (AIFO_TOOLCHAIN_MEMORY)
+    #[arg(long = "toolchain-memory", value_name = "SIZE", value_parser = aifo_coder::parse_memory_limit)]
+    pub(crate) toolchain_memory: Option<String>,
+
+    /// Max processes in each toolchain sidecar (AIFO_TOOLCHAIN_PIDS_LIMIT)
+    #[arg(long = "toolchain-pids-limit", value_name = "N", value_parser = aifo_coder::parse_pids_limit, allow_negative_numbers = true)]
+    pub(crate) toolchain_pids_limit: Option<i64>,
+
+    /// Ulimit for each toolchain sidecar (repeatable)
+    #[arg(long = "toolchain-ulimit", value_name = "NAME=SOFT[:HARD]", value_parser = aifo_coder::parse_ulimit)]
+    pub(crate) toolchain_ulimit: Vec<String>,
+}
+
+impl LimitArgs {
+    /// AIFO_CODER_* / AIFO_TOOLCHAIN_* variables for the limits given on the command line.
+    pub(crate) fn env_overrides(&self) -> Vec<(&'static str, String)> {
+        let joined = |v: &Vec<String>| (!v.is_empty()).then(|| v.join(","));
+        [
+            ("AIFO_CODER_CPUS", self.cpus.clone()),
+            ("AIFO_CODER_MEMORY", self.memory.clone()),
+            (
+                "AIFO_CODER_PIDS_LIMIT",
+                self.pids_limit.map(|p| p.to_string()),
+            ),
+            ("AIFO_CODER_ULIMITS", joined(&self.ulimit)),
+            ("AIFO_TOOLCHAIN_CPUS", self.toolchain_cpus.clone()),
+            ("AIFO_TOOLCHAIN_MEMORY", self.toolchain_memory.clone()),
+            (
+                "AIFO_TOOLCHAIN_PIDS_LIMIT",
+                self.toolchain_pids_limit.map(|p| p.to_string()),
+            ),
+            ("AIFO_TOOLCHAIN_ULIMITS", joined(&self.toolchain_ulimit)),
+        ]
+        .into_iter()
+        .filter_map(|(k, v)| v.map(|v| (k, v)))
+        .collect()
+    }
+}
+
+/// Value of `--profile` in raw argv (before any `--` separator).
+fn profile_name_from_args(argv: &[OsString]) -> Option<String> {
+    let mut it = argv.iter().skip(1).map(|a| a.to_string_lossy());
+    while let Some(a) = it.next() {
+        if a == "--" {
+            break;
+        }
+        if let Some(v) = a.strip_prefix("--profile=") {
+            return Some(v.to_string());
+        }
+        if a == "--profile" {
+            return it.next().map(|v| v.to_string());
+        }
+    }
+    None
+}
+
+/// Parse argv; when no agent subcommand is given but the selected profile names an agent,
+/// parse again with that agent (or `run NAME` for custom agents) inserted before any `--`.
+pub(crate) fn parse_cli() -> Cli {
+    let argv: Vec<OsString> = std::env::args_os().collect();
+    let err = match Cli::try_parse_from(&argv) {
+        Ok(cli) => return cli,
+        Err(e) => e,
+    };
+    // With `--` and no agent, clap reports the first agent argument as an invalid subcommand.
+    let missing = match err.kind() {
+        ErrorKind::MissingSubcommand | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand => true,
+        ErrorKind::InvalidSubcommand => argv.iter().any(|a| a == "--"),
+        _ => false,
+    };
+    let agent = profile_name_from_args(&argv).and_then(|name| {
+        let resolved = aifo_coder::config_init().ok()?;
+        resolved.config.profiles.get(&name)?.agent.clone()
+    });
+    match agent {
+        Some(agent) if missing => {
+            let mut argv = argv;
+            let at = argv.iter().position(|a| a == "--").unwrap_or(argv.len());
+            argv.insert(at, OsString::from(&agent));
+            if !aifo_coder::BUILTIN_AGENTS.contains(&agent.as_str()) {
+                argv.insert(at, OsString::from("run"));
+            }
+            Cli::try_parse_from(argv).unwrap_or_else(|e| e.exit())
+        }
+        _ => err.exit(),
+    }
+}
+
+/// Apply a profile boolean to FLAG while it still has its DEFAULT (no CLI flag). Both true and
+/// false are recorded in APPLIED under KEY so config files cannot override them.
+fn profile_flag(
+    flag: &mut bool,
+    default: bool,
+    value: Option<bool>,
+    key: &str,
+    applied: &mut Vec<String>,
+) {
+    if let Some(v) = value.filter(|_| *flag == default) {
+        *flag = v;
+        applied.push(key.to_string());
+    }
+}
+
+impl Cli {
+    /// Fill options not passed on the command line from the profile selected with --profile.
+    ///
+    /// Runs before `apply_config_defaults`, so profile values beat the plain config sections.
+    /// Profile `env` entries override config-file `[env]` values but not variables already
+    /// exported by the caller.
+    pub(crate) fn apply_profile(
+        &mut self,
+        resolved: &aifo_coder::ResolvedConfig,
+    ) -> Result<(), String> {
+        let Some(name) = self.profile.clone() else {
+            return Ok(());
+        };
+        let Some(p) = resolved.config.profiles.get(&name) else {
+            let known: Vec<&str> = resolved
+                .config
+                .profiles
+                .keys()
+                .map(String::as_str)
+                .collect();
+            return Err(if known.is_empty() {
+                format!("unknown profile '{name}' (no [profiles.*] defined in config files)")
+            } else {
+                format!("unknown profile '{name}' (defined: {})", known.join(", "))
+            });
+        };
+
+        if self.flavor.is_none() {
+            if let Some(f) = p.flavor {
+                self.flavor = Some(match f {
+                    aifo_coder::ImageFlavor::Full => Flavor::Full,
+                    aifo_coder::ImageFlavor::Slim => Flavor::Slim,
+                });
+                self.profile_applied.push("image.flavor".to_string());
+            }
+        }
+        if self.toolchain.is_empty() {
+            if let Some(specs) = p.toolchains.as_ref() {
+                for s in specs {
+                    let spec = s
+                        .parse::<ToolchainSpec>()
+                        .map_err(|e| format!("profile {name} toolchains: {e}"))?;
+                    self.toolchain.push(spec);
+                }
+                self.profile_applied.push("toolchains.specs".to_string());
+            }
+        }
+        if self.toolchain_bootstrap.is_empty() {
+            if let Some(b) = p.bootstrap.as_ref() {
+                self.toolchain_bootstrap = b.clone();
+                self.profile_applied
+                    .push("toolchains.bootstrap".to_string());
+            }
+        }
+        profile_flag(
+            &mut self.no_toolchain_cache,
+            false,
+            p.no_toolchain_cache,
+            "toolchains.no_cache",
+            &mut self.profile_applied,
+        );
+        profile_flag(
+            &mut self.toolchain_unix_socket,
+            false,
+            p.toolchain_unix_socket,
+            "toolchains.unix_socket",
+            &mut self.profile_applied,
+        );
+        if self.docker_network.is_none() {
+            self.docker_network = p
+                .network
+                .as_ref()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty());
+            if self.docker_network.is_some() {
+                self.profile_applied.push("network.name".to_string());
+            }
+        }
+        profile_flag(
+            &mut self.docker_network_isolate,
+            false,
+            p.network_isolate,
+            "network.isolate",
+            &mut self.profile_applied,
+        );
+        if self.fork_layout.is_none() {
+            if let Some(l) = p.fork_layout.as_deref() {
+                let l =
+                    validate_layout(l).map_err(|e| format!("profile {name} fork_layout: {e}"))?;
+                self.fork_layout = Some(l);
+                self.profile_applied.push("fork.layout".to_string());
+            }
+        }
+        profile_flag(
+            &mut self.fork_include_dirty,
+            false,
+            p.fork_include_dirty,
+            "fork.include_dirty",
+            &mut self.profile_applied,
+        );
+        profile_flag(
+            &mut self.fork_dissociate,
+            false,
+            p.fork_dissociate,
+            "fork.dissociate",
+            &mut self.profile_applied,
+        );
+        profile_flag(
+            &mut self.fork_merging_autoclean,
+            true,
+            p.fork_merge_autoclean,
+            "fork.merge_autoclean",
+            &mut self.profile_applied,
+        );
+        for (k, v) in &p.env {
+            let var = format!("AIFO_ENV_{k}");
+            if !resolved.env_preset.contains(&var) {
+                std::env::set_var(&var, v);
+                self.profile_applied.push(format!("env.{k}"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether KEY was set by the selected profile; config files must not override it.
+    fn set_by_profile(&self, key: &str) -> bool {
+        self.profile_applied.iter().any(|k| k == key)
+    }
+
+    /// Fill options the user did not pass on the command line from layered config files.
+    ///
+    /// CLI flags always win. Network defaults are skipped when AIFO_SESSION_NETWORK is set so
+    /// the environment keeps precedence over files.
+    pub(crate) fn apply_config_defaults(
+        &mut self,
+        cfg: &aifo_coder::AifoConfig,
+    ) -> Result<(), String> {
+        if self.toolchain.is_empty() {
+            if let Some(specs) = cfg.toolchains.specs.as_ref() {
+                for s in specs {
+                    let spec = s
+                        .parse::<ToolchainSpec>()
+                        .map_err(|e| format!("config toolchains.specs: {e}"))?;
+                    self.toolchain.push(spec);
+                }
+                self.config_applied.push("toolchains.specs");
+            }
+        }
+        if self.toolchain_bootstrap.is_empty() {
+            if let Some(b) = cfg.toolchains.bootstrap.as_ref() {
+                self.toolchain_bootstrap = b.clone();
+                self.config_applied.push("toolchains.bootstrap");
+            }
+        }
+        if !self.no_toolchain_cache
+            && !self.set_by_profile("toolchains.no_cache")
+            && cfg.toolchains.no_cache == Some(true)
+        {
+            self.no_toolchain_cache = true;
+            self.config_applied.push("toolchains.no_cache");
+        }
+        if !self.toolchain_unix_socket
+            && !self.set_by_profile("toolchains.unix_socket")
+            && cfg.toolchains.unix_socket == Some(true)
+        {
+            self.toolchain_unix_socket = true;
+            self.config_applied.push("toolchains.unix_socket");
+        }
+
+        let env_net_set = std::env::var("AIFO_SESSION_NETWORK")
+            .ok()
+            .is_some_and(|s| !s.trim().is_empty());
+        if self.docker_network.is_none() && !env_net_set {
+            self.docker_network = cfg
+                .network
+                .name
+                .as_ref()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty());
+            if self.docker_network.is_some() {
+                self.config_applied.push("network.name");
+            }
+        }
+        if !self.docker_network_isolate
+            && !self.set_by_profile("network.isolate")
+            && cfg.network.isolate == Some(true)
+        {
+            self.docker_network_isolate = true;
+            self.config_applied.push("network.isolate");
+        }
+
+        if self.fork_layout.is_none() {
+            if let Some(l) = cfg.fork.layout.as_deref() {
+                let l = validate_layout(l).map_err(|e| format!("config fork.layout: {e}"))?;
+                self.fork_layout = Some(l);
+                self.config_applied.push("fork.layout");
+            }
+        }
+        if !self.fork_include_dirty
+            && !self.set_by_profile("fork.include_dirty")
+            && cfg.fork.include_dirty == Some(true)
+        {
+            self.fork_include_dirty = true;
+            self.config_applied.push("fork.include_dirty");
+        }
+        if !self.fork_dissociate
+            && !self.set_by_profile("fork.dissociate")
+            && cfg.fork.dissociate == Some(true)
+        {
+            self.fork_dissociate = true;
+            self.config_applied.push("fork.dissociate");
+        }
+        if self.fork_merging_autoclean
+            && !self.set_by_profile("fork.merge_autoclean")
+            && cfg.fork.merge_autoclean == Some(false)
+        {
+            self.fork_merging_autoclean = false;
+            self.config_applied.push("fork.merge_autoclean");
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -411,4 +930,166 @@ mod tests {
         let res = Cli::try_parse_from(["aifo-coder", "--fork=0", "aider"]);
         assert!(res.is_err(), "fork value 0 should be rejected");
     }
+
+    #[test]
+    fn config_defaults_fill_unset_flags_only() {
+        let mut cfg = aifo_coder::AifoConfig::default();
+        cfg.toolchains.specs = Some(vec!["rust@1.80".to_string(), "node".to_string()]);
+        cfg.toolchains.bootstrap = Some(vec!["typescript=global".to_string()]);
+        cfg.fork.layout = Some("even-v".to_string());
+
+        let mut cli = Cli::parse_from(["aifo-coder", "--toolchain", "python", "aider"]);
+        cli.apply_config_defaults(&cfg).expect("apply config");
+        assert_eq!(cli.toolchain.len(), 1, "CLI toolchains must win");
+        assert_eq!(cli.toolchain[0].kind, "python");
+        assert_eq!(cli.toolchain_bootstrap, vec!["typescript=global"]);
+        assert_eq!(cli.fork_layout.as_deref(), Some("even-v"));
+
+        let mut cli = Cli::parse_from(["aifo-coder", "aider"]);
+        cli.apply_config_defaults(&cfg).expect("apply config");
+        let kinds: Vec<&str> = cli.toolchain.iter().map(|t| t.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["rust", "node"]);
+    }
+
+    #[test]
+    fn profile_fills_unset_flags_before_config_defaults() {
+        let mut resolved = aifo_coder::ResolvedConfig::default();
+        resolved.config.toolchains.specs = Some(vec!["go".to_string()]);
+        resolved.config.network.isolate = Some(true);
+        let profile = aifo_coder::ProfileConfig {
+            flavor: Some(aifo_coder::ImageFlavor::Slim),
+            toolchains: Some(vec!["rust@1.80".to_string(), "node".to_string()]),
+            bootstrap: Some(vec!["typescript=global".to_string()]),
+            fork_layout: Some("even-h".to_string()),
+            ..Default::default()
+        };
+        resolved
+            .config
+            .profiles
+            .insert("backend".to_string(), profile);
+
+        let mut cli = Cli::parse_from(["aifo-coder", "--profile", "backend", "aider"]);
+        cli.apply_profile(&resolved).expect("apply profile");
+        cli.apply_config_defaults(&resolved.config)
+            .expect("apply config");
+        let kinds: Vec<&str> = cli.toolchain.iter().map(|t| t.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["rust", "node"], "profile must beat config");
+        assert_eq!(cli.flavor, Some(super::Flavor::Slim));
+        assert!(cli.docker_network_isolate, "config still fills the rest");
+        assert_eq!(
+            cli.profile_applied,
+            vec![
+                "image.flavor",
+                "toolchains.specs",
+                "toolchains.bootstrap",
+                "fork.layout"
+            ]
+        );
+
+        let mut cli = Cli::parse_from([
+            "aifo-coder",
+            "--profile=backend",
+            "--flavor",
+            "full",
+            "--toolchain",
+            "python",
+            "aider",
+        ]);
+        cli.apply_profile(&resolved).expect("apply profile");
+        assert_eq!(cli.flavor, Some(super::Flavor::Full), "CLI flag must win");
+        assert_eq!(cli.toolchain.len(), 1);
+        assert_eq!(cli.toolchain[0].kind, "python");
+    }
+
+    #[test]
+    fn profile_false_beats_config_true() {
+        let mut resolved = aifo_coder::ResolvedConfig::default();
+        resolved.config.network.isolate = Some(true);
+        resolved.config.fork.dissociate = Some(true);
+        resolved.config.fork.merge_autoclean = Some(false);
+        let profile = aifo_coder::ProfileConfig {
+            network_isolate: Some(false),
+            fork_dissociate: Some(false),
+            fork_merge_autoclean: Some(true),
+            env: [("AIFO_UT_PROFILE_ENV".to_string(), "1".to_string())].into(),
+            ..Default::default()
+        };
+        resolved
+            .config
+            .profiles
+            .insert("local".to_string(), profile);
+
+        let mut cli = Cli::parse_from(["aifo-coder", "--profile", "local", "aider"]);
+        cli.apply_profile(&resolved).expect("apply profile");
+        cli.apply_config_defaults(&resolved.config)
+            .expect("apply config");
+        std::env::remove_var("AIFO_ENV_AIFO_UT_PROFILE_ENV");
+        assert!(!cli.docker_network_isolate);
+        assert!(!cli.fork_dissociate);
+        assert!(cli.fork_merging_autoclean);
+        assert!(cli.config_applied.is_empty(), "{:?}", cli.config_applied);
+        assert_eq!(
+            cli.profile_applied,
+            vec![
+                "network.isolate",
+                "fork.dissociate",
+                "fork.merge_autoclean",
+                "env.AIFO_UT_PROFILE_ENV"
+            ]
+        );
+
+        // A CLI flag still wins over the profile.
+        let mut cli = Cli::parse_from([
+            "aifo-coder",
+            "--profile",
+            "local",
+            "--docker-network-isolate",
+            "aider",
+        ]);
+        cli.apply_profile(&resolved).expect("apply profile");
+        std::env::remove_var("AIFO_ENV_AIFO_UT_PROFILE_ENV");
+        assert!(cli.docker_network_isolate);
+        assert!(!cli.profile_applied.iter().any(|k| k == "network.isolate"));
+    }
+
+    #[test]
+    fn profile_unknown_name_is_an_error() {
+        let mut resolved = aifo_coder::ResolvedConfig::default();
+        resolved
+            .config
+            .profiles
+            .insert("backend".to_string(), Default::default());
+        let mut cli = Cli::parse_from(["aifo-coder", "--profile", "frontend", "aider"]);
+        let err = cli.apply_profile(&resolved).unwrap_err();
+        assert!(
+            err.contains("frontend") && err.contains("backend"),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn profile_name_is_read_before_agent_separator() {
+        let argv = |v: &[&str]| v.iter().map(std::ffi::OsString::from).collect::<Vec<_>>();
+        assert_eq!(
+            super::profile_name_from_args(&argv(&["aifo-coder", "--profile", "be"])).as_deref(),
+            Some("be")
+        );
+        assert_eq!(
+            super::profile_name_from_args(&argv(&["aifo-coder", "--profile=be", "--"])).as_deref(),
+            Some("be")
+        );
+        assert_eq!(
+            super::profile_name_from_args(&argv(&["aifo-coder", "--", "--profile", "be"])),
+            None
+        );
+    }
+
+    #[test]
+    fn config_defaults_reject_invalid_values() {
+        let mut cfg = aifo_coder::AifoConfig::default();
+        cfg.fork.layout = Some("diagonal".to_string());
+        let mut cli = Cli::parse_from(["aifo-coder", "aider"]);
+        let err = cli.apply_config_defaults(&cfg).unwrap_err();
+        assert!(err.contains("fork.layout"), "unexpected error: {err}");
+    }
 }
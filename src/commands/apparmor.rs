@@ -0,0 +1,164 @@
+//! `aifo-coder apparmor render|install|status`: manage the AppArmor profile shipped in
+//! apparmor/aifo-coder.apparmor.tpl so desired_apparmor_profile() can select it.
+
+use std::io::Write;
+use std::process::ExitCode;
+
+use aifo_coder::AppArmorInstallState;
+
+use crate::cli::{ApparmorCmd, Cli};
+
+fn error(msg: &str) -> ExitCode {
+    aifo_coder::log_error_stderr(aifo_coder::color_enabled_stderr(), msg);
+    ExitCode::from(1)
+}
+
+fn info(msg: &str) {
+    aifo_coder::log_info_stderr(aifo_coder::color_enabled_stderr(), msg);
+}
+
+pub fn run_apparmor(cli: &Cli, cmd: &ApparmorCmd) -> ExitCode {
+    match cmd {
+        ApparmorCmd::Render { name, output } => {
+            let profile = aifo_coder::render_apparmor_profile(name);
+            match output {
+                Some(path) => match std::fs::write(path, profile) {
+                    Ok(()) => {
+                        info(&format!(
+                            "aifo-coder: wrote AppArmor profile '{}' to {}",
+                            name,
+                            path.display()
+                        ));
+                        ExitCode::from(0)
+                    }
+                    Err(e) => error(&format!(
+                        "aifo-coder: error: cannot write {}: {}",
+                        path.display(),
+                        e
+                    )),
+                },
+                None => {
+                    print!("{profile}");
+                    ExitCode::from(0)
+                }
+            }
+        }
+        ApparmorCmd::Install { name, no_load } => install(cli, name, *no_load),
+        ApparmorCmd::Status { name } => {
+            print_status(&aifo_coder::apparmor_status(name));
+            ExitCode::from(0)
+        }
+    }
+}
+
+fn install(cli: &Cli, name: &str, no_load: bool) -> ExitCode {
+    let dest = aifo_coder::apparmor_install_dir().join(name);
+    if !no_load && !cfg!(target_os = "linux") {
+        return error(&format!(
+            "aifo-coder: error: AppArmor profiles load inside the container engine's VM; {} {}",
+            format_args!("run 'aifo-coder apparmor render -o {name}' and load it there, e.g."),
+            format_args!("colima ssh -- sudo apparmor_parser -r -W \"$PWD/{name}\""),
+        ));
+    }
+    if cli.dry_run {
+        info(&format!(
+            "aifo-coder: would write AppArmor profile '{}' to {}",
+            name,
+            dest.display()
+        ));
+        if !no_load {
+            info(&format!(
+                "aifo-coder: would run: apparmor_parser -r -W {}",
+                dest.display()
+            ));
+        }
+        return ExitCode::from(0);
+    }
+
+    // Validate a scratch copy first so a broken render never replaces a working profile.
+    let profile = aifo_coder::render_apparmor_profile(name);
+    let scratch = tempfile::Builder::new()
+        .prefix("aifo-apparmor-")
+        .tempfile()
+        .and_then(|mut f| f.write_all(profile.as_bytes()).map(|_| f));
+    let scratch = match scratch {
+        Ok(f) => f,
+        Err(e) => return error(&format!("aifo-coder: error: cannot render profile: {e}")),
+    };
+    match aifo_coder::validate_apparmor_profile(scratch.path()) {
+        Some(Ok(())) => info("aifo-coder: apparmor_parser accepted the rendered profile"),
+        Some(Err(e)) => return error(&format!("aifo-coder: error: {e}")),
+        None => aifo_coder::warn_print("apparmor_parser not found; installing without validation."),
+    }
+
+    let written = dest
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::write(&dest, &profile));
+    if let Err(e) = written {
+        let hint = if e.kind() == std::io::ErrorKind::PermissionDenied {
+            " (re-run with sudo)"
+        } else {
+            ""
+        };
+        return error(&format!(
+            "aifo-coder: error: cannot write {}: {}{}",
+            dest.display(),
+            e,
+            hint
+        ));
+    }
+    info(&format!(
+        "aifo-coder: installed AppArmor profile '{}' to {}",
+        name,
+        dest.display()
+    ));
+    if no_load {
+        return ExitCode::from(0);
+    }
+    match aifo_coder::load_apparmor_profile(&dest) {
+        Ok(()) => {
+            info(&format!("aifo-coder: loaded AppArmor profile '{name}'"));
+            ExitCode::from(0)
+        }
+        Err(e) => error(&format!("aifo-coder: error: {e}")),
+    }
+}
+
+fn print_status(st: &aifo_coder::AppArmorStatus) {
+    println!("profile:          {}", st.name);
+    println!(
+        "kernel apparmor:  {}",
+        if st.kernel_enabled {
+            "enabled"
+        } else {
+            "not enabled"
+        }
+    );
+    println!(
+        "installed:        {} ({})",
+        st.installed.as_str(),
+        st.path.display()
+    );
+    println!(
+        "loaded:           {}",
+        match st.loaded {
+            Some(true) => "yes",
+            Some(false) => "no",
+            None => "unknown",
+        }
+    );
+    println!(
+        "apparmor_parser:  {}",
+        st.parser
+            .as_ref()
+            .map_or_else(|| "not found".to_string(), |p| p.display().to_string())
+    );
+    if st.installed != AppArmorInstallState::Current || st.loaded == Some(false) {
+        println!();
+        println!(
+            "To install or refresh it: sudo aifo-coder apparmor install --name {}",
+            st.name
+        );
+    }
+}
@@ -0,0 +1,134 @@
+//! `aifo-coder audit show`: print the toolexec proxy audit log of one session, filtered.
+
+use std::process::ExitCode;
+
+use aifo_coder::AuditFilter;
+use serde_json::Value;
+
+use crate::cli::AuditCmd;
+
+fn error(msg: &str) -> ExitCode {
+    aifo_coder::log_error_stderr(aifo_coder::color_enabled_stderr(), msg);
+    ExitCode::from(1)
+}
+
+pub fn run_audit(cmd: &AuditCmd) -> ExitCode {
+    match cmd {
+        AuditCmd::Show {
+            session,
+            tool,
+            event,
+            failed,
+            json,
+        } => {
+            let Some(sid) = session.clone().or_else(aifo_coder::audit_latest_session) else {
+                return error(&format!(
+                    "aifo-coder: error: no audit logs under {}",
+                    aifo_coder::audit_dir().display()
+                ));
+            };
+            let records = match aifo_coder::audit_read(&sid) {
+                Ok(r) => r,
+                Err(e) => {
+                    return error(&format!(
+                        "aifo-coder: error: cannot read {}: {}",
+                        aifo_coder::audit_log_path(&sid).display(),
+                        e
+                    ))
+                }
+            };
+            let filter = AuditFilter {
+                tool: tool.clone(),
+                event: event.clone(),
+                failed: *failed,
+            };
+            let shown: Vec<&Value> = records.iter().filter(|r| filter.matches(r)).collect();
+            if *json {
+                for r in &shown {
+                    println!("{r}");
+                }
+            } else {
+                aifo_coder::log_info_stderr(
+                    aifo_coder::color_enabled_stderr(),
+                    &format!(
+                        "aifo-coder: audit session {} ({} of {} records) in {}",
+                        sid,
+                        shown.len(),
+                        records.len(),
+                        aifo_coder::audit_log_path(&sid).display()
+                    ),
+                );
+                for r in &shown {
+                    println!("{}", format_record(r));
+                }
+            }
+            ExitCode::from(0)
+        }
+    }
+}
+
+/// One human-readable line per record.
+fn format_record(r: &Value) -> String {
+    let s = |k: &str| r.get(k).and_then(Value::as_str).unwrap_or("");
+    let argv: Vec<String> = r
+        .get("argv")
+        .and_then(Value::as_array)
+        .map(|a| {
+            a.iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default();
+    let event = s("event");
+    let program = if event == "notify" {
+        s("cmd")
+    } else {
+        s("tool")
+    };
+    let mut words = vec![program.to_string()];
+    words.extend(argv);
+    let mut line = format!(
+        "{}  {:<6}  {}",
+        s("ts"),
+        event,
+        aifo_coder::shell_join(&words)
+    );
+    let exit = match r.get("exit_code").and_then(Value::as_i64) {
+        Some(code) => format!("exit={code}"),
+        None => "exit=-".to_string(),
+    };
+    let secs = r.get("duration_ms").and_then(Value::as_u64).unwrap_or(0) as f64 / 1000.0;
+    match event {
+        "exec" => {
+            line.push_str(&format!(
+                "  {}  {:.1}s  {}B  {}  cwd={}  id={}",
+                exit,
+                secs,
+                r.get("bytes_out").and_then(Value::as_u64).unwrap_or(0),
+                s("kind"),
+                s("cwd"),
+                s("exec_id")
+            ));
+            if r.get("exit_code").is_some_and(Value::is_null) {
+                line.push_str("  (client disconnected)");
+            }
+            let signals: Vec<&str> = r
+                .get("signals")
+                .and_then(Value::as_array)
+                .map(|a| a.iter().filter_map(Value::as_str).collect())
+                .unwrap_or_default();
+            if !signals.is_empty() {
+                line.push_str(&format!("  signals={}", signals.join(",")));
+            }
+        }
+        "notify" => {
+            line.push_str(&format!("  {exit}  {secs:.1}s"));
+            if !s("error").is_empty() {
+                line.push_str(&format!("  error={}", s("error")));
+            }
+        }
+        "denied" => line.push_str(&format!("  {}: {}", s("rule"), s("reason"))),
+        _ => {}
+    }
+    line
+}
@@ -0,0 +1,409 @@
+//! `aifo-coder config show|explain`: effective launcher settings with their provenance.
+//!
+//! Env-backed knobs come from the lib's ResolvedConfig; CLI-level defaults (toolchains,
+//! network, fork options) are attributed via `Cli::config_applied`; image and registry
+//! values are resolved the same way a run would resolve them.
+
+use std::process::ExitCode;
+
+use aifo_coder::{ConfigSource, EffectiveSetting, ResolvedConfig};
+
+use crate::agent_images::default_image_for_quiet;
+use crate::cli::{Cli, ConfigCmd};
+
+const TOOLCHAIN_KINDS: &[&str] = &["rust", "node", "python", "c-cpp", "go"];
+
+/// Env overrides consulted by default_toolchain_image, per normalized kind.
+fn toolchain_image_env_overrides(kind: &str) -> &'static [&'static str] {
+    match kind {
+        "rust" => &[
+            "AIFO_RUST_TOOLCHAIN_IMAGE",
+            "AIFO_RUST_TOOLCHAIN_USE_OFFICIAL",
+            "AIFO_RUST_TOOLCHAIN_VERSION",
+            "RUST_TOOLCHAIN_TAG",
+            "AIFO_TOOLCHAIN_TAG",
+        ],
+        "node" => &[
+            "AIFO_NODE_TOOLCHAIN_IMAGE",
+            "AIFO_NODE_TOOLCHAIN_VERSION",
+            "NODE_TOOLCHAIN_TAG",
+            "AIFO_TOOLCHAIN_TAG",
+        ],
+        "c-cpp" => &["CPP_TOOLCHAIN_TAG", "AIFO_TOOLCHAIN_TAG"],
+        _ => &["AIFO_TOOLCHAIN_TAG"],
+    }
+}
+
+fn env_set(k: &str) -> bool {
+    std::env::var(k).ok().is_some_and(|v| !v.trim().is_empty())
+}
+
+/// Source of a CLI-level option: filled from a config file, passed explicitly, or default.
+fn cli_source(cli: &Cli, resolved: &ResolvedConfig, key: &str, explicit: bool) -> ConfigSource {
+    if cli.profile_applied.iter().any(|k| k == key) {
+        ConfigSource::Profile(cli.profile.clone().unwrap_or_default())
+    } else if cli.config_applied.contains(&key) {
+        resolved
+            .origin(key)
+            .cloned()
+            .unwrap_or_else(|| ConfigSource::Derived("config".to_string()))
+    } else if explicit {
+        ConfigSource::Cli
+    } else {
+        ConfigSource::Default
+    }
+}
+
+fn bool_str(b: bool) -> &'static str {
+    if b {
+        "1"
+    } else {
+        "0"
+    }
+}
+
+fn registry_source(raw: &str, fallback: ConfigSource) -> ConfigSource {
+    match raw {
+        "env" | "env-empty" => fallback,
+        "cache" => ConfigSource::Derived("registry-cache".to_string()),
+        "curl" | "tcp" => ConfigSource::Derived(format!("registry-probe:{raw}")),
+        _ => ConfigSource::Default,
+    }
+}
+
+/// Compute every effective setting the launcher would use for this invocation.
+pub(crate) fn effective_settings(cli: &Cli, resolved: &ResolvedConfig) -> Vec<EffectiveSetting> {
+    let mut out: Vec<EffectiveSetting> = Vec::new();
+
+    for knob in aifo_coder::CONFIG_ENV_KNOBS {
+        let mut s = resolved.knob_setting(knob);
+        match knob.key {
+            "image.flavor" if cli.flavor.is_some() => {
+                s.source = cli_source(cli, resolved, "image.flavor", true)
+            }
+            "registry.internal_prefix" => {
+                let v = aifo_coder::preferred_internal_registry_prefix_quiet();
+                let src = aifo_coder::preferred_internal_registry_source();
+                s.value = v.trim_end_matches('/').to_string();
+                s.source = registry_source(&src, s.source.clone());
+            }
+            "registry.mirror_prefix" => {
+                let v = aifo_coder::preferred_mirror_registry_prefix_quiet();
+                let src = aifo_coder::preferred_mirror_registry_source();
+                s.value = v.trim_end_matches('/').to_string();
+                s.source = registry_source(&src, s.source.clone());
+            }
+            _ => {}
+        }
+        out.push(s);
+    }
+
+    let custom_agents = resolved.config.agents.keys().map(String::as_str);
+    for agent in aifo_coder::BUILTIN_AGENTS
+        .iter()
+        .copied()
+        .chain(custom_agents)
+    {
+        let key = format!("image.agent.{agent}");
+        let custom_image = resolved
+            .config
+            .agents
+            .get(agent)
+            .and_then(|c| c.image.as_deref());
+        let s = if let Some(img) = cli.image.as_deref() {
+            EffectiveSetting::new(&key, img, ConfigSource::Cli)
+        } else if let Some(img) = custom_image {
+            let origin = resolved
+                .origin(&format!("agents.{agent}.image"))
+                .cloned()
+                .unwrap_or(ConfigSource::Default);
+            EffectiveSetting::new(&key, img, origin)
+        } else if env_set("AIFO_CODER_IMAGE") {
+            let mut s = EffectiveSetting::new(
+                &key,
+                default_image_for_quiet(agent),
+                aifo_coder::config_env_source("AIFO_CODER_IMAGE"),
+            );
+            s.env = Some("AIFO_CODER_IMAGE");
+            s
+        } else {
+            EffectiveSetting::new(
+                &key,
+                default_image_for_quiet(agent),
+                ConfigSource::Derived("image-resolution".to_string()),
+            )
+        };
+        out.push(s);
+    }
+
+    for kind in TOOLCHAIN_KINDS {
+        let key = format!("toolchain.image.{kind}");
+        let from_spec = cli
+            .toolchain
+            .iter()
+            .find(|t| t.kind == *kind)
+            .and_then(|t| t.resolved_image_override());
+        let s = if let Some(img) = from_spec {
+            EffectiveSetting::new(
+                &key,
+                img,
+                cli_source(cli, resolved, "toolchains.specs", true),
+            )
+        } else if let Some(var) = toolchain_image_env_overrides(kind)
+            .iter()
+            .find(|k| env_set(k))
+        {
+            let mut s = EffectiveSetting::new(
+                &key,
+                aifo_coder::default_toolchain_image(kind),
+                aifo_coder::config_env_source(var),
+            );
+            s.env = Some(var);
+            s
+        } else {
+            EffectiveSetting::new(
+                &key,
+                aifo_coder::default_toolchain_image(kind),
+                ConfigSource::Derived("default_toolchain_image".to_string()),
+            )
+        };
+        out.push(s);
+    }
+
+    let specs: Vec<&str> = cli.toolchain.iter().map(|t| t.as_str()).collect();
+    if let Some(name) = cli.profile.as_deref() {
+        out.push(EffectiveSetting::new("profile", name, ConfigSource::Cli));
+    }
+    out.push(EffectiveSetting::new(
+        "toolchains.specs",
+        specs.join(","),
+        cli_source(cli, resolved, "toolchains.specs", !specs.is_empty()),
+    ));
+    out.push(EffectiveSetting::new(
+        "toolchains.bootstrap",
+        cli.toolchain_bootstrap.join(","),
+        cli_source(
+            cli,
+            resolved,
+            "toolchains.bootstrap",
+            !cli.toolchain_bootstrap.is_empty(),
+        ),
+    ));
+    out.push(EffectiveSetting::new(
+        "toolchains.no_cache",
+        bool_str(cli.no_toolchain_cache),
+        cli_source(cli, resolved, "toolchains.no_cache", cli.no_toolchain_cache),
+    ));
+    out.push(EffectiveSetting::new(
+        "toolchains.unix_socket",
+        bool_str(cli.toolchain_unix_socket),
+        cli_source(
+            cli,
+            resolved,
+            "toolchains.unix_socket",
+            cli.toolchain_unix_socket,
+        ),
+    ));
+
+    // Mirrors configure_network_env() in main without creating a session id.
+    let cli_net = cli
+        .docker_network
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty());
+    let env_net = std::env::var("AIFO_SESSION_NETWORK")
+        .ok()
+        .filter(|s| !s.trim().is_empty());
+    let (net, net_src) = match (cli_net, env_net) {
+        (Some(n), _) => (
+            n.to_string(),
+            cli_source(cli, resolved, "network.name", true),
+        ),
+        (None, Some(n)) => (n, aifo_coder::config_env_source("AIFO_SESSION_NETWORK")),
+        (None, None) => ("bridge".to_string(), ConfigSource::Default),
+    };
+    let net = if cli.docker_network_isolate {
+        format!("{net}-<session-id>")
+    } else {
+        net
+    };
+    let mut s = EffectiveSetting::new("network.name", net, net_src);
+    s.env = Some("AIFO_SESSION_NETWORK");
+    out.push(s);
+    out.push(EffectiveSetting::new(
+        "network.isolate",
+        bool_str(cli.docker_network_isolate),
+        cli_source(cli, resolved, "network.isolate", cli.docker_network_isolate),
+    ));
+
+    out.push(EffectiveSetting::new(
+        "fork.layout",
+        cli.fork_layout
+            .clone()
+            .unwrap_or_else(|| "tiled".to_string()),
+        cli_source(cli, resolved, "fork.layout", cli.fork_layout.is_some()),
+    ));
+    out.push(EffectiveSetting::new(
+        "fork.include_dirty",
+        bool_str(cli.fork_include_dirty),
+        cli_source(cli, resolved, "fork.include_dirty", cli.fork_include_dirty),
+    ));
+    out.push(EffectiveSetting::new(
+        "fork.dissociate",
+        bool_str(cli.fork_dissociate),
+        cli_source(cli, resolved, "fork.dissociate", cli.fork_dissociate),
+    ));
+    out.push(EffectiveSetting::new(
+        "fork.merge_autoclean",
+        bool_str(cli.fork_merging_autoclean),
+        cli_source(
+            cli,
+            resolved,
+            "fork.merge_autoclean",
+            !cli.fork_merging_autoclean,
+        ),
+    ));
+
+    // Config-file [env] entries plus those only the selected profile sets.
+    let profile_env = cli
+        .profile_applied
+        .iter()
+        .filter_map(|k| k.strip_prefix("env."));
+    let names: std::collections::BTreeSet<&str> = resolved
+        .config
+        .env
+        .keys()
+        .map(String::as_str)
+        .chain(profile_env)
+        .collect();
+    for name in names {
+        let key = format!("env.{name}");
+        let var = format!("AIFO_ENV_{name}");
+        let source = if resolved.env_preset.contains(&var) {
+            aifo_coder::config_env_source(&var)
+        } else if cli.profile_applied.contains(&key) {
+            ConfigSource::Profile(cli.profile.clone().unwrap_or_default())
+        } else {
+            resolved
+                .origin(&key)
+                .cloned()
+                .unwrap_or(ConfigSource::Default)
+        };
+        let value = std::env::var(&var)
+            .ok()
+            .or_else(|| resolved.config.env.get(name).cloned())
+            .unwrap_or_default();
+        out.push(EffectiveSetting::new(&key, value, source));
+    }
+
+    out
+}
+
+fn display_value(v: &str) -> &str {
+    if v.is_empty() {
+        "(unset)"
+    } else {
+        v
+    }
+}
+
+fn print_show(cli: &Cli, resolved: &ResolvedConfig, json: bool) -> ExitCode {
+    let settings = effective_settings(cli, resolved);
+    if json {
+        let files: Vec<String> = resolved
+            .loaded
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect();
+        let doc = serde_json::json!({
+            "files": files,
+            "settings": settings.iter().map(EffectiveSetting::to_json).collect::<Vec<_>>(),
+        });
+        println!("{doc}");
+        return ExitCode::from(0);
+    }
+    if resolved.loaded.is_empty() {
+        println!("config files: (none)");
+    } else {
+        println!("config files:");
+        for p in &resolved.loaded {
+            println!("  {}", p.display());
+        }
+    }
+    println!();
+    let width = settings.iter().map(|s| s.key.len()).max().unwrap_or(0);
+    for s in &settings {
+        println!(
+            "{:<width$}  {}  [{}]",
+            s.key,
+            display_value(&s.value),
+            s.source.label(),
+            width = width
+        );
+    }
+    ExitCode::from(0)
+}
+
+fn print_explain(cli: &Cli, resolved: &ResolvedConfig, key: &str, json: bool) -> ExitCode {
+    let settings = effective_settings(cli, resolved);
+    let Some(s) = settings.iter().find(|s| s.key == key) else {
+        aifo_coder::log_error_stderr(
+            aifo_coder::color_enabled_stderr(),
+            &format!(
+                "aifo-coder: error: unknown config key '{key}'; run 'aifo-coder config show' to list keys"
+            ),
+        );
+        return ExitCode::from(1);
+    };
+    let layers = resolved.layer_values(key);
+    let default = aifo_coder::config_knob(key).map(|k| k.default);
+    if json {
+        let doc = serde_json::json!({
+            "key": s.key,
+            "value": s.value,
+            "source": s.source.label(),
+            "env": s.env,
+            "default": default,
+            "layers": layers
+                .iter()
+                .map(|(src, v)| serde_json::json!({
+                    "source": src.label(),
+                    "value": aifo_coder::config_value_to_env(v),
+                }))
+                .collect::<Vec<_>>(),
+        });
+        println!("{doc}");
+        return ExitCode::from(0);
+    }
+    println!("{}", s.key);
+    println!("  value:  {}", display_value(&s.value));
+    println!("  source: {}", s.source.label());
+    if let Some(var) = s.env {
+        println!("  env:    {var}");
+    }
+    if let Some(d) = default {
+        println!("  default: {}", display_value(d));
+    }
+    if layers.is_empty() {
+        println!("  config layers: (none set this key)");
+    } else {
+        println!("  config layers (lowest precedence first):");
+        for (src, v) in &layers {
+            println!(
+                "    {}: {}",
+                src.label(),
+                aifo_coder::config_value_to_env(v).unwrap_or_default()
+            );
+        }
+    }
+    ExitCode::from(0)
+}
+
+pub fn run_config(cli: &Cli, cmd: &ConfigCmd) -> ExitCode {
+    let fallback = ResolvedConfig::default();
+    let resolved = aifo_coder::config_resolved().unwrap_or(&fallback);
+    match cmd {
+        ConfigCmd::Show { json } => print_show(cli, resolved, *json),
+        ConfigCmd::Explain { key, json } => print_explain(cli, resolved, key, *json),
+    }
+}
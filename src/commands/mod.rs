@@ -4,6 +4,15 @@ use crate::cli::{Cli, ToolchainSpec};
 use crate::doctor::run_doctor;
 use crate::warnings::warn_if_tmp_workspace;
 
+mod apparmor;
+mod audit;
+mod config;
+mod security;
+pub use apparmor::run_apparmor;
+pub use audit::run_audit;
+pub use config::run_config;
+pub use security::run_security;
+
 pub fn images_effective() -> Vec<(String, String)> {
     // Keep order consistent with docs and tests
     let agents = [
@@ -22,7 +31,7 @@ pub fn images_effective() -> Vec<(String, String)> {
 
 pub fn run_images(cli: &Cli) -> std::process::ExitCode {
     let _ = cli; // silence unused for future extensions
-    print_startup_banner();
+    print_startup_banner(None);
     let _ = warn_if_tmp_workspace(false);
 
     let use_err = aifo_coder::color_enabled_stderr();
@@ -102,7 +111,7 @@ pub fn run_cache_clear(_cli: &Cli) -> std::process::ExitCode {
 }
 
 pub fn run_toolchain_cache_clear(cli: &Cli) -> std::process::ExitCode {
-    print_startup_banner();
+    print_startup_banner(None);
     let _ = warn_if_tmp_workspace(false);
     match aifo_coder::toolchain_purge_caches(cli.verbose) {
         Ok(()) => {
@@ -127,7 +136,7 @@ pub fn run_toolchain(
     no_cache: bool,
     args: Vec<String>,
 ) -> std::process::ExitCode {
-    print_startup_banner();
+    print_startup_banner(None);
     let use_err = aifo_coder::color_enabled_stderr();
     if !warn_if_tmp_workspace(true) {
         aifo_coder::log_error_stderr(use_err, "aborted.");
@@ -179,7 +188,7 @@ pub fn run_toolchain(
 }
 
 pub fn run_doctor_command(cli: &Cli) -> std::process::ExitCode {
-    print_startup_banner();
+    print_startup_banner(None);
     let _ = warn_if_tmp_workspace(false);
     run_doctor(cli.verbose);
     std::process::ExitCode::from(0)
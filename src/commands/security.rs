@@ -0,0 +1,36 @@
+//! `aifo-coder security ...`: inspect and export the security profiles applied to containers.
+
+use std::process::ExitCode;
+
+use crate::cli::SecurityCmd;
+
+pub fn run_security(cmd: &SecurityCmd) -> ExitCode {
+    match cmd {
+        SecurityCmd::ExportSeccomp { relaxed, output } => {
+            let json = aifo_coder::seccomp_profile_json(*relaxed);
+            match output {
+                Some(path) => match std::fs::write(path, json) {
+                    Ok(()) => {
+                        let use_err = aifo_coder::color_enabled_stderr();
+                        aifo_coder::log_info_stderr(
+                            use_err,
+                            &format!("aifo-coder: wrote seccomp profile to {}", path.display()),
+                        );
+                        ExitCode::from(0)
+                    }
+                    Err(e) => {
+                        aifo_coder::log_error_stderr(
+                            aifo_coder::color_enabled_stderr(),
+                            &format!("aifo-coder: error: cannot write {}: {}", path.display(), e),
+                        );
+                        ExitCode::from(1)
+                    }
+                },
+                None => {
+                    print!("{json}");
+                    ExitCode::from(0)
+                }
+            }
+        }
+    }
+}
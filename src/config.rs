@@ -0,0 +1,1059 @@
+#![allow(clippy::module_name_repetitions)]
+//! Layered launcher configuration: system → user → repo → env → CLI.
+//!
+//! Layers are TOML files:
+//! - system: /etc/aifo-coder/config.toml (override path via AIFO_CODER_SYSTEM_CONFIG)
+//! - user:   $XDG_CONFIG_HOME/aifo-coder/config.toml or ~/.config/aifo-coder/config.toml
+//!           (override path via AIFO_CODER_USER_CONFIG)
+//! - repo:   <repo-root>/.aifo-coder.toml
+//!
+//! Each file is validated against the typed schema on its own (so errors name the offending
+//! file) and then deep-merged; later layers win per key, arrays are replaced wholesale.
+//!
+//! The repo layer comes with the checkout, so it may not set sections that weaken isolation or
+//! choose what runs on the host (images, registries, runtime, security, network, proxy, mounts,
+//! LLM settings, resource limits), nor a profile's network, unless a system or user file lists
+//! the repository in `trusted_repos`. It may add agents and profiles but not change the ones a
+//! system or user file defines.
+//!
+//! Knobs that the launcher historically read from AIFO_* variables are exported into the
+//! process environment when still unset. Environment values therefore override files, CLI flags
+//! (applied afterwards in main) override both, and fork panes, sidecar startup and the proxy keep
+//! reading the same variables they always did. Set AIFO_CODER_NO_CONFIG=1 to skip all files.
+
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::limits::ResourceLimits;
+use crate::toolchain::{ExecLimitRule, ToolPolicyRule};
+
+/// Repo-local configuration file name (committed alongside the project).
+pub const REPO_CONFIG_FILE: &str = ".aifo-coder.toml";
+
+const SYSTEM_CONFIG_PATH: &str = "/etc/aifo-coder/config.toml";
+
+/// Keys a repo config file may never set, trusted or not: they run host commands, pull host
+/// files or host secrets into containers, or decide trust themselves.
+const REPO_DENIED_KEYS: &[(&str, &str)] = &[
+    ("trusted_repos", SYSTEM_OR_USER),
+    ("agents.*.config_dirs", SYSTEM_OR_USER),
+    ("agents.*.env_from", SYSTEM_OR_USER),
+    // Helper commands run via `sh -c` on the host.
+    ("llm.api_key_command", API_KEY_COMMAND_HINT),
+    ("llm.providers.*.api_key_command", API_KEY_COMMAND_HINT),
+];
+
+const SYSTEM_OR_USER: &str = "set it in the system or user config";
+const API_KEY_COMMAND_HINT: &str =
+    "set it in the system or user config, or export AIFO_API_KEY_COMMAND";
+
+/// Named entries a repo config file may add but not change once a system or user file defines
+/// them: merging key by key would let the checkout retarget a user's agent (image, bin, env)
+/// while it keeps the user's env_from and config_dirs, or loosen a user's profile.
+const REPO_OWNED_ENTRIES: &[&str] = &["agents", "profiles"];
+
+/// Sections a repo config file may only set when its repository is listed in `trusted_repos`.
+/// `[toolchains]` stays open: it picks sidecars whose images still come from the restricted
+/// image and registry settings, and bootstrap steps run inside the hardened sidecar.
+const REPO_TRUSTED_SECTIONS: &[&str] = &[
+    "image", "registry", "runtime", "security", "network", "proxy", "mounts", "llm", "limits",
+];
+
+/// Profile keys that stand in for a restricted section, under the same trust rule.
+const REPO_TRUSTED_KEYS: &[&str] = &["profiles.*.network", "profiles.*.network_isolate"];
+
+const TRUSTED_REPOS_HINT: &str =
+    "set it in the system or user config, or list this repository in trusted_repos there";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFlavor {
+    Full,
+    Slim,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImageConfig {
+    /// Agent image prefix (AIFO_CODER_IMAGE_PREFIX).
+    pub prefix: Option<String>,
+    /// Agent image tag (AIFO_CODER_IMAGE_TAG).
+    pub tag: Option<String>,
+    /// Image flavor (AIFO_CODER_IMAGE_FLAVOR).
+    pub flavor: Option<ImageFlavor>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RegistryConfig {
+    /// AIFO_CODER_INTERNAL_REGISTRY_PREFIX
+    pub internal_prefix: Option<String>,
+    /// AIFO_CODER_MIRROR_REGISTRY_PREFIX
+    pub mirror_prefix: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// Default for --docker-network (ignored when AIFO_SESSION_NETWORK is set).
+    pub name: Option<String>,
+    /// Default for --docker-network-isolate.
+    pub isolate: Option<bool>,
+    /// AIFO_CODER_EGRESS_ALLOWLIST: route egress through the allowlist proxy.
+    pub egress_allowlist: Option<bool>,
+    /// AIFO_CODER_EGRESS_ALLOW: extra allowed hosts (`*.example.com` matches subdomains).
+    pub egress_allow: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolchainsConfig {
+    /// Default --toolchain specs when none are given on the command line.
+    pub specs: Option<Vec<String>>,
+    /// Default --toolchain-bootstrap actions when none are given on the command line.
+    pub bootstrap: Option<Vec<String>>,
+    /// Default for --no-toolchain-cache.
+    pub no_cache: Option<bool>,
+    /// Default for --toolchain-unix-socket.
+    pub unix_socket: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuntimeConfig {
+    /// Container engine: docker, podman or auto (AIFO_CODER_CONTAINER_RUNTIME).
+    pub engine: Option<String>,
+}
+
+/// `[security]`: container hardening.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SecurityConfig {
+    /// AIFO_CODER_HARDENED: cap-drop ALL, no-new-privileges and a read-only root.
+    pub hardened: Option<bool>,
+    /// AIFO_CODER_CAP_ADD: capabilities kept in hardened mode.
+    pub cap_add: Option<Vec<String>>,
+    /// AIFO_CODER_SECCOMP: aifo (generated profile), engine or unconfined.
+    pub seccomp: Option<String>,
+    /// AIFO_TOOLCHAIN_SECCOMP_RELAXED: toolchain kinds given the relaxed profile.
+    pub seccomp_relaxed: Option<Vec<String>>,
+}
+
+/// `[llm]`: how agents reach the LLM endpoint.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmConfig {
+    /// AIFO_CODER_LLM_GATEWAY: keep the API key on the host behind the credential gateway.
+    pub gateway: Option<bool>,
+    /// AIFO_CODER_LLM_PROVIDER: provider whose `[llm.providers.NAME]` entry applies.
+    pub provider: Option<String>,
+    /// AIFO_API_KEY_COMMAND: host command printing the API key when AIFO_API_KEY is unset.
+    /// System and user config only.
+    pub api_key_command: Option<String>,
+    /// Per-provider settings (`[llm.providers.NAME]`).
+    pub providers: BTreeMap<String, LlmProviderConfig>,
+}
+
+/// `[llm.providers.NAME]`: settings used while NAME is the selected provider.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmProviderConfig {
+    /// Host command printing this provider's API key; beats `[llm] api_key_command`.
+    /// System and user config only.
+    pub api_key_command: Option<String>,
+}
+
+/// `[limits]`: resource limits for agent containers and toolchain sidecars.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// Defaults for every agent container.
+    pub agent: ResourceLimits,
+    /// Per-agent overrides (`[limits.agents.NAME]`).
+    pub agents: BTreeMap<String, ResourceLimits>,
+    /// Defaults for every toolchain sidecar.
+    pub toolchain: ResourceLimits,
+    /// Per-kind overrides (`[limits.toolchains.KIND]`).
+    pub toolchains: BTreeMap<String, ResourceLimits>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProxyConfig {
+    /// AIFO_TOOLEEXEC_MAX_SECS
+    pub max_secs: Option<u64>,
+    /// AIFO_TOOLEEXEC_TIMEOUT_SECS
+    pub timeout_secs: Option<u64>,
+    /// AIFO_TOOLEEXEC_MAX_CONNECTIONS
+    pub max_connections: Option<u64>,
+    /// AIFO_TOOLEEXEC_WRITE_TIMEOUT_SECS
+    pub write_timeout_secs: Option<u64>,
+    /// AIFO_TOOLEEXEC_BIND_HOST
+    pub bind_host: Option<String>,
+    /// AIFO_TOOLEEXEC_TLS (mutual TLS with session certificates for the TCP proxy)
+    pub tls: Option<bool>,
+    /// AIFO_TOOLEEXEC_APPROVAL (auto, tty, command or deny)
+    pub approval: Option<String>,
+    /// AIFO_TOOLEEXEC_APPROVAL_TIMEOUT_SECS
+    pub approval_timeout_secs: Option<u64>,
+    /// AIFO_TOOLEEXEC_AUDIT (false disables the JSONL audit log)
+    pub audit: Option<bool>,
+    /// AIFO_TOOLEEXEC_AUDIT_DIR
+    pub audit_dir: Option<String>,
+    /// AIFO_TOOLEEXEC_RECORD ("1" for the default transcript file, or a path)
+    pub record: Option<String>,
+    /// AIFO_TOOLEEXEC_RECORD_MAX_BYTES
+    pub record_max_bytes: Option<u64>,
+    /// AIFO_TOOLEEXEC_REPLAY (transcript served instead of running tools)
+    pub replay: Option<String>,
+    /// AIFO_TOOLEEXEC_TOKEN_TTL_SECS (lifetime of scoped agent tokens; 0 never expires)
+    pub token_ttl_secs: Option<u64>,
+    /// AIFO_TOOLEEXEC_TOKEN_SCOPES: exec, notify, signal, status, token
+    pub token_scopes: Option<Vec<String>>,
+    /// AIFO_TOOLEEXEC_TOKEN_KINDS: sidecar kinds the agent token may exec in
+    pub token_kinds: Option<Vec<String>>,
+    /// AIFO_TOOLEEXEC_REQUIRE_SIGNED (refuse requests without X-Aifo-Signature; launcher default on)
+    pub require_signed: Option<bool>,
+    /// AIFO_TOOLEEXEC_ENV_ALLOW: extra agent variables forwarded to execs (`*` wildcards)
+    pub env_allow: Option<Vec<String>>,
+    /// AIFO_TOOLEEXEC_ENV_DENY: agent variables never forwarded (`*` disables forwarding)
+    pub env_deny: Option<Vec<String>>,
+    /// AIFO_TOOLEEXEC_IDLE_SECS: stop execs without output for this long (0 disables)
+    pub idle_secs: Option<u64>,
+    /// AIFO_TOOLEEXEC_MAX_OUTPUT_BYTES: truncate and stop execs past this output (0 disables)
+    pub max_output_bytes: Option<u64>,
+    /// Per-tool argument rules (`[[proxy.policy.TOOL]]`), applied before each exec.
+    pub policy: BTreeMap<String, Vec<ToolPolicyRule>>,
+    /// Per-tool runtime/idle/output limits (`[[proxy.limits.TOOL]]`).
+    pub limits: BTreeMap<String, Vec<ExecLimitRule>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MountsConfig {
+    /// AIFO_CONFIG_HOST_DIR
+    pub config_host_dir: Option<String>,
+    /// AIFO_CONFIG_MAX_SIZE
+    pub config_max_size: Option<u64>,
+    /// AIFO_CONFIG_ALLOW_EXT
+    pub config_allow_ext: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ForkConfig {
+    /// Default for --fork-layout.
+    pub layout: Option<String>,
+    /// Default for --fork-include-dirty.
+    pub include_dirty: Option<bool>,
+    /// Default for --fork-dissociate.
+    pub dissociate: Option<bool>,
+    /// Set to false to behave as if --fork-merge-no-autoclean was given.
+    pub merge_autoclean: Option<bool>,
+    /// AIFO_CODER_FORK_STALE_DAYS
+    pub stale_days: Option<u64>,
+    /// AIFO_CODER_FORK_AUTOCLEAN
+    pub autoclean: Option<bool>,
+}
+
+/// A named bundle of CLI defaults selected with `--profile NAME` (`[profiles.NAME]`).
+///
+/// Profile values fill options not given on the command line and take precedence over the
+/// plain config sections and AIFO_* environment defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProfileConfig {
+    /// Agent to run when no agent subcommand is given.
+    pub agent: Option<String>,
+    /// --flavor
+    pub flavor: Option<ImageFlavor>,
+    /// --toolchain (repeatable), e.g. ["rust@1.80", "node"]
+    pub toolchains: Option<Vec<String>>,
+    /// --toolchain-bootstrap (repeatable), e.g. ["typescript=global"]
+    pub bootstrap: Option<Vec<String>>,
+    /// --no-toolchain-cache
+    pub no_toolchain_cache: Option<bool>,
+    /// --toolchain-unix-socket
+    pub toolchain_unix_socket: Option<bool>,
+    /// --docker-network
+    pub network: Option<String>,
+    /// --docker-network-isolate
+    pub network_isolate: Option<bool>,
+    /// --fork-layout
+    pub fork_layout: Option<String>,
+    /// --fork-include-dirty
+    pub fork_include_dirty: Option<bool>,
+    /// --fork-dissociate
+    pub fork_dissociate: Option<bool>,
+    /// Set to false to behave as if --fork-merge-no-autoclean was given.
+    pub fork_merge_autoclean: Option<bool>,
+    /// Extra container environment exported as AIFO_ENV_<NAME>.
+    pub env: BTreeMap<String, String>,
+}
+
+/// Built-in agent names; custom agents may not reuse them.
+pub const BUILTIN_AGENTS: &[&str] = &[
+    "codex",
+    "crush",
+    "aider",
+    "openhands",
+    "opencode",
+    "plandex",
+    "letta",
+];
+
+/// Runtime a custom agent executes locally; the smart shims keep it out of toolchain sidecars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentRuntime {
+    Node,
+    Python,
+    None,
+}
+
+/// A user-defined agent (`[agents.NAME]`), run with `aifo-coder run NAME -- ARGS`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CustomAgentConfig {
+    /// Full image reference; defaults to {prefix}-{name}[-slim]:{tag} like built-in agents.
+    pub image: Option<String>,
+    /// Absolute path of the agent binary inside the image; defaults to NAME resolved via PATH.
+    pub bin: Option<String>,
+    /// PATH inside the container; defaults to the shim-first PATH of built-in agents.
+    pub path: Option<String>,
+    /// Agent runtime (node, python or none) selecting the smart-shim flavor.
+    pub runtime: Option<AgentRuntime>,
+    /// Host directories (absolute or ~/...) whose small config files are staged for the agent.
+    /// System and user config only.
+    pub config_dirs: Vec<String>,
+    /// Fixed container environment.
+    pub env: BTreeMap<String, String>,
+    /// Container variables copied from host variables: CONTAINER_VAR = "HOST_VAR".
+    /// System and user config only.
+    pub env_from: BTreeMap<String, String>,
+    /// LLM provider variables the agent reads (e.g. ["OPENAI_API_KEY"]); default: all of them.
+    pub llm_env: Option<Vec<String>>,
+}
+
+fn is_env_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn validate_custom_agent(name: &str, agent: &CustomAgentConfig) -> Result<(), String> {
+    let valid_name = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid_name {
+        return Err("name must use lowercase letters, digits, '-' or '_'".to_string());
+    }
+    if BUILTIN_AGENTS.contains(&name) {
+        return Err("name is reserved for a built-in agent".to_string());
+    }
+    if let Some(bin) = agent.bin.as_deref() {
+        if !bin.starts_with('/') {
+            return Err(format!("bin must be an absolute path, got '{bin}'"));
+        }
+    }
+    for k in agent
+        .env
+        .keys()
+        .chain(agent.env_from.keys())
+        .chain(agent.llm_env.iter().flatten())
+    {
+        if !is_env_name(k) {
+            return Err(format!("invalid environment variable name '{k}'"));
+        }
+    }
+    Ok(())
+}
+
+/// Typed view of the merged configuration files.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AifoConfig {
+    pub image: ImageConfig,
+    pub registry: RegistryConfig,
+    pub runtime: RuntimeConfig,
+    pub security: SecurityConfig,
+    pub llm: LlmConfig,
+    pub limits: LimitsConfig,
+    pub network: NetworkConfig,
+    pub toolchains: ToolchainsConfig,
+    pub proxy: ProxyConfig,
+    pub mounts: MountsConfig,
+    pub fork: ForkConfig,
+    /// Extra container environment; each entry is exported as AIFO_ENV_<NAME>.
+    pub env: BTreeMap<String, String>,
+    /// Named run profiles; same-named profiles from several files merge field by field.
+    pub profiles: BTreeMap<String, ProfileConfig>,
+    /// User-defined agents run with `aifo-coder run NAME`.
+    pub agents: BTreeMap<String, CustomAgentConfig>,
+    /// Repository roots (absolute or ~/...) whose repo config may set restricted sections.
+    /// Only honored in the system and user files.
+    pub trusted_repos: Vec<String>,
+}
+
+/// Where an effective value came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    Default,
+    System(PathBuf),
+    User(PathBuf),
+    Repo(PathBuf),
+    /// Loaded from a .env file by dotenvy (only fills variables that were unset).
+    DotEnv(PathBuf),
+    Env,
+    Cli,
+    /// Filled from the `--profile` selected on the command line.
+    Profile(String),
+    /// Computed at runtime (registry probe/disk cache, image lookup, session id).
+    Derived(String),
+}
+
+impl ConfigSource {
+    /// Short label used in diagnostics (e.g., "repo:/path/.aifo-coder.toml").
+    pub fn label(&self) -> String {
+        match self {
+            ConfigSource::Default => "default".to_string(),
+            ConfigSource::System(p) => format!("system:{}", p.display()),
+            ConfigSource::User(p) => format!("user:{}", p.display()),
+            ConfigSource::Repo(p) => format!("repo:{}", p.display()),
+            ConfigSource::DotEnv(p) => format!("dotenv:{}", p.display()),
+            ConfigSource::Env => "env".to_string(),
+            ConfigSource::Cli => "cli".to_string(),
+            ConfigSource::Profile(name) => format!("profile:{name}"),
+            ConfigSource::Derived(what) => format!("derived:{what}"),
+        }
+    }
+}
+
+/// Config file locations considered by the loader, lowest precedence first.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigPaths {
+    pub system: Option<PathBuf>,
+    pub user: Option<PathBuf>,
+    pub repo: Option<PathBuf>,
+}
+
+/// A config key that feeds an AIFO_* variable read elsewhere in the launcher.
+#[derive(Debug, Clone, Copy)]
+pub struct ConfigKnob {
+    /// Dotted config key (e.g., "proxy.max_secs").
+    pub key: &'static str,
+    /// Environment variable the key is exported to.
+    pub env: &'static str,
+    /// Value the launcher uses when neither a file nor the environment sets it.
+    pub default: &'static str,
+}
+
+const fn knob(key: &'static str, env: &'static str, default: &'static str) -> ConfigKnob {
+    ConfigKnob { key, env, default }
+}
+
+/// Keys exported to the environment, with the defaults applied by their readers.
+pub const CONFIG_ENV_KNOBS: &[ConfigKnob] = &[
+    knob("image.prefix", "AIFO_CODER_IMAGE_PREFIX", "aifo-coder"),
+    knob(
+        "image.tag",
+        "AIFO_CODER_IMAGE_TAG",
+        concat!("release-", env!("CARGO_PKG_VERSION")),
+    ),
+    knob("image.flavor", "AIFO_CODER_IMAGE_FLAVOR", "full"),
+    knob(
+        "registry.internal_prefix",
+        "AIFO_CODER_INTERNAL_REGISTRY_PREFIX",
+        "",
+    ),
+    knob(
+        "registry.mirror_prefix",
+        "AIFO_CODER_MIRROR_REGISTRY_PREFIX",
+        "",
+    ),
+    knob("runtime.engine", "AIFO_CODER_CONTAINER_RUNTIME", "auto"),
+    knob(
+        "network.egress_allowlist",
+        "AIFO_CODER_EGRESS_ALLOWLIST",
+        "0",
+    ),
+    knob("network.egress_allow", "AIFO_CODER_EGRESS_ALLOW", ""),
+    knob("security.hardened", "AIFO_CODER_HARDENED", "0"),
+    knob(
+        "security.cap_add",
+        "AIFO_CODER_CAP_ADD",
+        "CHOWN,DAC_OVERRIDE,FOWNER,SETGID,SETUID",
+    ),
+    knob("security.seccomp", "AIFO_CODER_SECCOMP", "aifo"),
+    knob(
+        "security.seccomp_relaxed",
+        "AIFO_TOOLCHAIN_SECCOMP_RELAXED",
+        "",
+    ),
+    knob("llm.gateway", "AIFO_CODER_LLM_GATEWAY", "0"),
+    knob("llm.provider", "AIFO_CODER_LLM_PROVIDER", ""),
+    knob("llm.api_key_command", "AIFO_API_KEY_COMMAND", ""),
+    knob("proxy.max_secs", "AIFO_TOOLEEXEC_MAX_SECS", "300"),
+    knob("proxy.timeout_secs", "AIFO_TOOLEEXEC_TIMEOUT_SECS", ""),
+    knob(
+        "proxy.max_connections",
+        "AIFO_TOOLEEXEC_MAX_CONNECTIONS",
+        "64",
+    ),
+    knob(
+        "proxy.write_timeout_secs",
+        "AIFO_TOOLEEXEC_WRITE_TIMEOUT_SECS",
+        "60",
+    ),
+    knob("proxy.idle_secs", "AIFO_TOOLEEXEC_IDLE_SECS", "0"),
+    knob(
+        "proxy.max_output_bytes",
+        "AIFO_TOOLEEXEC_MAX_OUTPUT_BYTES",
+        "0",
+    ),
+    knob("proxy.bind_host", "AIFO_TOOLEEXEC_BIND_HOST", "127.0.0.1"),
+    knob("proxy.tls", "AIFO_TOOLEEXEC_TLS", "0"),
+    knob("proxy.approval", "AIFO_TOOLEEXEC_APPROVAL", "auto"),
+    knob(
+        "proxy.approval_timeout_secs",
+        "AIFO_TOOLEEXEC_APPROVAL_TIMEOUT_SECS",
+        "120",
+    ),
+    knob("proxy.audit", "AIFO_TOOLEEXEC_AUDIT", "1"),
+    knob("proxy.audit_dir", "AIFO_TOOLEEXEC_AUDIT_DIR", ""),
+    knob("proxy.record", "AIFO_TOOLEEXEC_RECORD", ""),
+    knob(
+        "proxy.record_max_bytes",
+        "AIFO_TOOLEEXEC_RECORD_MAX_BYTES",
+        "8388608",
+    ),
+    knob("proxy.replay", "AIFO_TOOLEEXEC_REPLAY", ""),
+    knob("proxy.token_ttl_secs", "AIFO_TOOLEEXEC_TOKEN_TTL_SECS", "0"),
+    knob("proxy.token_scopes", "AIFO_TOOLEEXEC_TOKEN_SCOPES", ""),
+    knob("proxy.token_kinds", "AIFO_TOOLEEXEC_TOKEN_KINDS", ""),
+    knob("proxy.require_signed", "AIFO_TOOLEEXEC_REQUIRE_SIGNED", "1"),
+    knob("proxy.env_allow", "AIFO_TOOLEEXEC_ENV_ALLOW", ""),
+    knob("proxy.env_deny", "AIFO_TOOLEEXEC_ENV_DENY", ""),
+    knob("mounts.config_host_dir", "AIFO_CONFIG_HOST_DIR", ""),
+    knob("mounts.config_max_size", "AIFO_CONFIG_MAX_SIZE", "262144"),
+    knob(
+        "mounts.config_allow_ext",
+        "AIFO_CONFIG_ALLOW_EXT",
+        "json,toml,yaml,yml,ini,conf,crt,pem,key,token,gitconfig",
+    ),
+    knob("fork.stale_days", "AIFO_CODER_FORK_STALE_DAYS", "30"),
+    knob("fork.autoclean", "AIFO_CODER_FORK_AUTOCLEAN", "0"),
+];
+
+/// Find the knob for a dotted key.
+pub fn config_knob(key: &str) -> Option<&'static ConfigKnob> {
+    CONFIG_ENV_KNOBS.iter().find(|k| k.key == key)
+}
+
+/// Merged configuration plus per-key provenance.
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedConfig {
+    /// Typed merged view of all file layers.
+    pub config: AifoConfig,
+    /// Raw merged TOML (used to render values generically).
+    pub merged: toml::Table,
+    /// Dotted key → file layer that supplied the winning value.
+    pub origins: BTreeMap<String, ConfigSource>,
+    /// Files that were found and loaded, lowest precedence first.
+    pub loaded: Vec<PathBuf>,
+    /// Knob env vars that were already set before file values were exported.
+    pub env_preset: BTreeSet<String>,
+    /// Each loaded layer on its own, lowest precedence first (used by `config explain`).
+    pub layers: Vec<(ConfigSource, toml::Table)>,
+}
+
+impl ResolvedConfig {
+    /// Lookup a merged value by dotted key (e.g., "proxy.max_secs").
+    pub fn value(&self, key: &str) -> Option<&toml::Value> {
+        let mut parts = key.split('.');
+        let first = parts.next()?;
+        let mut cur = self.merged.get(first)?;
+        for p in parts {
+            cur = cur.as_table()?.get(p)?;
+        }
+        Some(cur)
+    }
+
+    /// Source of the winning file value for a dotted key, if any file set it.
+    pub fn origin(&self, key: &str) -> Option<&ConfigSource> {
+        self.origins.get(key)
+    }
+
+    /// Values each loaded layer assigns to a dotted key, lowest precedence first.
+    pub fn layer_values(&self, key: &str) -> Vec<(ConfigSource, toml::Value)> {
+        let mut out = Vec::new();
+        for (source, table) in &self.layers {
+            let mut parts = key.split('.');
+            let mut cur = parts.next().and_then(|first| table.get(first));
+            for p in parts {
+                cur = cur.and_then(|v| v.as_table()).and_then(|t| t.get(p));
+            }
+            if let Some(v) = cur {
+                out.push((source.clone(), v.clone()));
+            }
+        }
+        out
+    }
+
+    /// Invalid-data error "WHAT (FILE): MSG" naming the last file layer that set KEY.
+    fn invalid_value(&self, key: &str, what: &str, msg: String) -> io::Error {
+        let file = self
+            .layer_values(key)
+            .last()
+            .map(|(src, _)| src.label())
+            .unwrap_or_else(|| "config".to_string());
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{what} ({file}): {msg}"),
+        )
+    }
+
+    /// Effective value and provenance of an env-backed knob for this process.
+    ///
+    /// Call after `apply_to_env`; variables that were set before files were exported are
+    /// attributed to the environment (or the .env file that provided them).
+    pub fn knob_setting(&self, knob: &ConfigKnob) -> EffectiveSetting {
+        let current = env::var(knob.env).ok();
+        let (value, source) = match current {
+            Some(v) if !self.env_preset.contains(knob.env) && self.origin(knob.key).is_some() => (
+                v,
+                self.origin(knob.key).cloned().unwrap_or(ConfigSource::Env),
+            ),
+            Some(v) => (v, config_env_source(knob.env)),
+            None => (knob.default.to_string(), ConfigSource::Default),
+        };
+        EffectiveSetting {
+            key: knob.key.to_string(),
+            value,
+            source,
+            env: Some(knob.env),
+        }
+    }
+
+    /// Export file-provided knobs into the process environment when the variable is unset.
+    ///
+    /// Returns the env var names that were set.
+    pub fn apply_to_env(&mut self) -> Vec<String> {
+        let mut set = Vec::new();
+        for knob in CONFIG_ENV_KNOBS {
+            if env::var_os(knob.env).is_some() {
+                self.env_preset.insert(knob.env.to_string());
+                continue;
+            }
+            if let Some(v) = self.value(knob.key).and_then(config_value_to_env) {
+                env::set_var(knob.env, v);
+                set.push(knob.env.to_string());
+            }
+        }
+        for (name, val) in &self.config.env {
+            let var = format!("AIFO_ENV_{name}");
+            if env::var_os(&var).is_some() {
+                self.env_preset.insert(var);
+                continue;
+            }
+            env::set_var(&var, val);
+            set.push(var);
+        }
+        set
+    }
+}
+
+/// Render a scalar/array TOML value the way AIFO_* variables expect it.
+pub fn config_value_to_env(v: &toml::Value) -> Option<String> {
+    match v {
+        toml::Value::String(s) => Some(s.clone()),
+        toml::Value::Integer(i) => Some(i.to_string()),
+        toml::Value::Float(f) => Some(f.to_string()),
+        toml::Value::Boolean(b) => Some(if *b { "1" } else { "0" }.to_string()),
+        toml::Value::Array(items) => {
+            let parts: Option<Vec<String>> = items.iter().map(config_value_to_env).collect();
+            parts.map(|p| p.join(","))
+        }
+        toml::Value::Datetime(d) => Some(d.to_string()),
+        toml::Value::Table(_) => None,
+    }
+}
+
+fn env_path(name: &str) -> Option<PathBuf> {
+    env::var(name)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Per-user config file location (does not check existence).
+pub fn user_config_path() -> Option<PathBuf> {
+    if let Some(p) = env_path("AIFO_CODER_USER_CONFIG") {
+        return Some(p);
+    }
+    let base = env_path("XDG_CONFIG_HOME").or_else(|| home::home_dir().map(|h| h.join(".config")));
+    base.map(|b| b.join("aifo-coder").join("config.toml"))
+}
+
+/// Discover config file locations for the current process (does not check existence).
+pub fn config_discover_paths() -> ConfigPaths {
+    let system =
+        env_path("AIFO_CODER_SYSTEM_CONFIG").or_else(|| Some(PathBuf::from(SYSTEM_CONFIG_PATH)));
+    let repo = crate::repo_root().map(|r| r.join(REPO_CONFIG_FILE));
+    ConfigPaths {
+        system,
+        user: user_config_path(),
+        repo,
+    }
+}
+
+fn parse_layer(path: &Path) -> io::Result<Option<toml::Table>> {
+    let content = match fs::read_to_string(path) {
+        Ok(s) => s,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(io::Error::new(
+                e.kind(),
+                format!("cannot read config file {}: {}", path.display(), e),
+            ))
+        }
+    };
+    let invalid = |msg: String| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid config file {}: {}", path.display(), msg.trim_end()),
+        )
+    };
+    let table: toml::Table = toml::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+    // Validate the layer on its own so the error names the file that introduced the problem.
+    AifoConfig::deserialize(toml::Value::Table(table.clone()))
+        .map_err(|e| invalid(e.to_string()))?;
+    Ok(Some(table))
+}
+
+/// First dotted key in TABLE matching PATTERN, where a `*` segment matches any key.
+fn find_key(table: &toml::Table, pattern: &str) -> Option<String> {
+    let (head, rest) = match pattern.split_once('.') {
+        Some((h, r)) => (h, Some(r)),
+        None => (pattern, None),
+    };
+    for (k, v) in table
+        .iter()
+        .filter(|(k, _)| head == "*" || k.as_str() == head)
+    {
+        match rest {
+            None => return Some(k.clone()),
+            Some(rest) => {
+                if let Some(found) = v.as_table().and_then(|t| find_key(t, rest)) {
+                    return Some(format!("{k}.{found}"));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Whether the repository owning REPO_FILE is listed in `trusted_repos` of the layers so far.
+fn repo_trusted(merged: &toml::Table, repo_file: &Path) -> bool {
+    let Some(root) = repo_file.parent() else {
+        return false;
+    };
+    let canon = |p: &Path| fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+    let root = canon(root);
+    merged
+        .get("trusted_repos")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str())
+        .any(|entry| {
+            let entry = match entry.strip_prefix("~/") {
+                Some(rest) => match home::home_dir() {
+                    Some(h) => h.join(rest),
+                    None => return false,
+                },
+                None => PathBuf::from(entry),
+            };
+            canon(&entry) == root
+        })
+}
+
+/// Refuse repo-layer keys that only the system and user files may set; MERGED holds those layers.
+fn check_repo_layer(
+    path: &Path,
+    table: &toml::Table,
+    merged: &toml::Table,
+    trusted: bool,
+) -> io::Result<()> {
+    let refuse = |key: String, hint: &str| {
+        io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!(
+                "invalid config file {}: '{key}' may not be set in a repository config file; {hint}",
+                path.display()
+            ),
+        )
+    };
+    for (pattern, hint) in REPO_DENIED_KEYS {
+        if let Some(key) = find_key(table, pattern) {
+            return Err(refuse(key, hint));
+        }
+    }
+    for section in REPO_OWNED_ENTRIES {
+        let (Some(repo), Some(base)) = (
+            table.get(*section).and_then(|v| v.as_table()),
+            merged.get(*section).and_then(|v| v.as_table()),
+        ) else {
+            continue;
+        };
+        if let Some(name) = repo.keys().find(|name| base.contains_key(*name)) {
+            return Err(refuse(
+                format!("{section}.{name}"),
+                "it is defined in the system or user config; change it there or pick another name",
+            ));
+        }
+    }
+    if !trusted {
+        for section in REPO_TRUSTED_SECTIONS {
+            if table.contains_key(*section) {
+                return Err(refuse(section.to_string(), TRUSTED_REPOS_HINT));
+            }
+        }
+        for pattern in REPO_TRUSTED_KEYS {
+            if let Some(key) = find_key(table, pattern) {
+                return Err(refuse(key, TRUSTED_REPOS_HINT));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn merge_table(
+    dst: &mut toml::Table,
+    src: toml::Table,
+    prefix: &str,
+    source: &ConfigSource,
+    origins: &mut BTreeMap<String, ConfigSource>,
+) {
+    for (k, v) in src {
+        let key = if prefix.is_empty() {
+            k.clone()
+        } else {
+            format!("{prefix}.{k}")
+        };
+        match v {
+            toml::Value::Table(t) => {
+                let entry = dst
+                    .entry(k)
+                    .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+                if !entry.is_table() {
+                    *entry = toml::Value::Table(toml::Table::new());
+                }
+                if let toml::Value::Table(inner) = entry {
+                    merge_table(inner, t, &key, source, origins);
+                }
+            }
+            other => {
+                dst.insert(k, other);
+                origins.insert(key, source.clone());
+            }
+        }
+    }
+}
+
+/// Load and merge the given layers without touching the process environment.
+pub fn config_load_from(paths: &ConfigPaths) -> io::Result<ResolvedConfig> {
+    let mut resolved = ResolvedConfig::default();
+    let layers = [
+        paths.system.clone().map(ConfigSource::System),
+        paths.user.clone().map(ConfigSource::User),
+        paths.repo.clone().map(ConfigSource::Repo),
+    ];
+    for source in layers.into_iter().flatten() {
+        let path = match &source {
+            ConfigSource::System(p) | ConfigSource::User(p) | ConfigSource::Repo(p) => p.clone(),
+            ConfigSource::Default
+            | ConfigSource::DotEnv(_)
+            | ConfigSource::Env
+            | ConfigSource::Cli
+            | ConfigSource::Profile(_)
+            | ConfigSource::Derived(_) => continue,
+        };
+        if let Some(table) = parse_layer(&path)? {
+            if matches!(source, ConfigSource::Repo(_)) {
+                let trusted = repo_trusted(&resolved.merged, &path);
+                check_repo_layer(&path, &table, &resolved.merged, trusted)?;
+            }
+            resolved.layers.push((source.clone(), table.clone()));
+            merge_table(
+                &mut resolved.merged,
+                table,
+                "",
+                &source,
+                &mut resolved.origins,
+            );
+            resolved.loaded.push(path);
+        }
+    }
+    resolved.config = AifoConfig::deserialize(toml::Value::Table(resolved.merged.clone()))
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    for (name, agent) in &resolved.config.agents {
+        validate_custom_agent(name, agent).map_err(|msg| {
+            resolved.invalid_value(
+                &format!("agents.{name}"),
+                &format!("invalid agent '{name}'"),
+                msg,
+            )
+        })?;
+    }
+    for cap in resolved.config.security.cap_add.iter().flatten() {
+        crate::hardening::parse_capability(cap)
+            .map_err(|msg| resolved.invalid_value("security.cap_add", "[security]", msg))?;
+    }
+    if let Some(mode) = resolved.config.security.seccomp.as_deref() {
+        crate::seccomp::parse_seccomp_mode(mode)
+            .map_err(|msg| resolved.invalid_value("security.seccomp", "[security]", msg))?;
+    }
+    for host in resolved.config.network.egress_allow.iter().flatten() {
+        crate::egress::parse_egress_host(host)
+            .map_err(|msg| resolved.invalid_value("network.egress_allow", "[network]", msg))?;
+    }
+    let llm = &resolved.config.llm;
+    for (key, name) in llm
+        .provider
+        .iter()
+        .map(|p| ("llm.provider".to_string(), p))
+        .chain(
+            llm.providers
+                .keys()
+                .map(|p| (format!("llm.providers.{p}"), p)),
+        )
+    {
+        crate::llm_providers::llm_provider_spec(name)
+            .map_err(|msg| resolved.invalid_value(&key, "[llm]", msg))?;
+    }
+    let limits = &resolved.config.limits;
+    let sections = [("limits.agent".to_string(), &limits.agent)]
+        .into_iter()
+        .chain(
+            limits
+                .agents
+                .iter()
+                .map(|(k, v)| (format!("limits.agents.{k}"), v)),
+        )
+        .chain([("limits.toolchain".to_string(), &limits.toolchain)])
+        .chain(
+            limits
+                .toolchains
+                .iter()
+                .map(|(k, v)| (format!("limits.toolchains.{k}"), v)),
+        );
+    for (key, spec) in sections {
+        spec.validate()
+            .map_err(|msg| resolved.invalid_value(&key, &format!("[{key}]"), msg))?;
+    }
+    Ok(resolved)
+}
+
+/// One effective launcher setting with its provenance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EffectiveSetting {
+    pub key: String,
+    pub value: String,
+    pub source: ConfigSource,
+    /// Environment variable backing this setting, when there is one.
+    pub env: Option<&'static str>,
+}
+
+impl EffectiveSetting {
+    pub fn new(key: &str, value: impl Into<String>, source: ConfigSource) -> Self {
+        Self {
+            key: key.to_string(),
+            value: value.into(),
+            source,
+            env: None,
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "key": self.key,
+            "value": self.value,
+            "source": self.source.label(),
+            "env": self.env,
+        })
+    }
+}
+
+static DOTENV_LOADED: OnceCell<(PathBuf, BTreeSet<String>)> = OnceCell::new();
+
+/// Load the nearest .env file (cwd or a parent) without overriding existing variables.
+///
+/// Records which keys it filled so provenance can distinguish .env from the real environment.
+pub fn config_load_dotenv() {
+    let Ok(cwd) = env::current_dir() else {
+        return;
+    };
+    let Some(path) = cwd
+        .ancestors()
+        .map(|d| d.join(".env"))
+        .find(|p| p.is_file())
+    else {
+        return;
+    };
+    let Ok(iter) = dotenvy::from_path_iter(&path) else {
+        return;
+    };
+    let mut filled = BTreeSet::new();
+    for (k, v) in iter.flatten() {
+        if env::var_os(&k).is_none() {
+            env::set_var(&k, v);
+            filled.insert(k);
+        }
+    }
+    let _ = DOTENV_LOADED.set((path, filled));
+}
+
+/// Provenance of a variable present in the process environment.
+pub fn config_env_source(var: &str) -> ConfigSource {
+    match DOTENV_LOADED.get() {
+        Some((path, keys)) if keys.contains(var) => ConfigSource::DotEnv(path.clone()),
+        _ => ConfigSource::Env,
+    }
+}
+
+static RESOLVED_CONFIG: OnceCell<ResolvedConfig> = OnceCell::new();
+
+/// Load configuration files for this process once and export knobs into the environment.
+///
+/// Subsequent calls return the cached result.
+pub fn config_init() -> io::Result<&'static ResolvedConfig> {
+    RESOLVED_CONFIG.get_or_try_init(|| {
+        if env::var("AIFO_CODER_NO_CONFIG").ok().as_deref() == Some("1") {
+            return Ok(ResolvedConfig::default());
+        }
+        let mut resolved = config_load_from(&config_discover_paths())?;
+        resolved.apply_to_env();
+        Ok(resolved)
+    })
+}
+
+/// Custom agent definition from the loaded configuration, if NAME is one.
+pub fn config_custom_agent(name: &str) -> Option<&'static CustomAgentConfig> {
+    config_resolved()?.config.agents.get(name)
+}
+
+/// The configuration loaded by `config_init`, or None when it has not run.
+pub fn config_resolved() -> Option<&'static ResolvedConfig> {
+    RESOLVED_CONFIG.get()
+}
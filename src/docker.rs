@@ -6,6 +6,8 @@
 
 pub use crate::docker_mod::{
     build_docker_cmd, build_docker_preview_args_only, build_docker_preview_only,
-    cleanup_aider_staging_from_env, compute_effective_agent_image_for_run, container_runtime_path,
-    format_image_metadata, image_exists, image_metadata,
+    cleanup_aider_staging_from_env, compute_effective_agent_image_for_run, container_runtime,
+    container_runtime_kind, container_runtime_path, format_image_metadata, image_exists,
+    image_metadata, runtime_for_program, ContainerRuntime, DockerRuntime, FakeRuntime, FakeState,
+    PodmanRuntime, RuntimeIo, RuntimeKind, RuntimeOutput, FAKE_NETWORK_GATEWAY,
 };
@@ -5,6 +5,7 @@
 //! organized by responsibility. Public APIs are re-exported by `docker_mod`.
 
 pub(crate) mod env;
+pub(crate) mod fake;
 pub(crate) mod images;
 pub(crate) mod mounts;
 pub(crate) mod run;
@@ -0,0 +1,195 @@
+//! In-memory container runtime for exercising launcher logic without a daemon.
+//!
+//! `FakeRuntime` interprets the docker-compatible CLI arguments passed to `invoke`, tracks
+//! images, containers, networks and volumes in memory and records every call.
+
+use std::collections::BTreeSet;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::runtime::{ContainerRuntime, RuntimeIo, RuntimeKind, RuntimeOutput};
+
+/// Gateway reported for every fake network.
+pub const FAKE_NETWORK_GATEWAY: &str = "10.89.0.1";
+
+/// Objects known to a `FakeRuntime` plus the calls it received.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FakeState {
+    pub images: BTreeSet<String>,
+    /// Running (detached) containers by name.
+    pub containers: BTreeSet<String>,
+    pub networks: BTreeSet<String>,
+    /// Networks created with `--internal` (also listed in `networks`).
+    pub internal_networks: BTreeSet<String>,
+    pub volumes: BTreeSet<String>,
+    /// Every invocation's arguments, in order.
+    pub calls: Vec<Vec<String>>,
+    /// Exit code returned for `exec` calls.
+    pub exec_code: i32,
+    /// stdout returned for captured `exec` calls.
+    pub exec_stdout: String,
+    /// When set, `run` fails with exit code 125 (like an engine that cannot start containers).
+    pub fail_run: bool,
+}
+
+/// Container runtime test double; see the module docs.
+#[derive(Debug, Default)]
+pub struct FakeRuntime {
+    state: Mutex<FakeState>,
+}
+
+impl FakeRuntime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_image(self, image: &str) -> Self {
+        self.update(|s| {
+            s.images.insert(image.to_string());
+        });
+        self
+    }
+
+    pub fn with_network(self, name: &str) -> Self {
+        self.update(|s| {
+            s.networks.insert(name.to_string());
+        });
+        self
+    }
+
+    pub fn with_container(self, name: &str) -> Self {
+        self.update(|s| {
+            s.containers.insert(name.to_string());
+        });
+        self
+    }
+
+    /// Snapshot of the current state.
+    pub fn state(&self) -> FakeState {
+        self.state.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    /// Recorded invocations, in order.
+    pub fn calls(&self) -> Vec<Vec<String>> {
+        self.state().calls
+    }
+
+    /// Mutate the state (e.g. to script exec results or failures).
+    pub fn update(&self, f: impl FnOnce(&mut FakeState)) {
+        if let Ok(mut s) = self.state.lock() {
+            f(&mut s);
+        }
+    }
+}
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+/// Named volumes referenced by `-v NAME:/path` mounts (host paths are ignored).
+fn named_volumes(args: &[String]) -> Vec<String> {
+    args.windows(2)
+        .filter(|w| w[0] == "-v")
+        .filter_map(|w| w[1].split_once(':').map(|(src, _)| src.to_string()))
+        .filter(|src| !src.is_empty() && !src.starts_with('/') && !src.starts_with('.'))
+        .collect()
+}
+
+fn code(ok: bool) -> RuntimeOutput {
+    RuntimeOutput {
+        code: Some(if ok { 0 } else { 1 }),
+        ..RuntimeOutput::default()
+    }
+}
+
+impl ContainerRuntime for FakeRuntime {
+    fn kind(&self) -> RuntimeKind {
+        RuntimeKind::Fake
+    }
+
+    fn program(&self) -> &Path {
+        Path::new("fake-runtime")
+    }
+
+    fn invoke(&self, args: &[String], io: RuntimeIo) -> io::Result<RuntimeOutput> {
+        let mut s = self
+            .state
+            .lock()
+            .map_err(|_| io::Error::other("fake runtime state poisoned"))?;
+        s.calls.push(args.to_vec());
+        let words: Vec<&str> = args.iter().map(String::as_str).collect();
+        let out = match words.as_slice() {
+            ["network", "inspect", name] => code(s.networks.contains(*name)),
+            ["network", "create", name] => code(s.networks.insert(name.to_string())),
+            ["network", "create", "--internal", name] => {
+                s.internal_networks.insert(name.to_string());
+                code(s.networks.insert(name.to_string()))
+            }
+            ["network", "inspect", "-f", _, name] => {
+                let exists = s.networks.contains(*name);
+                RuntimeOutput {
+                    stdout: if exists && io == RuntimeIo::Capture {
+                        FAKE_NETWORK_GATEWAY.to_string()
+                    } else {
+                        String::new()
+                    },
+                    ..code(exists)
+                }
+            }
+            ["network", "rm", name] => code(s.networks.remove(*name)),
+            ["volume", "create", name] => {
+                s.volumes.insert(name.to_string());
+                code(true)
+            }
+            ["volume", "inspect", name] => code(s.volumes.contains(*name)),
+            ["volume", "rm", rest @ ..] => {
+                if let Some(name) = rest.last() {
+                    s.volumes.remove(*name);
+                }
+                code(true)
+            }
+            ["image", "inspect", image] => code(s.images.contains(*image)),
+            ["pull", image] => {
+                s.images.insert(image.to_string());
+                code(true)
+            }
+            ["inspect", name] | ["container", "inspect", name] => {
+                code(s.containers.contains(*name))
+            }
+            ["stop", .., name] | ["rm", "-f", name] => code(s.containers.remove(*name)),
+            ["run", rest @ ..] => {
+                if s.fail_run {
+                    RuntimeOutput {
+                        code: Some(125),
+                        ..RuntimeOutput::default()
+                    }
+                } else {
+                    let rest: Vec<String> = rest.iter().map(|a| a.to_string()).collect();
+                    for v in named_volumes(&rest) {
+                        s.volumes.insert(v);
+                    }
+                    let detached = rest.iter().any(|a| a == "-d");
+                    if let (true, Some(name)) = (detached, flag_value(&rest, "--name")) {
+                        s.containers.insert(name.to_string());
+                    }
+                    code(true)
+                }
+            }
+            ["exec", ..] => RuntimeOutput {
+                code: Some(s.exec_code),
+                stdout: if io == RuntimeIo::Capture {
+                    s.exec_stdout.clone()
+                } else {
+                    String::new()
+                },
+                stderr: String::new(),
+            },
+            _ => code(true),
+        };
+        Ok(out)
+    }
+}
@@ -3,24 +3,17 @@
 
 use std::collections::HashMap;
 use std::path::Path;
-use std::process::{Command, Stdio};
 
 use serde::Deserialize;
 
+use super::runtime::{runtime_for_program, RuntimeIo};
+
 /// Return true if a docker image exists locally (without pulling).
 pub fn image_exists(runtime: &Path, image: &str) -> bool {
     if crate::cli_ignore_local_images() {
         return false;
     }
-    Command::new(runtime)
-        .arg("image")
-        .arg("inspect")
-        .arg(image)
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-        .map(|s| s.success())
-        .unwrap_or(false)
+    runtime_for_program(runtime).image_exists(image)
 }
 
 #[derive(Debug, Clone)]
@@ -58,18 +51,14 @@ struct InspectImage {
 
 /// Inspect a docker image and return key metadata (labels, creation time, id).
 pub fn image_metadata(runtime: &Path, image: &str) -> Option<ImageMetadata> {
-    let output = Command::new(runtime)
-        .arg("image")
-        .arg("inspect")
-        .arg(image)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null())
-        .output()
+    let args = ["image", "inspect", image].map(String::from);
+    let output = runtime_for_program(runtime)
+        .invoke(&args, RuntimeIo::Capture)
         .ok()?;
-    if !output.status.success() {
+    if !output.success() {
         return None;
     }
-    let mut items: Vec<InspectImage> = serde_json::from_slice(&output.stdout).ok()?;
+    let mut items: Vec<InspectImage> = serde_json::from_str(&output.stdout).ok()?;
     let first = items.pop()?;
     let labels = if !first.container_config.labels.is_empty() {
         first.container_config.labels
@@ -8,8 +8,6 @@ use std::io;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use url::Url;
-
 #[cfg(unix)]
 use nix::unistd::{getgid, getuid};
 
@@ -24,7 +22,7 @@ use crate::docker_mod::docker::images::image_exists;
 use crate::docker_mod::docker::mounts::{
     validate_mount_source_dir, validate_unix_socket_dir_owner_mode,
 };
-use crate::docker_mod::docker::runtime::container_runtime_path;
+use crate::docker_mod::docker::runtime::{container_runtime, container_runtime_kind, RuntimeKind};
 use crate::ShellScript;
 
 const SHIM_FIRST_PATH: &str =
@@ -120,7 +118,18 @@ fn agent_bin_and_path(agent: &str) -> (String, String) {
         "openhands" => "/opt/venv-openhands/bin/openhands",
         "opencode" => "/usr/local/bin/opencode",
         "plandex" => "/usr/local/bin/plandex",
-        _ => agent,
+        _ => {
+            // Custom agents declare their binary and PATH in config ([agents.NAME]).
+            if let Some(custom) = crate::config_custom_agent(agent) {
+                let abs = custom.bin.clone().unwrap_or_else(|| agent.to_string());
+                let path = custom
+                    .path
+                    .clone()
+                    .unwrap_or_else(|| SHIM_FIRST_PATH.to_string());
+                return (abs, path);
+            }
+            agent
+        }
     }
     .to_string();
 
@@ -146,16 +155,26 @@ fn compute_container_identity(agent: &str, prefix: &str) -> (String, String) {
     (container_name, hostname)
 }
 
-fn collect_user_flags(uid_opt: Option<u32>, gid_opt: Option<u32>) -> Vec<OsString> {
-    let mut user_flags: Vec<OsString> = Vec::new();
-    if let (Some(uid), Some(gid)) = (uid_opt, gid_opt) {
-        user_flags.push(OsString::from("--user"));
-        user_flags.push(OsString::from(format!("{uid}:{gid}")));
+fn collect_user_flags(
+    kind: RuntimeKind,
+    uid_opt: Option<u32>,
+    gid_opt: Option<u32>,
+) -> Vec<OsString> {
+    match (uid_opt, gid_opt) {
+        (Some(uid), Some(gid)) => kind
+            .user_flags(uid, gid)
+            .into_iter()
+            .map(OsString::from)
+            .collect(),
+        _ => Vec::new(),
     }
-    user_flags
 }
 
-fn collect_security_flags(apparmor_profile: Option<&str>) -> Vec<OsString> {
+fn collect_security_flags(
+    apparmor_profile: Option<&str>,
+    uid_opt: Option<u32>,
+    gid_opt: Option<u32>,
+) -> Vec<OsString> {
     let mut security_flags: Vec<OsString> = Vec::new();
     if let Some(profile) = apparmor_profile {
         if crate::docker_supports_apparmor() {
@@ -167,15 +186,39 @@ fn collect_security_flags(apparmor_profile: Option<&str>) -> Vec<OsString> {
             );
         }
     }
+    // Generated seccomp profile (or engine default/unconfined, per AIFO_CODER_SECCOMP)
+    for f in crate::seccomp_run_flags(None) {
+        security_flags.push(OsString::from(f));
+    }
+    // Hardened mode: cap-drop ALL, no-new-privileges, read-only root with tmpfs mounts
+    let ids = uid_opt.zip(gid_opt);
+    for f in crate::hardened_run_flags(ids) {
+        security_flags.push(OsString::from(f));
+    }
     security_flags
 }
 
 fn collect_env_flags(agent: &str, uid_opt: Option<u32>) -> Vec<OsString> {
     let mut env_flags: Vec<OsString> = Vec::new();
 
+    // With the credential gateway running, agents see only its URL and the session key.
+    let gateway = crate::llm_gateway_env();
+    let api_key = match &gateway {
+        Some((_, key)) => Some(key.clone()),
+        None => env::var("AIFO_API_KEY").ok(),
+    };
+    let api_base = match &gateway {
+        Some((url, _)) => Some(url.clone()),
+        None => env::var("AIFO_API_BASE").ok(),
+    };
+
     // Pass-through env
     for var in PASS_ENV_VARS.iter().copied() {
-        push_env_if_set(&mut env_flags, var);
+        match (var, &gateway) {
+            ("AIFO_API_KEY", Some((_, key))) => push_env_kv(&mut env_flags, var, key),
+            ("AIFO_API_BASE", Some((url, _))) => push_env_kv(&mut env_flags, var, url),
+            _ => push_env_if_set(&mut env_flags, var),
+        }
     }
 
     // Fixed environment
@@ -185,6 +228,11 @@ fn collect_env_flags(agent: &str, uid_opt: Option<u32>) -> Vec<OsString> {
     push_env_kv(&mut env_flags, "GNUPGHOME", "/home/coder/.gnupg");
     push_env_kv(&mut env_flags, "SHELL", "/opt/aifo/bin/sh");
 
+    // Egress allowlist: the session's internal network only reaches the host-side proxy.
+    for (k, v) in crate::egress_proxy_env() {
+        push_env_kv(&mut env_flags, &k, &v);
+    }
+
     // Phase 1 (smart shims): policy plumbing (opt-in)
     //
     // The embedded /opt/aifo/bin shims can proxy runtime tools (node/python) into sidecars,
@@ -194,10 +242,19 @@ fn collect_env_flags(agent: &str, uid_opt: Option<u32>) -> Vec<OsString> {
     //
     // Master toggle: explicitly opt-in for agents where we enable smart behavior.
     // This keeps default behavior unchanged for other agents.
+    let custom = crate::config_custom_agent(agent);
+    let custom_runtime = custom.and_then(|c| c.runtime);
     match agent {
         "codex" | "crush" | "opencode" | "letta" | "aider" | "openhands" => {
             push_env_kv(&mut env_flags, "AIFO_SHIM_SMART", "1");
         }
+        _ if matches!(
+            custom_runtime,
+            Some(crate::AgentRuntime::Node | crate::AgentRuntime::Python)
+        ) =>
+        {
+            push_env_kv(&mut env_flags, "AIFO_SHIM_SMART", "1");
+        }
         _ => {
             // Allow host override for any other agent/debug usage.
             push_env_kv_if_set(&mut env_flags, "AIFO_SHIM_SMART");
@@ -217,7 +274,15 @@ fn collect_env_flags(agent: &str, uid_opt: Option<u32>) -> Vec<OsString> {
         "aider" | "openhands" => {
             push_env_kv(&mut env_flags, "AIFO_SHIM_SMART_PYTHON", "1");
         }
-        _ => {}
+        _ => match custom_runtime {
+            Some(crate::AgentRuntime::Node) => {
+                push_env_kv(&mut env_flags, "AIFO_SHIM_SMART_NODE", "1");
+            }
+            Some(crate::AgentRuntime::Python) => {
+                push_env_kv(&mut env_flags, "AIFO_SHIM_SMART_PYTHON", "1");
+            }
+            Some(crate::AgentRuntime::None) | None => {}
+        },
     }
 
     // Phase 1: Config clone policy envs (entrypoint will perform the copy)
@@ -260,75 +325,35 @@ fn collect_env_flags(agent: &str, uid_opt: Option<u32>) -> Vec<OsString> {
         push_env_kv(&mut env_flags, "GPG_TTY", "/dev/tty");
     }
 
-    // Unified AIFO_* → OpenAI/Azure mappings
-    //
-    // OpenCode policy: NEVER pass OpenAI/Azure OpenAI envs (OPENAI_*/AZURE_OPENAI_*) or base/version.
-    // Only allow:
-    // - AZURE_API_KEY (from AIFO_API_KEY)
-    // - AZURE_RESOURCE_NAME (derived from AIFO_API_BASE unless explicitly set)
-    let is_opencode = agent == "opencode";
-
-    if let Ok(v) = env::var("AIFO_API_KEY") {
-        if !v.is_empty() {
-            if is_opencode {
-                push_env_kv(&mut env_flags, "AZURE_API_KEY", &v);
-            } else {
-                push_env_kv(&mut env_flags, "OPENAI_API_KEY", &v);
-                push_env_kv(&mut env_flags, "AZURE_OPENAI_API_KEY", &v);
-                push_env_kv(&mut env_flags, "AZURE_API_KEY", &v);
-            }
-        }
-    }
-    if let Ok(v) = env::var("AIFO_API_BASE") {
-        if !v.is_empty() {
-            if !is_opencode {
-                push_env_kv(&mut env_flags, "OPENAI_BASE_URL", &v);
-                push_env_kv(&mut env_flags, "OPENAI_API_BASE", &v);
-                push_env_kv(&mut env_flags, "AZURE_OPENAI_ENDPOINT", &v);
-                push_env_kv(&mut env_flags, "AZURE_API_BASE", &v);
-                push_env_kv(&mut env_flags, "OPENAI_API_TYPE", "azure");
-            }
-
-            // Derive AZURE_RESOURCE_NAME from AIFO_API_BASE when it looks like an Azure OpenAI endpoint,
-            // e.g. https://<resource>.openai.azure.com/.
-            //
-            // Do not override an explicit host-provided AZURE_RESOURCE_NAME.
-            if env::var("AZURE_RESOURCE_NAME")
-                .ok()
-                .filter(|s| !s.trim().is_empty())
-                .is_none()
-            {
-                if let Ok(url) = Url::parse(&v) {
-                    if let Some(host) = url.host_str() {
-                        if host.ends_with(".openai.azure.com") {
-                            if let Some(resource) = host.split('.').next() {
-                                if !resource.trim().is_empty() {
-                                    push_env_kv(&mut env_flags, "AZURE_RESOURCE_NAME", resource);
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-    if let Ok(v) = env::var("AIFO_API_VERSION") {
-        if !v.is_empty() && !is_opencode {
-            push_env_kv(&mut env_flags, "OPENAI_API_VERSION", &v);
-            push_env_kv(&mut env_flags, "AZURE_OPENAI_API_VERSION", &v);
-            push_env_kv(&mut env_flags, "AZURE_API_VERSION", &v);
-            push_env_kv(&mut env_flags, "LITELLM_AZURE_API_VERSION", &v);
-            push_env_kv(&mut env_flags, "AZURE_OPENAI_RESPONSES_API_VERSION", &v);
-        }
+    // AIFO_API_* → variables of the selected LLM provider, limited to those the agent reads.
+    // Behind the gateway the Azure resource name would let clients bypass it, so it is not derived.
+    let spec = crate::llm_selected_provider()
+        .or_else(|_| crate::llm_provider_spec(crate::DEFAULT_LLM_PROVIDER))
+        .expect("default LLM provider is known");
+    let values = crate::LlmValues {
+        key: api_key,
+        base: api_base,
+        version: env::var("AIFO_API_VERSION").ok(),
+        derive_azure_resource: gateway.is_none(),
+    };
+    for (k, v) in crate::llm_provider_env(spec, agent, &values) {
+        push_env_kv(&mut env_flags, &k, &v);
     }
 
     for k in [
         "AIFO_TOOLEEXEC_URL",
         "AIFO_TOOLEEXEC_TOKEN",
+        "AIFO_TOOLEEXEC_SIGNING_KEY",
+        "AIFO_TOOLEEXEC_ENV_ALLOW",
+        "AIFO_TOOLEEXEC_ENV_DENY",
         "AIFO_TOOLCHAIN_VERBOSE",
     ] {
         push_env_kv_if_set(&mut env_flags, k);
     }
+    // Client certificate for the mutual TLS proxy, mounted at a fixed path (see volume flags).
+    if env::var("AIFO_TOOLEEXEC_TLS_DIR").is_ok_and(|v| !v.trim().is_empty()) {
+        push_env_kv(&mut env_flags, "AIFO_TOOLEEXEC_TLS_DIR", "/run/aifo-tls");
+    }
 
     // Disable commit signing for Aider
     if agent == "aider" {
@@ -355,6 +380,18 @@ fn collect_env_flags(agent: &str, uid_opt: Option<u32>) -> Vec<OsString> {
         );
     }
 
+    // Custom agent environment: fixed values, then values copied from host variables.
+    if let Some(c) = custom {
+        for (k, v) in &c.env {
+            push_env_kv(&mut env_flags, k, v);
+        }
+        for (k, host_var) in &c.env_from {
+            if let Ok(v) = env::var(host_var) {
+                push_env_kv(&mut env_flags, k, &v);
+            }
+        }
+    }
+
     // User-provided variables via AIFO_ENV_* (strip prefix before passing into agent containers)
     push_prefixed_env_vars(&mut env_flags);
     // If proxy fallback marked proxies as unreachable, force direct connection inside agents
@@ -754,7 +791,25 @@ pub(crate) fn collect_volume_flags(agent: &str, host_home: &Path, pwd: &Path) ->
                     staged_dirs.push(p);
                 }
             }
-            _ => {}
+            _ => {
+                // Custom agents: every configured dir is staged into the same per-agent dir.
+                let dirs = crate::config_custom_agent(agent)
+                    .map(|c| c.config_dirs.as_slice())
+                    .unwrap_or_default();
+                for d in dirs {
+                    let src = match d.strip_prefix("~/") {
+                        Some(rest) => host_home.join(rest),
+                        None => PathBuf::from(d),
+                    };
+                    if let Some(p) =
+                        stage_top_level_files(agent, &src, &cfg_root, max_sz, &allowed_exts)
+                    {
+                        if !staged_dirs.contains(&p) {
+                            staged_dirs.push(p);
+                        }
+                    }
+                }
+            }
         }
 
         if !staged_dirs.is_empty() {
@@ -774,6 +829,7 @@ pub(crate) fn collect_volume_flags(agent: &str, host_home: &Path, pwd: &Path) ->
                     "openhands" => "openhands",
                     "opencode" => "opencode",
                     "plandex" => "plandex",
+                    _ if crate::config_custom_agent(agent).is_some() => agent,
                     _ => continue,
                 };
                 volume_flags.push(OsString::from("-v"));
@@ -1092,6 +1148,20 @@ pub(crate) fn collect_volume_flags(agent: &str, host_home: &Path, pwd: &Path) ->
         }
     }
 
+    // Optional proxy TLS client files (CA, client certificate and key), read-only
+    if let Ok(dir) = env::var("AIFO_TOOLEEXEC_TLS_DIR") {
+        if let Some(p) = validate_mount_source_dir(&dir, "AIFO_TOOLEEXEC_TLS_DIR") {
+            #[cfg(unix)]
+            let private = validate_unix_socket_dir_owner_mode(&p, "AIFO_TOOLEEXEC_TLS_DIR");
+            #[cfg(not(unix))]
+            let private = true;
+            if private {
+                volume_flags.push(OsString::from("-v"));
+                volume_flags.push(OsString::from(format!("{}:/run/aifo-tls:ro", p.display())));
+            }
+        }
+    }
+
     volume_flags
 }
 
@@ -1142,8 +1212,8 @@ pub fn build_docker_preview_args_only(
     let volume_flags = collect_volume_flags(agent, &host_home, &pwd);
 
     // User and security flags
-    let user_flags = collect_user_flags(uid_opt, gid_opt);
-    let security_flags = collect_security_flags(apparmor_profile);
+    let user_flags = collect_user_flags(container_runtime_kind(), uid_opt, gid_opt);
+    let security_flags = collect_security_flags(apparmor_profile, uid_opt, gid_opt);
 
     // Container identity
     let prefix = env::var("AIFO_CODER_IMAGE_PREFIX").unwrap_or_else(|_| "aifo-coder".to_string());
@@ -1203,6 +1273,7 @@ pub fn build_docker_preview_args_only(
     for f in &security_flags {
         preview_args.push(f.to_string_lossy().to_string());
     }
+    preview_args.extend(crate::agent_resource_limits(agent, None).run_flags());
 
     let resolved_image = crate::registry::resolve_image(image);
     preview_args.push(resolved_image);
@@ -1243,7 +1314,7 @@ pub fn build_docker_cmd(
     image: &str,
     apparmor_profile: Option<&str>,
 ) -> io::Result<(Command, String)> {
-    let runtime = container_runtime_path()?;
+    let rt = container_runtime()?;
 
     // TTY flags
     let tty_flags: Vec<&str> = if atty::is(atty::Stream::Stdin) || atty::is(atty::Stream::Stdout) {
@@ -1272,10 +1343,10 @@ pub fn build_docker_cmd(
     let volume_flags = collect_volume_flags(agent, &host_home, &pwd);
 
     // User mapping
-    let user_flags = collect_user_flags(uid_opt, gid_opt);
+    let user_flags = collect_user_flags(rt.kind(), uid_opt, gid_opt);
 
-    // AppArmor security flags
-    let security_flags = collect_security_flags(apparmor_profile);
+    // AppArmor and hardened-mode security flags
+    let security_flags = collect_security_flags(apparmor_profile, uid_opt, gid_opt);
     // Image prefix used for container naming
     let prefix = env::var("AIFO_CODER_IMAGE_PREFIX").unwrap_or_else(|_| "aifo-coder".to_string());
 
@@ -1317,7 +1388,7 @@ pub fn build_docker_cmd(
     let sh_cmd = build_container_sh_cmd(&path_value, &agent_joined)?;
 
     // docker run command
-    let mut cmd = Command::new(&runtime);
+    let mut cmd = Command::new(rt.program());
     let mut preview_args: Vec<String> = Vec::new();
 
     // program
@@ -1391,14 +1462,20 @@ pub fn build_docker_cmd(
         cmd.arg(f);
     }
 
+    // resource limits (CPU, memory, PIDs, ulimits)
+    for f in crate::agent_resource_limits(agent, None).run_flags() {
+        cmd.arg(&f);
+        preview_args.push(f);
+    }
+
     // Use the image passed in exactly; do not rewrite an explicit CLI override here.
     // Defaults and local :latest preferences are handled upstream in main.rs when --image is not provided.
     let effective_image = image.to_string();
     // Pre-pull image and auto-login on permission denied (interactive).
     let pull_verbose = env::var("AIFO_CODER_VERBOSE").ok().as_deref() == Some("1");
-    if !image_exists(runtime.as_path(), &effective_image) {
+    if !image_exists(rt.program(), &effective_image) {
         let _ = crate::docker_mod::docker::staging::pull_image_with_autologin(
-            runtime.as_path(),
+            rt.program(),
             &effective_image,
             pull_verbose,
             Some(agent),
@@ -1,12 +1,372 @@
 #![allow(clippy::module_name_repetitions)]
-//! Docker runtime discovery.
+//! Container runtime discovery and the `ContainerRuntime` abstraction.
+//!
+//! Engine selection: AIFO_CODER_CONTAINER_RUNTIME=docker|podman|auto (default auto: docker when
+//! found in PATH, else podman). Callers issue engine operations through `ContainerRuntime`
+//! so the same launcher logic runs against Docker, Podman or the in-memory `FakeRuntime`.
+//! Command previews keep docker syntax; Podman accepts the same CLI.
 
 use std::env;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
 
+use once_cell::sync::Lazy;
 use which::which;
 
+use crate::util::{ExecRequest, ExecService};
+
+static RUNTIME_EXEC: Lazy<ExecService> = Lazy::new(|| ExecService::new(Duration::from_secs(300)));
+
+/// Supported container engines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeKind {
+    Docker,
+    Podman,
+    /// In-memory test double (`FakeRuntime`).
+    Fake,
+}
+
+impl RuntimeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RuntimeKind::Docker => "docker",
+            RuntimeKind::Podman => "podman",
+            RuntimeKind::Fake => "fake",
+        }
+    }
+
+    /// `run` flags mapping the host user into the container.
+    ///
+    /// Rootless Podman additionally keeps the host uid/gid in the user namespace so files
+    /// written to bind mounts stay owned by the invoking user.
+    pub fn user_flags(self, uid: u32, gid: u32) -> Vec<String> {
+        let mut flags = Vec::new();
+        if self == RuntimeKind::Podman {
+            flags.push("--userns=keep-id".to_string());
+        }
+        flags.push("--user".to_string());
+        flags.push(format!("{uid}:{gid}"));
+        flags
+    }
+}
+
+/// How stdio of a runtime invocation is wired.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeIo {
+    /// Inherit the launcher's stdio (interactive or streamed output).
+    Inherit,
+    /// Discard stdout/stderr.
+    Quiet,
+    /// Capture stdout/stderr into the returned output.
+    Capture,
+}
+
+/// Result of a runtime invocation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuntimeOutput {
+    /// Exit code; None when terminated by a signal.
+    pub code: Option<i32>,
+    /// Captured stdout (empty unless `RuntimeIo::Capture`).
+    pub stdout: String,
+    /// Captured stderr (empty unless `RuntimeIo::Capture`).
+    pub stderr: String,
+}
+
+impl RuntimeOutput {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+/// Operations the launcher needs from a container engine.
+///
+/// Implementors provide `invoke`; the named operations default to the docker-compatible CLI
+/// form of each command.
+pub trait ContainerRuntime: Send + Sync {
+    fn kind(&self) -> RuntimeKind;
+
+    /// Engine binary, for commands callers must spawn themselves (interactive agent runs).
+    fn program(&self) -> &Path;
+
+    /// Run the engine with raw CLI arguments (without the program name).
+    fn invoke(&self, args: &[String], io: RuntimeIo) -> io::Result<RuntimeOutput>;
+
+    /// Like `invoke`, but give up after `timeout` where the backend supports it.
+    fn invoke_with_timeout(
+        &self,
+        args: &[String],
+        io: RuntimeIo,
+        timeout: Duration,
+    ) -> io::Result<RuntimeOutput> {
+        let _ = timeout;
+        self.invoke(args, io)
+    }
+
+    /// `run ARGS` (ARGS include flags, image and command).
+    fn run(&self, args: &[String], io: RuntimeIo) -> io::Result<RuntimeOutput> {
+        self.invoke(&prepend("run", args), io)
+    }
+
+    /// `exec ARGS` (ARGS include flags, container and command).
+    fn exec(&self, args: &[String], io: RuntimeIo) -> io::Result<RuntimeOutput> {
+        self.invoke(&prepend("exec", args), io)
+    }
+
+    /// True when a container (or other object) named NAME exists.
+    fn inspect(&self, name: &str) -> bool {
+        self.invoke(&strings(&["inspect", name]), RuntimeIo::Quiet)
+            .map(|o| o.success())
+            .unwrap_or(false)
+    }
+
+    /// Stop a container, optionally with a grace period in seconds.
+    fn stop(
+        &self,
+        name: &str,
+        grace_secs: Option<u32>,
+        io: RuntimeIo,
+    ) -> io::Result<RuntimeOutput> {
+        let mut args = vec!["stop".to_string()];
+        if let Some(t) = grace_secs {
+            args.push("--time".to_string());
+            args.push(t.to_string());
+        }
+        args.push(name.to_string());
+        self.invoke(&args, io)
+    }
+
+    fn network_exists(&self, name: &str) -> bool {
+        self.invoke(&strings(&["network", "inspect", name]), RuntimeIo::Quiet)
+            .map(|o| o.success())
+            .unwrap_or(false)
+    }
+
+    fn network_create(&self, name: &str, io: RuntimeIo) -> io::Result<RuntimeOutput> {
+        self.invoke(&strings(&["network", "create", name]), io)
+    }
+
+    /// Create a network without a route out of it (`network create --internal`).
+    fn network_create_internal(&self, name: &str, io: RuntimeIo) -> io::Result<RuntimeOutput> {
+        self.invoke(&strings(&["network", "create", "--internal", name]), io)
+    }
+
+    /// Gateway address of network NAME (the host side of its bridge), if it has one.
+    fn network_gateway(&self, name: &str) -> Option<String> {
+        let template = match self.kind() {
+            RuntimeKind::Podman => "{{range .Subnets}}{{.Gateway}} {{end}}",
+            _ => "{{range .IPAM.Config}}{{.Gateway}} {{end}}",
+        };
+        let out = self
+            .invoke(
+                &strings(&["network", "inspect", "-f", template, name]),
+                RuntimeIo::Capture,
+            )
+            .ok()
+            .filter(RuntimeOutput::success)?;
+        out.stdout
+            .split_whitespace()
+            .find(|g| g.parse::<std::net::Ipv4Addr>().is_ok())
+            .map(str::to_string)
+    }
+
+    fn network_remove(&self, name: &str, io: RuntimeIo) -> io::Result<RuntimeOutput> {
+        self.invoke(&strings(&["network", "rm", name]), io)
+    }
+
+    fn volume_create(&self, name: &str, io: RuntimeIo) -> io::Result<RuntimeOutput> {
+        self.invoke(&strings(&["volume", "create", name]), io)
+    }
+
+    /// Force-remove a named volume (missing volumes are not an error for the engine).
+    fn volume_remove(&self, name: &str, io: RuntimeIo) -> io::Result<RuntimeOutput> {
+        self.invoke(&strings(&["volume", "rm", "-f", name]), io)
+    }
+
+    /// True when IMAGE is present locally (no pull).
+    fn image_exists(&self, image: &str) -> bool {
+        self.invoke(&strings(&["image", "inspect", image]), RuntimeIo::Quiet)
+            .map(|o| o.success())
+            .unwrap_or(false)
+    }
+
+    fn image_pull(&self, image: &str, io: RuntimeIo) -> io::Result<RuntimeOutput> {
+        self.invoke_with_timeout(&strings(&["pull", image]), io, Duration::from_secs(300))
+    }
+
+    /// Interactive registry login (HOST None selects the engine default registry).
+    fn login(&self, host: Option<&str>) -> io::Result<RuntimeOutput> {
+        let mut args = vec!["login".to_string()];
+        if let Some(h) = host {
+            args.push(h.to_string());
+        }
+        self.invoke_with_timeout(&args, RuntimeIo::Inherit, Duration::from_secs(120))
+    }
+}
+
+fn strings(parts: &[&str]) -> Vec<String> {
+    parts.iter().map(|s| s.to_string()).collect()
+}
+
+fn prepend(first: &str, rest: &[String]) -> Vec<String> {
+    let mut v = Vec::with_capacity(rest.len() + 1);
+    v.push(first.to_string());
+    v.extend(rest.iter().cloned());
+    v
+}
+
+fn invoke_cli(program: &Path, args: &[String], io: RuntimeIo) -> io::Result<RuntimeOutput> {
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+    match io {
+        RuntimeIo::Capture => {
+            let out = cmd.output()?;
+            Ok(RuntimeOutput {
+                code: out.status.code(),
+                stdout: String::from_utf8_lossy(&out.stdout).into_owned(),
+                stderr: String::from_utf8_lossy(&out.stderr).into_owned(),
+            })
+        }
+        RuntimeIo::Quiet | RuntimeIo::Inherit => {
+            if io == RuntimeIo::Quiet {
+                cmd.stdout(Stdio::null()).stderr(Stdio::null());
+            }
+            let status = cmd.status()?;
+            Ok(RuntimeOutput {
+                code: status.code(),
+                ..RuntimeOutput::default()
+            })
+        }
+    }
+}
+
+fn invoke_cli_with_timeout(
+    program: &Path,
+    args: &[String],
+    io: RuntimeIo,
+    timeout: Duration,
+) -> io::Result<RuntimeOutput> {
+    let request = ExecRequest::new(program.as_os_str().to_os_string())
+        .args(args.iter().cloned())
+        .inherit_env(true)
+        .timeout(timeout)
+        .capture_output(io != RuntimeIo::Inherit);
+    let out = RUNTIME_EXEC.run(request).map_err(io::Error::other)?;
+    let captured = io == RuntimeIo::Capture;
+    Ok(RuntimeOutput {
+        code: out.status.code(),
+        stdout: if captured { out.stdout } else { String::new() },
+        stderr: if captured { out.stderr } else { String::new() },
+    })
+}
+
+/// Docker engine driven through the `docker` CLI.
+#[derive(Debug, Clone)]
+pub struct DockerRuntime {
+    program: PathBuf,
+}
+
+impl DockerRuntime {
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+        }
+    }
+}
+
+impl ContainerRuntime for DockerRuntime {
+    fn kind(&self) -> RuntimeKind {
+        RuntimeKind::Docker
+    }
+
+    fn program(&self) -> &Path {
+        &self.program
+    }
+
+    fn invoke(&self, args: &[String], io: RuntimeIo) -> io::Result<RuntimeOutput> {
+        invoke_cli(&self.program, args, io)
+    }
+
+    fn invoke_with_timeout(
+        &self,
+        args: &[String],
+        io: RuntimeIo,
+        timeout: Duration,
+    ) -> io::Result<RuntimeOutput> {
+        invoke_cli_with_timeout(&self.program, args, io, timeout)
+    }
+}
+
+/// Podman engine (typically rootless) driven through the docker-compatible `podman` CLI.
+///
+/// `run` invocations map the host user with `--userns=keep-id` (see `RuntimeKind::user_flags`).
+#[derive(Debug, Clone)]
+pub struct PodmanRuntime {
+    program: PathBuf,
+}
+
+impl PodmanRuntime {
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+        }
+    }
+}
+
+impl ContainerRuntime for PodmanRuntime {
+    fn kind(&self) -> RuntimeKind {
+        RuntimeKind::Podman
+    }
+
+    fn program(&self) -> &Path {
+        &self.program
+    }
+
+    fn invoke(&self, args: &[String], io: RuntimeIo) -> io::Result<RuntimeOutput> {
+        invoke_cli(&self.program, args, io)
+    }
+
+    fn invoke_with_timeout(
+        &self,
+        args: &[String],
+        io: RuntimeIo,
+        timeout: Duration,
+    ) -> io::Result<RuntimeOutput> {
+        invoke_cli_with_timeout(&self.program, args, io, timeout)
+    }
+}
+
+/// Engine requested via AIFO_CODER_CONTAINER_RUNTIME (None for auto).
+fn requested_runtime_kind() -> io::Result<Option<RuntimeKind>> {
+    let v = env::var("AIFO_CODER_CONTAINER_RUNTIME").unwrap_or_default();
+    match v.trim().to_ascii_lowercase().as_str() {
+        "" | "auto" => Ok(None),
+        "docker" => Ok(Some(RuntimeKind::Docker)),
+        "podman" => Ok(Some(RuntimeKind::Podman)),
+        other => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "unsupported container runtime '{other}' in AIFO_CODER_CONTAINER_RUNTIME (expected docker, podman or auto)"
+            ),
+        )),
+    }
+}
+
+/// Engine that `container_runtime_path` would select, without failing when none is installed.
+pub fn container_runtime_kind() -> RuntimeKind {
+    match requested_runtime_kind() {
+        Ok(Some(kind)) => kind,
+        _ => {
+            if which("docker").is_err() && which("podman").is_ok() {
+                RuntimeKind::Podman
+            } else {
+                RuntimeKind::Docker
+            }
+        }
+    }
+}
+
 pub fn container_runtime_path() -> io::Result<PathBuf> {
     // Allow tests or callers to explicitly disable Docker detection to avoid hard failures
     if env::var("AIFO_CODER_TEST_DISABLE_DOCKER").ok().as_deref() == Some("1")
@@ -18,11 +378,48 @@ pub fn container_runtime_path() -> io::Result<PathBuf> {
         ));
     }
 
-    if let Ok(p) = which("docker") {
-        return Ok(p);
+    match requested_runtime_kind()? {
+        Some(RuntimeKind::Podman) => which("podman").map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "Podman was requested via AIFO_CODER_CONTAINER_RUNTIME but was not found in PATH.",
+            )
+        }),
+        Some(_) => which("docker").map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "Docker is required but was not found in PATH.",
+            )
+        }),
+        None => {
+            if let Ok(p) = which("docker") {
+                return Ok(p);
+            }
+            if let Ok(p) = which("podman") {
+                return Ok(p);
+            }
+            Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "Docker is required but was not found in PATH.",
+            ))
+        }
+    }
+}
+
+/// Runtime wrapper for an engine binary; the kind is derived from the program name.
+pub fn runtime_for_program(program: &Path) -> Box<dyn ContainerRuntime> {
+    let is_podman = program
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .is_some_and(|s| s.starts_with("podman"));
+    if is_podman {
+        Box::new(PodmanRuntime::new(program))
+    } else {
+        Box::new(DockerRuntime::new(program))
     }
-    Err(io::Error::new(
-        io::ErrorKind::NotFound,
-        "Docker is required but was not found in PATH.",
-    ))
+}
+
+/// The selected container engine (see module docs for selection rules).
+pub fn container_runtime() -> io::Result<Box<dyn ContainerRuntime>> {
+    container_runtime_path().map(|p| runtime_for_program(&p))
 }
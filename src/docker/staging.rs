@@ -2,36 +2,14 @@
 //! Image selection helpers and staging cleanup for agent runs.
 
 use std::env;
-use std::ffi::OsString;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
-use std::time::Duration;
-
-use once_cell::sync::Lazy;
 
 use crate::docker_mod::docker::images::image_exists;
-use crate::docker_mod::docker::runtime::container_runtime_path;
-use crate::util::{ExecOutput, ExecRequest, ExecService};
-
-static DOCKER_EXEC: Lazy<ExecService> = Lazy::new(|| ExecService::new(Duration::from_secs(300)));
-
-fn run_runtime_command<I>(
-    runtime: &Path,
-    args: I,
-    capture: bool,
-    timeout: Duration,
-) -> io::Result<ExecOutput>
-where
-    I: IntoIterator<Item = OsString>,
-{
-    let request = ExecRequest::new(runtime.as_os_str().to_os_string())
-        .args(args)
-        .inherit_env(true)
-        .timeout(timeout)
-        .capture_output(capture);
-    DOCKER_EXEC.run(request).map_err(io::Error::other)
-}
+use crate::docker_mod::docker::runtime::{
+    container_runtime_path, runtime_for_program, ContainerRuntime, RuntimeIo,
+};
 
 /// Derive registry host from an image reference (first component if qualified).
 fn parse_registry_host(image: &str) -> Option<String> {
@@ -54,29 +32,19 @@ pub fn pull_image_with_autologin(
     // Effective verbosity: honor explicit flag or env set by CLI --verbose.
     let eff_verbose = verbose || env::var("AIFO_CODER_VERBOSE").ok().as_deref() == Some("1");
     let use_err = crate::color_enabled_stderr();
+    let rt = runtime_for_program(runtime);
+    let runtime: &dyn ContainerRuntime = rt.as_ref();
 
     // Helper to do a pull with inherited stdio so progress is visible.
-    let pull_inherit = |rt: &Path, img: &str| -> io::Result<bool> {
-        let out = run_runtime_command(
-            rt,
-            ["pull", img].into_iter().map(OsString::from),
-            false,
-            Duration::from_secs(300),
-        )?;
-
-        Ok(out.status.success())
+    let pull_inherit = |rt: &dyn ContainerRuntime, img: &str| -> io::Result<bool> {
+        Ok(rt.image_pull(img, RuntimeIo::Inherit)?.success())
     };
 
     // Helper to do a pull with captured output so we can parse error text.
-    let pull_captured = |rt: &Path, img: &str| -> io::Result<(bool, String)> {
-        let out = run_runtime_command(
-            rt,
-            ["pull", img].into_iter().map(OsString::from),
-            true,
-            Duration::from_secs(300),
-        )?;
+    let pull_captured = |rt: &dyn ContainerRuntime, img: &str| -> io::Result<(bool, String)> {
+        let out = rt.image_pull(img, RuntimeIo::Capture)?;
         let combined = format!("{}\n{}", out.stdout, out.stderr).to_ascii_lowercase();
-        Ok((out.status.success(), combined))
+        Ok((out.success(), combined))
     };
 
     let auth_patterns = [
@@ -104,20 +72,13 @@ pub fn pull_image_with_autologin(
 
         if auto_enabled && interactive && looks_auth_error {
             let host = parse_registry_host(image);
-            let mut login_args = vec![OsString::from("login")];
             if let Some(h) = host.as_deref() {
                 crate::log_info_stderr(use_err, &format!("aifo-coder: docker: docker login {}", h));
-                login_args.push(OsString::from(h));
             } else {
                 crate::log_info_stderr(use_err, "aifo-coder: docker: docker login");
             }
-            let login_out = run_runtime_command(
-                runtime,
-                login_args.into_iter(),
-                false,
-                Duration::from_secs(120),
-            )?;
-            if !login_out.status.success() {
+            let login_out = runtime.login(host.as_deref())?;
+            if !login_out.success() {
                 return Err(io::Error::new(
                     io::ErrorKind::PermissionDenied,
                     "docker login failed",
@@ -173,13 +134,8 @@ pub fn pull_image_with_autologin(
         };
         crate::log_info_stderr(use_err, &msg);
 
-        let out = run_runtime_command(
-            runtime,
-            ["pull", image].into_iter().map(OsString::from),
-            true,
-            Duration::from_secs(300),
-        )?;
-        if out.status.success() {
+        let out = runtime.image_pull(image, RuntimeIo::Capture)?;
+        if out.success() {
             return Ok(());
         }
 
@@ -195,17 +151,8 @@ pub fn pull_image_with_autologin(
             } else {
                 crate::log_info_stderr(use_err, "aifo-coder: docker login");
             }
-            let mut login_args = vec![OsString::from("login")];
-            if let Some(h) = host.as_deref() {
-                login_args.push(OsString::from(h));
-            }
-            let st = run_runtime_command(
-                runtime,
-                login_args.into_iter(),
-                false,
-                Duration::from_secs(120),
-            )?;
-            if !st.status.success() {
+            let st = runtime.login(host.as_deref())?;
+            if !st.success() {
                 return Err(io::Error::new(
                     io::ErrorKind::PermissionDenied,
                     "docker login failed",
@@ -213,13 +160,8 @@ pub fn pull_image_with_autologin(
             }
 
             crate::log_info_stderr(use_err, "aifo-coder: retrying pull after login");
-            let out2 = run_runtime_command(
-                runtime,
-                ["pull", image].into_iter().map(OsString::from),
-                true,
-                Duration::from_secs(300),
-            )?;
-            if out2.status.success() {
+            let out2 = runtime.image_pull(image, RuntimeIo::Capture)?;
+            if out2.success() {
                 return Ok(());
             }
             return Err(io::Error::new(
@@ -243,13 +185,8 @@ pub fn pull_image_with_autologin(
                 tail.split_once(':').map(|(n, _)| n).unwrap_or(tail),
                 tag
             );
-            let out_hub = run_runtime_command(
-                runtime,
-                ["pull", unqual.as_str()].into_iter().map(OsString::from),
-                true,
-                Duration::from_secs(300),
-            )?;
-            if out_hub.status.success() {
+            let out_hub = runtime.image_pull(&unqual, RuntimeIo::Capture)?;
+            if out_hub.success() {
                 Ok(())
             } else {
                 Err(io::Error::new(
@@ -260,10 +197,7 @@ pub fn pull_image_with_autologin(
         } else {
             Err(io::Error::new(
                 io::ErrorKind::PermissionDenied,
-                format!(
-                    "docker pull failed (status {:?})",
-                    out.status.code().unwrap_or(-1)
-                ),
+                format!("docker pull failed (status {:?})", out.code.unwrap_or(-1)),
             ))
         }
     }
@@ -5,7 +5,8 @@
 //! implementation is split into focused submodules under `docker/`.
 //!
 //! Structure (issue 5):
-//! - docker/runtime.rs: runtime detection / availability
+//! - docker/runtime.rs: runtime detection and the ContainerRuntime trait (Docker/Podman)
+//! - docker/fake.rs: in-memory ContainerRuntime for tests
 //! - docker/images.rs: image existence and pull helpers
 //! - docker/env.rs: env forwarding helpers
 //! - docker/mounts.rs: mount policy / validation helpers
@@ -14,9 +15,13 @@
 #[path = "docker/docker.rs"]
 pub(crate) mod docker;
 
+pub use docker::fake::{FakeRuntime, FakeState, FAKE_NETWORK_GATEWAY};
 pub use docker::images::{format_image_metadata, image_exists, image_metadata};
 pub use docker::run::{
     build_docker_cmd, build_docker_preview_args_only, build_docker_preview_only,
 };
-pub use docker::runtime::container_runtime_path;
+pub use docker::runtime::{
+    container_runtime, container_runtime_kind, container_runtime_path, runtime_for_program,
+    ContainerRuntime, DockerRuntime, PodmanRuntime, RuntimeIo, RuntimeKind, RuntimeOutput,
+};
 pub use docker::staging::{cleanup_aider_staging_from_env, compute_effective_agent_image_for_run};
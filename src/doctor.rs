@@ -139,6 +139,30 @@ pub fn run_doctor(verbose: bool) {
     let prof_str = profile.as_deref().unwrap_or("(disabled)");
     eprintln!("  apparmor profile:      {}", prof_str);
 
+    // Shipped profile: installed file vs. the current template render, and kernel load state
+    if cfg!(target_os = "linux") {
+        let st = aifo_coder::apparmor_status(aifo_coder::APPARMOR_PROFILE_NAME);
+        let loaded = match st.loaded {
+            Some(true) => "loaded",
+            Some(false) => "not loaded",
+            None => "load state unknown",
+        };
+        eprintln!(
+            "  apparmor shipped:      {}, {} ({})",
+            st.installed.as_str(),
+            loaded,
+            st.path.display()
+        );
+        if verbose
+            && (st.installed != aifo_coder::AppArmorInstallState::Current
+                || st.loaded == Some(false))
+        {
+            eprintln!(
+                "    tip: Install or refresh the shipped profile: sudo aifo-coder apparmor install"
+            );
+        }
+    }
+
     // Confirm active AppArmor profile from inside a short-lived container
     if let Ok(rt) = aifo_coder::container_runtime_path() {
         let image = default_image_for_quiet("crush");
@@ -206,10 +230,7 @@ pub fn run_doctor(verbose: bool) {
                         eprintln!(
                             "    tip: Container is unconfined. Generate and load the profile:"
                         );
-                        eprintln!("    tip:   make apparmor");
-                        eprintln!(
-                            "    tip:   sudo apparmor_parser -r -W \"build/apparmor/aifo-coder\""
-                        );
+                        eprintln!("    tip:   sudo aifo-coder apparmor install");
                         eprintln!("    tip: Then re-run with AppArmor enabled.");
                     } else {
                         eprintln!("    tip: Container appears unconfined. Ensure your Docker VM/distribution supports AppArmor and it is enabled.");
@@ -227,6 +248,108 @@ pub fn run_doctor(verbose: bool) {
     }
     eprintln!();
 
+    // Hardened mode: configured protections, confirmed inside a short-lived container when on
+    let hardened = aifo_coder::hardened_mode_enabled();
+    eprintln!(
+        "  hardened mode:         {}",
+        if hardened { "on" } else { "off" }
+    );
+    let observed = if hardened {
+        aifo_coder::container_runtime_path()
+            .ok()
+            .and_then(|rt| probe_hardening(&rt))
+    } else {
+        None
+    };
+    for (label, on) in aifo_coder::hardened_protections() {
+        let state = match (&observed, on) {
+            (_, false) => "inactive".to_string(),
+            (None, true) => "configured".to_string(),
+            (Some(seen), true) => {
+                if seen.contains(&label) {
+                    "active".to_string()
+                } else {
+                    "FAIL (not observed in container)".to_string()
+                }
+            }
+        };
+        eprintln!("    {:<20} {}", format!("{label}:"), state);
+    }
+    if hardened {
+        let caps = aifo_coder::hardened_cap_add();
+        let caps = if caps.is_empty() {
+            "(none)".to_string()
+        } else {
+            caps.join(",")
+        };
+        eprintln!("    {:<20} {}", "cap-add:", caps);
+    } else if verbose {
+        eprintln!(
+            "    tip: enable with --hardened, AIFO_CODER_HARDENED=1 or [security] hardened = true"
+        );
+    }
+
+    // Seccomp profile supplied by the launcher
+    let seccomp = match aifo_coder::seccomp_mode() {
+        aifo_coder::SeccompMode::Aifo => {
+            let relaxed = std::env::var("AIFO_TOOLCHAIN_SECCOMP_RELAXED").unwrap_or_default();
+            if relaxed.trim().is_empty() {
+                "aifo-coder".to_string()
+            } else {
+                format!("aifo-coder (relaxed for toolchains: {})", relaxed.trim())
+            }
+        }
+        aifo_coder::SeccompMode::Engine => "engine default".to_string(),
+        aifo_coder::SeccompMode::Unconfined => "unconfined".to_string(),
+    };
+    eprintln!("  seccomp profile:       {}", seccomp);
+    if verbose {
+        eprintln!("    tip: inspect it with 'aifo-coder security export-seccomp'");
+    }
+
+    // Egress allowlist: hosts agents and sidecars may reach through the host proxy
+    let egress = aifo_coder::egress_allowlist_enabled();
+    eprintln!(
+        "  egress allowlist:      {}",
+        if egress { "on" } else { "off" }
+    );
+    if egress {
+        let allow = aifo_coder::egress_allowlist(&[]);
+        let allow = if allow.is_empty() {
+            "(none)".to_string()
+        } else {
+            allow.join(", ")
+        };
+        eprintln!("    {:<20} {}", "allow:", allow);
+    } else if verbose {
+        eprintln!(
+            "    tip: enable with --egress-allowlist, AIFO_CODER_EGRESS_ALLOWLIST=1 or [network] egress_allowlist = true"
+        );
+    }
+
+    // LLM credential gateway: whether the API key stays on the host
+    let gateway = aifo_coder::llm_gateway_enabled();
+    eprintln!(
+        "  llm gateway:           {}",
+        if gateway {
+            "on (agents get a session key)"
+        } else {
+            "off (agents get AIFO_API_KEY)"
+        }
+    );
+    if !gateway && verbose {
+        eprintln!(
+            "    tip: enable with --llm-gateway, AIFO_CODER_LLM_GATEWAY=1 or [llm] gateway = true"
+        );
+    }
+
+    // LLM provider: which container variables AIFO_API_* are mapped to
+    match aifo_coder::llm_selected_provider() {
+        Ok(p) => eprintln!("  llm provider:          {} ({})", p.name, p.description),
+        Err(e) => eprintln!("  llm provider:          invalid: {e}"),
+    }
+    eprintln!();
+
     // Docker command and version
     match aifo_coder::container_runtime_path() {
         Ok(p) => {
@@ -846,7 +969,13 @@ pub fn run_doctor(verbose: bool) {
                 .map(|v| !v.trim().is_empty())
                 .unwrap_or(false)
         };
-        let has_key = present("AIFO_API_KEY");
+        // A configured key helper counts as a source; doctor does not run it (it may prompt).
+        let helper = aifo_coder::llm_api_key_helper().filter(|_| !present("AIFO_API_KEY"));
+        let has_key = present("AIFO_API_KEY") || helper.is_some();
+        let key_name = match &helper {
+            Some(h) => format!("AIFO_API_KEY (via {})", h.label()),
+            None => "AIFO_API_KEY".to_string(),
+        };
         let has_base = present("AIFO_API_BASE");
         let has_version = present("AIFO_API_VERSION");
 
@@ -855,7 +984,7 @@ pub fn run_doctor(verbose: bool) {
         eprintln!(
             "  {:<label_w$} {:<name_w$} {}",
             "environment:",
-            "AIFO_API_KEY",
+            key_name,
             icon(has_key),
             label_w = label_w,
             name_w = name_w
@@ -1025,3 +1154,52 @@ pub fn run_doctor(verbose: bool) {
     eprintln!("doctor: completed diagnostics.");
     eprintln!();
 }
+
+/// Run a hardened probe container and return the protection labels it observed.
+fn probe_hardening(rt: &std::path::Path) -> Option<Vec<&'static str>> {
+    let image = default_image_for_quiet("crush");
+    let script = ShellScript::new()
+        .push(r#"grep -E '^(CapEff|NoNewPrivs):' /proc/self/status"#.to_string())
+        .push(
+            r#"if touch /.aifo-ro-probe 2>/dev/null; then echo rootfs:rw; else echo rootfs:ro; fi"#
+                .to_string(),
+        )
+        .build()
+        .ok()?;
+    let out = Command::new(rt)
+        .arg("run")
+        .arg("--rm")
+        .args(aifo_coder::hardened_run_flags(None))
+        .arg("--entrypoint")
+        .arg("sh")
+        .arg(image)
+        .arg("-c")
+        .arg(script)
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let text = String::from_utf8_lossy(&out.stdout).to_string();
+    let field = |name: &str| {
+        text.lines()
+            .find_map(|l| l.strip_prefix(name))
+            .map(|v| v.trim().to_string())
+    };
+    // Every added-back capability is a single bit; anything beyond them means caps were not dropped.
+    let allowed_caps = aifo_coder::hardened_cap_add().len() as u32;
+    let mut seen = Vec::new();
+    if let Some(eff) = field("CapEff:").and_then(|v| u64::from_str_radix(&v, 16).ok()) {
+        if eff.count_ones() <= allowed_caps {
+            seen.push("cap-drop ALL");
+        }
+    }
+    if field("NoNewPrivs:").as_deref() == Some("1") {
+        seen.push("no-new-privileges");
+    }
+    if text.lines().any(|l| l.trim() == "rootfs:ro") {
+        seen.push("read-only rootfs");
+    }
+    Some(seen)
+}
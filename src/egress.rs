@@ -0,0 +1,402 @@
+//! Egress allowlist proxy: agents and sidecars reach the network only through approved hosts.
+//!
+//! Enabled by AIFO_CODER_EGRESS_ALLOWLIST=1 (`--egress-allowlist`, `[network] egress_allowlist`).
+//! The launcher then puts agent and sidecars on an internal session network (no route out) and
+//! starts a host-side HTTP/HTTPS forward proxy bound to that network's gateway. Containers get
+//! HTTP(S)_PROXY pointing at it; the proxy answers CONNECT and absolute-form HTTP requests for
+//! allowed hosts and returns 403 for everything else.
+//!
+//! Allowed hosts: the LLM endpoint from AIFO_API_BASE, the package registries of the requested
+//! toolchain kinds and AIFO_CODER_EGRESS_ALLOW (comma-separated; `*.example.com` matches
+//! subdomains). Every decision is appended to the session egress log.
+
+use std::env;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::toolchain::pool::{append_log_line, serve_pooled, WorkerPool};
+
+/// Env var carrying the proxy URL containers should use; set while the egress proxy runs.
+pub const EGRESS_PROXY_URL_ENV: &str = "AIFO_EGRESS_PROXY_URL";
+
+/// Package registries reachable from each toolchain kind (normalized kind names).
+pub const EGRESS_TOOLCHAIN_HOSTS: &[(&str, &[&str])] = &[
+    (
+        "rust",
+        &[
+            "crates.io",
+            "index.crates.io",
+            "static.crates.io",
+            "static.rust-lang.org",
+        ],
+    ),
+    ("node", &["registry.npmjs.org", "registry.yarnpkg.com"]),
+    ("python", &["pypi.org", "files.pythonhosted.org"]),
+    ("go", &["proxy.golang.org", "sum.golang.org"]),
+];
+
+/// Largest request head (request line plus headers) the proxy accepts.
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+const HEAD_READ_TIMEOUT: Duration = Duration::from_secs(30);
+const UPSTREAM_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Client connections handled at once (each open tunnel holds one); further ones get 503.
+const EGRESS_MAX_CONNECTIONS: usize = 256;
+
+/// True when the egress allowlist is on (AIFO_CODER_EGRESS_ALLOWLIST=1|true|yes|on).
+pub fn egress_allowlist_enabled() -> bool {
+    env::var("AIFO_CODER_EGRESS_ALLOWLIST")
+        .ok()
+        .map(|v| v.trim().to_ascii_lowercase())
+        .is_some_and(|v| matches!(v.as_str(), "1" | "true" | "yes" | "on"))
+}
+
+/// Validate an allowlist entry (`host`, `*.domain` or `.domain`); returns it lowercased.
+pub fn parse_egress_host(s: &str) -> Result<String, String> {
+    let t = s.trim().trim_end_matches('.').to_ascii_lowercase();
+    let bare = t
+        .strip_prefix("*.")
+        .or_else(|| t.strip_prefix('.'))
+        .unwrap_or(&t);
+    let valid = !bare.is_empty()
+        && bare
+            .split('.')
+            .all(|l| !l.is_empty() && l.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'));
+    if valid {
+        Ok(t)
+    } else {
+        Err(format!(
+            "invalid egress host '{s}': expected a host name such as api.example.com or *.example.com"
+        ))
+    }
+}
+
+/// Host of the LLM endpoint configured via AIFO_API_BASE.
+pub fn egress_llm_host() -> Option<String> {
+    let base = env::var("AIFO_API_BASE").ok()?;
+    let url = url::Url::parse(base.trim()).ok()?;
+    url.host_str().map(|h| {
+        h.trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_ascii_lowercase()
+    })
+}
+
+/// Allowed hosts for a session with the given toolchain kinds, sorted and deduplicated.
+pub fn egress_allowlist(kinds: &[String]) -> Vec<String> {
+    let mut hosts: Vec<String> = Vec::new();
+    hosts.extend(egress_llm_host());
+    for kind in kinds {
+        let kind = crate::normalize_toolchain_kind(kind);
+        if let Some((_, list)) = EGRESS_TOOLCHAIN_HOSTS.iter().find(|(k, _)| *k == kind) {
+            hosts.extend(list.iter().map(|h| h.to_string()));
+        }
+    }
+    if let Ok(extra) = env::var("AIFO_CODER_EGRESS_ALLOW") {
+        hosts.extend(extra.split(',').filter_map(|h| parse_egress_host(h).ok()));
+    }
+    hosts.sort();
+    hosts.dedup();
+    hosts
+}
+
+/// True when HOST matches an entry of ALLOW (exact, or a subdomain of a `*.`/`.` entry).
+pub fn egress_host_allowed(allow: &[String], host: &str) -> bool {
+    let host = host.trim().trim_end_matches('.').to_ascii_lowercase();
+    allow.iter().any(
+        |entry| match entry.strip_prefix("*.").or_else(|| entry.strip_prefix('.')) {
+            Some(domain) => host
+                .strip_suffix(domain)
+                .is_some_and(|rest| rest.ends_with('.')),
+            None => *entry == host,
+        },
+    )
+}
+
+/// Proxy variables injected into containers while the egress proxy runs (empty otherwise).
+///
+/// NO_PROXY keeps loopback and the proxy's own host (which also serves the toolexec proxy) direct.
+pub fn egress_proxy_env() -> Vec<(String, String)> {
+    let Some(url) = env::var(EGRESS_PROXY_URL_ENV)
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+    else {
+        return Vec::new();
+    };
+    let mut no_proxy = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+    if let Some(h) = url::Url::parse(&url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+    {
+        no_proxy.push(h);
+    }
+    let no_proxy = no_proxy.join(",");
+    let mut out = Vec::new();
+    for (upper, lower, value) in [
+        ("HTTP_PROXY", "http_proxy", &url),
+        ("HTTPS_PROXY", "https_proxy", &url),
+        ("NO_PROXY", "no_proxy", &no_proxy),
+    ] {
+        out.push((upper.to_string(), value.clone()));
+        out.push((lower.to_string(), value.clone()));
+    }
+    out
+}
+
+/// Default egress log for SESSION_ID (AIFO_CODER_EGRESS_LOG overrides it).
+pub fn egress_log_path(session_id: &str) -> PathBuf {
+    if let Some(p) = env::var("AIFO_CODER_EGRESS_LOG")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+    {
+        return PathBuf::from(p);
+    }
+    let base = env::var("XDG_RUNTIME_DIR")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir);
+    base.join(format!("aifo-coder-egress-{session_id}.log"))
+}
+
+struct EgressCtx {
+    allow: Vec<String>,
+    log: Option<PathBuf>,
+    verbose: bool,
+}
+
+impl EgressCtx {
+    fn record(&self, allowed: bool, method: &str, host: &str, port: u16) {
+        let verdict = if allowed { "allow" } else { "deny" };
+        if self.verbose {
+            crate::log_info_stderr(
+                crate::color_enabled_stderr(),
+                &format!("aifo-coder: egress {verdict} {method} {host}:{port}"),
+            );
+        }
+        if let Some(path) = &self.log {
+            let ts = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let _ = append_log_line(path, &format!("{ts} {verdict} {method} {host}:{port}\n"));
+        }
+    }
+}
+
+/// Start the egress proxy on BIND_HOST (random port) enforcing ALLOW; decisions go to LOG.
+///
+/// Returns the bound port, the running flag (store false to stop) and the accept-loop handle.
+pub fn egress_start_proxy(
+    bind_host: &str,
+    allow: Vec<String>,
+    log: Option<PathBuf>,
+    verbose: bool,
+) -> io::Result<(u16, Arc<AtomicBool>, JoinHandle<()>)> {
+    let listener = TcpListener::bind((bind_host, 0)).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("egress proxy bind on {bind_host} failed: {e}"),
+        )
+    })?;
+    let port = listener.local_addr()?.port();
+    listener.set_nonblocking(true)?;
+    let running = Arc::new(AtomicBool::new(true));
+    let running_cl = running.clone();
+    let ctx = Arc::new(EgressCtx {
+        allow,
+        log,
+        verbose,
+    });
+    let handle = std::thread::spawn(move || {
+        let pool = WorkerPool::new(EGRESS_MAX_CONNECTIONS, "aifo-egress");
+        serve_pooled(
+            &listener,
+            &pool,
+            &running_cl,
+            "egress proxy",
+            ctx.verbose,
+            |s| respond(s, "503 Service Unavailable", "egress proxy busy\n"),
+            |stream| {
+                let ctx = ctx.clone();
+                move || handle_client(&ctx, stream)
+            },
+        );
+    });
+    Ok((port, running, handle))
+}
+
+fn respond(stream: &mut TcpStream, status: &str, body: &str) {
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.flush();
+}
+
+/// Split "host:port" (IPv6 hosts in brackets) into its parts.
+fn split_host_port(target: &str) -> Option<(String, u16)> {
+    let (host, port) = target.rsplit_once(':')?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = port.parse::<u16>().ok()?;
+    (!host.is_empty()).then(|| (host.to_ascii_lowercase(), port))
+}
+
+fn connect_upstream(host: &str, port: u16) -> io::Result<TcpStream> {
+    let mut last_err = io::Error::new(io::ErrorKind::NotFound, "no address");
+    for addr in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, UPSTREAM_CONNECT_TIMEOUT) {
+            Ok(s) => return Ok(s),
+            Err(e) => last_err = e,
+        }
+    }
+    Err(last_err)
+}
+
+fn handle_client(ctx: &EgressCtx, mut client: TcpStream) {
+    let _ = client.set_nonblocking(false);
+    let _ = client.set_read_timeout(Some(HEAD_READ_TIMEOUT));
+    let mut reader = BufReader::new(match client.try_clone() {
+        Ok(c) => c,
+        Err(_) => return,
+    });
+    let mut lines: Vec<String> = Vec::new();
+    let mut total = 0usize;
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(n) => total += n,
+        }
+        if total > MAX_HEAD_BYTES {
+            respond(&mut client, "431 Request Header Fields Too Large", "");
+            return;
+        }
+        let line = line.trim_end_matches(['\r', '\n']).to_string();
+        if line.is_empty() {
+            break;
+        }
+        lines.push(line);
+    }
+    let request_line = lines.first().cloned().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        respond(&mut client, "400 Bad Request", "malformed request line\n");
+        return;
+    };
+
+    let connect = method.eq_ignore_ascii_case("CONNECT");
+    let (host, port, origin_form) = if connect {
+        match split_host_port(target) {
+            Some((h, p)) => (h, p, String::new()),
+            None => {
+                respond(&mut client, "400 Bad Request", "CONNECT needs host:port\n");
+                return;
+            }
+        }
+    } else {
+        let parsed = url::Url::parse(target)
+            .ok()
+            .filter(|u| u.scheme() == "http");
+        let Some(u) = parsed else {
+            respond(
+                &mut client,
+                "400 Bad Request",
+                "only CONNECT and absolute http:// requests are proxied\n",
+            );
+            return;
+        };
+        let host = u
+            .host_str()
+            .unwrap_or_default()
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_ascii_lowercase();
+        let port = u.port_or_known_default().unwrap_or(80);
+        let mut path = u.path().to_string();
+        if let Some(q) = u.query() {
+            path.push('?');
+            path.push_str(q);
+        }
+        (host, port, path)
+    };
+
+    let allowed = egress_host_allowed(&ctx.allow, &host);
+    ctx.record(allowed, method, &host, port);
+    if !allowed {
+        respond(
+            &mut client,
+            "403 Forbidden",
+            &format!("aifo-coder: egress to {host} is not in the allowlist\n"),
+        );
+        return;
+    }
+    let mut upstream = match connect_upstream(&host, port) {
+        Ok(s) => s,
+        Err(e) => {
+            respond(
+                &mut client,
+                "502 Bad Gateway",
+                &format!("aifo-coder: cannot reach {host}:{port}: {e}\n"),
+            );
+            return;
+        }
+    };
+    let _ = client.set_read_timeout(None);
+
+    // Bytes the client sent past the head (TLS hello or request body) belong upstream.
+    let buffered = reader.buffer().to_vec();
+    if connect {
+        let _ = client.write_all(b"HTTP/1.1 200 Connection established\r\n\r\n");
+    } else {
+        let mut head = format!("{method} {origin_form} {version}\r\n");
+        for h in lines.iter().skip(1) {
+            let name = h.split(':').next().unwrap_or_default().trim();
+            if [
+                "proxy-connection",
+                "proxy-authorization",
+                "connection",
+                "keep-alive",
+            ]
+            .iter()
+            .any(|n| name.eq_ignore_ascii_case(n))
+            {
+                continue;
+            }
+            head.push_str(h);
+            head.push_str("\r\n");
+        }
+        head.push_str("Connection: close\r\n\r\n");
+        if upstream.write_all(head.as_bytes()).is_err() {
+            return;
+        }
+    }
+    if !buffered.is_empty() && upstream.write_all(&buffered).is_err() {
+        return;
+    }
+    relay(client, upstream);
+}
+
+/// Copy bytes in both directions until upstream closes.
+fn relay(client: TcpStream, upstream: TcpStream) {
+    let (Ok(mut client_rd), Ok(mut upstream_wr)) = (client.try_clone(), upstream.try_clone())
+    else {
+        return;
+    };
+    let up = std::thread::spawn(move || {
+        let _ = io::copy(&mut client_rd, &mut upstream_wr);
+        let _ = upstream_wr.shutdown(Shutdown::Write);
+    });
+    let mut upstream_rd = upstream;
+    let mut client_wr = client;
+    let _ = io::copy(&mut upstream_rd, &mut client_wr);
+    // Upstream is done; closing the client side also ends the other direction.
+    let _ = client_wr.shutdown(Shutdown::Both);
+    let _ = up.join();
+}
@@ -0,0 +1,129 @@
+//! Egress allowlist session RAII: start the host-side proxy, export its URL, stop on drop.
+//!
+//! Behavior
+//! - Binds the proxy to the gateway of the internal session network, the only address agent and
+//!   sidecar containers on that network can reach; exports AIFO_EGRESS_PROXY_URL for their env.
+//! - Moves the TCP toolexec proxy onto the same gateway so shims keep working without a route out.
+//! - On drop stops the proxy and removes the internal network unless running inside a fork pane.
+
+use std::io;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::cli::Cli;
+
+pub struct EgressSession {
+    flag: Option<Arc<AtomicBool>>,
+    handle: Option<std::thread::JoinHandle<()>>,
+    network: Option<String>,
+    verbose: bool,
+    in_fork_pane: bool,
+}
+
+impl EgressSession {
+    /// Start the egress proxy when the allowlist is enabled and not in dry-run.
+    pub fn start_if_requested(cli: &Cli) -> Result<Option<Self>, io::Error> {
+        if !aifo_coder::egress_allowlist_enabled() {
+            return Ok(None);
+        }
+        let use_err = aifo_coder::color_enabled_stderr();
+        let (kinds, _overrides) = crate::toolchain_session::plan_from_cli(cli);
+        let allow = aifo_coder::egress_allowlist(&kinds);
+        let net = aifo_coder::session_network_from_env()
+            .map(|n| n.name)
+            .unwrap_or_default();
+        if aifo_coder::egress_llm_host().is_none() {
+            aifo_coder::log_warn_stderr(
+                use_err,
+                "aifo-coder: warning: AIFO_API_BASE is not set; the egress allowlist has no LLM endpoint.",
+            );
+        }
+        if cli.dry_run {
+            aifo_coder::log_info_stderr(
+                use_err,
+                &format!(
+                    "aifo-coder: would route egress through the allowlist proxy on internal network {} (allow: {})",
+                    net,
+                    allow.join(", ")
+                ),
+            );
+            return Ok(None);
+        }
+        let fail = |msg: String| {
+            aifo_coder::log_error_stderr(use_err, &format!("aifo-coder: error: {msg}"));
+            io::Error::other(msg)
+        };
+        if !cfg!(target_os = "linux") {
+            return Err(fail(
+                "the egress allowlist needs a Linux engine host; containers on an internal network cannot reach the host through a Docker VM"
+                    .to_string(),
+            ));
+        }
+        let rt = aifo_coder::container_runtime().map_err(|e| fail(e.to_string()))?;
+        let gateway = rt.network_gateway(&net).ok_or_else(|| {
+            fail(format!(
+                "cannot determine the gateway of session network {net}"
+            ))
+        })?;
+        let sid = std::env::var("AIFO_CODER_FORK_SESSION").unwrap_or_default();
+        let log = aifo_coder::egress_log_path(&sid);
+        let (port, flag, handle) =
+            aifo_coder::egress_start_proxy(&gateway, allow.clone(), Some(log.clone()), cli.verbose)
+                .map_err(|e| fail(format!("failed to start egress proxy: {e}")))?;
+        std::env::set_var(
+            aifo_coder::EGRESS_PROXY_URL_ENV,
+            format!("http://{gateway}:{port}"),
+        );
+        std::env::set_var("AIFO_EGRESS_GATEWAY", &gateway);
+        let toolexec_bind = std::env::var("AIFO_TOOLEEXEC_BIND_HOST").unwrap_or_default();
+        if toolexec_bind.trim().is_empty() || toolexec_bind.trim() == "127.0.0.1" {
+            std::env::set_var("AIFO_TOOLEEXEC_BIND_HOST", &gateway);
+        }
+        if cli.verbose {
+            aifo_coder::log_info_stderr(
+                use_err,
+                &format!(
+                    "aifo-coder: egress allowlist proxy on {}:{} (allow: {}); log: {}",
+                    gateway,
+                    port,
+                    allow.join(", "),
+                    log.display()
+                ),
+            );
+        }
+
+        // Fork panes share the session network; the launcher that created it removes it.
+        let in_fork_pane = std::env::var("AIFO_CODER_FORK_INDEX")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .is_some();
+        Ok(Some(Self {
+            flag: Some(flag),
+            handle: Some(handle),
+            network: aifo_coder::session_network_from_env()
+                .filter(|n| n.managed)
+                .map(|n| n.name),
+            verbose: cli.verbose,
+            in_fork_pane,
+        }))
+    }
+}
+
+impl Drop for EgressSession {
+    fn drop(&mut self) {
+        if let Some(flag) = self.flag.take() {
+            flag.store(false, Ordering::SeqCst);
+        }
+        if let Some(h) = self.handle.take() {
+            let _ = h.join();
+        }
+        if self.in_fork_pane {
+            return;
+        }
+        if let (Some(net), Ok(rt)) = (self.network.take(), aifo_coder::container_runtime()) {
+            aifo_coder::remove_network_with(rt.as_ref(), &net, self.verbose);
+        }
+    }
+}
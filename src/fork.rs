@@ -318,7 +318,7 @@ pub fn fork_clean(repo_root: &Path, opts: &ForkCleanOpts) -> std::io::Result<i32
                 if created_at == 0 {
                     return false;
                 }
-                let age = (now.saturating_sub(created_at) / 86400) as u64;
+                let age = now.saturating_sub(created_at) / 86400;
                 age >= days
             })
             .collect()
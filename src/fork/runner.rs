@@ -170,6 +170,7 @@ pub fn fork_run(cli: &Cli, panes: usize) -> ExitCode {
         Agent::OpenCode { .. } => "opencode",
         Agent::Plandex { .. } => "plandex",
         Agent::Letta { .. } => "letta",
+        Agent::Run { name, .. } => name.as_str(),
         _ => "aider",
     };
     let state_base = env::var("AIFO_CODER_FORK_STATE_BASE")
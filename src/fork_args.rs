@@ -51,42 +51,66 @@ pub(crate) fn fork_build_child_args(cli: &crate::cli::Cli) -> Vec<String> {
     if cli.dry_run {
         args.push("--dry-run".to_string());
     }
+    if cli.hardened || aifo_coder::hardened_mode_enabled() {
+        args.push("--hardened".to_string());
+    }
+    for k in &cli.toolchain_seccomp_relaxed {
+        args.push("--toolchain-seccomp-relaxed".to_string());
+        args.push(k.clone());
+    }
+    if cli.egress_allowlist || aifo_coder::egress_allowlist_enabled() {
+        args.push("--egress-allowlist".to_string());
+    }
+    for h in &cli.egress_allow {
+        args.push("--egress-allow".to_string());
+        args.push(h.clone());
+    }
+    if cli.llm_gateway || aifo_coder::llm_gateway_enabled() {
+        args.push("--llm-gateway".to_string());
+    }
 
     // Subcommand and its args
-    match &cli.command {
-        Agent::Codex { args: a } => {
-            args.push("codex".to_string());
-            args.extend(a.clone());
-        }
-        Agent::Crush { args: a } => {
-            args.push("crush".to_string());
-            args.extend(a.clone());
-        }
-        Agent::Aider { args: a } => {
-            args.push("aider".to_string());
-            args.extend(a.clone());
-        }
-        Agent::OpenHands { args: a } => {
-            args.push("openhands".to_string());
-            args.extend(a.clone());
-        }
-        Agent::OpenCode { args: a } => {
-            args.push("opencode".to_string());
-            args.extend(a.clone());
-        }
-        Agent::Plandex { args: a } => {
-            args.push("plandex".to_string());
-            args.extend(a.clone());
-        }
-        Agent::Letta { args: a } => {
-            args.push("letta".to_string());
-            args.extend(a.clone());
-        }
+    let (agent, sub_args): (&str, &[String]) = match &cli.command {
+        Agent::Codex { args: a } => ("codex", a),
+        Agent::Crush { args: a } => ("crush", a),
+        Agent::Aider { args: a } => ("aider", a),
+        Agent::OpenHands { args: a } => ("openhands", a),
+        Agent::OpenCode { args: a } => ("opencode", a),
+        Agent::Plandex { args: a } => ("plandex", a),
+        Agent::Letta { args: a } => ("letta", a),
+        Agent::Run { name, args: a } => (name.as_str(), a),
         // For non-agent subcommands, default to aider to avoid starting doctor/images in panes.
-        _ => {
-            args.push("aider".to_string());
+        _ => ("aider", &[]),
+    };
+
+    // Resource limits: panes get the effective agent limits (config, CLI or their share of the
+    // host); sidecars are shared by the session, so only explicit toolchain flags are forwarded.
+    args.extend(aifo_coder::agent_resource_limits(agent, cli.fork).run_flags());
+    let tl = &cli.limits;
+    let toolchain_flags = [
+        ("--toolchain-cpus", tl.toolchain_cpus.clone()),
+        ("--toolchain-memory", tl.toolchain_memory.clone()),
+        (
+            "--toolchain-pids-limit",
+            tl.toolchain_pids_limit.map(|p| p.to_string()),
+        ),
+    ];
+    for (flag, value) in toolchain_flags {
+        if let Some(v) = value {
+            args.push(flag.to_string());
+            args.push(v);
         }
     }
+    for u in &tl.toolchain_ulimit {
+        args.push("--toolchain-ulimit".to_string());
+        args.push(u.clone());
+    }
+
+    if matches!(&cli.command, Agent::Run { .. }) {
+        args.push("run".to_string());
+    }
+    args.push(agent.to_string());
+    args.extend(sub_args.iter().cloned());
 
     args
 }
@@ -108,7 +132,17 @@ mod args_tests {
                     .expect("valid toolchain spec"),
             ],
             docker_network: Some("bridge".to_string()),
+            profile: None,
+            limits: crate::cli::LimitArgs {
+                toolchain_pids_limit: Some(2048),
+                ..Default::default()
+            },
+            hardened: true,
+            toolchain_seccomp_relaxed: vec!["rust".to_string()],
             docker_network_isolate: false,
+            egress_allowlist: true,
+            egress_allow: vec!["*.example.com".to_string()],
+            llm_gateway: true,
             no_toolchain_cache: true,
             toolchain_unix_socket: false,
             toolchain_bootstrap: vec!["typescript=global".to_string()],
@@ -133,6 +167,8 @@ mod args_tests {
             command: crate::cli::Agent::Aider {
                 args: vec!["--help".to_string(), "--".to_string(), "extra".to_string()],
             },
+            config_applied: Vec::new(),
+            profile_applied: Vec::new(),
         }
     }
 
@@ -186,6 +222,37 @@ mod args_tests {
             "expected --dry-run in child args: {}",
             joined
         );
+        // Fork panes get a per-pane share of the host plus explicit sidecar limits
+        assert!(
+            joined.contains("--cpus ") && joined.contains("--pids-limit "),
+            "expected per-pane resource limits in child args: {}",
+            joined
+        );
+        assert!(
+            joined.contains("--toolchain-pids-limit 2048"),
+            "expected explicit toolchain limit in child args: {}",
+            joined
+        );
+        assert!(
+            args.iter().any(|s| s == "--hardened"),
+            "expected --hardened in child args: {}",
+            joined
+        );
+        assert!(
+            joined.contains("--toolchain-seccomp-relaxed rust"),
+            "expected relaxed seccomp kinds in child args: {}",
+            joined
+        );
+        assert!(
+            joined.contains("--egress-allowlist --egress-allow *.example.com"),
+            "expected egress allowlist flags in child args: {}",
+            joined
+        );
+        assert!(
+            joined.contains("--llm-gateway"),
+            "expected llm gateway flag in child args: {}",
+            joined
+        );
         // Must NOT contain any fork flags
         for bad in [
             "--fork ",
@@ -274,4 +341,23 @@ mod args_tests {
             args2
         );
     }
+
+    #[test]
+    fn test_run_subcommand_preserves_agent_name_in_child_args() {
+        let cli = crate::cli::Cli::parse_from([
+            "aifo-coder",
+            "--fork=2",
+            "run",
+            "my-agent",
+            "--",
+            "--flag",
+        ]);
+        let args = fork_build_child_args(&cli);
+        let tail: Vec<&str> = args.iter().map(String::as_str).collect();
+        assert!(
+            tail.ends_with(&["run", "my-agent", "--flag"]),
+            "expected run subcommand with agent name, got: {:?}",
+            args
+        );
+    }
 }
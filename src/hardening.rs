@@ -0,0 +1,111 @@
+//! Hardened container mode: dropped capabilities, no-new-privileges and a read-only root.
+//!
+//! Enabled by AIFO_CODER_HARDENED=1 (`--hardened`, `[security] hardened = true`). When on, agent
+//! containers, toolchain sidecars and support-matrix probes run with `--cap-drop ALL` plus a small
+//! add-back list (AIFO_CODER_CAP_ADD), `--security-opt no-new-privileges` and `--read-only`.
+//! Writable paths are then limited to bind mounts, named volumes and the tmpfs mounts below.
+
+use std::env;
+
+/// Whether hardened mode is on when AIFO_CODER_HARDENED is unset.
+pub const HARDENED_DEFAULT: bool = false;
+
+/// Capabilities kept in hardened mode unless AIFO_CODER_CAP_ADD says otherwise.
+///
+/// They only matter when the container process runs as root (Podman keep-id, root hosts): the
+/// entrypoint fixes ownership of $HOME and drops to the runtime user via gosu.
+pub const HARDENED_CAP_ADD_DEFAULT: &[&str] =
+    &["CHOWN", "DAC_OVERRIDE", "FOWNER", "SETGID", "SETUID"];
+
+/// Home directory of the runtime user inside agent and toolchain images.
+const CONTAINER_HOME: &str = "/home/coder";
+
+/// Validate a capability name (e.g. "NET_BIND_SERVICE" or "cap_chown"); returns it normalized.
+pub fn parse_capability(s: &str) -> Result<String, String> {
+    let t = s.trim().to_ascii_uppercase();
+    let name = t.strip_prefix("CAP_").unwrap_or(&t);
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+    {
+        Ok(name.to_string())
+    } else {
+        Err(format!(
+            "invalid capability '{s}': expected a name such as CHOWN or NET_BIND_SERVICE"
+        ))
+    }
+}
+
+/// True when hardened mode is enabled (AIFO_CODER_HARDENED=1|true|yes|on).
+pub fn hardened_mode_enabled() -> bool {
+    match env::var("AIFO_CODER_HARDENED") {
+        Ok(v) => matches!(
+            v.trim().to_ascii_lowercase().as_str(),
+            "1" | "true" | "yes" | "on"
+        ),
+        Err(_) => HARDENED_DEFAULT,
+    }
+}
+
+/// Capabilities added back after `--cap-drop ALL`; an empty AIFO_CODER_CAP_ADD keeps none.
+pub fn hardened_cap_add() -> Vec<String> {
+    match env::var("AIFO_CODER_CAP_ADD") {
+        Ok(v) => v
+            .split(',')
+            .filter(|c| !c.trim().is_empty())
+            .filter_map(|c| parse_capability(c).ok())
+            .collect(),
+        Err(_) => HARDENED_CAP_ADD_DEFAULT
+            .iter()
+            .map(|c| c.to_string())
+            .collect(),
+    }
+}
+
+/// tmpfs mounts backing the writable paths of a read-only container.
+///
+/// /tmp and $HOME (caches, gnupg, nss_wrapper files) are world-writable like in the images;
+/// XDG_RUNTIME_DIR (/tmp/runtime-UID) is private to UID when the user mapping is known.
+pub fn hardened_tmpfs_mounts(uid_gid: Option<(u32, u32)>) -> Vec<String> {
+    let mut mounts = vec![
+        "/tmp:rw,nosuid,nodev,mode=1777".to_string(),
+        format!("{CONTAINER_HOME}:rw,nosuid,nodev,mode=1777"),
+    ];
+    if let Some((uid, gid)) = uid_gid {
+        mounts.push(format!(
+            "/tmp/runtime-{uid}:rw,nosuid,nodev,noexec,mode=0700,uid={uid},gid={gid}"
+        ));
+    }
+    mounts
+}
+
+/// docker/podman `run` flags for hardened mode; empty when hardened mode is off.
+pub fn hardened_run_flags(uid_gid: Option<(u32, u32)>) -> Vec<String> {
+    if !hardened_mode_enabled() {
+        return Vec::new();
+    }
+    let mut flags = vec!["--cap-drop".to_string(), "ALL".to_string()];
+    for cap in hardened_cap_add() {
+        flags.push("--cap-add".to_string());
+        flags.push(cap);
+    }
+    flags.push("--security-opt".to_string());
+    flags.push("no-new-privileges".to_string());
+    flags.push("--read-only".to_string());
+    for m in hardened_tmpfs_mounts(uid_gid) {
+        flags.push("--tmpfs".to_string());
+        flags.push(m);
+    }
+    flags
+}
+
+/// Protections applied to new containers, as (label, active) pairs for doctor and banners.
+pub fn hardened_protections() -> Vec<(&'static str, bool)> {
+    let on = hardened_mode_enabled();
+    vec![
+        ("cap-drop ALL", on),
+        ("no-new-privileges", on),
+        ("read-only rootfs", on),
+    ]
+}
@@ -11,9 +11,21 @@
 //! - util::*: small helpers (shell/json escaping, URL decoding, Docker security parsing, fs utilities).
 //! - color.rs: color mode and paint/log wrappers (exact strings preserved).
 //! - apparmor.rs: host AppArmor detection and profile selection helpers.
+//! - config.rs: layered TOML configuration (system → user → repo) exported into AIFO_* env.
+//! - limits.rs: CPU/memory/PID limits for agent containers and toolchain sidecars.
+//! - hardening.rs: hardened mode run flags (cap-drop, no-new-privileges, read-only root).
+//! - seccomp.rs: generated seccomp profile (strict for agents, relaxable per toolchain).
+//! - egress.rs: host-side allowlist proxy for agent/sidecar egress on an internal network.
+//! - llm_gateway.rs: host-side LLM reverse proxy injecting the real API key (containers get a session key).
+//! - llm_credentials.rs: API key helper commands resolved on the host at launch.
+//! - llm_providers.rs: declarative AIFO_API_* → provider variable mapping per agent.
 //!
 //! Environment invariants (documented for contributors)
 //! - AIFO_TOOLEEXEC_URL/TOKEN: exported by proxy start; injected into agent env; respected by shims.
+//! - AIFO_TOOLEEXEC_TLS_DIR: session client certificates exported by a mutual TLS proxy start;
+//!   mounted read-only at /run/aifo-tls in the agent, where it names that path.
+//! - AIFO_TOOLEEXEC_SIGNING_KEY: per-session request-signing key exported by proxy start; injected
+//!   into agent env for shims, never sent over the wire.
 //! - AIFO_SESSION_NETWORK: session network to join (default bridge; CLI/env override). Networks
 //!   created by the launcher (e.g., via --docker-network-isolate) are removed on cleanup.
 //! - AIFO_TOOLEEXEC_ADD_HOST (Linux): when "1", add host-gateway entry; used for troubleshooting.
@@ -27,8 +39,10 @@
 mod apparmor;
 #[allow(clippy::doc_overindented_list_items)]
 mod color;
+mod config;
 mod docker;
 mod docker_mod;
+mod egress;
 mod errors;
 mod fork;
 #[path = "fork/meta.rs"]
@@ -38,9 +52,15 @@ mod fork_strategy;
 #[cfg(windows)]
 #[path = "fork/windows/helpers.rs"]
 mod fork_windows_helpers;
+mod hardening;
+mod limits;
+mod llm_credentials;
+mod llm_gateway;
+mod llm_providers;
 mod lock;
 pub mod proxy;
 mod registry;
+mod seccomp;
 #[cfg(feature = "otel")]
 mod telemetry;
 mod toolchain;
@@ -58,7 +78,19 @@ pub fn shim_tool_names() -> &'static [&'static str] {
 
 pub use apparmor::*;
 pub use color::*;
+pub use config::{
+    config_custom_agent, config_discover_paths, config_env_source, config_init, config_knob,
+    config_load_dotenv, config_load_from, config_resolved, config_value_to_env, user_config_path,
+    AgentRuntime, AifoConfig, ConfigKnob, ConfigPaths, ConfigSource, CustomAgentConfig,
+    EffectiveSetting, ImageFlavor, LimitsConfig, LlmConfig, LlmProviderConfig, ProfileConfig,
+    ResolvedConfig, SecurityConfig, BUILTIN_AGENTS, CONFIG_ENV_KNOBS, REPO_CONFIG_FILE,
+};
 pub use docker::*;
+pub use egress::{
+    egress_allowlist, egress_allowlist_enabled, egress_host_allowed, egress_llm_host,
+    egress_log_path, egress_proxy_env, egress_start_proxy, parse_egress_host, EGRESS_PROXY_URL_ENV,
+    EGRESS_TOOLCHAIN_HOSTS,
+};
 pub use errors::exit_code_for_io_error;
 pub use errors::{display_for_fork_error, display_for_toolchain_error};
 pub use errors::{exit_code_for_fork_error, exit_code_for_toolchain_error};
@@ -70,12 +102,38 @@ pub use fork_windows_helpers::{
     fork_bash_inner_string, fork_ps_inner_string, ps_wait_process_cmd, wt_build_new_tab_args,
     wt_build_split_args, wt_orient_for_layout,
 };
+pub use hardening::{
+    hardened_cap_add, hardened_mode_enabled, hardened_protections, hardened_run_flags,
+    hardened_tmpfs_mounts, parse_capability, HARDENED_CAP_ADD_DEFAULT, HARDENED_DEFAULT,
+};
+pub use limits::{
+    agent_resource_limits, fork_pane_default_limits, parse_cpus_limit, parse_memory_limit,
+    parse_pids_limit, parse_ulimit, toolchain_resource_limits, ResourceLimits,
+    FORK_PANE_PIDS_LIMIT,
+};
+pub use llm_credentials::{
+    llm_api_key_helper, llm_provider, llm_resolve_api_key, llm_run_api_key_helper, ApiKeyHelper,
+    API_KEY_COMMAND_ENV,
+};
+pub use llm_gateway::{
+    llm_gateway_enabled, llm_gateway_env, llm_gateway_log_path, llm_gateway_session_key,
+    llm_gateway_start, LlmGatewayConfig, LLM_GATEWAY_KEY_ENV, LLM_GATEWAY_URL_ENV,
+};
+pub use llm_providers::{
+    agent_llm_vars, llm_provider_env, llm_provider_spec, llm_selected_provider, LlmProviderSpec,
+    LlmValue, LlmValues, DEFAULT_LLM_PROVIDER, LLM_PROVIDERS,
+};
 pub use lock::*;
 pub use proxy::*;
 pub use registry::*;
+pub use seccomp::{
+    parse_seccomp_mode, seccomp_mode, seccomp_profile_json, seccomp_relaxed_for, seccomp_run_flags,
+    write_seccomp_profile, SeccompMode, SECCOMP_DENIED_SYSCALLS, SECCOMP_RELAXED_SYSCALLS,
+};
 pub use toolchain::sidecar::{
-    ensure_network_exists, session_network_from_env, set_generated_session_network_env,
-    set_session_network_env,
+    ensure_network_exists, ensure_network_exists_with, remove_network_with,
+    session_network_from_env, set_generated_session_network_env, set_session_network_env,
+    set_session_network_internal,
 };
 pub use toolchain::*;
 pub use ui::warn::{warn_print, warn_prompt_continue_or_quit};
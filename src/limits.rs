@@ -0,0 +1,277 @@
+//! Resource limits (CPU, memory, PIDs, ulimits) for agent containers and toolchain sidecars.
+//!
+//! Resolution, lowest precedence first:
+//! - agents: fork-pane share of the host (fork mode only) → `[limits.agent]` →
+//!   `[limits.agents.NAME]` → AIFO_CODER_{CPUS,MEMORY,PIDS_LIMIT,ULIMITS} (set by --cpus etc.)
+//! - sidecars: `[limits.toolchain]` → `[limits.toolchains.KIND]` →
+//!   AIFO_TOOLCHAIN_{CPUS,MEMORY,PIDS_LIMIT,ULIMITS} (set by --toolchain-cpus etc.)
+//!
+//! Sidecars are shared by all panes of a fork session, so fork defaults apply to agents only.
+//! Invalid environment values are ignored; config files are validated on load.
+
+use std::env;
+
+use serde::{Deserialize, Deserializer};
+
+/// PID limit given to each fork pane's agent container unless configured otherwise.
+pub const FORK_PANE_PIDS_LIMIT: i64 = 4096;
+
+/// Limits for one container; unset fields leave the engine default (unlimited).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResourceLimits {
+    /// --cpus (e.g. "2" or "1.5"); numbers and strings are accepted in config files.
+    #[serde(deserialize_with = "de_cpus")]
+    pub cpus: Option<String>,
+    /// --memory (e.g. "4g", "512m").
+    pub memory: Option<String>,
+    /// --pids-limit (-1 for unlimited).
+    pub pids: Option<i64>,
+    /// --ulimit entries (e.g. "nofile=4096:8192").
+    pub ulimits: Vec<String>,
+}
+
+fn de_cpus<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Cpus {
+        Int(i64),
+        Float(f64),
+        Str(String),
+    }
+    Ok(match Option::<Cpus>::deserialize(d)? {
+        Some(Cpus::Int(n)) => Some(n.to_string()),
+        Some(Cpus::Float(f)) => Some(f.to_string()),
+        Some(Cpus::Str(s)) => Some(s),
+        None => None,
+    })
+}
+
+/// Validate a --cpus value (positive decimal number).
+pub fn parse_cpus_limit(s: &str) -> Result<String, String> {
+    let t = s.trim();
+    match t.parse::<f64>() {
+        Ok(v) if v.is_finite() && v > 0.0 => Ok(t.to_string()),
+        _ => Err(format!(
+            "invalid cpus '{s}': expected a positive number such as 2 or 1.5"
+        )),
+    }
+}
+
+/// Validate a --memory value (bytes with optional b/k/m/g suffix).
+pub fn parse_memory_limit(s: &str) -> Result<String, String> {
+    let t = s.trim().to_ascii_lowercase();
+    let digits = t.trim_end_matches(['b', 'k', 'm', 'g']);
+    let suffix_len = t.len() - digits.len();
+    let ok = !digits.is_empty()
+        && suffix_len <= 1
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && digits.parse::<u64>().is_ok_and(|n| n > 0);
+    if ok {
+        Ok(t)
+    } else {
+        Err(format!(
+            "invalid memory '{s}': expected a size such as 512m or 4g"
+        ))
+    }
+}
+
+/// Validate a --pids-limit value (positive, or -1 for unlimited).
+pub fn parse_pids_limit(s: &str) -> Result<i64, String> {
+    match s.trim().parse::<i64>() {
+        Ok(n) if n > 0 || n == -1 => Ok(n),
+        _ => Err(format!(
+            "invalid pids limit '{s}': expected a positive number or -1"
+        )),
+    }
+}
+
+/// Validate a --ulimit value (NAME=SOFT[:HARD]).
+pub fn parse_ulimit(s: &str) -> Result<String, String> {
+    let t = s.trim();
+    let valid = t.split_once('=').is_some_and(|(name, vals)| {
+        !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_lowercase())
+            && vals
+                .split(':')
+                .all(|v| v == "-1" || (!v.is_empty() && v.chars().all(|c| c.is_ascii_digit())))
+            && vals.split(':').count() <= 2
+    });
+    if valid {
+        Ok(t.to_string())
+    } else {
+        Err(format!(
+            "invalid ulimit '{s}': expected NAME=SOFT[:HARD], e.g. nofile=4096:8192"
+        ))
+    }
+}
+
+impl ResourceLimits {
+    pub fn is_empty(&self) -> bool {
+        self.cpus.is_none()
+            && self.memory.is_none()
+            && self.pids.is_none()
+            && self.ulimits.is_empty()
+    }
+
+    /// Check every set field; used when loading config files.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(c) = self.cpus.as_deref() {
+            parse_cpus_limit(c)?;
+        }
+        if let Some(m) = self.memory.as_deref() {
+            parse_memory_limit(m)?;
+        }
+        if let Some(p) = self.pids {
+            parse_pids_limit(&p.to_string())?;
+        }
+        for u in &self.ulimits {
+            parse_ulimit(u)?;
+        }
+        Ok(())
+    }
+
+    /// Overlay fields set in OTHER (a non-empty ulimit list replaces ours).
+    pub fn overlay(&mut self, other: &ResourceLimits) {
+        if other.cpus.is_some() {
+            self.cpus = other.cpus.clone();
+        }
+        if other.memory.is_some() {
+            self.memory = other.memory.clone();
+        }
+        if other.pids.is_some() {
+            self.pids = other.pids;
+        }
+        if !other.ulimits.is_empty() {
+            self.ulimits = other.ulimits.clone();
+        }
+    }
+
+    /// docker/podman `run` flags for these limits.
+    pub fn run_flags(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        if let Some(c) = &self.cpus {
+            flags.push("--cpus".to_string());
+            flags.push(c.clone());
+        }
+        if let Some(m) = &self.memory {
+            flags.push("--memory".to_string());
+            flags.push(m.clone());
+        }
+        if let Some(p) = self.pids {
+            flags.push("--pids-limit".to_string());
+            flags.push(p.to_string());
+        }
+        for u in &self.ulimits {
+            flags.push("--ulimit".to_string());
+            flags.push(u.clone());
+        }
+        flags
+    }
+
+    /// Compact summary for banners and logs, e.g. "cpus=2 memory=4g pids=4096" or "none".
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(c) = &self.cpus {
+            parts.push(format!("cpus={c}"));
+        }
+        if let Some(m) = &self.memory {
+            parts.push(format!("memory={m}"));
+        }
+        if let Some(p) = self.pids {
+            parts.push(format!("pids={p}"));
+        }
+        for u in &self.ulimits {
+            parts.push(format!("ulimit={u}"));
+        }
+        if parts.is_empty() {
+            "none".to_string()
+        } else {
+            parts.join(" ")
+        }
+    }
+}
+
+/// Limits from PREFIX_{CPUS,MEMORY,PIDS_LIMIT,ULIMITS}; invalid values are skipped.
+fn limits_from_env(prefix: &str) -> ResourceLimits {
+    let get = |suffix: &str| {
+        env::var(format!("{prefix}_{suffix}"))
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+    };
+    ResourceLimits {
+        cpus: get("CPUS").and_then(|v| parse_cpus_limit(&v).ok()),
+        memory: get("MEMORY").and_then(|v| parse_memory_limit(&v).ok()),
+        pids: get("PIDS_LIMIT").and_then(|v| parse_pids_limit(&v).ok()),
+        ulimits: get("ULIMITS")
+            .map(|v| v.split(',').filter_map(|u| parse_ulimit(u).ok()).collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// Total host memory in bytes, when it can be determined cheaply.
+fn host_memory_bytes() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let info = std::fs::read_to_string("/proc/meminfo").ok()?;
+        let line = info.lines().find(|l| l.starts_with("MemTotal:"))?;
+        let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+        Some(kib * 1024)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Per-pane share of the host for a fork session with PANES panes.
+///
+/// CPUs are divided evenly (rounded down to 0.1, at least 0.5), memory likewise when the host
+/// total is known, and each pane gets FORK_PANE_PIDS_LIMIT processes.
+pub fn fork_pane_default_limits(panes: usize) -> ResourceLimits {
+    let panes = panes.max(1) as u64;
+    let host_cpus = std::thread::available_parallelism()
+        .map(|n| n.get() as u64)
+        .unwrap_or(1);
+    let tenths = (host_cpus * 10 / panes).max(5);
+    let cpus = if tenths.is_multiple_of(10) {
+        (tenths / 10).to_string()
+    } else {
+        format!("{}.{}", tenths / 10, tenths % 10)
+    };
+    let memory = host_memory_bytes().map(|b| format!("{}m", (b / panes / (1024 * 1024)).max(256)));
+    ResourceLimits {
+        cpus: Some(cpus),
+        memory,
+        pids: Some(FORK_PANE_PIDS_LIMIT),
+        ulimits: Vec::new(),
+    }
+}
+
+/// Effective limits for AGENT's container; FORK_PANES is Some(n) when launching n fork panes.
+pub fn agent_resource_limits(agent: &str, fork_panes: Option<usize>) -> ResourceLimits {
+    let mut limits = fork_panes.map(fork_pane_default_limits).unwrap_or_default();
+    if let Some(resolved) = crate::config_resolved() {
+        let cfg = &resolved.config.limits;
+        limits.overlay(&cfg.agent);
+        if let Some(per_agent) = cfg.agents.get(agent) {
+            limits.overlay(per_agent);
+        }
+    }
+    limits.overlay(&limits_from_env("AIFO_CODER"));
+    limits
+}
+
+/// Effective limits for the sidecar of toolchain KIND (normalized, e.g. "c-cpp").
+pub fn toolchain_resource_limits(kind: &str) -> ResourceLimits {
+    let mut limits = ResourceLimits::default();
+    if let Some(resolved) = crate::config_resolved() {
+        let cfg = &resolved.config.limits;
+        limits.overlay(&cfg.toolchain);
+        if let Some(per_kind) = cfg.toolchains.get(kind) {
+            limits.overlay(per_kind);
+        }
+    }
+    limits.overlay(&limits_from_env("AIFO_TOOLCHAIN"));
+    limits
+}
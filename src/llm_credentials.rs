@@ -0,0 +1,162 @@
+//! LLM API key helpers: obtain AIFO_API_KEY on the host from a credential helper command.
+//!
+//! Like a git credential helper, `[llm] api_key_command` (AIFO_API_KEY_COMMAND) is a shell
+//! command whose first line of output is the key, e.g. `pass show llm/azure`. When a provider is
+//! selected (`[llm] provider`, AIFO_CODER_LLM_PROVIDER), its `[llm.providers.NAME]
+//! api_key_command` wins. An AIFO_API_KEY already set in the environment or .env wins over both.
+//!
+//! Helper commands run on the host, so a repository cannot supply them: the repo
+//! `.aifo-coder.toml` may not set `api_key_command`, and an AIFO_API_KEY_COMMAND loaded from a
+//! `.env` file is refused.
+//!
+//! The helper runs at most once per launcher process, with the terminal on stdin/stderr so it
+//! can prompt. The key is exported as AIFO_API_KEY for the rest of the session (fork panes and
+//! the credential gateway inherit it); it is never written to disk or logged.
+
+use once_cell::sync::OnceCell;
+use std::env;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Environment variable naming the default API key helper command.
+pub const API_KEY_COMMAND_ENV: &str = "AIFO_API_KEY_COMMAND";
+
+/// A configured API key helper.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKeyHelper {
+    /// Provider whose `[llm.providers.NAME]` entry supplied the command, if any.
+    pub provider: Option<String>,
+    /// Shell command printing the key.
+    pub command: String,
+}
+
+impl ApiKeyHelper {
+    /// Where the command is configured, for messages.
+    pub fn label(&self) -> String {
+        match &self.provider {
+            Some(p) => format!("[llm.providers.{p}] api_key_command"),
+            None => "api_key_command".to_string(),
+        }
+    }
+}
+
+fn env_nonempty(name: &str) -> Option<String> {
+    env::var(name)
+        .ok()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+/// Selected LLM provider (AIFO_CODER_LLM_PROVIDER), lowercased.
+pub fn llm_provider() -> Option<String> {
+    env_nonempty("AIFO_CODER_LLM_PROVIDER").map(|p| p.to_ascii_lowercase())
+}
+
+/// The .env file that set AIFO_API_KEY_COMMAND, if that is where it came from.
+fn dotenv_api_key_command() -> Option<PathBuf> {
+    env_nonempty(API_KEY_COMMAND_ENV)?;
+    match crate::config_env_source(API_KEY_COMMAND_ENV) {
+        crate::ConfigSource::DotEnv(path) => Some(path),
+        _ => None,
+    }
+}
+
+/// The helper that supplies AIFO_API_KEY when it is unset, if one is configured.
+pub fn llm_api_key_helper() -> Option<ApiKeyHelper> {
+    if let Some(provider) = llm_provider() {
+        let command = crate::config_resolved()
+            .and_then(|r| r.config.llm.providers.get(&provider))
+            .and_then(|p| p.api_key_command.clone())
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty());
+        if let Some(command) = command {
+            return Some(ApiKeyHelper {
+                provider: Some(provider),
+                command,
+            });
+        }
+    }
+    if dotenv_api_key_command().is_some() {
+        return None;
+    }
+    env_nonempty(API_KEY_COMMAND_ENV).map(|command| ApiKeyHelper {
+        provider: None,
+        command,
+    })
+}
+
+/// Run HELPER and return the first line it prints (trimmed).
+pub fn llm_run_api_key_helper(helper: &ApiKeyHelper) -> Result<String, String> {
+    let mut cmd = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C").arg(&helper.command);
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c").arg(&helper.command);
+        c
+    };
+    let out = cmd
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("cannot run {} `{}`: {e}", helper.label(), helper.command))?;
+    if !out.status.success() {
+        let status = out
+            .status
+            .code()
+            .map(|c| format!("exit status {c}"))
+            .unwrap_or_else(|| "terminated by signal".to_string());
+        return Err(format!(
+            "{} `{}` failed ({status}); fix the helper or set AIFO_API_KEY",
+            helper.label(),
+            helper.command
+        ));
+    }
+    let stdout = String::from_utf8(out.stdout).map_err(|_| {
+        format!(
+            "{} `{}` printed a key that is not valid UTF-8",
+            helper.label(),
+            helper.command
+        )
+    })?;
+    let key = stdout.lines().next().unwrap_or_default().trim().to_string();
+    if key.is_empty() {
+        return Err(format!(
+            "{} `{}` printed no key",
+            helper.label(),
+            helper.command
+        ));
+    }
+    Ok(key)
+}
+
+static RESOLVED: OnceCell<Result<Option<ApiKeyHelper>, String>> = OnceCell::new();
+
+/// Export AIFO_API_KEY from the configured helper unless it is already set.
+///
+/// Runs the helper at most once per process. Returns the helper that supplied the key, or
+/// None when AIFO_API_KEY was already set or no helper is configured.
+pub fn llm_resolve_api_key() -> Result<Option<ApiKeyHelper>, String> {
+    RESOLVED
+        .get_or_init(|| {
+            if env_nonempty("AIFO_API_KEY").is_some() {
+                return Ok(None);
+            }
+            let Some(helper) = llm_api_key_helper() else {
+                if let Some(path) = dotenv_api_key_command() {
+                    return Err(format!(
+                        "{API_KEY_COMMAND_ENV} from {} is ignored: helper commands run on the \
+                         host and must come from the environment or the system/user config",
+                        path.display()
+                    ));
+                }
+                return Ok(None);
+            };
+            let key = llm_run_api_key_helper(&helper)?;
+            env::set_var("AIFO_API_KEY", key);
+            Ok(Some(helper))
+        })
+        .clone()
+}
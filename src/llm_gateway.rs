@@ -0,0 +1,472 @@
+//! LLM credential gateway: a host-side reverse proxy that keeps the real API key out of containers.
+//!
+//! Enabled by AIFO_CODER_LLM_GATEWAY=1 (`--llm-gateway`, `[llm] gateway`). The launcher starts
+//! the gateway next to the toolexec proxy and hands agent containers a session-scoped key plus a
+//! base URL pointing at the gateway instead of AIFO_API_KEY/AIFO_API_BASE. The gateway accepts
+//! OpenAI/Azure-compatible requests carrying the session key (Authorization: Bearer, api-key or
+//! x-api-key), swaps in the real key and forwards them to the AIFO_API_BASE origin, streaming the
+//! response back. Each request is logged with its request/response sizes; bodies never are.
+
+use std::env;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::toolchain::pool::{append_log_line, serve_pooled, WorkerPool};
+
+/// Base URL containers use for LLM requests while the gateway runs.
+pub const LLM_GATEWAY_URL_ENV: &str = "AIFO_LLM_GATEWAY_URL";
+/// Session-scoped key containers present to the gateway in place of the real API key.
+pub const LLM_GATEWAY_KEY_ENV: &str = "AIFO_LLM_GATEWAY_KEY";
+
+/// Largest request head (request line plus headers) the gateway accepts.
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+/// Largest request body the gateway buffers before forwarding.
+const MAX_BODY_BYTES: usize = 32 * 1024 * 1024;
+const HEAD_READ_TIMEOUT: Duration = Duration::from_secs(60);
+const UPSTREAM_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Requests handled at once; further connections get 503 (streamed completions hold a worker).
+const GATEWAY_MAX_CONNECTIONS: usize = 64;
+
+/// Headers that describe one hop and are never forwarded as-is.
+const HOP_HEADERS: &[&str] = &[
+    "host",
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "content-length",
+];
+
+/// Headers that may carry the API key, in the form each provider expects.
+const KEY_HEADERS: &[&str] = &["authorization", "api-key", "x-api-key"];
+
+/// True when the credential gateway is on (AIFO_CODER_LLM_GATEWAY=1|true|yes|on).
+pub fn llm_gateway_enabled() -> bool {
+    env::var("AIFO_CODER_LLM_GATEWAY")
+        .ok()
+        .map(|v| v.trim().to_ascii_lowercase())
+        .is_some_and(|v| matches!(v.as_str(), "1" | "true" | "yes" | "on"))
+}
+
+/// Gateway base URL and session key for container env; None unless the gateway runs.
+pub fn llm_gateway_env() -> Option<(String, String)> {
+    let url = env::var(LLM_GATEWAY_URL_ENV)
+        .ok()
+        .filter(|v| !v.trim().is_empty())?;
+    let key = env::var(LLM_GATEWAY_KEY_ENV)
+        .ok()
+        .filter(|v| !v.trim().is_empty())?;
+    Some((url, key))
+}
+
+/// Fresh session key (`aifo-session-` plus 128 random bits in hex).
+pub fn llm_gateway_session_key() -> io::Result<String> {
+    let mut buf = [0u8; 16];
+    getrandom::getrandom(&mut buf)
+        .map_err(|e| io::Error::other(format!("secure RNG failed: {e}")))?;
+    let hex: String = buf.iter().map(|b| format!("{b:02x}")).collect();
+    Ok(format!("aifo-session-{hex}"))
+}
+
+/// Default gateway log for SESSION_ID (AIFO_CODER_LLM_GATEWAY_LOG overrides it).
+pub fn llm_gateway_log_path(session_id: &str) -> PathBuf {
+    if let Some(p) = env::var("AIFO_CODER_LLM_GATEWAY_LOG")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+    {
+        return PathBuf::from(p);
+    }
+    let base = env::var("XDG_RUNTIME_DIR")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir);
+    base.join(format!("aifo-coder-llm-gateway-{session_id}.log"))
+}
+
+/// Settings for one gateway instance.
+#[derive(Debug, Clone)]
+pub struct LlmGatewayConfig {
+    /// Upstream endpoint (AIFO_API_BASE); requests go to its origin with the path containers used.
+    pub upstream: url::Url,
+    /// Real API key injected into forwarded requests.
+    pub api_key: String,
+    /// Key containers must present.
+    pub session_key: String,
+    /// Append-only request log (sizes and status only).
+    pub log: Option<PathBuf>,
+    pub verbose: bool,
+}
+
+impl LlmGatewayConfig {
+    /// Container-side base URL for a gateway reachable at HOST:PORT (keeps the upstream path).
+    pub fn container_base_url(&self, host: &str, port: u16) -> String {
+        let path = self.upstream.path().trim_end_matches('/');
+        format!("http://{host}:{port}{path}")
+    }
+}
+
+struct GatewayCtx {
+    cfg: LlmGatewayConfig,
+    origin: String,
+    client: reqwest::blocking::Client,
+}
+
+impl GatewayCtx {
+    fn record(&self, method: &str, path: &str, status: u16, req: usize, resp: u64, t: Instant) {
+        let ms = t.elapsed().as_millis();
+        if self.cfg.verbose {
+            crate::log_info_stderr(
+                crate::color_enabled_stderr(),
+                &format!(
+                    "aifo-coder: llm gateway {method} {path} -> {status} (request {req} B, response {resp} B, {ms} ms)"
+                ),
+            );
+        }
+        if let Some(log) = &self.cfg.log {
+            let ts = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            let line = format!("{ts} {method} {path} {status} req={req} resp={resp} ms={ms}\n");
+            let _ = append_log_line(log, &line);
+        }
+    }
+}
+
+/// Start the gateway on BIND_HOST (random port).
+///
+/// Returns the bound port, the running flag (store false to stop) and the accept-loop handle.
+pub fn llm_gateway_start(
+    bind_host: &str,
+    cfg: LlmGatewayConfig,
+) -> io::Result<(u16, Arc<AtomicBool>, JoinHandle<()>)> {
+    if !matches!(cfg.upstream.scheme(), "http" | "https") || cfg.upstream.host_str().is_none() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "LLM gateway upstream must be an http(s) URL: {}",
+                cfg.upstream
+            ),
+        ));
+    }
+    let origin = cfg.upstream.origin().ascii_serialization();
+    let client = reqwest::blocking::Client::builder()
+        .no_gzip()
+        .redirect(reqwest::redirect::Policy::none())
+        .connect_timeout(UPSTREAM_CONNECT_TIMEOUT)
+        .timeout(None)
+        .build()
+        .map_err(|e| io::Error::other(format!("LLM gateway client setup failed: {e}")))?;
+    let listener = TcpListener::bind((bind_host, 0)).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("LLM gateway bind on {bind_host} failed: {e}"),
+        )
+    })?;
+    let port = listener.local_addr()?.port();
+    listener.set_nonblocking(true)?;
+    let running = Arc::new(AtomicBool::new(true));
+    let running_cl = running.clone();
+    let ctx = Arc::new(GatewayCtx {
+        cfg,
+        origin,
+        client,
+    });
+    let handle = std::thread::spawn(move || {
+        let pool = WorkerPool::new(GATEWAY_MAX_CONNECTIONS, "aifo-llm-gateway");
+        serve_pooled(
+            &listener,
+            &pool,
+            &running_cl,
+            "llm gateway",
+            ctx.cfg.verbose,
+            |s| respond_error(s, "503 Service Unavailable", "LLM gateway busy"),
+            |stream| {
+                let ctx = ctx.clone();
+                move || handle_client(&ctx, stream)
+            },
+        );
+    });
+    Ok((port, running, handle))
+}
+
+/// OpenAI-style JSON error so SDK clients surface the message.
+fn respond_error(stream: &mut TcpStream, status: &str, message: &str) {
+    let body = format!(
+        "{{\"error\":{{\"message\":{},\"type\":\"aifo_gateway_error\"}}}}",
+        serde_json::Value::String(message.to_string())
+    );
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.flush();
+}
+
+fn header_name(line: &str) -> &str {
+    line.split(':').next().unwrap_or_default().trim()
+}
+
+fn header_value(line: &str) -> &str {
+    line.split_once(':')
+        .map(|(_, v)| v.trim())
+        .unwrap_or_default()
+}
+
+/// Compare without an early exit so timing does not reveal how much of the key matched.
+fn keys_equal(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+/// Key presented in a key-carrying header (Bearer prefix stripped).
+fn presented_key(name: &str, value: &str) -> Option<String> {
+    if name.eq_ignore_ascii_case("authorization") {
+        let (scheme, token) = value.split_once(' ')?;
+        scheme
+            .eq_ignore_ascii_case("bearer")
+            .then(|| token.trim().to_string())
+    } else {
+        Some(value.to_string())
+    }
+}
+
+fn read_chunked<R: BufRead>(reader: &mut R, body: &mut Vec<u8>) -> Result<(), &'static str> {
+    loop {
+        let mut size_line = String::new();
+        reader
+            .read_line(&mut size_line)
+            .map_err(|_| "malformed chunked body")?;
+        let size_hex = size_line.trim().split(';').next().unwrap_or_default();
+        let size = usize::from_str_radix(size_hex, 16).map_err(|_| "malformed chunked body")?;
+        if size == 0 {
+            // Trailers end with an empty line.
+            loop {
+                let mut l = String::new();
+                match reader.read_line(&mut l) {
+                    Ok(0) => return Ok(()),
+                    Ok(_) if l.trim().is_empty() => return Ok(()),
+                    Ok(_) => {}
+                    Err(_) => return Err("malformed chunked body"),
+                }
+            }
+        }
+        if body.len() + size > MAX_BODY_BYTES {
+            return Err("request body too large");
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader
+            .read_exact(&mut body[start..])
+            .map_err(|_| "truncated chunked body")?;
+        let mut crlf = [0u8; 2];
+        reader
+            .read_exact(&mut crlf)
+            .map_err(|_| "malformed chunked body")?;
+    }
+}
+
+fn handle_client(ctx: &GatewayCtx, mut client: TcpStream) {
+    let started = Instant::now();
+    let _ = client.set_nonblocking(false);
+    let _ = client.set_read_timeout(Some(HEAD_READ_TIMEOUT));
+    let mut reader = BufReader::new(match client.try_clone() {
+        Ok(c) => c,
+        Err(_) => return,
+    });
+    let mut lines: Vec<String> = Vec::new();
+    let mut total = 0usize;
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) | Err(_) => return,
+            Ok(n) => total += n,
+        }
+        if total > MAX_HEAD_BYTES {
+            respond_error(
+                &mut client,
+                "431 Request Header Fields Too Large",
+                "request head too large",
+            );
+            return;
+        }
+        let line = line.trim_end_matches(['\r', '\n']).to_string();
+        if line.is_empty() {
+            break;
+        }
+        lines.push(line);
+    }
+    let request_line = lines.first().cloned().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target), Some(_version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        respond_error(&mut client, "400 Bad Request", "malformed request line");
+        return;
+    };
+    let method = method.to_string();
+    if !target.starts_with('/') {
+        respond_error(
+            &mut client,
+            "400 Bad Request",
+            "the LLM gateway only accepts origin-form requests",
+        );
+        return;
+    }
+    let path = target.split('?').next().unwrap_or_default().to_string();
+    let headers = &lines[1..];
+
+    // Authenticate with the session key before reading any body.
+    let authorized = headers.iter().any(|h| {
+        let name = header_name(h);
+        KEY_HEADERS.iter().any(|k| name.eq_ignore_ascii_case(k))
+            && presented_key(name, header_value(h))
+                .is_some_and(|k| keys_equal(&k, &ctx.cfg.session_key))
+    });
+    if !authorized {
+        ctx.record(&method, &path, 401, 0, 0, started);
+        respond_error(
+            &mut client,
+            "401 Unauthorized",
+            "aifo-coder: missing or invalid session key for the LLM gateway",
+        );
+        return;
+    }
+
+    let mut body: Vec<u8> = Vec::new();
+    let chunked = headers.iter().any(|h| {
+        header_name(h).eq_ignore_ascii_case("transfer-encoding")
+            && header_value(h).to_ascii_lowercase().contains("chunked")
+    });
+    let content_length = headers
+        .iter()
+        .find(|h| header_name(h).eq_ignore_ascii_case("content-length"))
+        .map(|h| header_value(h).parse::<usize>());
+    if chunked {
+        if let Err(msg) = read_chunked(&mut reader, &mut body) {
+            ctx.record(&method, &path, 400, body.len(), 0, started);
+            respond_error(&mut client, "400 Bad Request", msg);
+            return;
+        }
+    } else if let Some(len) = content_length {
+        let Ok(len) = len else {
+            respond_error(&mut client, "400 Bad Request", "invalid Content-Length");
+            return;
+        };
+        if len > MAX_BODY_BYTES {
+            ctx.record(&method, &path, 413, len, 0, started);
+            respond_error(
+                &mut client,
+                "413 Payload Too Large",
+                "request body too large",
+            );
+            return;
+        }
+        body.resize(len, 0);
+        if reader.read_exact(&mut body).is_err() {
+            return;
+        }
+    }
+    let req_bytes = body.len();
+
+    let Ok(http_method) = reqwest::Method::from_bytes(method.as_bytes()) else {
+        respond_error(&mut client, "400 Bad Request", "invalid method");
+        return;
+    };
+    let mut req = ctx
+        .client
+        .request(http_method, format!("{}{}", ctx.origin, target));
+    for h in headers {
+        let name = header_name(h);
+        if HOP_HEADERS.iter().any(|n| name.eq_ignore_ascii_case(n)) {
+            continue;
+        }
+        if KEY_HEADERS.iter().any(|k| name.eq_ignore_ascii_case(k)) {
+            let value = if name.eq_ignore_ascii_case("authorization") {
+                format!("Bearer {}", ctx.cfg.api_key)
+            } else {
+                ctx.cfg.api_key.clone()
+            };
+            req = req.header(name, value);
+            continue;
+        }
+        req = req.header(name, header_value(h));
+    }
+    let resp = match req.body(body).send() {
+        Ok(r) => r,
+        Err(e) => {
+            ctx.record(&method, &path, 502, req_bytes, 0, started);
+            respond_error(
+                &mut client,
+                "502 Bad Gateway",
+                &format!("aifo-coder: LLM upstream {} unreachable: {e}", ctx.origin),
+            );
+            return;
+        }
+    };
+    let _ = client.set_read_timeout(None);
+
+    let status = resp.status();
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or("")
+    );
+    for (name, value) in resp.headers() {
+        if HOP_HEADERS
+            .iter()
+            .any(|n| name.as_str().eq_ignore_ascii_case(n))
+        {
+            continue;
+        }
+        if let Ok(v) = value.to_str() {
+            head.push_str(&format!("{}: {}\r\n", name.as_str(), v));
+        }
+    }
+    // Keep the upstream length when known; otherwise the body runs until the connection closes.
+    if let Some(len) = resp.content_length() {
+        head.push_str(&format!("Content-Length: {len}\r\n"));
+    }
+    head.push_str("Connection: close\r\n\r\n");
+    if client.write_all(head.as_bytes()).is_err() {
+        return;
+    }
+    let mut resp = resp;
+    let mut resp_bytes: u64 = 0;
+    let mut buf = [0u8; 16 * 1024];
+    loop {
+        match resp.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                // Forward each chunk immediately so streamed (SSE) completions stay live.
+                if client.write_all(&buf[..n]).is_err() {
+                    break;
+                }
+                resp_bytes += n as u64;
+            }
+        }
+    }
+    let _ = client.flush();
+    let _ = client.shutdown(std::net::Shutdown::Both);
+    ctx.record(
+        &method,
+        &path,
+        status.as_u16(),
+        req_bytes,
+        resp_bytes,
+        started,
+    );
+}
@@ -0,0 +1,121 @@
+//! LLM credential gateway session RAII: start the gateway, export container env, stop on drop.
+//!
+//! Behavior
+//! - Binds where the toolexec proxy binds (AIFO_TOOLEEXEC_BIND_HOST), so containers reach it the
+//!   same way: host.docker.internal, or the internal network gateway under the egress allowlist.
+//! - Exports AIFO_LLM_GATEWAY_URL/KEY; collect_env_flags() then hands agents the session key and
+//!   gateway URL instead of AIFO_API_KEY/AIFO_API_BASE.
+
+use std::io;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use crate::cli::Cli;
+
+pub struct LlmGatewaySession {
+    flag: Option<Arc<AtomicBool>>,
+    handle: Option<std::thread::JoinHandle<()>>,
+}
+
+impl LlmGatewaySession {
+    /// Start the gateway when enabled and not in dry-run.
+    pub fn start_if_requested(cli: &Cli) -> Result<Option<Self>, io::Error> {
+        if !aifo_coder::llm_gateway_enabled() {
+            return Ok(None);
+        }
+        let use_err = aifo_coder::color_enabled_stderr();
+        let fail = |msg: String| {
+            aifo_coder::log_error_stderr(use_err, &format!("aifo-coder: error: {msg}"));
+            io::Error::other(msg)
+        };
+        let api_key = std::env::var("AIFO_API_KEY")
+            .ok()
+            .filter(|v| !v.trim().is_empty());
+        let upstream = std::env::var("AIFO_API_BASE")
+            .ok()
+            .and_then(|v| url::Url::parse(v.trim()).ok())
+            .filter(|u| matches!(u.scheme(), "http" | "https"));
+        let (Some(api_key), Some(upstream)) = (api_key, upstream) else {
+            return Err(fail(
+                "the LLM gateway needs AIFO_API_KEY and an http(s) AIFO_API_BASE on the host"
+                    .to_string(),
+            ));
+        };
+        if cli.dry_run {
+            aifo_coder::log_info_stderr(
+                use_err,
+                &format!(
+                    "aifo-coder: would route LLM requests for {} through the credential gateway; agents would get a session key",
+                    upstream.origin().ascii_serialization()
+                ),
+            );
+            return Ok(None);
+        }
+
+        let session_key = aifo_coder::llm_gateway_session_key().map_err(|e| fail(e.to_string()))?;
+        let sid = std::env::var("AIFO_CODER_FORK_SESSION").unwrap_or_default();
+        let log = aifo_coder::llm_gateway_log_path(&sid);
+        let cfg = aifo_coder::LlmGatewayConfig {
+            upstream,
+            api_key,
+            session_key: session_key.clone(),
+            log: Some(log.clone()),
+            verbose: cli.verbose,
+        };
+        let bind_host = std::env::var("AIFO_TOOLEEXEC_BIND_HOST")
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "127.0.0.1".to_string());
+        let (port, flag, handle) = aifo_coder::llm_gateway_start(&bind_host, cfg.clone())
+            .map_err(|e| fail(format!("failed to start LLM gateway: {e}")))?;
+
+        let container_host = match std::env::var("AIFO_EGRESS_GATEWAY")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+        {
+            Some(gw) => gw,
+            None => {
+                #[cfg(target_os = "linux")]
+                std::env::set_var("AIFO_TOOLEEXEC_ADD_HOST", "1");
+                "host.docker.internal".to_string()
+            }
+        };
+        std::env::set_var(
+            aifo_coder::LLM_GATEWAY_URL_ENV,
+            cfg.container_base_url(&container_host, port),
+        );
+        std::env::set_var(aifo_coder::LLM_GATEWAY_KEY_ENV, &session_key);
+        if cli.verbose {
+            aifo_coder::log_info_stderr(
+                use_err,
+                &format!(
+                    "aifo-coder: LLM credential gateway on {}:{} for {}; log: {}",
+                    bind_host,
+                    port,
+                    cfg.upstream.origin().ascii_serialization(),
+                    log.display()
+                ),
+            );
+        }
+        Ok(Some(Self {
+            flag: Some(flag),
+            handle: Some(handle),
+        }))
+    }
+}
+
+impl Drop for LlmGatewaySession {
+    fn drop(&mut self) {
+        if let Some(flag) = self.flag.take() {
+            flag.store(false, Ordering::SeqCst);
+        }
+        if let Some(h) = self.handle.take() {
+            let _ = h.join();
+        }
+        std::env::remove_var(aifo_coder::LLM_GATEWAY_URL_ENV);
+        std::env::remove_var(aifo_coder::LLM_GATEWAY_KEY_ENV);
+    }
+}
@@ -0,0 +1,240 @@
+//! LLM provider mapping: which container variables carry AIFO_API_KEY/BASE/VERSION.
+//!
+//! Each provider lists the variables its clients read and which AIFO_API_* value fills them.
+//! `[llm] provider` (AIFO_CODER_LLM_PROVIDER) selects one provider for every agent; the default
+//! `azure` keeps the historical combined OpenAI/Azure OpenAI mapping. Agents declare the provider
+//! variables they understand (built-ins below, custom agents via `[agents.NAME] llm_env`); the
+//! others are not passed to them.
+
+use std::env;
+use url::Url;
+
+/// Provider selected when AIFO_CODER_LLM_PROVIDER is unset.
+pub const DEFAULT_LLM_PROVIDER: &str = "azure";
+
+/// Source of a provider variable's value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlmValue {
+    /// AIFO_API_KEY (the session key when the credential gateway runs).
+    Key,
+    /// AIFO_API_BASE (the gateway URL when the credential gateway runs).
+    Base,
+    /// AIFO_API_VERSION.
+    Version,
+    /// Constant, set whenever AIFO_API_BASE is.
+    Fixed(&'static str),
+    /// Resource name parsed from an `https://<resource>.openai.azure.com` AIFO_API_BASE.
+    AzureResource,
+    /// OpenCode inline config pointing its Azure provider at AIFO_API_BASE. Set only behind the
+    /// credential gateway (where no resource name is derived) and only for agents listing it.
+    OpencodeAzureBase,
+}
+
+/// Container variables one provider's clients read.
+#[derive(Debug, Clone, Copy)]
+pub struct LlmProviderSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub vars: &'static [(&'static str, LlmValue)],
+}
+
+impl LlmProviderSpec {
+    /// True when the provider has a variable filled from AIFO_API_VERSION.
+    pub fn uses_version(&self) -> bool {
+        self.vars.iter().any(|(_, v)| *v == LlmValue::Version)
+    }
+}
+
+/// Known providers; variables are emitted in table order.
+pub const LLM_PROVIDERS: &[LlmProviderSpec] = &[
+    LlmProviderSpec {
+        name: "azure",
+        description: "Azure OpenAI (also fills the plain OpenAI variables)",
+        vars: &[
+            ("OPENAI_API_KEY", LlmValue::Key),
+            ("AZURE_OPENAI_API_KEY", LlmValue::Key),
+            ("AZURE_API_KEY", LlmValue::Key),
+            ("OPENAI_BASE_URL", LlmValue::Base),
+            ("OPENAI_API_BASE", LlmValue::Base),
+            ("AZURE_OPENAI_ENDPOINT", LlmValue::Base),
+            ("AZURE_API_BASE", LlmValue::Base),
+            ("OPENAI_API_TYPE", LlmValue::Fixed("azure")),
+            ("AZURE_RESOURCE_NAME", LlmValue::AzureResource),
+            ("OPENCODE_CONFIG_CONTENT", LlmValue::OpencodeAzureBase),
+            ("OPENAI_API_VERSION", LlmValue::Version),
+            ("AZURE_OPENAI_API_VERSION", LlmValue::Version),
+            ("AZURE_API_VERSION", LlmValue::Version),
+            ("LITELLM_AZURE_API_VERSION", LlmValue::Version),
+            ("AZURE_OPENAI_RESPONSES_API_VERSION", LlmValue::Version),
+        ],
+    },
+    LlmProviderSpec {
+        name: "openai",
+        description: "OpenAI or any OpenAI-compatible endpoint",
+        vars: &[
+            ("OPENAI_API_KEY", LlmValue::Key),
+            ("OPENAI_BASE_URL", LlmValue::Base),
+            ("OPENAI_API_BASE", LlmValue::Base),
+        ],
+    },
+    LlmProviderSpec {
+        name: "anthropic",
+        description: "Anthropic Messages API or a compatible endpoint",
+        vars: &[
+            ("ANTHROPIC_API_KEY", LlmValue::Key),
+            ("ANTHROPIC_BASE_URL", LlmValue::Base),
+        ],
+    },
+    LlmProviderSpec {
+        name: "gemini",
+        description: "Google Gemini API",
+        vars: &[
+            ("GEMINI_API_KEY", LlmValue::Key),
+            ("GOOGLE_API_KEY", LlmValue::Key),
+            ("GOOGLE_GENERATIVE_AI_API_KEY", LlmValue::Key),
+            ("GOOGLE_GEMINI_BASE_URL", LlmValue::Base),
+        ],
+    },
+    LlmProviderSpec {
+        name: "ollama",
+        description: "Ollama or another local OpenAI-compatible server",
+        vars: &[
+            ("OPENAI_API_KEY", LlmValue::Key),
+            ("OPENAI_BASE_URL", LlmValue::Base),
+            ("OPENAI_API_BASE", LlmValue::Base),
+            ("OLLAMA_API_BASE", LlmValue::Base),
+        ],
+    },
+];
+
+/// Built-in agents that read only some provider variables; unlisted agents read all of them.
+///
+/// OpenCode picks its provider from whichever variables are present, so it must never see the
+/// OpenAI/Azure OpenAI variables; it is configured through AZURE_API_KEY/AZURE_RESOURCE_NAME, or
+/// behind the gateway through an inline config setting the Azure base URL.
+const AGENT_LLM_VARS: &[(&str, &[&str])] = &[(
+    "opencode",
+    &[
+        "AZURE_API_KEY",
+        "AZURE_RESOURCE_NAME",
+        "OPENCODE_CONFIG_CONTENT",
+        "ANTHROPIC_API_KEY",
+        "ANTHROPIC_BASE_URL",
+        "GEMINI_API_KEY",
+        "GOOGLE_GENERATIVE_AI_API_KEY",
+    ],
+)];
+
+/// Look up a provider by name (case-insensitive).
+pub fn llm_provider_spec(name: &str) -> Result<&'static LlmProviderSpec, String> {
+    let wanted = name.trim().to_ascii_lowercase();
+    LLM_PROVIDERS
+        .iter()
+        .find(|p| p.name == wanted)
+        .ok_or_else(|| {
+            let known: Vec<&str> = LLM_PROVIDERS.iter().map(|p| p.name).collect();
+            format!(
+                "unknown LLM provider '{}' (expected one of: {})",
+                name.trim(),
+                known.join(", ")
+            )
+        })
+}
+
+/// Provider selected by AIFO_CODER_LLM_PROVIDER, or the default.
+pub fn llm_selected_provider() -> Result<&'static LlmProviderSpec, String> {
+    let name = crate::llm_provider().unwrap_or_else(|| DEFAULT_LLM_PROVIDER.to_string());
+    llm_provider_spec(&name)
+}
+
+/// Provider variables AGENT understands; None means all of them.
+pub fn agent_llm_vars(agent: &str) -> Option<Vec<String>> {
+    if let Some(vars) = crate::config_custom_agent(agent).and_then(|c| c.llm_env.clone()) {
+        return Some(vars);
+    }
+    AGENT_LLM_VARS
+        .iter()
+        .find(|(name, _)| *name == agent)
+        .map(|(_, vars)| vars.iter().map(|v| v.to_string()).collect())
+}
+
+/// Values the provider mapping draws from.
+#[derive(Debug, Clone, Default)]
+pub struct LlmValues {
+    pub key: Option<String>,
+    pub base: Option<String>,
+    pub version: Option<String>,
+    /// Derive AZURE_RESOURCE_NAME from the base URL (off behind the credential gateway).
+    pub derive_azure_resource: bool,
+}
+
+fn azure_resource(base: &str) -> Option<String> {
+    let url = Url::parse(base).ok()?;
+    let host = url.host_str()?;
+    if !host.ends_with(".openai.azure.com") {
+        return None;
+    }
+    host.split('.')
+        .next()
+        .filter(|r| !r.trim().is_empty())
+        .map(str::to_string)
+}
+
+/// OpenCode config setting the Azure provider's base URL; like the resource-derived default it
+/// ends in `/openai`.
+fn opencode_azure_config(base: &str) -> String {
+    let base = base.trim_end_matches('/');
+    let base = if base.ends_with("/openai") {
+        base.to_string()
+    } else {
+        format!("{base}/openai")
+    };
+    serde_json::json!({ "provider": { "azure": { "options": { "baseURL": base } } } }).to_string()
+}
+
+/// Container variables for AGENT under SPEC, limited to the ones the agent understands.
+pub fn llm_provider_env(
+    spec: &LlmProviderSpec,
+    agent: &str,
+    values: &LlmValues,
+) -> Vec<(String, String)> {
+    let understood = agent_llm_vars(agent);
+    let nonempty = |v: &Option<String>| v.clone().filter(|s| !s.is_empty());
+    // Do not override an explicit host-provided AZURE_RESOURCE_NAME.
+    let resource = if values.derive_azure_resource
+        && env::var("AZURE_RESOURCE_NAME")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .is_none()
+    {
+        nonempty(&values.base).and_then(|b| azure_resource(&b))
+    } else {
+        None
+    };
+    let mut out = Vec::new();
+    for (name, source) in spec.vars {
+        if understood
+            .as_ref()
+            .is_some_and(|u| !u.iter().any(|v| v == name))
+        {
+            continue;
+        }
+        let value = match source {
+            LlmValue::Key => nonempty(&values.key),
+            LlmValue::Base => nonempty(&values.base),
+            LlmValue::Version => nonempty(&values.version),
+            LlmValue::Fixed(v) => nonempty(&values.base).map(|_| v.to_string()),
+            LlmValue::AzureResource => resource.clone(),
+            LlmValue::OpencodeAzureBase
+                if !values.derive_azure_resource && understood.is_some() =>
+            {
+                nonempty(&values.base).map(|b| opencode_azure_config(&b))
+            }
+            LlmValue::OpencodeAzureBase => None,
+        };
+        if let Some(v) = value {
+            out.push((name.to_string(), v));
+        }
+    }
+    out
+}
@@ -1,4 +1,3 @@
-use clap::Parser;
 use std::path::PathBuf;
 use std::process::{Command, ExitCode};
 
@@ -30,8 +29,10 @@ mod banner;
 mod cli;
 mod commands;
 mod doctor;
+mod egress_session;
 mod fork_args;
 mod guidance;
+mod llm_gateway_session;
 mod support;
 mod toolchain_session;
 mod warnings;
@@ -79,6 +80,67 @@ fn apply_cli_globals(cli: &Cli) {
     if cli.verbose {
         std::env::set_var("AIFO_CODER_VERBOSE", "1");
     }
+    // Resource limit flags override AIFO_CODER_*/AIFO_TOOLCHAIN_* limit variables.
+    for (k, v) in cli.limits.env_overrides() {
+        std::env::set_var(k, v);
+    }
+    if cli.hardened {
+        std::env::set_var("AIFO_CODER_HARDENED", "1");
+    }
+    if !cli.toolchain_seccomp_relaxed.is_empty() {
+        std::env::set_var(
+            "AIFO_TOOLCHAIN_SECCOMP_RELAXED",
+            cli.toolchain_seccomp_relaxed.join(","),
+        );
+    }
+    if cli.egress_allowlist {
+        std::env::set_var("AIFO_CODER_EGRESS_ALLOWLIST", "1");
+    }
+    if !cli.egress_allow.is_empty() {
+        std::env::set_var("AIFO_CODER_EGRESS_ALLOW", cli.egress_allow.join(","));
+    }
+    if cli.llm_gateway {
+        std::env::set_var("AIFO_CODER_LLM_GATEWAY", "1");
+    }
+}
+
+/// Export AIFO_API_KEY from the configured key helper; false after printing an error.
+///
+/// Dry-run only reports the helper that would run, so previews never prompt for a secret.
+fn resolve_llm_api_key(cli: &Cli) -> bool {
+    let use_err = aifo_coder::color_enabled_stderr();
+    let key_set = std::env::var("AIFO_API_KEY")
+        .ok()
+        .is_some_and(|v| !v.trim().is_empty());
+    if cli.dry_run {
+        if let Some(helper) = aifo_coder::llm_api_key_helper().filter(|_| !key_set) {
+            aifo_coder::log_info_stderr(
+                use_err,
+                &format!(
+                    "aifo-coder: would run {} `{}` to obtain AIFO_API_KEY",
+                    helper.label(),
+                    helper.command
+                ),
+            );
+        }
+        return true;
+    }
+    match aifo_coder::llm_resolve_api_key() {
+        Ok(Some(helper)) => {
+            if cli.verbose {
+                aifo_coder::log_info_stderr(
+                    use_err,
+                    &format!("aifo-coder: AIFO_API_KEY obtained from {}", helper.label()),
+                );
+            }
+            true
+        }
+        Ok(None) => true,
+        Err(e) => {
+            aifo_coder::log_error_stderr(use_err, &format!("aifo-coder: error: {e}"));
+            false
+        }
+    }
 }
 
 const FULLSCREEN_GPG_AGENTS: &[&str] = &["opencode", "codex"];
@@ -155,6 +217,19 @@ fn configure_network_env(cli: &Cli) {
         .ok()
         .filter(|s| !s.trim().is_empty());
 
+    // The egress allowlist needs a network without a route out; it replaces any other choice.
+    if aifo_coder::egress_allowlist_enabled() {
+        if cli_net.is_some() || cli.docker_network_isolate {
+            aifo_coder::warn_print(
+                "--docker-network/--docker-network-isolate are ignored with --egress-allowlist.",
+            );
+        }
+        let net = format!("aifo-egress-{session_id}");
+        aifo_coder::set_session_network_env(&net, true, true, "egress");
+        aifo_coder::set_session_network_internal(true);
+        return;
+    }
+
     // CLI flags win; otherwise respect pre-set env; else default to bridge.
     if cli_net.is_none() && !cli.docker_network_isolate {
         if env_net.is_none() {
@@ -181,7 +256,9 @@ fn configure_network_env(cli: &Cli) {
 }
 
 fn ensure_session_network_if_needed(cli: &Cli) -> Result<(), u8> {
-    if cli.dry_run || !cli.toolchain.is_empty() {
+    // Toolchain sessions create the network themselves, except that the egress proxy must bind
+    // to its gateway before any sidecar starts.
+    if cli.dry_run || (!cli.toolchain.is_empty() && !aifo_coder::egress_allowlist_enabled()) {
         return Ok(());
     }
     if let Some(net) = aifo_coder::session_network_from_env() {
@@ -426,6 +503,10 @@ fn handle_misc_subcommands(cli: &Cli) -> Option<ExitCode> {
             }
         }
         Agent::Images => Some(crate::commands::run_images(cli)),
+        Agent::Config { cmd } => Some(crate::commands::run_config(cli, cmd)),
+        Agent::Security { cmd } => Some(crate::commands::run_security(cmd)),
+        Agent::Audit { cmd } => Some(crate::commands::run_audit(cmd)),
+        Agent::Apparmor { cmd } => Some(crate::commands::run_apparmor(cli, cmd)),
         Agent::CacheClear => Some(crate::commands::run_cache_clear(cli)),
         Agent::ToolchainCacheClear => Some(crate::commands::run_toolchain_cache_clear(cli)),
         Agent::Toolchain {
@@ -442,7 +523,7 @@ fn handle_misc_subcommands(cli: &Cli) -> Option<ExitCode> {
     }
 }
 
-fn resolve_agent_and_args(cli: &Cli) -> Option<(&'static str, Vec<String>)> {
+fn resolve_agent_and_args(cli: &Cli) -> Option<(&str, Vec<String>)> {
     match &cli.command {
         Agent::Codex { args } => ("codex", args.clone()).into(),
         Agent::Letta { args } => ("letta", args.clone()).into(),
@@ -451,6 +532,7 @@ fn resolve_agent_and_args(cli: &Cli) -> Option<(&'static str, Vec<String>)> {
         Agent::OpenHands { args } => ("openhands", args.clone()).into(),
         Agent::OpenCode { args } => ("opencode", args.clone()).into(),
         Agent::Plandex { args } => ("plandex", args.clone()).into(),
+        Agent::Run { name, args } => (name.as_str(), args.clone()).into(),
         _ => None,
     }
 }
@@ -539,13 +621,66 @@ fn main() -> ExitCode {
     // Leading blank line at program start
     eprintln!();
     // Load environment variables from .env if present (no error if missing)
-    dotenvy::dotenv().ok();
+    aifo_coder::config_load_dotenv();
 
     // Ensure proxy env vars are available to any child processes/tools.
     propagate_proxy_env_for_child_tools();
 
     // Parse command-line arguments into structured CLI options
-    let cli = Cli::parse();
+    let mut cli = crate::cli::parse_cli();
+
+    // Layered config files (system → user → repo) fill knobs not set via env or CLI;
+    // a selected --profile fills first so its values beat the plain config sections.
+    match aifo_coder::config_init() {
+        Ok(resolved) => {
+            let applied = cli
+                .apply_profile(resolved)
+                .and_then(|_| cli.apply_config_defaults(&resolved.config));
+            if let Err(e) = applied {
+                aifo_coder::log_error_stderr(
+                    aifo_coder::color_enabled_stderr(),
+                    &format!("aifo-coder: error: {e}"),
+                );
+                return ExitCode::from(1);
+            }
+        }
+        Err(e) => {
+            aifo_coder::log_error_stderr(
+                aifo_coder::color_enabled_stderr(),
+                &format!("aifo-coder: error: {e}"),
+            );
+            return ExitCode::from(1);
+        }
+    }
+
+    // An unknown AIFO_CODER_LLM_PROVIDER from the environment (files are validated on load).
+    if let Err(e) = aifo_coder::llm_selected_provider() {
+        aifo_coder::log_error_stderr(
+            aifo_coder::color_enabled_stderr(),
+            &format!("aifo-coder: error: {e}"),
+        );
+        return ExitCode::from(1);
+    }
+
+    if let Agent::Run { name, .. } = &cli.command {
+        if !aifo_coder::BUILTIN_AGENTS.contains(&name.as_str())
+            && aifo_coder::config_custom_agent(name).is_none()
+        {
+            let custom: Vec<&str> = aifo_coder::config_resolved()
+                .map(|r| r.config.agents.keys().map(String::as_str).collect())
+                .unwrap_or_default();
+            let known = if custom.is_empty() {
+                "none; declare one under [agents.NAME] in a config file".to_string()
+            } else {
+                custom.join(", ")
+            };
+            aifo_coder::log_error_stderr(
+                aifo_coder::color_enabled_stderr(),
+                &format!("aifo-coder: error: unknown agent '{name}' (custom agents: {known})"),
+            );
+            return ExitCode::from(1);
+        }
+    }
 
     // Propagate CLI verbosity to telemetry so init can emit concise OTEL logs when requested.
     if cli.verbose {
@@ -597,8 +732,11 @@ fn main() -> ExitCode {
     apply_cli_globals(&cli);
     let use_err = aifo_coder::color_enabled_stderr();
 
-    // Fork orchestrator: run early if requested
+    // Fork orchestrator: run early if requested; panes inherit the helper-provided key.
     if let Some(n) = cli.fork {
+        if !resolve_llm_api_key(&cli) {
+            return ExitCode::from(1);
+        }
         return crate::fork::runner::fork_run(&cli, n);
     }
     // Optional auto-clean of stale fork sessions and stale session notice
@@ -624,10 +762,12 @@ fn main() -> ExitCode {
         Some(v) => v,
         None => return ExitCode::from(0),
     };
+    // The toolexec proxy records the agent in its audit log.
+    std::env::set_var("AIFO_AGENT_NAME", agent);
 
     // Print startup banner before any further diagnostics
     if !cli.quiet {
-        print_startup_banner();
+        print_startup_banner(Some(agent));
     }
     // Initialize optional OpenTelemetry telemetry if compiled and enabled via env.
     // This is fully best-effort and must not change exit codes or stdout/stderr defaults.
@@ -661,6 +801,16 @@ fn main() -> ExitCode {
 
         return ExitCode::from(1);
     }
+    // Run the API key helper (if configured) before checking LLM credentials
+    if !resolve_llm_api_key(&cli) {
+        #[cfg(feature = "otel")]
+        {
+            let duration = run_start.elapsed();
+            aifo_coder::record_run_end(agent, &toolchains_for_run, 1, duration);
+        }
+
+        return ExitCode::from(1);
+    }
     // Warn and optionally block if LLM credentials are missing
     if !crate::warnings::warn_if_missing_llm_credentials(true) {
         aifo_coder::log_error_stderr(use_err, "aborted.");
@@ -694,6 +844,35 @@ fn main() -> ExitCode {
         return ExitCode::from(code);
     }
 
+    // Egress proxy RAII; declared first so it outlives the toolchain session using its gateway.
+    let _egress_session = match crate::egress_session::EgressSession::start_if_requested(&cli) {
+        Ok(es) => es,
+        Err(_) => {
+            // Errors are already printed inside start_if_requested()
+            #[cfg(feature = "otel")]
+            {
+                let duration = run_start.elapsed();
+                aifo_coder::record_run_end(agent, &toolchains_for_run, 1, duration);
+            }
+            return ExitCode::from(1);
+        }
+    };
+
+    // LLM credential gateway RAII; started after the egress session so it can bind to its gateway.
+    let _llm_gateway_session =
+        match crate::llm_gateway_session::LlmGatewaySession::start_if_requested(&cli) {
+            Ok(gs) => gs,
+            Err(_) => {
+                // Errors are already printed inside start_if_requested()
+                #[cfg(feature = "otel")]
+                {
+                    let duration = run_start.elapsed();
+                    aifo_coder::record_run_end(agent, &toolchains_for_run, 1, duration);
+                }
+                return ExitCode::from(1);
+            }
+        };
+
     // Toolchain session RAII
     let mut _toolchain_session: Option<crate::toolchain_session::ToolchainSession> = None;
 
@@ -762,13 +941,17 @@ fn main() -> ExitCode {
     }
 
     // Resolve effective image reference (CLI override > environment > computed default)
-    let image = cli
+    // A custom agent's configured image is treated like an explicit --image.
+    let image_override = cli
         .image
+        .clone()
+        .or_else(|| aifo_coder::config_custom_agent(agent).and_then(|c| c.image.clone()));
+    let image = image_override
         .clone()
         .unwrap_or_else(|| default_image_for(agent));
     // Apply global/agent tag overrides for run when CLI didn't provide an explicit image.
     // Also resolve registry prefix when the tagged image isn't present locally.
-    let run_image = if cli.image.is_none() {
+    let run_image = if image_override.is_none() {
         // Prefer AIFO_CODER_IMAGE_TAG over AIFO_TAG
         let tag = std::env::var("AIFO_CODER_IMAGE_TAG")
             .ok()
@@ -814,7 +997,7 @@ fn main() -> ExitCode {
 
     // Finalize run image: CLI override wins and is used as-is;
     // else respect tag env; else prefer local ':latest' when present.
-    let run_image_final = if cli.image.is_some() {
+    let run_image_final = if image_override.is_some() {
         image.clone()
     } else if tag_env_present {
         run_image.clone()
@@ -835,12 +1018,12 @@ fn main() -> ExitCode {
     // - Dry-run: show CLI verbatim (if set) or resolved image (no Docker check).
     // - Real run: show the effective image (prefers local :latest when present), unless CLI overrides.
     let image_display = if cli.dry_run {
-        if cli.image.is_some() {
+        if image_override.is_some() {
             image.clone()
         } else {
             aifo_coder::resolve_agent_image_log_display(&image)
         }
-    } else if cli.image.is_some() {
+    } else if image_override.is_some() {
         image.clone()
     } else {
         // Use the final image we computed (matches actual run selection)
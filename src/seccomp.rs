@@ -0,0 +1,582 @@
+//! Tailored seccomp profile for agent containers and toolchain sidecars.
+//!
+//! The profile is generated from Docker's default allowlist minus syscalls coding agents never
+//! need (debugging/introspection, mounts and namespaces, kernel keyring, BPF, module loading).
+//! Capability-gated blocks of the Docker default are not carried over at all. A relaxed variant
+//! restores the debugging syscalls for toolchains that need them (e.g. the Rust sidecar when
+//! running debuggers or profilers).
+//!
+//! Mode (AIFO_CODER_SECCOMP, `[security] seccomp`):
+//! - aifo (default): write the profile to a private temp file and pass `--security-opt seccomp=FILE`
+//! - engine: pass nothing and keep the engine's default profile
+//! - unconfined: pass `--security-opt seccomp=unconfined`
+//!
+//! Toolchain kinds listed in AIFO_TOOLCHAIN_SECCOMP_RELAXED (`--toolchain-seccomp-relaxed`,
+//! `[security] seccomp_relaxed`) get the relaxed profile; "all" relaxes every sidecar.
+
+use std::env;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use serde_json::json;
+
+/// Unconditionally allowed syscalls of Docker's default seccomp profile (moby profiles/seccomp).
+const DOCKER_DEFAULT_ALLOW: &[&str] = &[
+    "accept",
+    "accept4",
+    "access",
+    "adjtimex",
+    "alarm",
+    "bind",
+    "brk",
+    "cachestat",
+    "capget",
+    "capset",
+    "chdir",
+    "chmod",
+    "chown",
+    "chown32",
+    "clock_adjtime",
+    "clock_adjtime64",
+    "clock_getres",
+    "clock_getres_time64",
+    "clock_gettime",
+    "clock_gettime64",
+    "clock_nanosleep",
+    "clock_nanosleep_time64",
+    "close",
+    "close_range",
+    "connect",
+    "copy_file_range",
+    "creat",
+    "dup",
+    "dup2",
+    "dup3",
+    "epoll_create",
+    "epoll_create1",
+    "epoll_ctl",
+    "epoll_ctl_old",
+    "epoll_pwait",
+    "epoll_pwait2",
+    "epoll_wait",
+    "epoll_wait_old",
+    "eventfd",
+    "eventfd2",
+    "execve",
+    "execveat",
+    "exit",
+    "exit_group",
+    "faccessat",
+    "faccessat2",
+    "fadvise64",
+    "fadvise64_64",
+    "fallocate",
+    "fanotify_mark",
+    "fchdir",
+    "fchmod",
+    "fchmodat",
+    "fchmodat2",
+    "fchown",
+    "fchown32",
+    "fchownat",
+    "fcntl",
+    "fcntl64",
+    "fdatasync",
+    "fgetxattr",
+    "flistxattr",
+    "flock",
+    "fork",
+    "fremovexattr",
+    "fsetxattr",
+    "fstat",
+    "fstat64",
+    "fstatat64",
+    "fstatfs",
+    "fstatfs64",
+    "fsync",
+    "ftruncate",
+    "ftruncate64",
+    "futex",
+    "futex_requeue",
+    "futex_time64",
+    "futex_wait",
+    "futex_waitv",
+    "futex_wake",
+    "futimesat",
+    "getcpu",
+    "getcwd",
+    "getdents",
+    "getdents64",
+    "getegid",
+    "getegid32",
+    "geteuid",
+    "geteuid32",
+    "getgid",
+    "getgid32",
+    "getgroups",
+    "getgroups32",
+    "getitimer",
+    "getpeername",
+    "getpgid",
+    "getpgrp",
+    "getpid",
+    "getppid",
+    "getpriority",
+    "getrandom",
+    "getresgid",
+    "getresgid32",
+    "getresuid",
+    "getresuid32",
+    "getrlimit",
+    "get_robust_list",
+    "getrusage",
+    "getsid",
+    "getsockname",
+    "getsockopt",
+    "get_thread_area",
+    "gettid",
+    "gettimeofday",
+    "getuid",
+    "getuid32",
+    "getxattr",
+    "inotify_add_watch",
+    "inotify_init",
+    "inotify_init1",
+    "inotify_rm_watch",
+    "io_cancel",
+    "ioctl",
+    "io_destroy",
+    "io_getevents",
+    "io_pgetevents",
+    "io_pgetevents_time64",
+    "ioprio_get",
+    "ioprio_set",
+    "io_setup",
+    "io_submit",
+    "ipc",
+    "kill",
+    "landlock_add_rule",
+    "landlock_create_ruleset",
+    "landlock_restrict_self",
+    "lchown",
+    "lchown32",
+    "lgetxattr",
+    "link",
+    "linkat",
+    "listen",
+    "listxattr",
+    "llistxattr",
+    "_llseek",
+    "lremovexattr",
+    "lseek",
+    "lsetxattr",
+    "lstat",
+    "lstat64",
+    "madvise",
+    "map_shadow_stack",
+    "membarrier",
+    "memfd_create",
+    "memfd_secret",
+    "mincore",
+    "mkdir",
+    "mkdirat",
+    "mknod",
+    "mknodat",
+    "mlock",
+    "mlock2",
+    "mlockall",
+    "mmap",
+    "mmap2",
+    "mprotect",
+    "mq_getsetattr",
+    "mq_notify",
+    "mq_open",
+    "mq_timedreceive",
+    "mq_timedreceive_time64",
+    "mq_timedsend",
+    "mq_timedsend_time64",
+    "mq_unlink",
+    "mremap",
+    "msgctl",
+    "msgget",
+    "msgrcv",
+    "msgsnd",
+    "msync",
+    "munlock",
+    "munlockall",
+    "munmap",
+    "name_to_handle_at",
+    "nanosleep",
+    "newfstatat",
+    "_newselect",
+    "open",
+    "openat",
+    "openat2",
+    "pause",
+    "pidfd_open",
+    "pidfd_send_signal",
+    "pipe",
+    "pipe2",
+    "pkey_alloc",
+    "pkey_free",
+    "pkey_mprotect",
+    "poll",
+    "ppoll",
+    "ppoll_time64",
+    "prctl",
+    "pread64",
+    "preadv",
+    "preadv2",
+    "prlimit64",
+    "process_mrelease",
+    "process_vm_readv",
+    "process_vm_writev",
+    "pselect6",
+    "pselect6_time64",
+    "ptrace",
+    "pwrite64",
+    "pwritev",
+    "pwritev2",
+    "read",
+    "readahead",
+    "readlink",
+    "readlinkat",
+    "readv",
+    "recv",
+    "recvfrom",
+    "recvmmsg",
+    "recvmmsg_time64",
+    "recvmsg",
+    "remap_file_pages",
+    "removexattr",
+    "rename",
+    "renameat",
+    "renameat2",
+    "restart_syscall",
+    "rmdir",
+    "rseq",
+    "rt_sigaction",
+    "rt_sigpending",
+    "rt_sigprocmask",
+    "rt_sigqueueinfo",
+    "rt_sigreturn",
+    "rt_sigsuspend",
+    "rt_sigtimedwait",
+    "rt_sigtimedwait_time64",
+    "rt_tgsigqueueinfo",
+    "sched_getaffinity",
+    "sched_getattr",
+    "sched_getparam",
+    "sched_get_priority_max",
+    "sched_get_priority_min",
+    "sched_getscheduler",
+    "sched_rr_get_interval",
+    "sched_rr_get_interval_time64",
+    "sched_setaffinity",
+    "sched_setattr",
+    "sched_setparam",
+    "sched_setscheduler",
+    "sched_yield",
+    "seccomp",
+    "select",
+    "semctl",
+    "semget",
+    "semop",
+    "semtimedop",
+    "semtimedop_time64",
+    "send",
+    "sendfile",
+    "sendfile64",
+    "sendmmsg",
+    "sendmsg",
+    "sendto",
+    "setfsgid",
+    "setfsgid32",
+    "setfsuid",
+    "setfsuid32",
+    "setgid",
+    "setgid32",
+    "setgroups",
+    "setgroups32",
+    "setitimer",
+    "setpgid",
+    "setpriority",
+    "setregid",
+    "setregid32",
+    "setresgid",
+    "setresgid32",
+    "setresuid",
+    "setresuid32",
+    "setreuid",
+    "setreuid32",
+    "setrlimit",
+    "set_robust_list",
+    "setsid",
+    "setsockopt",
+    "set_thread_area",
+    "set_tid_address",
+    "setuid",
+    "setuid32",
+    "setxattr",
+    "shmat",
+    "shmctl",
+    "shmdt",
+    "shmget",
+    "shutdown",
+    "sigaltstack",
+    "signalfd",
+    "signalfd4",
+    "sigprocmask",
+    "sigreturn",
+    "socketcall",
+    "socketpair",
+    "splice",
+    "stat",
+    "stat64",
+    "statfs",
+    "statfs64",
+    "statx",
+    "symlink",
+    "symlinkat",
+    "sync",
+    "sync_file_range",
+    "syncfs",
+    "sysinfo",
+    "tee",
+    "tgkill",
+    "time",
+    "timer_create",
+    "timer_delete",
+    "timer_getoverrun",
+    "timer_gettime",
+    "timer_gettime64",
+    "timer_settime",
+    "timer_settime64",
+    "timerfd_create",
+    "timerfd_gettime",
+    "timerfd_gettime64",
+    "timerfd_settime",
+    "timerfd_settime64",
+    "times",
+    "tkill",
+    "truncate",
+    "truncate64",
+    "ugetrlimit",
+    "umask",
+    "uname",
+    "unlink",
+    "unlinkat",
+    "utime",
+    "utimensat",
+    "utimensat_time64",
+    "utimes",
+    "vfork",
+    "vmsplice",
+    "wait4",
+    "waitid",
+    "waitpid",
+    "write",
+    "writev",
+];
+
+/// Syscalls the aifo-coder profile never allows for agents, whether or not Docker's default
+/// would (some are capability-gated there).
+pub const SECCOMP_DENIED_SYSCALLS: &[&str] = &[
+    "ptrace",
+    "process_vm_readv",
+    "process_vm_writev",
+    "name_to_handle_at",
+    "open_by_handle_at",
+    "mount",
+    "umount",
+    "umount2",
+    "move_mount",
+    "fsmount",
+    "fsopen",
+    "pivot_root",
+    "unshare",
+    "setns",
+    "keyctl",
+    "add_key",
+    "request_key",
+    "bpf",
+    "perf_event_open",
+    "userfaultfd",
+    "kexec_load",
+    "kexec_file_load",
+    "init_module",
+    "finit_module",
+    "delete_module",
+    "reboot",
+    "swapon",
+    "swapoff",
+    "syslog",
+    "acct",
+    "quotactl",
+];
+
+/// Denied syscalls the relaxed profile (for toolchains such as the Rust sidecar) allows again.
+pub const SECCOMP_RELAXED_SYSCALLS: &[&str] = &[
+    "ptrace",
+    "process_vm_readv",
+    "process_vm_writev",
+    "perf_event_open",
+];
+
+/// How seccomp is applied to launched containers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeccompMode {
+    /// Generated aifo-coder profile.
+    Aifo,
+    /// Engine default profile (no flag).
+    Engine,
+    /// No filtering.
+    Unconfined,
+}
+
+/// Parse a seccomp mode name (aifo|engine|unconfined).
+pub fn parse_seccomp_mode(s: &str) -> Result<SeccompMode, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "aifo" | "" => Ok(SeccompMode::Aifo),
+        "engine" | "default" => Ok(SeccompMode::Engine),
+        "unconfined" => Ok(SeccompMode::Unconfined),
+        _ => Err(format!(
+            "invalid seccomp mode '{s}': expected aifo, engine or unconfined"
+        )),
+    }
+}
+
+/// Mode from AIFO_CODER_SECCOMP; invalid values fall back to the aifo profile.
+pub fn seccomp_mode() -> SeccompMode {
+    env::var("AIFO_CODER_SECCOMP")
+        .ok()
+        .and_then(|v| parse_seccomp_mode(&v).ok())
+        .unwrap_or(SeccompMode::Aifo)
+}
+
+/// Whether the sidecar for toolchain KIND gets the relaxed profile.
+pub fn seccomp_relaxed_for(kind: &str) -> bool {
+    env::var("AIFO_TOOLCHAIN_SECCOMP_RELAXED")
+        .ok()
+        .is_some_and(|v| {
+            v.split(',').map(|k| k.trim()).any(|k| {
+                k.eq_ignore_ascii_case("all") || crate::normalize_toolchain_kind(k) == kind
+            })
+        })
+}
+
+/// The seccomp profile as JSON (Docker/OCI format); RELAXED allows SECCOMP_RELAXED_SYSCALLS.
+pub fn seccomp_profile_json(relaxed: bool) -> String {
+    let allowed = |name: &&str| {
+        !SECCOMP_DENIED_SYSCALLS.contains(name)
+            || (relaxed && SECCOMP_RELAXED_SYSCALLS.contains(name))
+    };
+    let mut allow: Vec<&str> = DOCKER_DEFAULT_ALLOW
+        .iter()
+        .copied()
+        .filter(allowed)
+        .collect();
+    if relaxed {
+        for name in SECCOMP_RELAXED_SYSCALLS {
+            if !allow.contains(name) {
+                allow.push(name);
+            }
+        }
+    }
+
+    let mut syscalls = vec![
+        json!({ "names": allow, "action": "SCMP_ACT_ALLOW" }),
+        // socket(2): everything except AF_VSOCK
+        json!({
+            "names": ["socket"],
+            "action": "SCMP_ACT_ALLOW",
+            "args": [{ "index": 0, "value": 40, "op": "SCMP_CMP_NE" }]
+        }),
+        // clone(2) without namespace flags; clone3 reports ENOSYS so libc falls back to clone
+        json!({
+            "names": ["clone"],
+            "action": "SCMP_ACT_ALLOW",
+            "args": [{ "index": 0, "value": 2114060288u64, "valueTwo": 0, "op": "SCMP_CMP_MASKED_EQ" }],
+            "excludes": { "arches": ["s390", "s390x"] }
+        }),
+        json!({ "names": ["clone3"], "action": "SCMP_ACT_ERRNO", "errnoRet": 38 }),
+        json!({
+            "names": ["arch_prctl", "modify_ldt"],
+            "action": "SCMP_ACT_ALLOW",
+            "includes": { "arches": ["amd64", "x32", "x86"] }
+        }),
+        json!({
+            "names": ["arm_fadvise64_64", "arm_sync_file_range", "sync_file_range2", "breakpoint", "cacheflush", "set_tls"],
+            "action": "SCMP_ACT_ALLOW",
+            "includes": { "arches": ["arm", "arm64"] }
+        }),
+    ];
+    if relaxed {
+        // Debuggers disable ASLR through personality(ADDR_NO_RANDOMIZE).
+        syscalls.push(json!({ "names": ["personality"], "action": "SCMP_ACT_ALLOW" }));
+    } else {
+        for value in [0u64, 8, 131072, 131080, 4294967295] {
+            syscalls.push(json!({
+                "names": ["personality"],
+                "action": "SCMP_ACT_ALLOW",
+                "args": [{ "index": 0, "value": value, "op": "SCMP_CMP_EQ" }]
+            }));
+        }
+    }
+
+    let profile = json!({
+        "defaultAction": "SCMP_ACT_ERRNO",
+        "defaultErrnoRet": 1,
+        "archMap": [
+            { "architecture": "SCMP_ARCH_X86_64", "subArchitectures": ["SCMP_ARCH_X86", "SCMP_ARCH_X32"] },
+            { "architecture": "SCMP_ARCH_AARCH64", "subArchitectures": ["SCMP_ARCH_ARM"] },
+            { "architecture": "SCMP_ARCH_S390X", "subArchitectures": ["SCMP_ARCH_S390"] },
+            { "architecture": "SCMP_ARCH_PPC64LE", "subArchitectures": [] },
+            { "architecture": "SCMP_ARCH_RISCV64", "subArchitectures": [] }
+        ],
+        "syscalls": syscalls,
+    });
+    serde_json::to_string_pretty(&profile).unwrap_or_default() + "\n"
+}
+
+/// Write the profile to a new private (0600) temp file and return its path.
+pub fn write_seccomp_profile(relaxed: bool) -> io::Result<PathBuf> {
+    let prefix = if relaxed {
+        "aifo-seccomp-relaxed-"
+    } else {
+        "aifo-seccomp-"
+    };
+    let mut file = tempfile::Builder::new()
+        .prefix(prefix)
+        .suffix(".json")
+        .tempfile()?;
+    file.write_all(seccomp_profile_json(relaxed).as_bytes())?;
+    let (_, path) = file.keep().map_err(|e| e.error)?;
+    Ok(path)
+}
+
+/// `run` flags applying the configured seccomp mode; TOOLCHAIN_KIND is set for sidecars.
+pub fn seccomp_run_flags(toolchain_kind: Option<&str>) -> Vec<String> {
+    match seccomp_mode() {
+        SeccompMode::Engine => Vec::new(),
+        SeccompMode::Unconfined => vec![
+            "--security-opt".to_string(),
+            "seccomp=unconfined".to_string(),
+        ],
+        SeccompMode::Aifo => {
+            let relaxed = toolchain_kind.is_some_and(seccomp_relaxed_for);
+            match write_seccomp_profile(relaxed) {
+                Ok(path) => vec![
+                    "--security-opt".to_string(),
+                    format!("seccomp={}", path.display()),
+                ],
+                Err(e) => {
+                    crate::warn_print(&format!(
+                        "failed to write seccomp profile ({e}); continuing with the engine default."
+                    ));
+                    Vec::new()
+                }
+            }
+        }
+    }
+}
@@ -545,6 +545,7 @@ fn run_version_check(
     let mut child = Command::new(rt)
         .arg("run")
         .arg("--rm")
+        .args(aifo_coder::hardened_run_flags(None))
         .arg("--entrypoint")
         .arg("/bin/sh")
         .arg(image)
@@ -606,7 +607,7 @@ fn run_version_check(
 pub fn run_support(verbose: bool, suppress_banner: bool, preface: Option<&str>) -> ExitCode {
     // Print startup header (version/host lines)
     if !suppress_banner {
-        print_startup_banner();
+        print_startup_banner(None);
     }
 
     // Require docker runtime; print prominent red line and exit 1 on missing
@@ -626,7 +627,11 @@ pub fn run_support(verbose: bool, suppress_banner: bool, preface: Option<&str>)
         aifo_coder::log_info_stderr(use_err, p);
     }
     eprintln!();
-    aifo_coder::log_info_stderr(use_err, "Support matrix:");
+    if aifo_coder::hardened_mode_enabled() {
+        aifo_coder::log_info_stderr(use_err, "Support matrix (hardened containers):");
+    } else {
+        aifo_coder::log_info_stderr(use_err, "Support matrix:");
+    }
     eprintln!();
 
     // Phase 3: lists, images and RNG
@@ -23,8 +23,12 @@ pub use routing::{
 };
 
 mod env;
+mod env_forward;
+mod limits;
 mod mounts;
 
+mod approval;
+mod audit;
 mod auth;
 mod http;
 mod notifications;
@@ -35,12 +39,43 @@ pub mod sidecar;
 pub use sidecar::{
     build_sidecar_exec_preview, build_sidecar_run_preview,
     build_sidecar_run_preview_with_overrides, toolchain_bootstrap_typescript_global,
-    toolchain_cleanup_session, toolchain_purge_caches, toolchain_purge_volume_names, toolchain_run,
+    toolchain_cleanup_session, toolchain_cleanup_session_with, toolchain_purge_caches,
+    toolchain_purge_caches_with, toolchain_purge_volume_names, toolchain_run,
     toolchain_start_session, BootstrapGuard,
 };
 
+mod policy;
+pub(crate) mod pool;
 mod proxy;
+mod pty;
+mod status;
+mod tls;
+mod tokens;
+mod transcript;
+pub use audit::{audit_dir, audit_latest_session, audit_log_path, audit_read, AuditFilter};
+pub use env_forward::{toolexec_env_filter, ENV_FORWARD_DEFAULT_ALLOW};
+pub use limits::{
+    resolve_exec_limits, tool_limit_rules, ExecLimitRule, ExecLimits, EXIT_IDLE_TIMEOUT,
+    EXIT_MAX_RUNTIME, EXIT_OUTPUT_LIMIT,
+};
+pub use policy::{
+    apply_tool_policy, policy_approval_rule, tool_policy_rules, PolicyDenial, ToolPolicyRule,
+};
 pub use proxy::toolexec_start_proxy;
+pub use pty::{
+    pty_parse_resize, pty_parse_size_header, pty_resize_payload, pty_write_frame, PtyFrameReader,
+    PTY_FRAME_EXIT, PTY_FRAME_OUTPUT, PTY_FRAME_RESIZE, PTY_FRAME_STDIN, PTY_FRAME_STDIN_EOF,
+    PTY_MAX_FRAME, PTY_SIZE_HEADER, PTY_UPGRADE, STDIN_UPGRADE,
+};
+pub use tls::{
+    toolexec_tls_client_config, toolexec_tls_connect, toolexec_tls_dir, TlsMaterial,
+    ToolexecTlsStream, TLS_CA_FILE, TLS_CLIENT_CERT_FILE, TLS_CLIENT_KEY_FILE,
+};
+pub use tokens::{
+    token_claims, toolexec_request_signature, toolexec_signature_headers, toolexec_signing_key,
+    SignedHeaders, TokenClaims, TokenScope, TOOLEXEC_SIGNING_KEY_ENV,
+};
+pub use transcript::transcript_path;
 
 fn log_parsed_request(verbose: bool, tool: &str, argv: &[String], cwd: &str, exec_id: &str) {
     if verbose {
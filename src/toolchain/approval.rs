@@ -0,0 +1,328 @@
+/*!
+Human approval gate for proxied commands matched by a policy rule with `approve = true`.
+
+The proxy holds the request (the shim keeps waiting for the response) and asks the host user,
+one prompt at a time, according to AIFO_TOOLEEXEC_APPROVAL:
+- tty: on the launcher's terminal (/dev/tty); the user answers with a line (o/allow/once,
+  a/always/session, d/deny) and Enter. Other lines repeat the question.
+- command: via the notifications command (`notifications-command` in ~/.aider.conf.yml). It gets
+  the prompt as its trailing `{args}` argument plus AIFO_APPROVAL_TOOL/ARGV/CWD/RULE and must
+  print `allow`, `always` or `deny`; any other answer denies.
+- auto (default): command while the agent is attached to the launcher's terminal (`docker run
+  -it`, which would race the prompt for keystrokes and draw over the agent's UI); otherwise tty
+  when the launcher has one, else command.
+- deny: refuse everything that needs approval.
+
+No answer within AIFO_TOOLEEXEC_APPROVAL_TIMEOUT_SECS (default 120) denies. "always" covers the
+same tool and arguments for the rest of the proxy's lifetime.
+*/
+
+use std::collections::HashSet;
+use std::env;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::notifications::{self, NotifyError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ApprovalDecision {
+    Once,
+    Session,
+    Deny,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ApprovalMode {
+    Auto,
+    Tty,
+    Command,
+    Deny,
+}
+
+/// A held invocation awaiting a decision.
+pub(crate) struct ApprovalRequest<'a> {
+    pub tool: &'a str,
+    /// Arguments as they will run (keys "always" decisions).
+    pub argv: &'a [String],
+    /// Arguments as shown to the user (secrets redacted).
+    pub shown_argv: &'a str,
+    pub cwd: &'a str,
+    /// Policy rule that asked for approval.
+    pub rule: &'a str,
+}
+
+impl ApprovalRequest<'_> {
+    fn key(&self) -> String {
+        let mut parts = vec![self.tool.to_string()];
+        parts.extend(self.argv.iter().cloned());
+        crate::shell_join(&parts)
+    }
+
+    fn summary(&self) -> String {
+        let cmd = if self.shown_argv.is_empty() {
+            self.tool.to_string()
+        } else {
+            format!("{} {}", self.tool, self.shown_argv)
+        };
+        format!("{cmd} (cwd {}, rule {})", self.cwd, self.rule)
+    }
+}
+
+/// Parse an answer from the approval command or terminal.
+pub(crate) fn parse_approval_answer(answer: &str) -> Option<ApprovalDecision> {
+    let word = answer.split_whitespace().next()?.to_ascii_lowercase();
+    match word.as_str() {
+        "allow" | "once" | "yes" | "y" | "o" => Some(ApprovalDecision::Once),
+        "always" | "session" | "a" => Some(ApprovalDecision::Session),
+        "deny" | "no" | "n" | "d" => Some(ApprovalDecision::Deny),
+        _ => None,
+    }
+}
+
+/// Parse one terminal line: exactly one answer word, so "allow always" asks again.
+fn parse_tty_answer(line: &str) -> Option<ApprovalDecision> {
+    let mut words = line.split_whitespace();
+    let decision = parse_approval_answer(words.next()?)?;
+    words.next().is_none().then_some(decision)
+}
+
+/// Session-wide approval state shared by all proxy connections.
+pub(crate) struct ApprovalGate {
+    mode: ApprovalMode,
+    /// The agent runs attached to the launcher's terminal, so auto mode must not prompt there.
+    agent_on_tty: bool,
+    timeout: Duration,
+    always: Mutex<HashSet<String>>,
+    prompt: Mutex<()>,
+}
+
+impl ApprovalGate {
+    pub(crate) fn from_env() -> Self {
+        let mode = match env::var("AIFO_TOOLEEXEC_APPROVAL")
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "tty" => ApprovalMode::Tty,
+            "command" => ApprovalMode::Command,
+            "deny" => ApprovalMode::Deny,
+            _ => ApprovalMode::Auto,
+        };
+        let secs = env::var("AIFO_TOOLEEXEC_APPROVAL_TIMEOUT_SECS")
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .filter(|&v| v > 0)
+            .unwrap_or(120);
+        ApprovalGate {
+            mode,
+            // Same test the launcher uses to give the agent container `-it`.
+            agent_on_tty: atty::is(atty::Stream::Stdin) || atty::is(atty::Stream::Stdout),
+            timeout: Duration::from_secs(secs),
+            always: Mutex::new(HashSet::new()),
+            prompt: Mutex::new(()),
+        }
+    }
+
+    fn always(&self) -> std::sync::MutexGuard<'_, HashSet<String>> {
+        self.always.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Ok when the user approved REQ (or approved it for the session earlier); Err(reason) else.
+    pub(crate) fn check(&self, req: &ApprovalRequest) -> Result<(), String> {
+        let key = req.key();
+        if self.always().contains(&key) {
+            return Ok(());
+        }
+        let _turn = self.prompt.lock().unwrap_or_else(|e| e.into_inner());
+        // Another prompt may have granted the same command while we waited our turn.
+        if self.always().contains(&key) {
+            return Ok(());
+        }
+        let decision = match self.mode {
+            ApprovalMode::Deny => {
+                Err("approval required; AIFO_TOOLEEXEC_APPROVAL=deny".to_string())
+            }
+            ApprovalMode::Tty => self.ask_tty(req),
+            ApprovalMode::Command => self.ask_command(req),
+            ApprovalMode::Auto if self.agent_on_tty => self.ask_command(req),
+            ApprovalMode::Auto => match self.ask_tty(req) {
+                Err(reason) if reason == NO_TERMINAL => self.ask_command(req),
+                other => other,
+            },
+        }?;
+        match decision {
+            ApprovalDecision::Once => Ok(()),
+            ApprovalDecision::Session => {
+                self.always().insert(key);
+                Ok(())
+            }
+            ApprovalDecision::Deny => Err("denied by the host user".to_string()),
+        }
+    }
+
+    fn timeout_reason(&self) -> String {
+        format!("no approval within {}s", self.timeout.as_secs())
+    }
+
+    #[cfg(unix)]
+    fn ask_tty(&self, req: &ApprovalRequest) -> Result<ApprovalDecision, String> {
+        use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+        use std::io::{Read, Write};
+        use std::os::fd::AsFd;
+
+        let mut tty = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open("/dev/tty")
+            .map_err(|_| NO_TERMINAL.to_string())?;
+        let _ = write!(
+            tty,
+            "\r\naifo-coder: approval required: {}\r\n",
+            req.summary()
+        );
+        let ask = |tty: &mut std::fs::File| {
+            let _ = write!(
+                tty,
+                "  allow [o]nce, [a]lways this session, or [d]eny, then Enter ({}s): ",
+                self.timeout.as_secs()
+            );
+            let _ = tty.flush();
+        };
+        ask(&mut tty);
+        let deadline = std::time::Instant::now() + self.timeout;
+        let mut buf = [0u8; 64];
+        let mut line = Vec::new();
+        loop {
+            let left = deadline.saturating_duration_since(std::time::Instant::now());
+            if left.is_zero() {
+                let _ = write!(tty, "timed out, denied\r\n");
+                return Err(self.timeout_reason());
+            }
+            let ms = u16::try_from(left.as_millis().min(1000)).unwrap_or(1000);
+            let ready = {
+                let mut fds = [PollFd::new(tty.as_fd(), PollFlags::POLLIN)];
+                poll(&mut fds, PollTimeout::from(ms)).unwrap_or(0) > 0
+            };
+            if !ready {
+                continue;
+            }
+            let n = match tty.read(&mut buf) {
+                Ok(0) | Err(_) => return Err(NO_TERMINAL.to_string()),
+                Ok(n) => n,
+            };
+            // Whole lines only, so typing "allow" is not read as its first key "a" (always).
+            // Enter ends a line as \n on a cooked terminal and as \r on a raw one.
+            for &b in &buf[..n] {
+                if b != b'\n' && b != b'\r' {
+                    line.push(b);
+                    continue;
+                }
+                let answer = String::from_utf8_lossy(&line).into_owned();
+                line.clear();
+                if answer.trim().is_empty() {
+                    continue;
+                }
+                let Some(d) = parse_tty_answer(&answer) else {
+                    ask(&mut tty);
+                    continue;
+                };
+                let label = match d {
+                    ApprovalDecision::Once => "allowed once",
+                    ApprovalDecision::Session => "allowed for this session",
+                    ApprovalDecision::Deny => "denied",
+                };
+                let _ = write!(tty, "{label}\r\n");
+                return Ok(d);
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn ask_tty(&self, _req: &ApprovalRequest) -> Result<ApprovalDecision, String> {
+        Err(NO_TERMINAL.to_string())
+    }
+
+    fn ask_command(&self, req: &ApprovalRequest) -> Result<ApprovalDecision, String> {
+        let prompt = format!("aifo-coder: approval required: {}", req.summary());
+        let env = [
+            ("AIFO_APPROVAL_TOOL", req.tool.to_string()),
+            ("AIFO_APPROVAL_ARGV", req.shown_argv.to_string()),
+            ("AIFO_APPROVAL_CWD", req.cwd.to_string()),
+            ("AIFO_APPROVAL_RULE", req.rule.to_string()),
+        ];
+        match notifications::notifications_run_approval(&prompt, &env, self.timeout.as_secs()) {
+            Ok((_code, out)) => {
+                let text = String::from_utf8_lossy(&out);
+                Ok(text
+                    .lines()
+                    .find_map(parse_approval_answer)
+                    .unwrap_or(ApprovalDecision::Deny))
+            }
+            Err(NotifyError::Timeout) => Err(self.timeout_reason()),
+            Err(NotifyError::Policy(msg)) | Err(NotifyError::ExecSpawn(msg)) => {
+                Err(format!("no approver available ({msg})"))
+            }
+        }
+    }
+}
+
+const NO_TERMINAL: &str = "no terminal for approval prompts";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_approval_answer_words_and_keys() {
+        assert_eq!(
+            parse_approval_answer("allow\n"),
+            Some(ApprovalDecision::Once)
+        );
+        assert_eq!(
+            parse_approval_answer(" Always "),
+            Some(ApprovalDecision::Session)
+        );
+        assert_eq!(parse_approval_answer("a"), Some(ApprovalDecision::Session));
+        assert_eq!(
+            parse_approval_answer("deny please"),
+            Some(ApprovalDecision::Deny)
+        );
+        assert_eq!(parse_approval_answer("ok"), None);
+        assert_eq!(parse_approval_answer(""), None);
+
+        // Terminal lines: the whole word counts, never its first key.
+        assert_eq!(parse_tty_answer("allow"), Some(ApprovalDecision::Once));
+        assert_eq!(parse_tty_answer(" a "), Some(ApprovalDecision::Session));
+        assert_eq!(parse_tty_answer("allow always"), None);
+        assert_eq!(parse_tty_answer("alw"), None);
+    }
+
+    #[test]
+    fn test_deny_mode_refuses_and_session_grants_skip_the_prompt() {
+        let gate = ApprovalGate {
+            mode: ApprovalMode::Deny,
+            agent_on_tty: false,
+            timeout: Duration::from_secs(1),
+            always: Mutex::new(HashSet::new()),
+            prompt: Mutex::new(()),
+        };
+        let argv = vec!["install".to_string(), "left-pad".to_string()];
+        let req = ApprovalRequest {
+            tool: "npm",
+            argv: &argv,
+            shown_argv: "install left-pad",
+            cwd: "/workspace",
+            rule: "proxy.policy.npm[0]",
+        };
+        assert!(gate.check(&req).is_err());
+        gate.always().insert(req.key());
+        assert!(gate.check(&req).is_ok());
+        let other = vec!["install".to_string(), "lodash".to_string()];
+        let req2 = ApprovalRequest {
+            argv: &other,
+            ..req
+        };
+        assert!(gate.check(&req2).is_err());
+    }
+}
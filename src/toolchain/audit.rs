@@ -0,0 +1,325 @@
+/*!
+Append-only JSONL audit log of proxied executions and notifications.
+
+Each proxy session writes `<dir>/<session>.jsonl`, where dir is AIFO_TOOLEEXEC_AUDIT_DIR or
+`$XDG_STATE_HOME/aifo-coder/audit` (default `~/.local/state/aifo-coder/audit`). One JSON object
+per line, all with `ts` (UTC, RFC 3339), `session`, `agent` and `event`:
+- exec: exec_id, tool, argv (redacted), cwd, sidecar, kind, exit_code (null when the client
+  disconnected), duration_ms, bytes_out and signals (forwarded via /signal).
+- notify: cmd, argv, exit_code (null when the command failed), duration_ms and error.
+- denied: tool, argv, cwd, rule and reason for allowlist, policy and approval refusals.
+
+AIFO_TOOLEEXEC_AUDIT=0 disables the log. `aifo-coder audit show` reads it back.
+*/
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use super::notifications::NotifyError;
+use super::status::ExecEntry;
+
+/// `$XDG_STATE_HOME/aifo-coder` (default `~/.local/state/aifo-coder`).
+pub(crate) fn aifo_state_dir() -> PathBuf {
+    env::var_os("XDG_STATE_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| home::home_dir().map(|h| h.join(".local").join("state")))
+        .unwrap_or_else(env::temp_dir)
+        .join("aifo-coder")
+}
+
+/// Directory holding per-session audit files.
+pub fn audit_dir() -> PathBuf {
+    if let Some(d) = env::var_os("AIFO_TOOLEEXEC_AUDIT_DIR").filter(|v| !v.is_empty()) {
+        return PathBuf::from(d);
+    }
+    aifo_state_dir().join("audit")
+}
+
+/// Audit file of SESSION.
+pub fn audit_log_path(session: &str) -> PathBuf {
+    audit_dir().join(format!("{session}.jsonl"))
+}
+
+/// Session whose audit file was written most recently, if any.
+pub fn audit_latest_session() -> Option<String> {
+    fs::read_dir(audit_dir())
+        .ok()?
+        .flatten()
+        .filter_map(|e| {
+            let path = e.path();
+            let sid = path
+                .file_name()?
+                .to_str()?
+                .strip_suffix(".jsonl")?
+                .to_string();
+            let modified = e.metadata().ok()?.modified().ok()?;
+            Some((modified, sid))
+        })
+        .max()
+        .map(|(_, sid)| sid)
+}
+
+/// Records of SESSION in write order; lines that are not JSON objects are skipped.
+pub fn audit_read(session: &str) -> io::Result<Vec<Value>> {
+    let file = fs::File::open(audit_log_path(session))?;
+    Ok(io::BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|l| serde_json::from_str::<Value>(&l).ok())
+        .filter(Value::is_object)
+        .collect())
+}
+
+/// Record selection for `audit show`; unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    /// Exec tool or notification command.
+    pub tool: Option<String>,
+    /// exec, notify or denied.
+    pub event: Option<String>,
+    /// Only denials and non-zero, missing or interrupted exits.
+    pub failed: bool,
+}
+
+impl AuditFilter {
+    pub fn matches(&self, record: &Value) -> bool {
+        let field = |k: &str| record.get(k).and_then(Value::as_str);
+        if let Some(ev) = &self.event {
+            if field("event") != Some(ev.as_str()) {
+                return false;
+            }
+        }
+        if let Some(tool) = &self.tool {
+            if field("tool").or_else(|| field("cmd")) != Some(tool.as_str()) {
+                return false;
+            }
+        }
+        if self.failed {
+            let ok = record.get("exit_code").and_then(Value::as_i64) == Some(0)
+                && field("event") != Some("denied");
+            if ok {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// UTC RFC 3339 timestamp with milliseconds.
+pub(crate) fn rfc3339_utc(t: SystemTime) -> String {
+    let d = t.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = d.as_secs();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    // Civil date from days since 1970-01-01 (proleptic Gregorian).
+    let z = days + 719_468;
+    let (era, doe) = (z / 146_097, z % 146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60,
+        d.subsec_millis()
+    )
+}
+
+/// Audit writer of one proxy instance.
+pub(crate) struct AuditLog {
+    path: Option<PathBuf>,
+    session: String,
+    agent: String,
+}
+
+impl AuditLog {
+    /// Prepare the audit file of SESSION (private dir and file); disabled on error or by env.
+    pub(crate) fn open(session: &str, verbose: bool) -> Self {
+        let agent = env::var("AIFO_AGENT_NAME").unwrap_or_default();
+        let disabled = AuditLog {
+            path: None,
+            session: session.to_string(),
+            agent: agent.clone(),
+        };
+        if env::var("AIFO_TOOLEEXEC_AUDIT").ok().as_deref() == Some("0") {
+            return disabled;
+        }
+        let path = audit_log_path(session);
+        if let Err(e) = create_private_dir(path.parent().unwrap_or(Path::new("."))) {
+            crate::log_warn_stderr(
+                crate::color_enabled_stderr(),
+                &format!(
+                    "aifo-coder: warning: audit log disabled: cannot create {}: {}",
+                    path.display(),
+                    e
+                ),
+            );
+            return disabled;
+        }
+        if verbose {
+            eprintln!("aifo-coder: proxy audit log: {}", path.display());
+        }
+        AuditLog {
+            path: Some(path),
+            ..disabled
+        }
+    }
+
+    /// Finished exec ENTRY; EXIT is None when the client went away first.
+    pub(crate) fn exec(&self, exec_id: &str, entry: &ExecEntry, cwd: &str, exit: Option<i32>) {
+        let elapsed = SystemTime::now()
+            .duration_since(entry.started)
+            .unwrap_or_default();
+        self.append(json!({
+            "event": "exec",
+            "exec_id": exec_id,
+            "tool": entry.tool,
+            "argv": entry.argv,
+            "cwd": cwd,
+            "sidecar": entry.container,
+            "kind": entry.kind,
+            "exit_code": exit,
+            "duration_ms": millis(elapsed),
+            "bytes_out": entry.bytes.load(std::sync::atomic::Ordering::Relaxed),
+            "signals": entry.signals,
+        }));
+    }
+
+    /// Notification CMD with ARGV and its outcome.
+    pub(crate) fn notify(
+        &self,
+        cmd: &str,
+        argv: &[String],
+        result: &Result<(i32, Vec<u8>), NotifyError>,
+        elapsed: Duration,
+    ) {
+        let (exit, error) = match result {
+            Ok((code, _)) => (Some(*code), None),
+            Err(NotifyError::Policy(msg)) | Err(NotifyError::ExecSpawn(msg)) => {
+                (None, Some(msg.clone()))
+            }
+            Err(NotifyError::Timeout) => (None, Some("timeout".to_string())),
+        };
+        self.append(json!({
+            "event": "notify",
+            "cmd": cmd,
+            "argv": argv,
+            "exit_code": exit,
+            "duration_ms": millis(elapsed),
+            "error": error,
+        }));
+    }
+
+    /// Refused exec of TOOL (ARGV already redacted).
+    pub(crate) fn denied(&self, tool: &str, argv: &[String], cwd: &str, rule: &str, reason: &str) {
+        self.append(json!({
+            "event": "denied",
+            "tool": tool,
+            "argv": argv,
+            "cwd": cwd,
+            "rule": rule,
+            "reason": reason,
+        }));
+    }
+
+    fn append(&self, mut record: Value) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let head = json!({
+            "ts": rfc3339_utc(SystemTime::now()),
+            "session": self.session,
+            "agent": self.agent,
+        });
+        if let (Some(obj), Value::Object(head)) = (record.as_object_mut(), head) {
+            let body = std::mem::take(obj);
+            obj.extend(head);
+            obj.extend(body);
+        }
+        let mut line = record.to_string();
+        line.push('\n');
+        // One write per record on an O_APPEND file keeps concurrent connections' lines whole.
+        let mut opts = fs::OpenOptions::new();
+        opts.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            opts.mode(0o600);
+        }
+        let _ = opts
+            .open(path)
+            .and_then(|mut f| f.write_all(line.as_bytes()));
+    }
+}
+
+fn millis(d: Duration) -> u64 {
+    u64::try_from(d.as_millis()).unwrap_or(u64::MAX)
+}
+
+pub(crate) fn create_private_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc3339_utc_formats_civil_dates() {
+        assert_eq!(rfc3339_utc(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        let t = UNIX_EPOCH + Duration::from_millis(951_782_400_250);
+        assert_eq!(rfc3339_utc(t), "2000-02-29T00:00:00.250Z");
+        let t = UNIX_EPOCH + Duration::from_secs(1_791_979_199);
+        assert_eq!(rfc3339_utc(t), "2026-10-14T11:59:59.000Z");
+    }
+
+    #[test]
+    fn test_audit_filter_by_event_tool_and_failure() {
+        let ok = json!({"event": "exec", "tool": "cargo", "exit_code": 0});
+        let failed = json!({"event": "exec", "tool": "npm", "exit_code": 1});
+        let gone = json!({"event": "exec", "tool": "npm", "exit_code": null});
+        let denied = json!({"event": "denied", "tool": "cargo"});
+        let notify = json!({"event": "notify", "cmd": "say", "exit_code": 0});
+
+        let all = AuditFilter::default();
+        assert!([&ok, &failed, &gone, &denied, &notify]
+            .iter()
+            .all(|r| all.matches(r)));
+
+        let cargo = AuditFilter {
+            tool: Some("cargo".into()),
+            ..Default::default()
+        };
+        assert!(cargo.matches(&ok) && cargo.matches(&denied) && !cargo.matches(&failed));
+
+        let say = AuditFilter {
+            tool: Some("say".into()),
+            event: Some("notify".into()),
+            ..Default::default()
+        };
+        assert!(say.matches(&notify) && !say.matches(&ok));
+
+        let failures = AuditFilter {
+            failed: true,
+            ..Default::default()
+        };
+        assert!(!failures.matches(&ok) && !failures.matches(&notify));
+        assert!(failures.matches(&failed) && failures.matches(&gone) && failures.matches(&denied));
+    }
+}
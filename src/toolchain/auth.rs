@@ -2,7 +2,8 @@
 Authorization and protocol validation helpers for the proxy.
 */
 
-use std::collections::HashMap;
+use super::http::HttpRequest;
+use super::tokens::{secrets_equal, TokenAuthority, TokenClaims, TokenError};
 
 /// Supported shim protocol versions
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -46,52 +47,70 @@ mod tests {
     }
 }
 
-/// Result of validating Authorization and X-Aifo-Proto
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Result of validating Authorization, request signature and X-Aifo-Proto
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum AuthResult {
-    Authorized { proto: Proto },
+    Authorized {
+        proto: Proto,
+        grant: TokenClaims,
+    },
     MissingOrInvalidAuth,
+    /// A scoped token past its expiry.
+    Expired,
+    /// Missing (when required), stale, replayed or wrong X-Aifo-Signature.
+    BadSignature(String),
     MissingOrInvalidProto,
 }
 
-/// Return true when an Authorization header value authorizes the given token
-/// using the standard Bearer scheme (RFC 6750).
-/// Accepts:
-/// - "Bearer <token>" (scheme case-insensitive; at least one ASCII whitespace
-///   separating scheme and credentials)
-pub(crate) fn authorization_value_matches(value: &str, token: &str) -> bool {
+/// Credentials of an Authorization header value using the standard Bearer scheme (RFC 6750):
+/// "Bearer <token>" (scheme case-insensitive; at least one ASCII whitespace separating scheme
+/// and credentials).
+pub(crate) fn bearer_credential(value: &str) -> Option<&str> {
     let v = value.trim();
     // Split at the first ASCII whitespace to separate scheme and credentials
-    if let Some(idx) = v.find(|c: char| c.is_ascii_whitespace()) {
-        let (scheme, rest) = v.split_at(idx);
-        if scheme.eq_ignore_ascii_case("bearer") {
-            let cred = rest.trim();
-            return !cred.is_empty() && cred == token;
-        }
-    }
-    false
+    let idx = v.find(|c: char| c.is_ascii_whitespace())?;
+    let (scheme, rest) = v.split_at(idx);
+    let cred = rest.trim();
+    (scheme.eq_ignore_ascii_case("bearer") && !cred.is_empty()).then_some(cred)
 }
 
-/// Validate Authorization and X-Aifo-Proto against expectations,
-/// returning a tri-state indicating whether we are authorized and which proto applies.
-pub(crate) fn validate_auth_and_proto(
-    headers: &HashMap<String, String>,
-    token: &str,
-) -> AuthResult {
-    let mut auth_ok = false;
-    if let Some(v) = headers.get("authorization") {
-        if authorization_value_matches(v, token) {
-            auth_ok = true;
-        }
-    }
-    if !auth_ok {
+/// Return true when an Authorization header value authorizes the given token.
+pub(crate) fn authorization_value_matches(value: &str, token: &str) -> bool {
+    bearer_credential(value).is_some_and(|cred| secrets_equal(cred, token))
+}
+
+/// Validate Authorization (legacy or scoped token), the request signature and X-Aifo-Proto,
+/// returning whether we are authorized, with which grant and which proto applies.
+pub(crate) fn validate_auth_and_proto(req: &HttpRequest, tokens: &TokenAuthority) -> AuthResult {
+    let Some(token) = req
+        .headers
+        .get("authorization")
+        .and_then(|v| bearer_credential(v))
+    else {
         return AuthResult::MissingOrInvalidAuth;
+    };
+    let grant = match tokens.verify(token) {
+        Ok(g) => g,
+        Err(TokenError::Invalid) => return AuthResult::MissingOrInvalidAuth,
+        Err(TokenError::Expired) => return AuthResult::Expired,
+    };
+    if let Err(msg) = tokens.check_signature(req) {
+        return AuthResult::BadSignature(msg);
     }
     // Authorized: now require a valid proto header (1 or 2)
-    let ver = headers.get("x-aifo-proto").map(|s| s.trim().to_string());
+    let ver = req
+        .headers
+        .get("x-aifo-proto")
+        .map(|s| s.trim().to_string());
     match ver.as_deref() {
-        Some("1") => AuthResult::Authorized { proto: Proto::V1 },
-        Some("2") => AuthResult::Authorized { proto: Proto::V2 },
+        Some("1") => AuthResult::Authorized {
+            proto: Proto::V1,
+            grant,
+        },
+        Some("2") => AuthResult::Authorized {
+            proto: Proto::V2,
+            grant,
+        },
         _ => AuthResult::MissingOrInvalidProto,
     }
 }
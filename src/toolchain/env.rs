@@ -36,11 +36,16 @@ pub(crate) fn push_env(args: &mut Vec<String>, k: &str, v: &str) {
 /// Pass through selected environment variables from host into docker args.
 pub(crate) fn apply_passthrough_envs(args: &mut Vec<String>, keys: &[&str]) {
     let force_direct = crate::proxy::should_force_direct_proxy();
+    let egress = !crate::egress_proxy_env().is_empty();
     for name in keys {
         // Do not forward host rustup/cargo environment into sidecars
         if PROHIBITED_PASSTHROUGH_ENV.contains(name) {
             continue;
         }
+        // Under the egress allowlist the session proxy replaces host proxy settings
+        if egress && name.to_ascii_lowercase().ends_with("_proxy") {
+            continue;
+        }
         if force_direct && crate::proxy::PROXY_ENV_VARS.contains(name) {
             push_env(args, name, "");
             continue;
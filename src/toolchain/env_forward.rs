@@ -0,0 +1,208 @@
+//! Environment forwarding from the agent's shim to proxied execs.
+//!
+//! Sidecar execs start from the sidecar's own environment, so variables the agent sets
+//! (`RUST_LOG`, `NODE_ENV`, `CARGO_TARGET_DIR`, `PYTHONUNBUFFERED`, ...) would be lost on the way. The
+//! shim sends the ones that pass [`toolexec_env_filter`] as repeated `env=NAME=VALUE` form fields
+//! and the proxy, applying the same filter with its own configuration, adds them to
+//! `docker exec` as `-e` flags.
+//!
+//! A name is forwarded when it matches an allow pattern (the defaults below plus
+//! AIFO_TOOLEEXEC_ENV_ALLOW) and no deny pattern (AIFO_TOOLEEXEC_ENV_DENY; `*` disables
+//! forwarding). Patterns are comma-separated and use `*` as a wildcard. Regardless of the
+//! patterns, secret-looking names, names the sidecar environment depends on (PATH, HOME,
+//! CARGO_HOME, AIFO_*, ...), values with control characters and oversized values are dropped.
+
+use std::env;
+
+use super::policy::pattern_matches;
+
+/// Names forwarded without configuration.
+///
+/// Variables that load code or pick the compiler, linker or their flags (RUSTFLAGS,
+/// CARGO_BUILD_*, NODE_OPTIONS, PYTHONPATH, PYTEST_ADDOPTS, GOFLAGS, CC, CXX, *FLAGS, MAKEFLAGS)
+/// would let an agent change what an allowed command runs past the argument policy, so they are
+/// only forwarded when listed in AIFO_TOOLEEXEC_ENV_ALLOW.
+pub const ENV_FORWARD_DEFAULT_ALLOW: &[&str] = &[
+    "RUST_LOG",
+    "RUST_BACKTRACE",
+    "CARGO_TARGET_DIR",
+    "CARGO_PROFILE_*",
+    "CARGO_INCREMENTAL",
+    "CARGO_TERM_*",
+    "NODE_ENV",
+    "PYTHONUNBUFFERED",
+    "PYTHONDONTWRITEBYTECODE",
+    "PYTHONWARNINGS",
+    "CGO_ENABLED",
+    "CMAKE_BUILD_TYPE",
+    "CMAKE_GENERATOR",
+    "CI",
+    "DEBUG",
+    "LOG_LEVEL",
+    "NO_COLOR",
+    "FORCE_COLOR",
+    "TZ",
+    "LANG",
+    "LC_*",
+];
+
+/// Names the sidecar environment or the exec wrapper depend on; never forwarded.
+const RESERVED: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "SHELL",
+    "PWD",
+    "OLDPWD",
+    "HOSTNAME",
+    "TERM",
+    "SHLVL",
+    "GNUPGHOME",
+    "SSH_AUTH_SOCK",
+    "LD_PRELOAD",
+    "LD_LIBRARY_PATH",
+    "CARGO_HOME",
+    "RUSTUP_HOME",
+    "RUSTUP_TOOLCHAIN",
+    "PNPM_HOME",
+    "GOPATH",
+    "GOROOT",
+    "VIRTUAL_ENV",
+    "AIFO_*",
+];
+
+/// Name fragments that mark a variable as a secret.
+const SECRET_MARKERS: &[&str] = &[
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "PASSWD",
+    "PASSPHRASE",
+    "CREDENTIAL",
+    "PRIVATE",
+    "APIKEY",
+    "API_KEY",
+    "ACCESS_KEY",
+    "AUTH",
+    "COOKIE",
+];
+
+/// Most variables forwarded per exec.
+const ENV_FORWARD_MAX_VARS: usize = 64;
+/// Longest forwarded value in bytes.
+const ENV_FORWARD_MAX_VALUE: usize = 4096;
+
+/// Whether NAME looks like it holds a secret (`*TOKEN*`, `*_KEY`, `*PASSWORD*`, ...).
+fn env_forward_is_secret(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    upper.ends_with("_KEY") || SECRET_MARKERS.iter().any(|m| upper.contains(m))
+}
+
+fn patterns(var: &str) -> Vec<String> {
+    env::var(var)
+        .unwrap_or_default()
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn valid_name(name: &str) -> bool {
+    let mut bytes = name.bytes();
+    matches!(bytes.next(), Some(b) if b.is_ascii_alphabetic() || b == b'_')
+        && bytes.all(|b| b.is_ascii_alphanumeric() || b == b'_')
+        && name.len() <= 128
+}
+
+/// Split VARS into the ones to forward and the names dropped, in input order.
+///
+/// Uses AIFO_TOOLEEXEC_ENV_ALLOW/AIFO_TOOLEEXEC_ENV_DENY of the calling process; the first
+/// ENV_FORWARD_MAX_VARS accepted variables are kept.
+pub fn toolexec_env_filter<I>(vars: I) -> (Vec<(String, String)>, Vec<String>)
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let mut allow: Vec<String> = ENV_FORWARD_DEFAULT_ALLOW
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    allow.extend(patterns("AIFO_TOOLEEXEC_ENV_ALLOW"));
+    let deny = patterns("AIFO_TOOLEEXEC_ENV_DENY");
+
+    let mut kept: Vec<(String, String)> = Vec::new();
+    let mut dropped: Vec<String> = Vec::new();
+    for (name, value) in vars {
+        if !allow.iter().any(|p| pattern_matches(p, &name)) {
+            continue; // not asked for: silently ignored
+        }
+        let refused = !valid_name(&name)
+            || deny.iter().any(|p| pattern_matches(p, &name))
+            || RESERVED.iter().any(|p| pattern_matches(p, &name))
+            || env_forward_is_secret(&name)
+            || value.len() > ENV_FORWARD_MAX_VALUE
+            || value.chars().any(|c| c.is_control() && c != '\t')
+            || kept.len() >= ENV_FORWARD_MAX_VARS
+            || kept.iter().any(|(k, _)| *k == name);
+        if refused {
+            dropped.push(name);
+        } else {
+            kept.push((name, value));
+        }
+    }
+    (kept, dropped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_env_filter_keeps_defaults_and_strips_secrets_and_reserved() {
+        let (kept, dropped) = toolexec_env_filter(vars(&[
+            ("RUST_LOG", "debug"),
+            ("CARGO_TARGET_DIR", "/workspace/target-agent"),
+            ("LC_ALL", "C.UTF-8"),
+            ("EDITOR", "vi"),
+            ("NODE_ENV", "test\nINJECTED=1"),
+            ("CARGO_HOME", "/tmp/cargo"),
+            ("CARGO_TERM_TOKEN", "x"),
+            ("RUSTFLAGS", "-Clinker=/workspace/evil.sh"),
+            ("NODE_OPTIONS", "--require /workspace/evil.js"),
+            ("CC", "/workspace/evil-cc"),
+            ("LDFLAGS", "-fuse-ld=/workspace/evil"),
+        ]));
+        assert_eq!(
+            kept,
+            vars(&[
+                ("RUST_LOG", "debug"),
+                ("CARGO_TARGET_DIR", "/workspace/target-agent"),
+                ("LC_ALL", "C.UTF-8"),
+            ])
+        );
+        // Flag and code-loading variables are opt-in only, so they are ignored like EDITOR.
+        assert_eq!(dropped, ["NODE_ENV", "CARGO_TERM_TOKEN"]);
+    }
+
+    #[test]
+    fn test_env_forward_secret_names() {
+        for name in [
+            "GITHUB_TOKEN",
+            "npm_config__authToken",
+            "AWS_SECRET_ACCESS_KEY",
+            "SSH_KEY",
+        ] {
+            assert!(env_forward_is_secret(name), "{name}");
+        }
+        for name in ["RUST_LOG", "KEYBOARD", "MONKEY_BUSINESS"] {
+            assert!(!env_forward_is_secret(name), "{name}");
+        }
+    }
+}
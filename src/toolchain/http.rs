@@ -24,6 +24,9 @@ pub(crate) enum Endpoint {
     Exec,
     Notifications,
     Signal,
+    Token,
+    Health,
+    Status,
 }
 
 /// Simple case-insensitive header map (keys lowercased)
@@ -34,6 +37,8 @@ pub(crate) type HeaderMap = HashMap<String, String>;
 pub(crate) struct HttpRequest {
     pub method: Method,
     pub path_lc: String,
+    /// Request target as sent (path and query), as covered by request signatures.
+    pub target: String,
     pub query: Vec<(String, String)>,
     pub headers: HeaderMap,
     pub body: Vec<u8>,
@@ -97,6 +102,11 @@ pub(crate) fn read_http_request<R: Read>(reader: &mut R) -> io::Result<HttpReque
     }
 
     let (method, path_lc, query_pairs) = parse_request_line_and_query(&request_line);
+    let target = request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or("/")
+        .to_string();
     let headers = parse_headers(all_lines.iter().copied().skip(1));
 
     // Support Transfer-Encoding: chunked by de-chunking into body; otherwise honor Content-Length.
@@ -302,6 +312,7 @@ pub(crate) fn read_http_request<R: Read>(reader: &mut R) -> io::Result<HttpReque
     Ok(HttpRequest {
         method,
         path_lc,
+        target,
         query: query_pairs,
         headers,
         body,
@@ -314,6 +325,9 @@ pub(crate) fn classify_endpoint(path_lc: &str) -> Option<Endpoint> {
         "/exec" => Some(Endpoint::Exec),
         "/notify" => Some(Endpoint::Notifications),
         "/signal" => Some(Endpoint::Signal),
+        "/token" => Some(Endpoint::Token),
+        "/health" => Some(Endpoint::Health),
+        "/status" => Some(Endpoint::Status),
         _ => None,
     }
 }
@@ -423,6 +437,12 @@ mod tests {
     fn test_classify_endpoint_notify() {
         assert_eq!(classify_endpoint("/notify"), Some(Endpoint::Notifications));
     }
+
+    #[test]
+    fn test_classify_endpoint_health_status() {
+        assert_eq!(classify_endpoint("/health"), Some(Endpoint::Health));
+        assert_eq!(classify_endpoint("/status"), Some(Endpoint::Status));
+    }
 }
 
 #[cfg(test)]
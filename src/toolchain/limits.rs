@@ -0,0 +1,400 @@
+//! Per-exec runtime, idle-output and output-size limits enforced by the proxy.
+//!
+//! Defaults come from AIFO_TOOLEEXEC_MAX_SECS (or AIFO_TOOLEEXEC_TIMEOUT_SECS; default 300),
+//! AIFO_TOOLEEXEC_IDLE_SECS and AIFO_TOOLEEXEC_MAX_OUTPUT_BYTES (0 disables). Rules in the layered
+//! config override them per tool and subcommand:
+//!
+//! ```toml
+//! [[proxy.limits.cargo]]
+//! subcommands = ["test", "build"]
+//! max_secs = 1800
+//!
+//! [[proxy.limits.npm]]
+//! subcommands = ["view", "info"]
+//! max_secs = 30
+//! idle_secs = 10
+//!
+//! [[proxy.limits.cargo]]
+//! max_output_bytes = 10485760
+//! ```
+//!
+//! For each limit the first matching rule that sets it wins; an empty `subcommands` matches any
+//! invocation. When a limit trips the proxy sends INT to the exec's process group, TERM after a
+//! grace period and KILL after another, appends a marker line to the output and reports the
+//! limit's own exit code: 124 (max runtime), 123 (no output for idle_secs) or 122 (output
+//! truncated at max_output_bytes).
+
+use serde::Deserialize;
+use std::env;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use super::policy::subcommand;
+
+/// Exit code of an exec stopped after max_secs.
+pub const EXIT_MAX_RUNTIME: i32 = 124;
+/// Exit code of an exec stopped after idle_secs without output.
+pub const EXIT_IDLE_TIMEOUT: i32 = 123;
+/// Exit code of an exec stopped once its output reached max_output_bytes.
+pub const EXIT_OUTPUT_LIMIT: i32 = 122;
+
+/// One `[[proxy.limits.TOOL]]` rule; unset limits fall through to later rules and the defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExecLimitRule {
+    /// Subcommands the rule applies to; empty matches any invocation.
+    pub subcommands: Vec<String>,
+    /// Maximum runtime in seconds (0 = unlimited).
+    pub max_secs: Option<u64>,
+    /// Maximum time without output in seconds (0 = unlimited).
+    pub idle_secs: Option<u64>,
+    /// Maximum streamed output in bytes (0 = unlimited).
+    pub max_output_bytes: Option<u64>,
+}
+
+impl ExecLimitRule {
+    fn matches(&self, tool: &str, argv: &[String]) -> bool {
+        self.subcommands.is_empty()
+            || subcommand(tool, argv).is_some_and(|sub| self.subcommands.iter().any(|s| s == sub))
+    }
+}
+
+/// Limits applied to one exec; 0 disables a limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecLimits {
+    pub max_secs: u64,
+    pub idle_secs: u64,
+    pub max_output_bytes: u64,
+}
+
+impl ExecLimits {
+    /// Proxy-wide defaults: MAX_SECS plus AIFO_TOOLEEXEC_IDLE_SECS and
+    /// AIFO_TOOLEEXEC_MAX_OUTPUT_BYTES.
+    pub fn from_env(max_secs: u64) -> Self {
+        let num = |var: &str| {
+            env::var(var)
+                .ok()
+                .and_then(|s| s.trim().parse::<u64>().ok())
+                .unwrap_or(0)
+        };
+        ExecLimits {
+            max_secs,
+            idle_secs: num("AIFO_TOOLEEXEC_IDLE_SECS"),
+            max_output_bytes: num("AIFO_TOOLEEXEC_MAX_OUTPUT_BYTES"),
+        }
+    }
+
+    fn is_unlimited(&self) -> bool {
+        self.max_secs == 0 && self.idle_secs == 0 && self.max_output_bytes == 0
+    }
+}
+
+/// Limits for TOOL's ARGV: DEFAULTS overridden by the first matching RULES that set each limit.
+pub fn resolve_exec_limits(
+    defaults: ExecLimits,
+    rules: &[ExecLimitRule],
+    tool: &str,
+    argv: &[String],
+) -> ExecLimits {
+    let matching: Vec<&ExecLimitRule> = rules.iter().filter(|r| r.matches(tool, argv)).collect();
+    let pick = |get: fn(&ExecLimitRule) -> Option<u64>, default: u64| {
+        matching.iter().find_map(|r| get(r)).unwrap_or(default)
+    };
+    ExecLimits {
+        max_secs: pick(|r| r.max_secs, defaults.max_secs),
+        idle_secs: pick(|r| r.idle_secs, defaults.idle_secs),
+        max_output_bytes: pick(|r| r.max_output_bytes, defaults.max_output_bytes),
+    }
+}
+
+/// Configured limit rules for TOOL (empty when none or no config was loaded).
+pub fn tool_limit_rules(tool: &str) -> &'static [ExecLimitRule] {
+    crate::config_resolved()
+        .and_then(|r| r.config.proxy.limits.get(tool))
+        .map(Vec::as_slice)
+        .unwrap_or(&[])
+}
+
+/// The limit that stopped an exec.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LimitHit {
+    MaxRuntime,
+    Idle,
+    Output,
+}
+
+impl LimitHit {
+    pub(crate) fn exit_code(self) -> i32 {
+        match self {
+            LimitHit::MaxRuntime => EXIT_MAX_RUNTIME,
+            LimitHit::Idle => EXIT_IDLE_TIMEOUT,
+            LimitHit::Output => EXIT_OUTPUT_LIMIT,
+        }
+    }
+
+    pub(crate) fn is_timeout(self) -> bool {
+        self != LimitHit::Output
+    }
+
+    /// Config key of the limit, for logs.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            LimitHit::MaxRuntime => "max_secs",
+            LimitHit::Idle => "idle_secs",
+            LimitHit::Output => "max_output_bytes",
+        }
+    }
+
+    /// Line added to the exec's output.
+    pub(crate) fn marker(self, limits: &ExecLimits) -> String {
+        match self {
+            LimitHit::MaxRuntime => {
+                format!("aifo-coder proxy timeout: exceeded {}s\n", limits.max_secs)
+            }
+            LimitHit::Idle => format!(
+                "aifo-coder proxy timeout: no output for {}s\n",
+                limits.idle_secs
+            ),
+            LimitHit::Output => format!(
+                "\naifo-coder proxy: output truncated after {} bytes\n",
+                limits.max_output_bytes
+            ),
+        }
+    }
+}
+
+/// Progress of one exec, shared by its output readers and its limit watcher.
+pub(crate) struct ExecWatch {
+    started: Instant,
+    last_output_ms: AtomicU64,
+    output_bytes: AtomicU64,
+    hit: Mutex<Option<LimitHit>>,
+    done: AtomicBool,
+}
+
+impl ExecWatch {
+    pub(crate) fn new() -> Self {
+        ExecWatch {
+            started: Instant::now(),
+            last_output_ms: AtomicU64::new(0),
+            output_bytes: AtomicU64::new(0),
+            hit: Mutex::new(None),
+            done: AtomicBool::new(false),
+        }
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    /// Account N bytes of output; returns how many of them fit under MAX (0 = unlimited).
+    pub(crate) fn output(&self, n: usize, max: u64) -> usize {
+        self.last_output_ms
+            .store(self.elapsed_ms(), Ordering::SeqCst);
+        let before = self.output_bytes.fetch_add(n as u64, Ordering::SeqCst);
+        if max == 0 {
+            return n;
+        }
+        max.saturating_sub(before).min(n as u64) as usize
+    }
+
+    /// Account input sent to an upgraded exec: activity for idle_secs, like output.
+    pub(crate) fn input(&self) {
+        self.last_output_ms
+            .store(self.elapsed_ms(), Ordering::SeqCst);
+    }
+
+    /// Record HIT unless another limit tripped first; true when HIT is now the cause.
+    pub(crate) fn trip(&self, hit: LimitHit) -> bool {
+        let mut cur = self.hit.lock().unwrap_or_else(|e| e.into_inner());
+        if cur.is_some() {
+            return false;
+        }
+        *cur = Some(hit);
+        true
+    }
+
+    pub(crate) fn hit(&self) -> Option<LimitHit> {
+        *self.hit.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Mark the exec finished; the watcher stops escalating.
+    pub(crate) fn finish(&self) {
+        self.done.store(true, Ordering::SeqCst);
+    }
+
+    fn is_done(&self) -> bool {
+        self.done.load(Ordering::SeqCst)
+    }
+
+    /// Wait up to D; false when the exec finished meanwhile.
+    fn wait(&self, d: Duration) -> bool {
+        let deadline = Instant::now() + d;
+        while Instant::now() < deadline {
+            if self.is_done() {
+                return false;
+            }
+            std::thread::sleep(WATCH_TICK.min(deadline - Instant::now()));
+        }
+        !self.is_done()
+    }
+}
+
+const WATCH_TICK: Duration = Duration::from_millis(100);
+
+/// Enforce LIMITS on the exec behind WATCH until it finishes.
+///
+/// Trips max_secs and idle_secs itself (max_output_bytes is tripped by the output reader), then
+/// calls SIGNAL with INT, TERM after GRACE and KILL after another GRACE. Returns None when no
+/// limit is set.
+pub(crate) fn spawn_limit_watcher<F>(
+    watch: Arc<ExecWatch>,
+    limits: ExecLimits,
+    grace: Duration,
+    signal: F,
+) -> Option<JoinHandle<()>>
+where
+    F: Fn(&str, LimitHit) + Send + 'static,
+{
+    if limits.is_unlimited() {
+        return None;
+    }
+    Some(std::thread::spawn(move || {
+        let hit = loop {
+            if watch.is_done() {
+                return;
+            }
+            if let Some(hit) = watch.hit() {
+                break hit;
+            }
+            let now = watch.elapsed_ms();
+            let idle = now.saturating_sub(watch.last_output_ms.load(Ordering::SeqCst));
+            if limits.max_secs > 0 && now >= limits.max_secs.saturating_mul(1000) {
+                watch.trip(LimitHit::MaxRuntime);
+            } else if limits.idle_secs > 0 && idle >= limits.idle_secs.saturating_mul(1000) {
+                watch.trip(LimitHit::Idle);
+            } else {
+                std::thread::sleep(WATCH_TICK);
+            }
+        };
+        signal("INT", hit);
+        for sig in ["TERM", "KILL"] {
+            if !watch.wait(grace) {
+                return;
+            }
+            signal(sig, hit);
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(s: &str) -> Vec<String> {
+        s.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_resolve_exec_limits_first_matching_rule_per_limit() {
+        #[derive(Deserialize)]
+        struct Wrap {
+            rule: Vec<ExecLimitRule>,
+        }
+        let rules = toml::from_str::<Wrap>(
+            r#"
+            [[rule]]
+            subcommands = ["test"]
+            max_secs = 1800
+
+            [[rule]]
+            subcommands = ["view", "test"]
+            max_secs = 30
+            idle_secs = 10
+
+            [[rule]]
+            max_output_bytes = 1024
+            "#,
+        )
+        .expect("rules")
+        .rule;
+        let defaults = ExecLimits {
+            max_secs: 300,
+            idle_secs: 0,
+            max_output_bytes: 0,
+        };
+        assert_eq!(
+            resolve_exec_limits(defaults, &rules, "npm", &argv("--quiet test -- view")),
+            ExecLimits {
+                max_secs: 1800,
+                idle_secs: 10,
+                max_output_bytes: 1024,
+            }
+        );
+        assert_eq!(
+            resolve_exec_limits(defaults, &rules, "npm", &argv("view left-pad")),
+            ExecLimits {
+                max_secs: 30,
+                idle_secs: 10,
+                max_output_bytes: 1024,
+            }
+        );
+        assert_eq!(
+            resolve_exec_limits(defaults, &[], "npm", &argv("build")),
+            defaults
+        );
+    }
+
+    #[test]
+    fn test_limit_watcher_trips_idle_and_escalates_until_finished() {
+        let watch = Arc::new(ExecWatch::new());
+        assert_eq!(watch.output(10, 25), 10);
+        assert_eq!(watch.output(10, 25), 10);
+        assert_eq!(watch.output(10, 25), 5);
+        assert_eq!(watch.output(10, 25), 0);
+
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let sent_cl = sent.clone();
+        let limits = ExecLimits {
+            max_secs: 60,
+            idle_secs: 1,
+            max_output_bytes: 0,
+        };
+        let handle = spawn_limit_watcher(
+            watch.clone(),
+            limits,
+            Duration::from_millis(300),
+            move |sig, hit| sent_cl.lock().unwrap().push((sig.to_string(), hit)),
+        )
+        .expect("watcher");
+        // Finish the exec between TERM and KILL.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while sent.lock().unwrap().len() < 2 && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        watch.finish();
+        handle.join().unwrap();
+
+        assert_eq!(watch.hit(), Some(LimitHit::Idle));
+        assert!(
+            !watch.trip(LimitHit::MaxRuntime),
+            "first limit stays the cause"
+        );
+        assert_eq!(
+            *sent.lock().unwrap(),
+            [
+                ("INT".to_string(), LimitHit::Idle),
+                ("TERM".to_string(), LimitHit::Idle)
+            ]
+        );
+        assert!(spawn_limit_watcher(
+            watch,
+            ExecLimits::default(),
+            Duration::from_secs(5),
+            |_, _| {}
+        )
+        .is_none());
+    }
+}
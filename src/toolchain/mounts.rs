@@ -5,9 +5,8 @@ Mounts and ownership initialization for sidecars.
 - init_rust_named_volumes_if_needed: one-shot chown for rust named volumes (registry/git)
 */
 use std::path::Path;
-use std::process::{Command, Stdio};
 
-use crate::{shell_escape, shell_join, ShellScript};
+use crate::{shell_escape, shell_join, ContainerRuntime, RuntimeIo, ShellScript};
 
 /// Push a volume mount (-v host:container) into docker args.
 pub(crate) fn push_mount(args: &mut Vec<String>, spec: &str) {
@@ -16,7 +15,7 @@ pub(crate) fn push_mount(args: &mut Vec<String>, spec: &str) {
 }
 
 fn init_named_volume_with_stamp(
-    runtime: &Path,
+    rt: &dyn ContainerRuntime,
     image: &str,
     mount_spec: &str,
     dir_in_container: &str,
@@ -71,21 +70,19 @@ fn init_named_volume_with_stamp(
         );
     }
 
-    let mut cmd = Command::new(runtime);
-    for a in &args[1..] {
-        cmd.arg(a);
-    }
-    if !verbose {
-        cmd.stdout(Stdio::null()).stderr(Stdio::null());
-    }
-    let _ = cmd.status();
+    let io = if verbose {
+        RuntimeIo::Inherit
+    } else {
+        RuntimeIo::Quiet
+    };
+    let _ = rt.invoke(&args[1..], io);
 }
 
 /// Best-effort ownership initialization for named cargo volumes used by rust sidecar.
 /// Runs a short helper container as root that ensures target dir exists, chowns to uid:gid,
 /// and drops a stamp file to avoid repeated work. Uses the same image as the sidecar to avoid extra pulls.
 fn init_rust_named_volume(
-    runtime: &Path,
+    rt: &dyn ContainerRuntime,
     image: &str,
     subdir: &str,
     uid: u32,
@@ -94,12 +91,12 @@ fn init_rust_named_volume(
 ) {
     let mount = format!("aifo-cargo-{subdir}:/home/coder/.cargo/{subdir}");
     let dir = format!("/home/coder/.cargo/{subdir}");
-    init_named_volume_with_stamp(runtime, image, &mount, &dir, uid, gid, verbose);
+    init_named_volume_with_stamp(rt, image, &mount, &dir, uid, gid, verbose);
 }
 
 /// Inspect run-args and initialize named rust cargo volumes when they are selected (registry/git).
 pub(crate) fn init_rust_named_volumes_if_needed(
-    runtime: &Path,
+    rt: &dyn ContainerRuntime,
     image: &str,
     run_args: &[String],
     uidgid: Option<(u32, u32)>,
@@ -132,19 +129,25 @@ pub(crate) fn init_rust_named_volumes_if_needed(
     }
     let (uid, gid) = uidgid.unwrap_or((0u32, 0u32));
     if need_registry {
-        init_rust_named_volume(runtime, image, "registry", uid, gid, verbose);
+        init_rust_named_volume(rt, image, "registry", uid, gid, verbose);
     }
     if need_git {
-        init_rust_named_volume(runtime, image, "git", uid, gid, verbose);
+        init_rust_named_volume(rt, image, "git", uid, gid, verbose);
     }
 }
 
 ///// Best-effort ownership initialization for the consolidated Node cache volume.
 ///// Runs a short helper container that ensures /home/coder/.cache exists, chowns to uid:gid,
 ///// and stamps the directory to avoid repeated work.
-fn init_node_cache_volume(runtime: &Path, image: &str, uid: u32, gid: u32, verbose: bool) {
+fn init_node_cache_volume(
+    rt: &dyn ContainerRuntime,
+    image: &str,
+    uid: u32,
+    gid: u32,
+    verbose: bool,
+) {
     init_named_volume_with_stamp(
-        runtime,
+        rt,
         image,
         "aifo-node-cache:/home/coder/.cache",
         "/home/coder/.cache",
@@ -258,7 +261,7 @@ pub(crate) fn ensure_pnpm_store_host_writable(
 ///// Runs a short helper container that ensures /home/coder/.cache exists, chowns to uid:gid,
 ///// and stamps the directory to avoid repeated work.
 pub(crate) fn init_node_cache_volume_if_needed(
-    runtime: &Path,
+    rt: &dyn ContainerRuntime,
     image: &str,
     run_args: &[String],
     uidgid: Option<(u32, u32)>,
@@ -281,15 +284,21 @@ pub(crate) fn init_node_cache_volume_if_needed(
         return;
     }
     let (uid, gid) = uidgid.unwrap_or((0u32, 0u32));
-    init_node_cache_volume(runtime, image, uid, gid, verbose);
+    init_node_cache_volume(rt, image, uid, gid, verbose);
 }
 
 /// Best-effort ownership initialization for the node_modules overlay volume.
 /// Ensures /workspace/node_modules exists, is owned by uid:gid, and is stamped
 /// to avoid repeated work.
-fn init_node_modules_volume(runtime: &Path, image: &str, uid: u32, gid: u32, verbose: bool) {
+fn init_node_modules_volume(
+    rt: &dyn ContainerRuntime,
+    image: &str,
+    uid: u32,
+    gid: u32,
+    verbose: bool,
+) {
     init_named_volume_with_stamp(
-        runtime,
+        rt,
         image,
         "aifo-node-modules:/workspace/node_modules",
         "/workspace/node_modules",
@@ -301,7 +310,7 @@ fn init_node_modules_volume(runtime: &Path, image: &str, uid: u32, gid: u32, ver
 
 /// Inspect run-args and initialize the node_modules overlay volume when selected.
 pub(crate) fn init_node_modules_volume_if_needed(
-    runtime: &Path,
+    rt: &dyn ContainerRuntime,
     image: &str,
     run_args: &[String],
     uidgid: Option<(u32, u32)>,
@@ -324,5 +333,5 @@ pub(crate) fn init_node_modules_volume_if_needed(
         return;
     }
     let (uid, gid) = uidgid.unwrap_or((0u32, 0u32));
-    init_node_modules_volume(runtime, image, uid, gid, verbose);
+    init_node_modules_volume(rt, image, uid, gid, verbose);
 }
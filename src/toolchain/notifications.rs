@@ -285,6 +285,7 @@ fn clamp_max_args() -> usize {
 fn run_with_timeout(
     exec_abs: &PathBuf,
     args: &[String],
+    extra_env: &[(&str, String)],
     timeout_secs: u64,
 ) -> Result<(i32, Vec<u8>), NotifyError> {
     let mut cmd = Command::new(exec_abs);
@@ -327,6 +328,9 @@ fn run_with_timeout(
             }
         }
     }
+    for (k, v) in extra_env {
+        cmd.env(k, v);
+    }
     cmd.stdin(Stdio::null())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
@@ -606,5 +610,30 @@ pub(crate) fn notifications_handle_request(
     };
 
     // Execute with timeout; capture stdout+stderr
-    run_with_timeout(&cfg.exec_abs, &final_args, timeout_secs)
+    run_with_timeout(&cfg.exec_abs, &final_args, &[], timeout_secs)
+}
+
+/// Ask the configured notifications command for an approval decision.
+///
+/// PROMPT becomes the trailing `{args}` argument when the command has the placeholder; ENV is
+/// always passed. Returns (exit_code, output_bytes). The executable must live in a safe
+/// directory, as for notifications; the basename allowlist does not apply because the proxy,
+/// not the agent, picks the arguments.
+pub(crate) fn notifications_run_approval(
+    prompt: &str,
+    env: &[(&str, String)],
+    timeout_secs: u64,
+) -> Result<(i32, Vec<u8>), NotifyError> {
+    let cfg = parse_notif_cfg()?;
+    if !notifications_exec_in_safe_dir(&cfg.exec_abs) {
+        return Err(NotifyError::Policy(format!(
+            "notifications executable '{}' is not in a safe directory",
+            cfg.exec_abs.display()
+        )));
+    }
+    let mut args = cfg.fixed_args.clone();
+    if cfg.has_trailing_args_placeholder {
+        args.push(prompt.replace(['\r', '\n'], " "));
+    }
+    run_with_timeout(&cfg.exec_abs, &args, env, timeout_secs)
 }
@@ -0,0 +1,433 @@
+//! Declarative per-tool argument policy applied by the proxy before `docker exec`.
+//!
+//! Rules live in the layered config as `[[proxy.policy.TOOL]]` tables, keyed by the tool name the
+//! shim sends (`pip` and `pip3` are separate keys) and evaluated in order:
+//!
+//! ```toml
+//! [[proxy.policy.cargo]]
+//! subcommands = ["publish", "login", "yank"]
+//! deny = true
+//! message = "publishing from agents is disabled"
+//!
+//! [[proxy.policy.cargo]]
+//! subcommands = ["build", "test"]
+//! require = ["--locked"]
+//!
+//! [[proxy.policy.go]]
+//! subcommands = ["get"]
+//! unless_args = ["github.com/acme/*", "golang.org/x/*"]
+//! deny = true
+//! ```
+//!
+//! A rule matches when all of its selectors do: `subcommands` (the first positional argument,
+//! skipping flags and the values of the tool's global flags listed in VALUE_FLAGS), `args` (some
+//! argument matches a pattern) and `unless_args` (some positional argument after the subcommand
+//! matches none of the patterns). Patterns use `*` as a wildcard. Arguments after `--` belong to
+//! the tool's own child and are never inspected or changed.
+//!
+//! Deny and approve rules fail closed: they also match when a listed subcommand appears as any
+//! positional argument, so a global flag missing from VALUE_FLAGS (`npm --foo x publish`) cannot
+//! hide the real subcommand behind its value.
+//!
+//! A matching rule then rewrites exact arguments (`rewrite`), appends missing flags before `--`
+//! (`require`) or rejects the invocation (`deny`); the first denial wins. Rules with
+//! `approve = true` hold the final invocation until the host user approves it (see approval.rs).
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// One `[[proxy.policy.TOOL]]` rule.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolPolicyRule {
+    /// Subcommands the rule applies to; empty matches any invocation.
+    pub subcommands: Vec<String>,
+    /// Applies only when some argument matches one of these patterns.
+    pub args: Vec<String>,
+    /// Applies only when a positional argument after the subcommand matches none of these.
+    pub unless_args: Vec<String>,
+    /// Reject matching invocations with 403 Forbidden.
+    pub deny: bool,
+    /// Hold matching invocations until the host user approves them.
+    pub approve: bool,
+    /// Flags added when missing (`--locked`; `--flag=value` counts as present).
+    pub require: Vec<String>,
+    /// Exact arguments replaced by another argument (e.g. `install = "ci"` for npm).
+    pub rewrite: BTreeMap<String, String>,
+    /// Reason shown to the agent when the rule denies.
+    pub message: Option<String>,
+}
+
+/// Why an invocation was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyDenial {
+    /// Rule label, e.g. `proxy.policy.cargo[0]`.
+    pub rule: String,
+    pub reason: String,
+}
+
+/// `*` wildcard match over the whole string.
+pub(crate) fn pattern_matches(pattern: &str, s: &str) -> bool {
+    let Some((first, rest)) = pattern.split_once('*') else {
+        return pattern == s;
+    };
+    let Some(mut tail) = s.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let last = parts.pop().unwrap_or("");
+    for part in parts {
+        match tail.find(part) {
+            Some(i) => tail = &tail[i + part.len()..],
+            None => return false,
+        }
+    }
+    tail.len() >= last.len() && tail.ends_with(last)
+}
+
+/// Global flags taking a separate value, per tool (the `--flag=value` form needs no entry).
+const VALUE_FLAGS: &[(&[&str], &[&str])] = &[
+    (
+        &["cargo"],
+        &[
+            "-C",
+            "-Z",
+            "--color",
+            "--config",
+            "--explain",
+            "--manifest-path",
+        ],
+    ),
+    (
+        &["npm", "npx", "pnpm", "yarn"],
+        &[
+            "-C",
+            "-w",
+            "--access",
+            "--cache",
+            "--cwd",
+            "--dir",
+            "--filter",
+            "--globalconfig",
+            "--loglevel",
+            "--otp",
+            "--prefix",
+            "--registry",
+            "--scope",
+            "--tag",
+            "--userconfig",
+            "--workspace",
+        ],
+    ),
+    (
+        &["pip", "pip3", "uv"],
+        &[
+            "-i",
+            "--cache-dir",
+            "--cert",
+            "--client-cert",
+            "--config-file",
+            "--directory",
+            "--exists-action",
+            "--extra-index-url",
+            "--index-url",
+            "--log",
+            "--project",
+            "--proxy",
+            "--python",
+            "--retries",
+            "--timeout",
+            "--trusted-host",
+        ],
+    ),
+    (&["go"], &["-C"]),
+];
+
+fn is_flag(arg: &str) -> bool {
+    arg.starts_with('-') || arg.starts_with('+')
+}
+
+fn takes_value(tool: &str, flag: &str) -> bool {
+    VALUE_FLAGS
+        .iter()
+        .any(|(tools, flags)| tools.contains(&tool) && flags.contains(&flag))
+}
+
+/// Arguments up to (not including) `--`.
+fn inspected(argv: &[String]) -> &[String] {
+    let end = argv.iter().position(|a| a == "--").unwrap_or(argv.len());
+    &argv[..end]
+}
+
+/// Index of the subcommand in ARGS (already cut at `--`): the first positional argument, skipping
+/// the values of TOOL's global flags.
+fn subcommand_index(tool: &str, args: &[String]) -> Option<usize> {
+    let mut i = 0;
+    while i < args.len() {
+        if !is_flag(&args[i]) {
+            return Some(i);
+        }
+        i += if takes_value(tool, &args[i]) { 2 } else { 1 };
+    }
+    None
+}
+
+/// The subcommand of TOOL's ARGV (see subcommand_index).
+pub(crate) fn subcommand<'a>(tool: &str, argv: &'a [String]) -> Option<&'a str> {
+    let args = inspected(argv);
+    subcommand_index(tool, args).map(|i| args[i].as_str())
+}
+
+impl ToolPolicyRule {
+    fn matches(&self, tool: &str, argv: &[String]) -> bool {
+        let args = inspected(argv);
+        let sub_idx = subcommand_index(tool, args);
+        if (self.deny || self.approve) && !self.subcommands.is_empty() {
+            // Fail closed: try every positional argument as the subcommand.
+            return (0..args.len())
+                .filter(|&i| !is_flag(&args[i]))
+                .any(|i| self.matches_at(args, Some(i)));
+        }
+        self.matches_at(args, sub_idx)
+    }
+
+    /// Whether the selectors match ARGS with the subcommand at SUB_IDX.
+    fn matches_at(&self, args: &[String], sub_idx: Option<usize>) -> bool {
+        if !self.subcommands.is_empty() {
+            let Some(sub) = sub_idx.map(|i| args[i].as_str()) else {
+                return false;
+            };
+            if !self.subcommands.iter().any(|s| s == sub) {
+                return false;
+            }
+        }
+        if !self.args.is_empty()
+            && !args
+                .iter()
+                .any(|a| self.args.iter().any(|p| pattern_matches(p, a)))
+        {
+            return false;
+        }
+        if !self.unless_args.is_empty() {
+            let outside = args
+                .iter()
+                .skip(sub_idx.map(|i| i + 1).unwrap_or(args.len()))
+                .filter(|a| !is_flag(a))
+                .any(|a| !self.unless_args.iter().any(|p| pattern_matches(p, a)));
+            if !outside {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn apply(&self, argv: &mut Vec<String>) {
+        let end = argv.iter().position(|a| a == "--").unwrap_or(argv.len());
+        for a in argv[..end].iter_mut() {
+            if let Some(to) = self.rewrite.get(a.as_str()) {
+                *a = to.clone();
+            }
+        }
+        let mut at = end;
+        for flag in &self.require {
+            let present = argv[..at].iter().any(|a| {
+                a == flag
+                    || a.strip_prefix(flag.as_str())
+                        .is_some_and(|r| r.starts_with('='))
+            });
+            if !present {
+                argv.insert(at, flag.clone());
+                at += 1;
+            }
+        }
+    }
+}
+
+/// Apply RULES for TOOL to ARGV in order; returns the (possibly rewritten) argv or the denial.
+pub fn apply_tool_policy(
+    tool: &str,
+    rules: &[ToolPolicyRule],
+    argv: &[String],
+) -> Result<Vec<String>, PolicyDenial> {
+    let mut out = argv.to_vec();
+    for (i, rule) in rules.iter().enumerate() {
+        if !rule.matches(tool, &out) {
+            continue;
+        }
+        if rule.deny {
+            let reason = rule
+                .message
+                .clone()
+                .filter(|m| !m.trim().is_empty())
+                .unwrap_or_else(|| "denied by policy".to_string());
+            return Err(PolicyDenial {
+                rule: format!("proxy.policy.{tool}[{i}]"),
+                reason,
+            });
+        }
+        rule.apply(&mut out);
+    }
+    Ok(out)
+}
+
+/// Label of the first `approve` rule matching the (already rewritten) ARGV, if any.
+pub fn policy_approval_rule(
+    tool: &str,
+    rules: &[ToolPolicyRule],
+    argv: &[String],
+) -> Option<String> {
+    rules
+        .iter()
+        .position(|r| r.approve && r.matches(tool, argv))
+        .map(|i| format!("proxy.policy.{tool}[{i}]"))
+}
+
+/// Configured rules for TOOL (empty when none or no config was loaded).
+pub fn tool_policy_rules(tool: &str) -> &'static [ToolPolicyRule] {
+    crate::config_resolved()
+        .and_then(|r| r.config.proxy.policy.get(tool))
+        .map(Vec::as_slice)
+        .unwrap_or(&[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(s: &str) -> Vec<String> {
+        s.split_whitespace().map(str::to_string).collect()
+    }
+
+    fn rules(toml_src: &str) -> Vec<ToolPolicyRule> {
+        #[derive(Deserialize)]
+        struct Wrap {
+            rule: Vec<ToolPolicyRule>,
+        }
+        toml::from_str::<Wrap>(toml_src).expect("rules").rule
+    }
+
+    #[test]
+    fn test_policy_denies_subcommands_and_argument_patterns() {
+        let cargo = rules(
+            r#"
+            [[rule]]
+            subcommands = ["publish"]
+            deny = true
+            message = "no publishing"
+            "#,
+        );
+        let err =
+            apply_tool_policy("cargo", &cargo, &argv("+nightly publish --dry-run")).unwrap_err();
+        assert_eq!(err.rule, "proxy.policy.cargo[0]");
+        assert_eq!(err.reason, "no publishing");
+        // Nothing after `--` counts.
+        assert!(apply_tool_policy("cargo", &cargo, &argv("run -- publish")).is_ok());
+        // Global flags with a separate value cannot hide the subcommand, known or not.
+        for call in [
+            "--color always publish",
+            "--manifest-path x/Cargo.toml publish",
+            "-Z unstable-options --config k=v publish --dry-run",
+            "--unknown-flag value publish",
+        ] {
+            assert!(
+                apply_tool_policy("cargo", &cargo, &argv(call)).is_err(),
+                "cargo {call}"
+            );
+        }
+        let npm = rules(
+            r#"
+            [[rule]]
+            subcommands = ["publish"]
+            deny = true
+            "#,
+        );
+        assert!(apply_tool_policy("npm", &npm, &argv("--registry https://evil publish")).is_err());
+        assert!(apply_tool_policy("npm", &npm, &argv("--registry https://x install")).is_ok());
+
+        let pip = rules(
+            r#"
+            [[rule]]
+            subcommands = ["install"]
+            args = ["--index-url*", "-i"]
+            deny = true
+            "#,
+        );
+        let err = apply_tool_policy("pip", &pip, &argv("install --index-url=https://x/ pkg"))
+            .unwrap_err();
+        assert_eq!(err.reason, "denied by policy");
+        assert!(apply_tool_policy("pip", &pip, &argv("install requests")).is_ok());
+        assert!(apply_tool_policy("pip", &pip, &argv("download -i https://x/ pkg")).is_ok());
+
+        let go = rules(
+            r#"
+            [[rule]]
+            subcommands = ["get"]
+            unless_args = ["github.com/acme/*", "golang.org/x/*@*"]
+            deny = true
+            "#,
+        );
+        assert!(apply_tool_policy("go", &go, &argv("get -u github.com/acme/lib")).is_ok());
+        assert!(apply_tool_policy("go", &go, &argv("get golang.org/x/net@v0.1.0")).is_ok());
+        assert!(apply_tool_policy("go", &go, &argv("get github.com/evil/lib")).is_err());
+        assert!(apply_tool_policy("go", &go, &argv("-C get get github.com/evil/lib")).is_err());
+        assert!(apply_tool_policy("go", &go, &argv("-x get github.com/evil/lib")).is_err());
+        assert!(apply_tool_policy("go", &go, &argv("-C sub get github.com/acme/lib")).is_ok());
+    }
+
+    #[test]
+    fn test_policy_requires_flags_and_rewrites_before_double_dash() {
+        let cargo = rules(
+            r#"
+            [[rule]]
+            subcommands = ["build", "test"]
+            require = ["--locked", "--offline"]
+
+            [[rule]]
+            rewrite = { "-Zunstable-options" = "--verbose" }
+            "#,
+        );
+        assert_eq!(
+            apply_tool_policy("cargo", &cargo, &argv("test --offline=true -- --locked")).unwrap(),
+            argv("test --offline=true --locked -- --locked")
+        );
+        assert_eq!(
+            apply_tool_policy("cargo", &cargo, &argv("build -Zunstable-options")).unwrap(),
+            argv("build --verbose --locked --offline")
+        );
+        assert_eq!(
+            apply_tool_policy("cargo", &cargo, &argv("fmt")).unwrap(),
+            argv("fmt")
+        );
+        assert_eq!(
+            apply_tool_policy("cargo", &cargo, &argv("--color always test")).unwrap(),
+            argv("--color always test --locked --offline")
+        );
+        assert_eq!(
+            subcommand("cargo", &argv("-C dir +nightly build")),
+            Some("build")
+        );
+        assert_eq!(subcommand("cargo", &argv("--color")), None);
+
+        let npm = rules(
+            r#"
+            [[rule]]
+            rewrite = { "i" = "install" }
+
+            [[rule]]
+            subcommands = ["install", "publish"]
+            approve = true
+            "#,
+        );
+        let checked = apply_tool_policy("npm", &npm, &argv("i left-pad")).unwrap();
+        assert_eq!(
+            policy_approval_rule("npm", &npm, &checked).as_deref(),
+            Some("proxy.policy.npm[1]")
+        );
+        assert_eq!(policy_approval_rule("npm", &npm, &argv("test")), None);
+
+        assert!(pattern_matches("a*b*c", "axxbyyc"));
+        assert!(!pattern_matches("a*b*c", "axxbyy"));
+        assert!(pattern_matches("*", ""));
+        assert!(!pattern_matches("ab*ba", "aba"));
+    }
+}
@@ -0,0 +1,483 @@
+/*!
+Bounded worker pool and listener helpers for the toolexec proxy, also serving the egress proxy
+and the LLM credential gateway.
+
+- WorkerPool runs at most `max` connections at once. Worker threads are spawned on demand up to
+  that bound and reused afterwards (a job that panics is caught; its worker keeps serving). A
+  reservation beyond the bound, or one no worker thread could be started for, is refused so the
+  accept loop can answer 503 instead of queueing or dropping the connection.
+- ProxyListener/ProxyStream abstract TCP, TLS and unix sockets for the shared accept loop
+  (serve_pooled). On unix the loop blocks in poll(2) until a connection is pending, waking at most
+  every ACCEPT_WAKE_INTERVAL to notice shutdown; failed accepts (EMFILE) back off instead of
+  spinning. Rejections are drained and answered on short-lived threads (at most
+  MAX_REJECT_THREADS at once, extra connections are closed), so a client that trickles its
+  request or stalls a TLS handshake cannot hold up the accept loop.
+- append_log_line appends to the per-session request logs, created owner-only (0600).
+*/
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(target_os = "linux")]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Longest the accept loop sleeps before re-checking the running flag.
+pub(crate) const ACCEPT_WAKE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Pause after an accept error other than WouldBlock; poll(2) reports the listener readable
+/// again at once (e.g. on EMFILE), so waiting on it would spin.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// How long a rejected connection may pause between request bytes before the 503 is written.
+const DRAIN_WAIT: Duration = Duration::from_millis(20);
+/// How long draining a rejected connection may take in total.
+const DRAIN_DEADLINE: Duration = Duration::from_millis(100);
+const DRAIN_MAX_BYTES: usize = 64 * 1024;
+/// Rejections answered at once; beyond this a refused connection is closed without a response.
+const MAX_REJECT_THREADS: usize = 16;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+struct PoolShared {
+    max: usize,
+    active: AtomicUsize,
+    workers: AtomicUsize,
+    jobs: Mutex<mpsc::Receiver<Job>>,
+}
+
+/// A reserved worker; releases its place in the pool on drop.
+pub(crate) struct PoolSlot {
+    shared: Arc<PoolShared>,
+}
+
+impl Drop for PoolSlot {
+    fn drop(&mut self) {
+        self.shared.active.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Fixed-capacity pool of connection workers.
+pub(crate) struct WorkerPool {
+    shared: Arc<PoolShared>,
+    tx: mpsc::Sender<Job>,
+    name: String,
+}
+
+impl WorkerPool {
+    pub(crate) fn new(max: usize, name: &str) -> Self {
+        let (tx, rx) = mpsc::channel::<Job>();
+        WorkerPool {
+            shared: Arc::new(PoolShared {
+                max: max.max(1),
+                active: AtomicUsize::new(0),
+                workers: AtomicUsize::new(0),
+                jobs: Mutex::new(rx),
+            }),
+            tx,
+            name: name.to_string(),
+        }
+    }
+
+    /// Connections currently being handled.
+    pub(crate) fn active(&self) -> usize {
+        self.shared.active.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.shared.max
+    }
+
+    /// Reserve a worker for one connection; None when all `max` workers are busy.
+    pub(crate) fn try_reserve(&self) -> Option<PoolSlot> {
+        let max = self.shared.max;
+        let prev = self
+            .shared
+            .active
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < max).then_some(n + 1)
+            })
+            .ok()?;
+        let slot = PoolSlot {
+            shared: self.shared.clone(),
+        };
+        // Workers never exit before shutdown (jobs that panic are caught), so one per reserved
+        // slot guarantees a free worker. Without one the connection would wait forever.
+        if self.shared.workers.load(Ordering::SeqCst) <= prev && !self.spawn_worker() {
+            return None;
+        }
+        Some(slot)
+    }
+
+    /// Run JOB on a worker; SLOT is released when the job returns (or panics).
+    pub(crate) fn execute<F>(&self, slot: PoolSlot, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let _ = self.tx.send(Box::new(move || {
+            let _slot = slot;
+            job();
+        }));
+    }
+
+    /// Start one more worker thread; false when the thread could not be created.
+    fn spawn_worker(&self) -> bool {
+        let index = self.shared.workers.fetch_add(1, Ordering::SeqCst);
+        let shared = self.shared.clone();
+        let spawned = std::thread::Builder::new()
+            .name(format!("{}-{}", self.name, index))
+            .spawn(move || loop {
+                let job = {
+                    let rx = shared.jobs.lock().unwrap_or_else(|e| e.into_inner());
+                    rx.recv()
+                };
+                match job {
+                    // A panicking job must not take the worker (and its place) with it.
+                    Ok(job) => {
+                        let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(job));
+                    }
+                    // Sender dropped: the pool is shutting down.
+                    Err(_) => break,
+                }
+            });
+        if spawned.is_err() {
+            self.shared.workers.fetch_sub(1, Ordering::SeqCst);
+            return false;
+        }
+        true
+    }
+}
+
+/// Connection accepted by the proxy.
+pub(crate) trait ProxyStream: Read + Write + Send + 'static {
+    /// Switch to blocking mode with the given read timeout and write deadline.
+    fn set_deadlines(&self, read: Option<Duration>, write: Option<Duration>);
+
+    /// Second handle to the same connection, for relaying both directions at once.
+    fn try_clone_stream(&self) -> io::Result<Self>
+    where
+        Self: Sized;
+
+    /// Consume request bytes the client already sent (best effort, bounded), so that closing
+    /// the connection after a rejection does not reset it before the response is read.
+    fn drain_pending(&mut self) {
+        let deadline = Instant::now() + DRAIN_DEADLINE;
+        let mut buf = [0u8; 8192];
+        let mut total = 0usize;
+        while total < DRAIN_MAX_BYTES {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            self.set_deadlines(Some(left.min(DRAIN_WAIT)), Some(Duration::from_secs(1)));
+            match self.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => total += n,
+            }
+        }
+    }
+}
+
+impl ProxyStream for TcpStream {
+    fn set_deadlines(&self, read: Option<Duration>, write: Option<Duration>) {
+        let _ = self.set_nonblocking(false);
+        let _ = self.set_read_timeout(read);
+        let _ = self.set_write_timeout(write);
+    }
+
+    fn try_clone_stream(&self) -> io::Result<Self> {
+        self.try_clone()
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ProxyStream for UnixStream {
+    fn set_deadlines(&self, read: Option<Duration>, write: Option<Duration>) {
+        let _ = self.set_nonblocking(false);
+        let _ = self.set_read_timeout(read);
+        let _ = self.set_write_timeout(write);
+    }
+
+    fn try_clone_stream(&self) -> io::Result<Self> {
+        self.try_clone()
+    }
+}
+
+/// Non-blocking listener the accept loop waits on.
+pub(crate) trait ProxyListener: Send + 'static {
+    type Stream: ProxyStream;
+
+    fn accept_stream(&self) -> io::Result<Self::Stream>;
+
+    /// Block until a connection may be pending or TIMEOUT elapses.
+    fn wait_pending(&self, timeout: Duration);
+}
+
+#[cfg(unix)]
+fn poll_readable(fd: std::os::fd::BorrowedFd<'_>, timeout: Duration) {
+    use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+    let ms = u16::try_from(timeout.as_millis()).unwrap_or(u16::MAX);
+    let mut fds = [PollFd::new(fd, PollFlags::POLLIN)];
+    let _ = poll(&mut fds, PollTimeout::from(ms));
+}
+
+impl ProxyListener for TcpListener {
+    type Stream = TcpStream;
+
+    fn accept_stream(&self) -> io::Result<TcpStream> {
+        self.accept().map(|(s, _)| s)
+    }
+
+    fn wait_pending(&self, timeout: Duration) {
+        #[cfg(unix)]
+        {
+            use std::os::fd::AsFd;
+            poll_readable(self.as_fd(), timeout);
+        }
+        #[cfg(not(unix))]
+        {
+            std::thread::sleep(timeout.min(Duration::from_millis(10)));
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ProxyListener for UnixListener {
+    type Stream = UnixStream;
+
+    fn accept_stream(&self) -> io::Result<UnixStream> {
+        self.accept().map(|(s, _)| s)
+    }
+
+    fn wait_pending(&self, timeout: Duration) {
+        use std::os::fd::AsFd;
+        poll_readable(self.as_fd(), timeout);
+    }
+}
+
+/// Accept connections from LISTENER until RUNNING is cleared, running each on a POOL worker.
+///
+/// JOB turns an accepted connection into the work to run; when every worker is busy the request
+/// is drained and REJECT answers it (503) on a short-lived thread instead. LABEL names the server
+/// in verbose messages.
+pub(crate) fn serve_pooled<L, J>(
+    listener: &L,
+    pool: &WorkerPool,
+    running: &AtomicBool,
+    label: &str,
+    verbose: bool,
+    reject: fn(&mut L::Stream),
+    mut job: impl FnMut(L::Stream) -> J,
+) where
+    L: ProxyListener,
+    J: FnOnce() + Send + 'static,
+{
+    let rejecting = Arc::new(AtomicUsize::new(0));
+    while running.load(Ordering::SeqCst) {
+        let stream = match listener.accept_stream() {
+            Ok(s) => s,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                listener.wait_pending(ACCEPT_WAKE_INTERVAL);
+                continue;
+            }
+            Err(e) => {
+                if verbose {
+                    eprintln!("aifo-coder: {label} accept error: {e}");
+                }
+                std::thread::sleep(ACCEPT_ERROR_BACKOFF);
+                continue;
+            }
+        };
+        let Some(slot) = pool.try_reserve() else {
+            if verbose {
+                eprintln!(
+                    "aifo-coder: {label} busy; rejecting connection (active {} of max {})",
+                    pool.active(),
+                    pool.capacity()
+                );
+            }
+            spawn_reject(&rejecting, stream, reject);
+            continue;
+        };
+        pool.execute(slot, job(stream));
+    }
+}
+
+/// Drain and answer a refused connection off the accept thread; dropping STREAM closes it when
+/// MAX_REJECT_THREADS rejections are already in progress.
+fn spawn_reject<S: ProxyStream>(rejecting: &Arc<AtomicUsize>, stream: S, reject: fn(&mut S)) {
+    if rejecting
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+            (n < MAX_REJECT_THREADS).then_some(n + 1)
+        })
+        .is_err()
+    {
+        return;
+    }
+    let counter = rejecting.clone();
+    let spawned = std::thread::Builder::new()
+        .name("aifo-reject".to_string())
+        .spawn(move || {
+            let mut s = stream;
+            s.drain_pending();
+            reject(&mut s);
+            counter.fetch_sub(1, Ordering::SeqCst);
+        });
+    if spawned.is_err() {
+        rejecting.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Append LINE to the log at PATH, creating it readable by the owner only.
+pub(crate) fn append_log_line(path: &Path, line: &str) -> io::Result<()> {
+    let mut opts = fs::OpenOptions::new();
+    opts.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    opts.open(path)?.write_all(line.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worker_pool_refuses_beyond_capacity_and_reuses_workers() {
+        let pool = WorkerPool::new(2, "test-pool");
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let release_rx = Arc::new(Mutex::new(release_rx));
+        for _ in 0..2 {
+            let rx = release_rx.clone();
+            let slot = pool.try_reserve().expect("free worker");
+            pool.execute(slot, move || {
+                let _ = rx.lock().unwrap().recv();
+            });
+        }
+        assert_eq!(pool.active(), 2);
+        assert!(
+            pool.try_reserve().is_none(),
+            "third connection must be refused"
+        );
+
+        release_tx.send(()).unwrap();
+        release_tx.send(()).unwrap();
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while pool.active() > 0 && std::time::Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(pool.active(), 0);
+
+        let (done_tx, done_rx) = mpsc::channel::<()>();
+        let slot = pool.try_reserve().expect("free worker");
+        pool.execute(slot, move || {
+            let _ = done_tx.send(());
+        });
+        done_rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("job ran on a reused worker");
+        assert_eq!(pool.shared.workers.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_serve_pooled_trickling_rejection_does_not_block_accepts() {
+        use std::net::Shutdown;
+        use std::time::Instant;
+
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let running = Arc::new(AtomicBool::new(true));
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let release_rx = Arc::new(Mutex::new(release_rx));
+        let run = running.clone();
+        let server = std::thread::spawn(move || {
+            let pool = WorkerPool::new(1, "test-serve");
+            serve_pooled(
+                &listener,
+                &pool,
+                &run,
+                "test",
+                false,
+                |s| {
+                    let _ = s.write_all(b"HTTP/1.1 503 Service Unavailable\r\n\r\n");
+                },
+                |_stream| {
+                    let rx = release_rx.clone();
+                    move || {
+                        let _ = rx.lock().unwrap().recv();
+                    }
+                },
+            );
+        });
+        let busy_response = || {
+            let mut s = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            s.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            let _ = s.shutdown(Shutdown::Write);
+            let mut resp = String::new();
+            let _ = s.read_to_string(&mut resp);
+            resp
+        };
+
+        // Occupy the only worker, then keep a rejected client trickling its request.
+        let _held = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !busy_response().contains("503") {
+            assert!(Instant::now() < deadline, "worker never became busy");
+        }
+        let trickling = Arc::new(AtomicBool::new(true));
+        let trickle = {
+            let trickling = trickling.clone();
+            let mut s = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            std::thread::spawn(move || {
+                while trickling.load(Ordering::SeqCst) && s.write_all(b"x").is_ok() {
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+            })
+        };
+        std::thread::sleep(Duration::from_millis(50));
+
+        let started = Instant::now();
+        assert!(busy_response().contains("503"));
+        assert!(
+            started.elapsed() < Duration::from_secs(1),
+            "rejection took {:?}",
+            started.elapsed()
+        );
+
+        trickling.store(false, Ordering::SeqCst);
+        let _ = trickle.join();
+        running.store(false, Ordering::SeqCst);
+        let _ = release_tx.send(());
+        let _ = server.join();
+    }
+
+    #[test]
+    fn test_worker_pool_survives_panicking_jobs() {
+        let pool = WorkerPool::new(1, "test-pool-panic");
+        for _ in 0..3 {
+            let slot = pool.try_reserve().expect("free worker");
+            pool.execute(slot, || panic!("job failed"));
+            let deadline = std::time::Instant::now() + Duration::from_secs(5);
+            while pool.active() > 0 && std::time::Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+            assert_eq!(pool.active(), 0, "slot must be released after a panic");
+        }
+
+        let (done_tx, done_rx) = mpsc::channel::<()>();
+        let slot = pool.try_reserve().expect("free worker");
+        pool.execute(slot, move || {
+            let _ = done_tx.send(());
+        });
+        done_rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("worker still serves jobs after panics");
+        assert_eq!(pool.shared.workers.load(Ordering::SeqCst), 1);
+    }
+}
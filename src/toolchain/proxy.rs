@@ -2,14 +2,17 @@
 Proxy module: dispatcher, accept loop, and public toolexec_start_proxy API.
 
 Implements v3 signal propagation and timeout model:
-- Listener setup (TCP/unix), accept loop with backoff.
+- Listener setup (TCP, optionally mutual TLS, or unix), accept loop with backoff.
 - Per-connection dispatcher using http::read_http_request + http::classify_endpoint.
-- Centralized auth/proto via auth::validate_auth_and_proto.
+- Centralized auth/proto via auth::validate_auth_and_proto (legacy or scoped tokens, optional
+  signed requests); token scope and sidecar kind checked per endpoint.
 - /signal endpoint: authenticated signal forwarding by ExecId.
+- /token endpoint: token rotation and narrower derived tokens.
 - ExecId registry and streaming prelude includes X-Exec-Id (v2).
 - Setsid+PGID wrapper applied to v1 and v2 execs; PGID file at $HOME/.aifo-exec/<ExecId>/pgid.
 - Disconnect-triggered termination for v2 (INT -> TERM -> KILL).
-- No default proxy-imposed timeout for tool execs; optional max-runtime escalation (INT at T, TERM at T+5s, KILL at T+10s).
+- Per-exec limits (limits.rs): max runtime, idle output and output size, configurable per tool and
+  subcommand; a tripped limit escalates INT -> TERM (+5s) -> KILL (+10s) and sets its own exit code.
 - Notifications policy per spec with independent short timeout.
 - Streaming prelude only after successful spawn; plain 500 on spawn error.
 */
@@ -25,7 +28,6 @@ use opentelemetry::Context;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::env as std_env;
-#[cfg(target_os = "linux")]
 use std::fs;
 use std::io;
 use std::io::{Read, Write};
@@ -33,8 +35,8 @@ use std::net::TcpListener;
 #[cfg(target_os = "linux")]
 use std::os::unix::net::UnixListener;
 use std::path::{Component, Path, PathBuf};
-use std::process::{Command, Stdio};
-use std::sync::atomic::{AtomicBool, AtomicUsize};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
 use std::time::Duration;
@@ -50,7 +52,28 @@ use crate::container_runtime_path;
 use crate::shell_join;
 use crate::ShellScript;
 
+use super::approval::{ApprovalGate, ApprovalRequest};
+use super::audit::AuditLog;
+use super::env_forward::toolexec_env_filter;
+use super::limits::{
+    resolve_exec_limits, spawn_limit_watcher, tool_limit_rules, ExecLimits, ExecWatch, LimitHit,
+};
+use super::policy::{apply_tool_policy, policy_approval_rule, tool_policy_rules, PolicyDenial};
+use super::pool::{serve_pooled, ProxyListener, ProxyStream, WorkerPool};
+#[cfg(unix)]
+use super::pty::{
+    pty_parse_resize, pty_parse_size_header, pty_write_frame, PtyFrameReader, PTY_FRAME_EXIT,
+    PTY_FRAME_OUTPUT, PTY_FRAME_RESIZE, PTY_FRAME_STDIN, PTY_FRAME_STDIN_EOF, PTY_SIZE_HEADER,
+    PTY_UPGRADE, STDIN_UPGRADE,
+};
 use super::sidecar;
+use super::status::{self, ExecRegistry, SIDECAR_KINDS};
+use super::tls::{proxy_server_names, toolexec_tls_dir, TlsListener, TlsMaterial};
+use super::tokens::{
+    token_claims, toolexec_signing_key, TokenAuthority, TokenClaims, TokenScope,
+    ROTATION_GRACE_SECS, TOOLEXEC_SIGNING_KEY_ENV,
+};
+use super::transcript::{exec_env_from_preview, Replay, TranscriptWriter};
 use super::{auth, http, notifications};
 use super::{container_exists, select_kind_for_tool, sidecar_allowlist};
 
@@ -93,15 +116,31 @@ impl<'a> Injector for HeaderMapInjector<'a> {
 
 struct ProxyCtx {
     runtime: PathBuf,
-    token: String,
+    tokens: Arc<TokenAuthority>,
     session: String,
     timeout_secs: u64,
+    exec_limits: ExecLimits,
     verbose: bool,
     agent_container: Option<String>,
     uidgid: Option<(u32, u32)>,
+    started: std::time::Instant,
+    write_timeout: Option<Duration>,
+    approvals: Arc<ApprovalGate>,
+    audit: Arc<AuditLog>,
+    transcript: Option<Arc<TranscriptWriter>>,
+    replay: Option<Arc<Replay>>,
 }
 
 // Response helpers (moved from toolchain.rs)
+fn respond_json<W: Write>(w: &mut W, body: &[u8]) {
+    let header = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    );
+    let _ = w.write_all(header.as_bytes());
+    let _ = w.write_all(body);
+}
+
 fn respond_plain<W: Write>(w: &mut W, status: &str, exit_code: i32, body: &[u8]) {
     let header = format!(
         "HTTP/1.1 {status}\r\nContent-Type: text/plain; charset=utf-8\r\nX-Exit-Code: {exit_code}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
@@ -112,6 +151,33 @@ fn respond_plain<W: Write>(w: &mut W, status: &str, exit_code: i32, body: &[u8])
     let _ = w.flush();
 }
 
+/// 403 for a policy denial: a readable body plus X-Aifo-Policy-* headers the shim recognizes.
+fn respond_policy_denied<W: Write>(w: &mut W, tool: &str, denial: &PolicyDenial) {
+    let header_safe: String = denial
+        .reason
+        .chars()
+        .map(|c| {
+            if c.is_ascii() && !c.is_ascii_control() {
+                c
+            } else {
+                '?'
+            }
+        })
+        .collect();
+    let body = format!(
+        "aifo-coder: {tool} blocked by proxy policy {}: {}\n",
+        denial.rule, denial.reason
+    );
+    let header = format!(
+        "HTTP/1.1 403 Forbidden\r\nContent-Type: text/plain; charset=utf-8\r\nX-Exit-Code: 86\r\nX-Aifo-Policy: denied\r\nX-Aifo-Policy-Rule: {}\r\nX-Aifo-Policy-Reason: {header_safe}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        denial.rule,
+        body.len()
+    );
+    let _ = w.write_all(header.as_bytes());
+    let _ = w.write_all(body.as_bytes());
+    let _ = w.flush();
+}
+
 fn respond_chunked_prelude<W: Write>(w: &mut W, exec_id: Option<&str>) -> io::Result<()> {
     let mut hdr = String::from("HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nTransfer-Encoding: chunked\r\nTrailer: X-Exit-Code\r\nConnection: close\r\n");
     if let Some(id) = exec_id {
@@ -323,7 +389,8 @@ fn kill_in_container(
     let sig = signal.to_ascii_uppercase();
     let script = ShellScript::new()
         .extend([
-            format!(r#"pg="/home/coder/.aifo-exec/{exec_id}/pgid"; if [ -f "$pg" ]; then n=$(cat "$pg" 2>/dev/null); if [ -n "$n" ]; then kill -s {sig} -"$n" || true; fi; fi"#),
+            // dash's kill reads a negative pid as an option unless it follows `--`
+            format!(r#"pg="/home/coder/.aifo-exec/{exec_id}/pgid"; if [ -f "$pg" ]; then n=$(cat "$pg" 2>/dev/null); if [ -n "$n" ]; then kill -s {sig} -- -"$n" 2>/dev/null || kill -s {sig} -"$n" || true; fi; fi"#),
         ])
         .build()
         .unwrap_or_else(|_| "true".to_string());
@@ -356,6 +423,41 @@ fn kill_in_container(
     let _ = cmd2.status();
 }
 
+/// Grace period between INT, TERM and KILL once an exec limit trips.
+const LIMIT_ESCALATION_GRACE: Duration = Duration::from_secs(5);
+
+/// Enforce LIMITS on EXEC_ID in CONTAINER, signalling the exec's process group.
+fn watch_exec_limits(
+    ctx: &ProxyCtx,
+    container: &str,
+    exec_id: &str,
+    limits: ExecLimits,
+    watch: &Arc<ExecWatch>,
+) {
+    let runtime = ctx.runtime.clone();
+    let container = container.to_string();
+    let exec_id = exec_id.to_string();
+    let verbose = ctx.verbose;
+    let started = std::time::Instant::now();
+    let _ = spawn_limit_watcher(
+        watch.clone(),
+        limits,
+        LIMIT_ESCALATION_GRACE,
+        move |sig, hit| {
+            if verbose {
+                eprintln!(
+                    "\raifo-coder: exec limit: {} exceeded, sending {} to exec_id={} after {}s",
+                    hit.name(),
+                    sig,
+                    exec_id,
+                    started.elapsed().as_secs()
+                );
+            }
+            kill_in_container(&runtime, &container, &exec_id, sig, verbose);
+        },
+    );
+}
+
 /// Best-effort: kill the interactive /run shell inside the agent container using recorded tpgid.
 fn kill_agent_shell_in_agent_container(
     runtime: &PathBuf,
@@ -449,17 +551,54 @@ fn exec_wrapper_env_prelude() -> String {
         .unwrap_or_else(|_| "set -e".to_string())
 }
 
-/// Build docker exec spawn args with setsid+PGID wrapper (use_tty controls -t).
+/// Add the agent variables sent by the shim (`env=NAME=VALUE`) to a docker exec preview.
 ///
-/// Security note:
-/// - Do not embed user-controlled argv into a `sh -c` string.
-/// - Instead, pass argv as positional parameters (`-- "$@"`) and `exec "$@"` inside the wrapper.
-fn build_exec_args_with_wrapper(
+/// The proxy re-applies the forwarding filter with its own configuration and never overrides
+/// variables the preview already sets; forwarded and dropped names are logged in verbose mode.
+fn apply_forwarded_env(
+    exec_preview_args: &mut Vec<String>,
+    requested: Vec<(String, String)>,
+    tool: &str,
+    verbose: bool,
+) {
+    if requested.is_empty() {
+        return;
+    }
+    let (kept, mut dropped) = toolexec_env_filter(requested);
+    let preset: HashSet<String> = exec_preview_args
+        .windows(2)
+        .filter(|w| w[0] == "-e")
+        .filter_map(|w| w[1].split_once('=').map(|(k, _)| k.to_string()))
+        .collect();
+    let mut names: Vec<String> = Vec::new();
+    let mut flags: Vec<String> = Vec::new();
+    for (k, v) in kept {
+        if preset.contains(&k) {
+            dropped.push(k);
+            continue;
+        }
+        flags.push("-e".to_string());
+        flags.push(format!("{k}={v}"));
+        names.push(k);
+    }
+    // Right after `docker exec`, ahead of the container name and the command.
+    let at = 2.min(exec_preview_args.len());
+    exec_preview_args.splice(at..at, flags);
+    if verbose {
+        log_compact(&format!(
+            "aifo-coder: proxy env: tool={} forwarded=[{}] dropped=[{}]",
+            tool,
+            names.join(","),
+            dropped.join(",")
+        ));
+    }
+}
+
+/// Split a docker exec preview into the `exec ... <container>` prefix and the user command.
+fn split_exec_preview(
     container_name: &str,
     exec_preview_args: &[String],
-    use_tty: bool,
-) -> Vec<String> {
-    let mut spawn_args: Vec<String> = Vec::new();
+) -> (Vec<String>, Vec<String>) {
     let mut idx = None;
     for (i, a) in exec_preview_args.iter().enumerate().skip(1) {
         if a == container_name {
@@ -468,9 +607,43 @@ fn build_exec_args_with_wrapper(
         }
     }
     let idx = idx.unwrap_or(exec_preview_args.len().saturating_sub(1));
+    (
+        exec_preview_args[1..=idx].to_vec(),
+        exec_preview_args[idx + 1..].to_vec(),
+    )
+}
+
+/// Build docker exec spawn args with setsid+PGID wrapper (use_tty controls -t).
+///
+/// Security note:
+/// - Do not embed user-controlled argv into a `sh -c` string.
+/// - Instead, pass argv as positional parameters (`-- "$@"`) and `exec "$@"` inside the wrapper.
+fn build_exec_args_with_wrapper(
+    container_name: &str,
+    exec_preview_args: &[String],
+    use_tty: bool,
+) -> Vec<String> {
+    build_wrapped_exec_args(container_name, exec_preview_args, use_tty, false)
+}
 
-    // Up to and including container name
-    spawn_args.extend(exec_preview_args[1..=idx].iter().cloned());
+/// Build `docker exec -i` args for an exec fed from the shim's stdin.
+///
+/// Same setsid+PGID wrapper as streaming execs, except the backgrounded command keeps the exec's
+/// stdin: a non-interactive sh would otherwise point an asynchronous list at /dev/null.
+fn build_stdin_exec_args(container_name: &str, exec_preview_args: &[String]) -> Vec<String> {
+    let mut spawn_args = build_wrapped_exec_args(container_name, exec_preview_args, false, true);
+    spawn_args.insert(1, "-i".to_string());
+    spawn_args
+}
+
+fn build_wrapped_exec_args(
+    container_name: &str,
+    exec_preview_args: &[String],
+    use_tty: bool,
+    keep_stdin: bool,
+) -> Vec<String> {
+    // Up to and including container name; user command passed as "$@" to the wrapper
+    let (mut spawn_args, user_slice) = split_exec_preview(container_name, exec_preview_args);
 
     // Allocate a TTY for streaming to improve interactive flushing when requested.
     if use_tty {
@@ -479,9 +652,6 @@ fn build_exec_args_with_wrapper(
         spawn_args.insert(1, "-i".to_string());
     }
 
-    // User command slice after container name (passed as "$@" to the wrapper)
-    let user_slice: Vec<String> = exec_preview_args[idx + 1..].to_vec();
-
     // Wrapper runs the requested command in a new session (setsid) and records the "child" pid
     // so we can send signals to the whole process group later.
     //
@@ -512,7 +682,11 @@ fn build_exec_args_with_wrapper(
             r#"d="${HOME:-/home/coder}/.aifo-exec/${AIFO_EXEC_ID:-}"; mkdir -p "$d" 2>/dev/null || { d="/tmp/.aifo-exec/${AIFO_EXEC_ID:-}"; mkdir -p "$d" || true; }"#.to_string(),
             // Run command under setsid in the background and capture the PID (compound group in one fragment).
             // Keep the nested `sh -lc` for login-shell semantics.
-            format!(r#"( setsid sh -lc '{inner_cmd_sq}' -- "$@" ) & pg=$!"#),
+            if keep_stdin {
+                format!(r#"exec 3<&0; ( setsid sh -lc '{inner_cmd_sq}' -- "$@" <&3 3<&- ) & pg=$!"#)
+            } else {
+                format!(r#"( setsid sh -lc '{inner_cmd_sq}' -- "$@" ) & pg=$!"#)
+            },
             r#"printf "%s\n" "$pg" > "$d/pgid" 2>/dev/null || true"#.to_string(),
             r#"wait "$pg"; rm -rf "$d" || true"#.to_string(),
         ])
@@ -530,6 +704,60 @@ fn build_exec_args_with_wrapper(
     spawn_args
 }
 
+/// Build `docker exec -i -t` args for an interactive (PTY) exec.
+///
+/// Unlike the streaming wrapper, the command stays in the exec's own session and foreground
+/// process group so terminal signals (^C, ^Z) reach it; that group is recorded as the pgid for
+/// /signal. TERM is forwarded from the shim when it looks like a terminal name.
+fn build_pty_exec_args(
+    container_name: &str,
+    exec_preview_args: &[String],
+    term: Option<&str>,
+) -> Vec<String> {
+    let (mut spawn_args, user_slice) = split_exec_preview(container_name, exec_preview_args);
+    spawn_args.insert(1, "-t".to_string());
+    spawn_args.insert(1, "-i".to_string());
+    if let Some(t) = term.filter(|t| {
+        !t.is_empty()
+            && t.len() <= 64
+            && t.bytes()
+                .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.' | b'+'))
+    }) {
+        spawn_args.insert(1, format!("TERM={t}"));
+        spawn_args.insert(1, "-e".to_string());
+    }
+
+    let prelude = exec_wrapper_env_prelude();
+    let inner_cmd = ShellScript::new()
+        .extend([prelude.clone(), r#"exec "$@""#.to_string()])
+        .build()
+        .unwrap_or_else(|_| r#"exec "$@""#.to_string());
+    let inner_cmd_sq = inner_cmd.replace('\'', r#"'\''"#);
+    let script = ShellScript::new()
+        .extend([
+            prelude,
+            r#"eid="${AIFO_EXEC_ID:-}""#.to_string(),
+            r#"if [ -z "$eid" ]; then exec "$@"; fi"#.to_string(),
+            r#"d="${HOME:-/home/coder}/.aifo-exec/${AIFO_EXEC_ID:-}"; mkdir -p "$d" 2>/dev/null || { d="/tmp/.aifo-exec/${AIFO_EXEC_ID:-}"; mkdir -p "$d" || true; }"#.to_string(),
+            r#"printf "%s\n" "$$" > "$d/pgid" 2>/dev/null || true"#.to_string(),
+            // A no-op trap (not an ignore) so ^C reaches the tool and this shell survives it.
+            "trap : INT QUIT".to_string(),
+            "rc=0".to_string(),
+            format!(r#"sh -lc '{inner_cmd_sq}' -- "$@" || rc=$?"#),
+            r#"rm -rf "$d" || true"#.to_string(),
+            r#"exit "$rc""#.to_string(),
+        ])
+        .build()
+        .unwrap_or_else(|_| r#"exec "$@""#.to_string());
+
+    spawn_args.push("sh".to_string());
+    spawn_args.push("-c".to_string());
+    spawn_args.push(script);
+    spawn_args.push("aifo-exec".to_string());
+    spawn_args.extend(user_slice);
+    spawn_args
+}
+
 #[cfg_attr(
     feature = "otel",
     instrument(
@@ -562,8 +790,16 @@ pub fn toolexec_start_proxy(
     #[cfg(not(unix))]
     let (uid, gid) = (0u32, 0u32);
 
-    let token = random_token();
-    let token_for_thread = token.clone();
+    // Token handed to the agent: the legacy session token or a scoped one (see tokens.rs).
+    let (tokens, token) = TokenAuthority::from_env().map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            crate::display_for_toolchain_error(&crate::ToolchainError::Message(format!(
+                "proxy token setup failed: {e}"
+            ))),
+        )
+    })?;
+    let tokens = Arc::new(tokens);
     let timeout_secs: u64 = std_env::var("AIFO_TOOLEEXEC_MAX_SECS")
         .ok()
         .and_then(|s| s.parse::<u64>().ok())
@@ -575,14 +811,39 @@ pub fn toolexec_start_proxy(
                 .filter(|&v| v > 0)
         })
         .unwrap_or(300);
+    // Per-exec limits; [[proxy.limits.TOOL]] rules override them per invocation.
+    let exec_limits = ExecLimits::from_env(timeout_secs);
     let max_conns: usize = std_env::var("AIFO_TOOLEEXEC_MAX_CONNECTIONS")
         .ok()
         .and_then(|s| s.parse::<usize>().ok())
         .filter(|&v| v > 0)
         .unwrap_or(64);
+    // Write deadline per connection: a shim that stops reading fails the write instead of
+    // pinning its worker (0 disables).
+    let write_timeout_secs: u64 = std_env::var("AIFO_TOOLEEXEC_WRITE_TIMEOUT_SECS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(60);
+    // One approval gate per proxy so "always for this session" decisions are shared.
+    let approvals = Arc::new(ApprovalGate::from_env());
     let running = Arc::new(std::sync::atomic::AtomicBool::new(true));
-    let active_conns = Arc::new(std::sync::atomic::AtomicUsize::new(0));
     let session = session_id.to_string();
+    let audit = Arc::new(AuditLog::open(&session, verbose));
+    let transcript = TranscriptWriter::from_env(&session, verbose).map(Arc::new);
+    let replay = Replay::from_env()
+        .map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                crate::display_for_toolchain_error(&crate::ToolchainError::Message(format!(
+                    "proxy replay failed: {e}"
+                ))),
+            )
+        })?
+        .map(Arc::new);
+
+    // Request-signing key for the agent's shims on either transport; it only travels through
+    // the container env.
+    std_env::set_var(TOOLEXEC_SIGNING_KEY_ENV, toolexec_signing_key());
 
     // Optional unix socket (Linux)
     let use_unix = cfg!(target_os = "linux")
@@ -645,92 +906,30 @@ pub fn toolexec_start_proxy(
                 "AIFO_TOOLEEXEC_UNIX_DIR",
                 host_dir.to_string_lossy().to_string(),
             );
-            let running_cl2 = running.clone();
-            let active_conns_cl = active_conns.clone();
-            let token_for_thread2 = token_for_thread.clone();
+            let serve = ServeConfig {
+                runtime: runtime.clone(),
+                tokens: tokens.clone(),
+                session: session.clone(),
+                timeout_secs,
+                exec_limits,
+                write_timeout_secs,
+                verbose,
+                uid,
+                gid,
+                started: std::time::Instant::now(),
+                approvals: approvals.clone(),
+                audit: audit.clone(),
+                transcript: transcript.clone(),
+                replay: replay.clone(),
+            };
+            let running_cl = running.clone();
             let host_dir_cl = host_dir.clone();
             let sock_path_cl = sock_path.clone();
             let handle = std::thread::spawn(move || {
                 if verbose {
                     eprintln!("aifo-coder: toolexec proxy listening on unix socket");
                 }
-                let tool_cache =
-                    std::sync::Arc::new(std::sync::Mutex::new(
-                        HashMap::<(String, String), bool>::new(),
-                    ));
-                let exec_registry =
-                    std::sync::Arc::new(std::sync::Mutex::new(HashMap::<String, String>::new()));
-                let recent_signals =
-                    std::sync::Arc::new(std::sync::Mutex::new(
-                        HashMap::<String, std::time::Instant>::new(),
-                    ));
-                loop {
-                    if !running_cl2.load(std::sync::atomic::Ordering::SeqCst) {
-                        break;
-                    }
-                    let (stream, _addr) = match listener.accept() {
-                        Ok(pair) => pair,
-                        Err(e) => {
-                            if e.kind() == io::ErrorKind::WouldBlock {
-                                std::thread::sleep(Duration::from_millis(50));
-                                continue;
-                            } else {
-                                if verbose {
-                                    eprintln!("aifo-coder: accept error: {}", e);
-                                }
-                                std::thread::sleep(Duration::from_millis(50));
-                                continue;
-                            }
-                        }
-                    };
-                    let current = active_conns_cl.load(std::sync::atomic::Ordering::SeqCst);
-                    if current >= max_conns {
-                        if verbose {
-                            eprintln!(
-                                "aifo-coder: dropping connection; active {} exceeds max {}",
-                                current, max_conns
-                            );
-                        }
-                        continue;
-                    }
-                    active_conns_cl.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-                    let _ = stream.set_nonblocking(false);
-                    if timeout_secs > 0 {
-                        let _ = stream.set_read_timeout(Some(Duration::from_secs(timeout_secs)));
-                    } else {
-                        let _ = stream.set_read_timeout(None);
-                    }
-                    let _ = stream.set_write_timeout(None);
-
-                    let tc = tool_cache.clone();
-                    let er = exec_registry.clone();
-                    let rs = recent_signals.clone();
-                    let runtime_cl = runtime.clone();
-                    let token_cl = token_for_thread2.clone();
-                    let session_cl = session.clone();
-                    let active_conns_inner = active_conns_cl.clone();
-                    std::thread::spawn(move || {
-                        let disable_user =
-                            std_env::var("AIFO_TOOLEEXEC_DISABLE_USER").ok().as_deref()
-                                == Some("1");
-                        let ctx2 = ProxyCtx {
-                            runtime: runtime_cl,
-                            token: token_cl,
-                            session: session_cl,
-                            timeout_secs,
-                            verbose,
-                            agent_container: std_env::var("AIFO_CODER_CONTAINER_NAME").ok(),
-                            uidgid: if cfg!(unix) && !disable_user {
-                                Some((uid, gid))
-                            } else {
-                                None
-                            },
-                        };
-                        let mut s = stream;
-                        handle_connection(&ctx2, &mut s, &tc, &er, &rs);
-                        active_conns_inner.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
-                    });
-                }
+                serve_connections(listener, &serve, &running_cl, max_conns);
                 let _ = fs::remove_file(&sock_path_cl);
                 let _ = fs::remove_dir(&host_dir_cl);
                 if verbose {
@@ -766,98 +965,713 @@ pub fn toolexec_start_proxy(
     })?;
     let port = addr.port();
     let _ = listener.set_nonblocking(true);
+    // Optional mutual TLS: session certificates, with the client side exported for the agent.
+    let tls = if std_env::var("AIFO_TOOLEEXEC_TLS").ok().as_deref() == Some("1") {
+        let tls_err = |e: String| {
+            io::Error::other(crate::display_for_toolchain_error(
+                &crate::ToolchainError::Message(format!("proxy TLS setup failed: {e}")),
+            ))
+        };
+        let material = TlsMaterial::generate(&proxy_server_names(&bind_host)).map_err(tls_err)?;
+        let config = material.server_config().map_err(tls_err)?;
+        let dir = toolexec_tls_dir(&session);
+        material
+            .write_client_files(&dir)
+            .map_err(|e| tls_err(format!("{}: {e}", dir.display())))?;
+        std_env::set_var("AIFO_TOOLEEXEC_TLS_DIR", dir.to_string_lossy().to_string());
+        Some((config, dir))
+    } else {
+        None
+    };
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    let serve = ServeConfig {
+        runtime,
+        tokens,
+        session,
+        timeout_secs,
+        exec_limits,
+        write_timeout_secs,
+        verbose,
+        uid,
+        gid,
+        started: std::time::Instant::now(),
+        approvals,
+        audit,
+        transcript,
+        replay,
+    };
     let running_cl = running.clone();
-    let active_conns_cl = active_conns.clone();
-
     let handle = std::thread::spawn(move || {
         if verbose {
             eprintln!(
-                "aifo-coder: toolexec proxy listening on {}:{port}",
+                "aifo-coder: toolexec proxy listening on {scheme}://{}:{port}",
                 bind_host
             );
         }
-        let tool_cache = std::sync::Arc::new(std::sync::Mutex::new(HashMap::<
-            (String, String),
-            bool,
-        >::new()));
-        let exec_registry =
-            std::sync::Arc::new(std::sync::Mutex::new(HashMap::<String, String>::new()));
-        let recent_signals = std::sync::Arc::new(std::sync::Mutex::new(HashMap::<
-            String,
-            std::time::Instant,
-        >::new()));
-        loop {
-            if !running_cl.load(std::sync::atomic::Ordering::SeqCst) {
-                break;
-            }
-            let (stream, _addr) = match listener.accept() {
-                Ok(pair) => pair,
-                Err(e) => {
-                    if e.kind() == io::ErrorKind::WouldBlock {
-                        std::thread::sleep(Duration::from_millis(50));
-                        continue;
-                    } else {
-                        if verbose {
-                            eprintln!("aifo-coder: accept error: {}", e);
-                        }
-                        std::thread::sleep(Duration::from_millis(50));
-                        continue;
-                    }
-                }
-            };
-            let current = active_conns_cl.load(std::sync::atomic::Ordering::SeqCst);
-            if current >= max_conns {
-                if verbose {
-                    eprintln!(
-                        "aifo-coder: dropping connection; active {} exceeds max {}",
-                        current, max_conns
-                    );
-                }
-                continue;
+        match tls {
+            Some((config, dir)) => {
+                let listener = TlsListener {
+                    inner: listener,
+                    config,
+                };
+                serve_connections(listener, &serve, &running_cl, max_conns);
+                let _ = fs::remove_dir_all(&dir);
             }
-            active_conns_cl.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
-            let _ = stream.set_nonblocking(false);
-            if timeout_secs > 0 {
-                let _ = stream.set_read_timeout(Some(Duration::from_secs(timeout_secs)));
+            None => serve_connections(listener, &serve, &running_cl, max_conns),
+        }
+        if verbose {
+            eprintln!("aifo-coder: toolexec proxy stopped");
+        }
+    });
+    let url = format!("{scheme}://127.0.0.1:{}/exec", port);
+    Ok((url, token, running, handle))
+}
+
+/// Settings shared by every connection of one proxy instance.
+struct ServeConfig {
+    runtime: PathBuf,
+    tokens: Arc<TokenAuthority>,
+    session: String,
+    timeout_secs: u64,
+    exec_limits: ExecLimits,
+    write_timeout_secs: u64,
+    verbose: bool,
+    uid: u32,
+    gid: u32,
+    started: std::time::Instant,
+    approvals: Arc<ApprovalGate>,
+    audit: Arc<AuditLog>,
+    transcript: Option<Arc<TranscriptWriter>>,
+    replay: Option<Arc<Replay>>,
+}
+
+impl ServeConfig {
+    fn connection_ctx(&self) -> ProxyCtx {
+        let disable_user = std_env::var("AIFO_TOOLEEXEC_DISABLE_USER").ok().as_deref() == Some("1");
+        ProxyCtx {
+            runtime: self.runtime.clone(),
+            tokens: self.tokens.clone(),
+            session: self.session.clone(),
+            timeout_secs: self.timeout_secs,
+            exec_limits: self.exec_limits,
+            verbose: self.verbose,
+            agent_container: std_env::var("AIFO_CODER_CONTAINER_NAME").ok(),
+            uidgid: if cfg!(unix) && !disable_user {
+                Some((self.uid, self.gid))
             } else {
-                let _ = stream.set_read_timeout(None);
-            }
-            let _ = stream.set_write_timeout(None);
+                None
+            },
+            started: self.started,
+            write_timeout: (self.write_timeout_secs > 0)
+                .then(|| Duration::from_secs(self.write_timeout_secs)),
+            approvals: self.approvals.clone(),
+            audit: self.audit.clone(),
+            transcript: self.transcript.clone(),
+            replay: self.replay.clone(),
+        }
+    }
+}
+
+const ERR_BUSY: &[u8] = b"proxy busy: too many concurrent tool executions; retry shortly\n";
 
+/// Accept connections until RUNNING is cleared, handing each to a bounded worker pool.
+///
+/// Waits in poll(2) rather than sleeping, answers 503 when all MAX_CONNS workers are busy and
+/// gives every connection a read timeout and a write deadline.
+fn serve_connections<L: ProxyListener>(
+    listener: L,
+    cfg: &ServeConfig,
+    running: &AtomicBool,
+    max_conns: usize,
+) {
+    let pool = WorkerPool::new(max_conns, "aifo-proxy");
+    let tool_cache = Arc::new(Mutex::new(HashMap::<(String, String), bool>::new()));
+    let exec_registry = Arc::new(ExecRegistry::default());
+    let recent_signals = Arc::new(Mutex::new(HashMap::<String, std::time::Instant>::new()));
+    let secs = |s: u64| (s > 0).then(|| Duration::from_secs(s));
+    let read_timeout = secs(cfg.timeout_secs);
+    let write_timeout = secs(cfg.write_timeout_secs);
+    serve_pooled(
+        &listener,
+        &pool,
+        running,
+        "proxy",
+        cfg.verbose,
+        |s| respond_plain(s, "503 Service Unavailable", 86, ERR_BUSY),
+        |stream| {
+            stream.set_deadlines(read_timeout, write_timeout);
+            let ctx = cfg.connection_ctx();
             let tc = tool_cache.clone();
             let er = exec_registry.clone();
             let rs = recent_signals.clone();
-            let runtime_cl = runtime.clone();
-            let token_cl = token_for_thread.clone();
-            let session_cl = session.clone();
-            let active_conns_inner = active_conns_cl.clone();
-            std::thread::spawn(move || {
-                let disable_user =
-                    std_env::var("AIFO_TOOLEEXEC_DISABLE_USER").ok().as_deref() == Some("1");
-                let ctx2 = ProxyCtx {
-                    runtime: runtime_cl,
-                    token: token_cl,
-                    session: session_cl,
-                    timeout_secs,
-                    verbose,
-                    agent_container: std_env::var("AIFO_CODER_CONTAINER_NAME").ok(),
-                    uidgid: if cfg!(unix) && !disable_user {
-                        Some((uid, gid))
-                    } else {
-                        None
-                    },
-                };
+            move || {
                 let mut s = stream;
-                handle_connection(&ctx2, &mut s, &tc, &er, &rs);
-                active_conns_inner.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+                handle_connection(&ctx, &mut s, &tc, &er, &rs);
+            }
+        },
+    );
+}
+
+/// How an upgraded exec connection is attached to the command.
+#[cfg(unix)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExecChannel {
+    /// `Upgrade: aifo-pty`: a host pseudo-terminal of (cols, rows).
+    Pty((u16, u16)),
+    /// `Upgrade: aifo-stdin`: plain pipes; the shim streams its redirected stdin.
+    Stdin,
+}
+
+/// Channel requested by the shim's `Upgrade` header, if any.
+#[cfg(unix)]
+fn upgrade_requested(headers: &http::HeaderMap) -> Option<ExecChannel> {
+    let upgrade = headers.get("upgrade")?.trim();
+    if upgrade.eq_ignore_ascii_case(STDIN_UPGRADE) {
+        return Some(ExecChannel::Stdin);
+    }
+    if !upgrade.eq_ignore_ascii_case(PTY_UPGRADE) {
+        return None;
+    }
+    Some(ExecChannel::Pty(
+        headers
+            .get(&PTY_SIZE_HEADER.to_ascii_lowercase())
+            .and_then(|v| pty_parse_size_header(v))
+            .unwrap_or((80, 24)),
+    ))
+}
+
+#[cfg(unix)]
+#[derive(Debug, PartialEq, Eq)]
+enum RelayOutcome {
+    Exited(i32),
+    Disconnected,
+}
+
+#[cfg(unix)]
+fn set_pty_size(master: &std::fs::File, cols: u16, rows: u16) {
+    use std::os::fd::AsRawFd;
+    let ws = libc::winsize {
+        ws_row: rows,
+        ws_col: cols,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCSWINSZ reads a winsize struct from a valid pointer on an open pty master.
+    unsafe {
+        let _ = libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &ws);
+    }
+}
+
+/// Where stdin frames from the shim are written.
+#[cfg(unix)]
+enum RelayInput {
+    Pty(std::fs::File),
+    Pipe(Option<std::process::ChildStdin>),
+}
+
+#[cfg(unix)]
+impl RelayInput {
+    fn write(&mut self, data: &[u8]) {
+        match self {
+            RelayInput::Pty(master) => {
+                let _ = master.write_all(data);
+            }
+            RelayInput::Pipe(pipe) => {
+                // The command stopped reading (or exited); drop further input.
+                if pipe.as_mut().is_some_and(|p| p.write_all(data).is_err()) {
+                    *pipe = None;
+                }
+            }
+        }
+    }
+
+    fn eof(&mut self) {
+        match self {
+            // The terminal's EOF character (^D) in canonical mode.
+            RelayInput::Pty(master) => {
+                let _ = master.write_all(&[0x04]);
+            }
+            // Closing the pipe is what `docker exec -i` forwards as EOF.
+            RelayInput::Pipe(pipe) => drop(pipe.take()),
+        }
+    }
+
+    fn resize(&self, cols: u16, rows: u16) {
+        if let RelayInput::Pty(master) = self {
+            set_pty_size(master, cols, rows);
+        }
+    }
+}
+
+/// Spawn CMD attached to CHANNEL; returns the child, its input and its combined output.
+#[cfg(unix)]
+fn spawn_for_channel(
+    mut cmd: Command,
+    channel: ExecChannel,
+) -> io::Result<(Child, RelayInput, Box<dyn Read + Send>)> {
+    use std::os::unix::process::CommandExt;
+
+    let ExecChannel::Pty(size) = channel else {
+        cmd.stdin(Stdio::piped());
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        let mut child = cmd.spawn()?;
+        // The wrapper folds the tool's stderr into stdout; drain docker's own diagnostics.
+        if let Some(mut se) = child.stderr.take() {
+            std::thread::spawn(move || {
+                let _ = io::copy(&mut se, &mut io::sink());
             });
         }
-        if verbose {
-            eprintln!("aifo-coder: toolexec proxy stopped");
+        let input = RelayInput::Pipe(child.stdin.take());
+        let output: Box<dyn Read + Send> = match child.stdout.take() {
+            Some(out) => Box::new(out),
+            None => Box::new(io::empty()),
+        };
+        return Ok((child, input, output));
+    };
+
+    let ws = nix::pty::Winsize {
+        ws_row: size.1,
+        ws_col: size.0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    let pty = nix::pty::openpty(Some(&ws), None).map_err(io::Error::from)?;
+    cmd.stdin(Stdio::from(pty.slave.try_clone()?));
+    cmd.stdout(Stdio::from(pty.slave.try_clone()?));
+    cmd.stderr(Stdio::from(pty.slave));
+    // SAFETY: only async-signal-safe calls (setsid, ioctl) run between fork and exec.
+    unsafe {
+        cmd.pre_exec(|| {
+            nix::unistd::setsid().map_err(io::Error::from)?;
+            if libc::ioctl(0, libc::TIOCSCTTY, 0) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let mut child = cmd.spawn()?;
+    // Drop our copies of the slave so reads on the master end when the command exits.
+    drop(cmd);
+    let master = std::fs::File::from(pty.master);
+    match master.try_clone() {
+        Ok(output) => Ok((child, RelayInput::Pty(master), Box::new(output))),
+        Err(e) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            Err(e)
+        }
+    }
+}
+
+/// Output frames queued by the relay pump when the connection cannot be split (TLS).
+#[cfg(unix)]
+const RELAY_QUEUE_FRAMES: usize = 64;
+
+/// Where the relay writes output frames: a second handle to the connection, or a bounded queue
+/// the relay loop writes out between reads of a connection that cannot be split.
+#[cfg(unix)]
+enum RelayWriter<S> {
+    Stream(Mutex<S>),
+    Queue(std::sync::mpsc::SyncSender<Vec<u8>>),
+}
+
+#[cfg(unix)]
+impl<S: Write> RelayWriter<S> {
+    fn output(&self, data: &[u8]) -> io::Result<()> {
+        match self {
+            RelayWriter::Stream(writer) => {
+                let mut w = writer.lock().unwrap_or_else(|e| e.into_inner());
+                pty_write_frame(&mut *w, PTY_FRAME_OUTPUT, data)
+            }
+            RelayWriter::Queue(tx) => tx
+                .send(data.to_vec())
+                .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe)),
+        }
+    }
+}
+
+/// Write output frames the pump queued for a single-stream relay.
+#[cfg(unix)]
+fn flush_relay_queue<S: Write>(
+    stream: &mut S,
+    queue: Option<&std::sync::mpsc::Receiver<Vec<u8>>>,
+) -> io::Result<()> {
+    for data in queue.into_iter().flat_map(|rx| rx.try_iter()) {
+        pty_write_frame(stream, PTY_FRAME_OUTPUT, &data)?;
+    }
+    Ok(())
+}
+
+/// Run CMD attached to CHANNEL and relay it over STREAM using exec frames.
+///
+/// Sends UPGRADE_RESPONSE once the command has started; a spawn failure is returned as an error
+/// before anything is written. Output is forwarded by a helper thread while this thread applies
+/// stdin, EOF and resize frames. When STREAM cannot be split (TLS) the helper queues output and
+/// this thread writes it between short reads. The command is killed when the client goes away
+/// first.
+///
+/// Output and input are accounted on WATCH against LIMITS as for v2 streaming: output past
+/// max_output_bytes is cut with a marker, a tripped timeout adds its marker, and a tripped
+/// limit replaces the exit code. Stopping the command is left to the limit watcher.
+#[cfg(unix)]
+#[allow(clippy::too_many_arguments)]
+fn exec_relay<S: ProxyStream>(
+    stream: &mut S,
+    cmd: Command,
+    channel: ExecChannel,
+    upgrade_response: &[u8],
+    bytes_streamed: &Arc<std::sync::atomic::AtomicU64>,
+    write_timeout: Option<Duration>,
+    watch: &Arc<ExecWatch>,
+    limits: ExecLimits,
+) -> io::Result<RelayOutcome> {
+    let (mut child, mut input, mut output) = spawn_for_channel(cmd, channel)?;
+
+    let connected = stream
+        .write_all(upgrade_response)
+        .and_then(|_| stream.flush())
+        .is_ok();
+    if !connected {
+        let _ = child.kill();
+        let _ = child.wait();
+        return Ok(RelayOutcome::Disconnected);
+    }
+    // Output frames come from the pump, limit markers from this thread.
+    let (writer, queue) = match stream.try_clone_stream() {
+        Ok(w) => (RelayWriter::Stream(Mutex::new(w)), None),
+        Err(_) => {
+            let (tx, rx) = std::sync::mpsc::sync_channel(RELAY_QUEUE_FRAMES);
+            (RelayWriter::Queue(tx), Some(rx))
+        }
+    };
+    let writer = Arc::new(writer);
+    // Markers go behind any queued output so they stay in order.
+    let write_marker = |stream: &mut S, text: &str| match queue.as_ref() {
+        Some(rx) => flush_relay_queue(stream, Some(rx))
+            .and_then(|_| pty_write_frame(stream, PTY_FRAME_OUTPUT, text.as_bytes())),
+        None => writer.output(text.as_bytes()),
+    };
+
+    let bytes = bytes_streamed.clone();
+    let pump_writer = writer.clone();
+    let pump_watch = watch.clone();
+    let pump = std::thread::spawn(move || {
+        let mut buf = [0u8; 8192];
+        loop {
+            match output.read(&mut buf) {
+                // EIO on a pty once the last slave handle is closed.
+                Ok(0) | Err(_) => return true,
+                Ok(n) => {
+                    // Past max_output_bytes: send what fits and the marker, then keep draining
+                    // until the limit watcher has stopped the exec.
+                    let keep = pump_watch.output(n, limits.max_output_bytes);
+                    if keep > 0 && pump_writer.output(&buf[..keep]).is_err() {
+                        return false;
+                    }
+                    if keep < n
+                        && pump_watch.trip(LimitHit::Output)
+                        && pump_writer
+                            .output(LimitHit::Output.marker(&limits).as_bytes())
+                            .is_err()
+                    {
+                        return false;
+                    }
+                    bytes.fetch_add(keep as u64, Ordering::Relaxed);
+                }
+            }
+        }
+    });
+
+    // Short read timeout so the loop notices the command exiting while the client is idle,
+    // shorter still when it also has to write the queued output.
+    let poll = if queue.is_some() { 20 } else { 200 };
+    stream.set_deadlines(Some(Duration::from_millis(poll)), write_timeout);
+    let mut frames = PtyFrameReader::new();
+    let mut client_gone = false;
+    let mut marker_sent = false;
+    while !pump.is_finished() {
+        if flush_relay_queue(stream, queue.as_ref()).is_err() {
+            client_gone = true;
+            break;
+        }
+        if let Some(hit) = watch.hit().filter(|h| h.is_timeout() && !marker_sent) {
+            marker_sent = true;
+            let _ = write_marker(stream, &hit.marker(&limits));
+        }
+        match frames.read_frame(stream) {
+            Ok(Some((PTY_FRAME_STDIN, data))) => {
+                watch.input();
+                input.write(&data);
+            }
+            Ok(Some((PTY_FRAME_RESIZE, payload))) => {
+                if let Some((cols, rows)) = pty_parse_resize(&payload) {
+                    input.resize(cols, rows);
+                }
+            }
+            Ok(Some((PTY_FRAME_STDIN_EOF, _))) => input.eof(),
+            Ok(Some(_)) => {}
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) => {}
+            Ok(None) | Err(_) => {
+                client_gone = true;
+                break;
+            }
+        }
+    }
+    drop(input);
+
+    if client_gone {
+        let _ = child.kill();
+        let _ = child.wait();
+        // Unblock a pump waiting on the full queue.
+        drop(queue);
+        let _ = pump.join();
+        return Ok(RelayOutcome::Disconnected);
+    }
+    let client_ok =
+        pump.join().unwrap_or(false) && flush_relay_queue(stream, queue.as_ref()).is_ok();
+    let mut code = child.wait().ok().and_then(|s| s.code()).unwrap_or(1);
+    if let Some(hit) = watch.hit() {
+        // The exec ended before the loop saw the timeout: add its marker now.
+        if hit.is_timeout() && !marker_sent && client_ok {
+            let _ = write_marker(stream, &hit.marker(&limits));
+        }
+        code = hit.exit_code();
+    }
+    if !client_ok || pty_write_frame(stream, PTY_FRAME_EXIT, &code.to_be_bytes()).is_err() {
+        return Ok(RelayOutcome::Disconnected);
+    }
+    Ok(RelayOutcome::Exited(code))
+}
+
+/// Serve an upgraded exec: `docker exec -it` on a host PTY, or `docker exec -i` fed from the
+/// shim's stdin, relayed to the shim as frames.
+#[cfg(unix)]
+#[allow(clippy::too_many_arguments)]
+fn handle_upgraded_exec<S: ProxyStream>(
+    ctx: &ProxyCtx,
+    stream: &mut S,
+    name: &str,
+    exec_preview_args: &[String],
+    channel: ExecChannel,
+    term: Option<&str>,
+    exec_id: &str,
+    bytes_streamed: &Arc<std::sync::atomic::AtomicU64>,
+    (tool, kind): (&str, &str),
+    limits: ExecLimits,
+) -> Option<i32> {
+    let verbose = ctx.verbose;
+    let started = std::time::Instant::now();
+    let mut cmd = Command::new(&ctx.runtime);
+    let upgrade = match channel {
+        ExecChannel::Pty((cols, rows)) => {
+            if verbose {
+                log_compact(&format!(
+                    "aifo-coder: proxy exec: proto=pty size={cols}x{rows}"
+                ));
+            }
+            cmd.args(build_pty_exec_args(name, exec_preview_args, term));
+            PTY_UPGRADE
+        }
+        ExecChannel::Stdin => {
+            if verbose {
+                log_compact("aifo-coder: proxy exec: proto=stdin");
+            }
+            cmd.args(build_stdin_exec_args(name, exec_preview_args));
+            STDIN_UPGRADE
+        }
+    };
+    let upgrade = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: {upgrade}\r\nConnection: Upgrade\r\nX-Aifo-Exec-Id: {exec_id}\r\n\r\n"
+    );
+    // Same runtime/idle/output limits and INT/TERM/KILL escalation as v2 streaming.
+    let watch = Arc::new(ExecWatch::new());
+    watch_exec_limits(ctx, name, exec_id, limits, &watch);
+    let outcome = exec_relay(
+        stream,
+        cmd,
+        channel,
+        upgrade.as_bytes(),
+        bytes_streamed,
+        ctx.write_timeout,
+        &watch,
+        limits,
+    );
+    watch.finish();
+    match outcome {
+        Ok(RelayOutcome::Exited(code)) => {
+            log_request_result(verbose, tool, kind, code, &started);
+            Some(code)
+        }
+        Ok(RelayOutcome::Disconnected) => {
+            log_disconnect();
+            disconnect_terminate_exec_in_container(
+                &ctx.runtime,
+                name,
+                exec_id,
+                verbose,
+                ctx.agent_container.as_deref(),
+            );
+            None
+        }
+        Err(e) => {
+            log_request_result(verbose, tool, kind, 86, &started);
+            let msg = format!("aifo-coder proxy error: {e}\n");
+            respond_plain(stream, "500 Internal Server Error", 86, msg.as_bytes());
+            let _ = stream.flush();
+            Some(86)
+        }
+    }
+}
+
+/// Drop EXEC_ID from the registry and append its outcome to the audit log.
+fn finish_exec(
+    ctx: &ProxyCtx,
+    exec_registry: &ExecRegistry,
+    exec_id: &str,
+    cwd: &str,
+    exit: Option<i32>,
+) {
+    if let Some(entry) = exec_registry.remove(exec_id) {
+        ctx.audit.exec(exec_id, &entry, cwd, exit);
+    }
+}
+
+/// POST /token: a fresh token with the caller's grant (rotation, revoking the caller's token), or
+/// a subset of it given by `scope`/`kind` fields; `ttl_secs` may shorten the configured lifetime.
+/// Neither outlives the caller's token.
+fn issue_token<W: Write>(ctx: &ProxyCtx, w: &mut W, grant: &TokenClaims, body: &[u8]) {
+    let mut scopes: Vec<TokenScope> = Vec::new();
+    let mut kinds: Vec<String> = Vec::new();
+    let mut ttl_req: Option<u64> = None;
+    for (k, v) in http::parse_form_urlencoded(&String::from_utf8_lossy(body)) {
+        match k.as_str() {
+            "scope" => match TokenScope::parse(&v) {
+                Some(sc) if grant.allows(sc) => scopes.push(sc),
+                Some(_) => {
+                    let msg = format!("cannot grant scope {v}\n");
+                    respond_plain(w, "403 Forbidden", 86, msg.as_bytes());
+                    return;
+                }
+                None => {
+                    respond_plain(w, "400 Bad Request", 86, ERR_BAD_REQUEST);
+                    return;
+                }
+            },
+            "kind" if !SIDECAR_KINDS.contains(&v.as_str()) => {
+                respond_plain(w, "400 Bad Request", 86, ERR_BAD_REQUEST);
+                return;
+            }
+            "kind" if !grant.allows_kind(&v) => {
+                let msg = format!("cannot grant sidecar kind {v}\n");
+                respond_plain(w, "403 Forbidden", 86, msg.as_bytes());
+                return;
+            }
+            "kind" => kinds.push(v),
+            "ttl_secs" => match v.trim().parse::<u64>() {
+                Ok(n) => ttl_req = Some(n),
+                Err(_) => {
+                    respond_plain(w, "400 Bad Request", 86, ERR_BAD_REQUEST);
+                    return;
+                }
+            },
+            _ => {}
+        }
+    }
+    // Without fields this is a rotation: same grant, and the old token is revoked below.
+    let rotation = scopes.is_empty() && kinds.is_empty() && ttl_req.is_none();
+    if scopes.is_empty() {
+        scopes = grant.scopes.clone();
+    }
+    if kinds.is_empty() {
+        kinds = grant.kinds.clone();
+    }
+    let ttl = ctx.tokens.issued_ttl(ttl_req);
+    // Rotated and derived tokens never outlive the token they came from.
+    let token = ctx
+        .tokens
+        .issue_until(scopes.clone(), kinds.clone(), ttl, grant.exp);
+    if rotation {
+        // Shims sharing the cached token may be rotating it right now too.
+        ctx.tokens.revoke(grant, ROTATION_GRACE_SECS);
+    }
+    let exp = token_claims(&token).map_or(0, |c| c.exp);
+    let scope_names: Vec<&str> = scopes.iter().map(|s| s.as_str()).collect();
+    if ctx.verbose {
+        log_compact(&format!(
+            "aifo-coder: proxy token: issued scopes={} kinds={} ttl_secs={}",
+            scope_names.join(","),
+            kinds.join(","),
+            ttl
+        ));
+    }
+    let body = serde_json::json!({
+        "token": token,
+        "expires_at": exp,
+        "scopes": scope_names,
+        "kinds": kinds,
+    })
+    .to_string();
+    respond_json(w, body.as_bytes());
+}
+
+/// Answer an exec from the replay transcript: chunked with the recorded exit code as trailer,
+/// or buffered for v1 and upgrade requests; 404 when nothing was recorded for it.
+fn serve_replay<W: Write>(
+    w: &mut W,
+    replay: &Replay,
+    (tool, argv, cwd): (&str, &[String], &str),
+    exec_id: &str,
+    chunked: bool,
+    verbose: bool,
+) {
+    let shown = redact_argv_for_logs(argv);
+    let Some(rec) = replay.take(tool, &shown, cwd) else {
+        let mut words = vec![tool.to_string()];
+        words.extend(shown);
+        let msg = format!(
+            "aifo-coder: replay: no recorded exec for {} in {}\n",
+            shell_join(&words),
+            cwd
+        );
+        respond_plain(w, "404 Not Found", 86, msg.as_bytes());
+        return;
+    };
+    if verbose {
+        log_compact(&format!(
+            "aifo-coder: proxy replay: tool={} chunks={} exit={}",
+            tool,
+            rec.chunks.len(),
+            rec.exit_code
+        ));
+    }
+    if !chunked {
+        let body = rec.body();
+        let header = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\nX-Exit-Code: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            rec.exit_code,
+            body.len()
+        );
+        let _ = w.write_all(header.as_bytes());
+        let _ = w.write_all(&body);
+        let _ = w.flush();
+        return;
+    }
+    let started = std::time::Instant::now();
+    if respond_chunked_prelude(w, Some(exec_id)).is_err() {
+        return;
+    }
+    for (t_ms, data) in &rec.chunks {
+        std::thread::sleep(replay.delay(*t_ms, started.elapsed()));
+        if respond_chunked_write_chunk(w, data).is_err() {
+            return;
         }
-    });
-    let url = format!("http://127.0.0.1:{}/exec", port);
-    Ok((url, token, running, handle))
+    }
+    let _ = respond_chunked_trailer(w, rec.exit_code);
 }
 
 // Handle a single proxy connection
@@ -1045,14 +1859,13 @@ fn ensure_rust_toolchain_warm(
     warmed.insert(container.to_string());
 }
 
-fn handle_connection<S: Read + Write>(
+fn handle_connection<S: ProxyStream>(
     ctx: &ProxyCtx,
     stream: &mut S,
     tool_cache: &Arc<Mutex<HashMap<(String, String), bool>>>,
-    exec_registry: &Arc<Mutex<HashMap<String, String>>>,
+    exec_registry: &Arc<ExecRegistry>,
     recent_signals: &Arc<Mutex<HashMap<String, std::time::Instant>>>,
 ) {
-    let token: &str = &ctx.token;
     let session: &str = &ctx.session;
     let timeout_secs: u64 = ctx.timeout_secs;
     let verbose: bool = ctx.verbose;
@@ -1090,13 +1903,53 @@ fn handle_connection<S: Read + Write>(
     match endpoint {
         Some(http::Endpoint::Exec)
         | Some(http::Endpoint::Notifications)
-        | Some(http::Endpoint::Signal) => {
+        | Some(http::Endpoint::Signal)
+        | Some(http::Endpoint::Token) => {
             if req.method != http::Method::Post {
                 respond_plain(stream, "405 Method Not Allowed", 86, ERR_METHOD_NOT_ALLOWED);
                 let _ = stream.flush();
                 return;
             }
         }
+        Some(http::Endpoint::Health) | Some(http::Endpoint::Status) => {
+            if req.method != http::Method::Get {
+                respond_plain(stream, "405 Method Not Allowed", 86, ERR_METHOD_NOT_ALLOWED);
+                let _ = stream.flush();
+                return;
+            }
+            // Read-only: a valid bearer token with the status scope and, like every endpoint, a
+            // valid signature when present or required; X-Aifo-Proto is not required.
+            let authorized = req
+                .headers
+                .get("authorization")
+                .and_then(|v| auth::bearer_credential(v))
+                .and_then(|t| ctx.tokens.verify(t).ok())
+                .is_some_and(|grant| grant.allows(TokenScope::Status));
+            if !authorized {
+                respond_plain(stream, "401 Unauthorized", 86, ERR_UNAUTHORIZED);
+                let _ = stream.flush();
+                return;
+            }
+            if let Err(msg) = ctx.tokens.check_signature(&req) {
+                respond_plain(
+                    stream,
+                    "401 Unauthorized",
+                    86,
+                    format!("{msg}\n").as_bytes(),
+                );
+                let _ = stream.flush();
+                return;
+            }
+            let uptime = ctx.started.elapsed();
+            let body = if endpoint == Some(http::Endpoint::Health) {
+                status::health_json(uptime, exec_registry)
+            } else {
+                status::status_json(uptime, session, exec_registry)
+            };
+            respond_json(stream, body.as_bytes());
+            let _ = stream.flush();
+            return;
+        }
         None => {
             respond_plain(stream, "404 Not Found", 86, ERR_NOT_FOUND);
             let _ = stream.flush();
@@ -1105,7 +1958,59 @@ fn handle_connection<S: Read + Write>(
     }
 
     // Auth/proto centralized
-    let auth_res = auth::validate_auth_and_proto(&req.headers, token);
+    let auth_res = auth::validate_auth_and_proto(&req, &ctx.tokens);
+
+    // Expiry, signature and scope are final for every endpoint (except unauthenticated notify).
+    let notify_noauth = endpoint == Some(http::Endpoint::Notifications)
+        && std_env::var("AIFO_NOTIFICATIONS_NOAUTH").ok().as_deref() == Some("1");
+    if !notify_noauth {
+        let scope = match endpoint {
+            Some(http::Endpoint::Notifications) => TokenScope::Notify,
+            Some(http::Endpoint::Signal) => TokenScope::Signal,
+            Some(http::Endpoint::Token) => TokenScope::Token,
+            _ => TokenScope::Exec,
+        };
+        match &auth_res {
+            auth::AuthResult::Expired => {
+                respond_plain(stream, "401 Unauthorized", 86, b"token expired\n");
+                let _ = stream.flush();
+                return;
+            }
+            auth::AuthResult::BadSignature(msg) => {
+                respond_plain(
+                    stream,
+                    "401 Unauthorized",
+                    86,
+                    format!("{msg}\n").as_bytes(),
+                );
+                let _ = stream.flush();
+                return;
+            }
+            auth::AuthResult::Authorized { grant, .. } if !grant.allows(scope) => {
+                let msg = format!("token scope does not allow {}\n", scope.as_str());
+                respond_plain(stream, "403 Forbidden", 86, msg.as_bytes());
+                let _ = stream.flush();
+                return;
+            }
+            _ => {}
+        }
+    }
+
+    // /token: rotate the caller's token or derive a narrower one
+    if endpoint == Some(http::Endpoint::Token) {
+        match &auth_res {
+            auth::AuthResult::Authorized {
+                proto: auth::Proto::V2,
+                grant,
+            } => issue_token(ctx, stream, grant, &req.body),
+            auth::AuthResult::Authorized { .. } | auth::AuthResult::MissingOrInvalidProto => {
+                respond_plain(stream, "426 Upgrade Required", 86, ERR_UNSUPPORTED_PROTO);
+            }
+            _ => respond_plain(stream, "401 Unauthorized", 86, ERR_UNAUTHORIZED),
+        }
+        let _ = stream.flush();
+        return;
+    }
 
     // Extract incoming trace context (if any) for propagation into shim/tool execs.
     #[cfg(feature = "otel")]
@@ -1120,18 +2025,24 @@ fn handle_connection<S: Read + Write>(
     let mut tool = String::new();
     let mut cwd = "/workspace".to_string();
     let mut argv: Vec<String> = Vec::new();
+    let mut forwarded_env: Vec<(String, String)> = Vec::new();
     let mut notif_cmd: String = String::new();
     for (k, v) in req
         .query
         .iter()
         .cloned()
-        .chain(http::parse_form_urlencoded(&form).into_iter())
+        .chain(http::parse_form_urlencoded(&form))
     {
         let kl = k.to_ascii_lowercase();
         match kl.as_str() {
             "tool" => tool = v,
             "cwd" => cwd = v,
             "arg" => argv.push(v),
+            "env" => {
+                if let Some((name, value)) = v.split_once('=') {
+                    forwarded_env.push((name.to_string(), value.to_string()));
+                }
+            }
             "cmd" => notif_cmd = v,
             _ => {}
         }
@@ -1213,8 +2124,11 @@ fn handle_connection<S: Read + Write>(
                 .filter(|&v| v > 0)
                 .unwrap_or(if timeout_secs == 0 { 5 } else { timeout_secs });
             let started = std::time::Instant::now();
-            match notifications::notifications_handle_request(&notif_cmd, &argv, verbose, notif_to)
-            {
+            let result =
+                notifications::notifications_handle_request(&notif_cmd, &argv, verbose, notif_to);
+            ctx.audit
+                .notify(&notif_cmd, &argv, &result, started.elapsed());
+            match result {
                 Ok((status_code, body_out)) => {
                     log_request_result(verbose, &notif_cmd, "notify", status_code, &started);
                     // Tiny nudge to improve host-log vs agent-UI ordering
@@ -1259,7 +2173,7 @@ fn handle_connection<S: Read + Write>(
 
         // Auth/proto enforcement applies before caps/required-cmd in authenticated mode.
         match auth_res {
-            auth::AuthResult::Authorized { proto } => {
+            auth::AuthResult::Authorized { proto, .. } => {
                 if !matches!(proto, auth::Proto::V2) {
                     respond_plain(
                         stream,
@@ -1289,9 +2203,12 @@ fn handle_connection<S: Read + Write>(
                     .filter(|&v| v > 0)
                     .unwrap_or(if timeout_secs == 0 { 5 } else { timeout_secs });
                 let started = std::time::Instant::now();
-                match notifications::notifications_handle_request(
+                let result = notifications::notifications_handle_request(
                     &notif_cmd, &argv, verbose, notif_to,
-                ) {
+                );
+                ctx.audit
+                    .notify(&notif_cmd, &argv, &result, started.elapsed());
+                match result {
                     Ok((status_code, body_out)) => {
                         log_request_result(verbose, &notif_cmd, "notify", status_code, &started);
                         // Tiny nudge to improve host-log vs agent-UI ordering
@@ -1338,7 +2255,9 @@ fn handle_connection<S: Read + Write>(
                 let _ = stream.flush();
                 return;
             }
-            auth::AuthResult::MissingOrInvalidAuth => {
+            auth::AuthResult::MissingOrInvalidAuth
+            | auth::AuthResult::Expired
+            | auth::AuthResult::BadSignature(_) => {
                 respond_plain(stream, "401 Unauthorized", 86, ERR_UNAUTHORIZED);
                 let _ = stream.flush();
                 return;
@@ -1349,7 +2268,7 @@ fn handle_connection<S: Read + Write>(
     // /signal endpoint
     if matches!(endpoint, Some(http::Endpoint::Signal)) {
         match auth_res {
-            auth::AuthResult::Authorized { .. } => {
+            auth::AuthResult::Authorized { grant, .. } => {
                 // Parse form for exec_id and signal
                 let form = String::from_utf8_lossy(&req.body).to_string();
                 let mut exec_id = String::new();
@@ -1366,18 +2285,18 @@ fn handle_connection<S: Read + Write>(
                     let _ = stream.flush();
                     return;
                 }
-                let container = if let Some(name) = exec_registry
-                    .lock()
-                    .unwrap_or_else(|e| e.into_inner())
-                    .get(&exec_id)
-                    .cloned()
-                {
-                    name
-                } else {
+                let Some((container, kind)) = exec_registry.container(&exec_id) else {
                     respond_plain(stream, "404 Not Found", 86, ERR_NOT_FOUND);
                     let _ = stream.flush();
                     return;
                 };
+                // Like execs, signals only reach the sidecar kinds the token covers.
+                if !grant.allows_kind(&kind) {
+                    let msg = format!("token not valid for sidecar kind {kind}\n");
+                    respond_plain(stream, "403 Forbidden", 86, msg.as_bytes());
+                    let _ = stream.flush();
+                    return;
+                }
                 // Allow only a safe subset of signals
                 let sig = signal.to_ascii_uppercase();
                 let allowed = ["INT", "TERM", "HUP", "KILL"];
@@ -1397,6 +2316,7 @@ fn handle_connection<S: Read + Write>(
                     let mut rs = recent_signals.lock().unwrap_or_else(|e| e.into_inner());
                     rs.insert(exec_id.clone(), std::time::Instant::now());
                 }
+                exec_registry.record_signal(&exec_id, &sig);
                 kill_in_container(&ctx.runtime, &container, &exec_id, &sig, verbose);
                 // 204 No Content without exit code header
                 let _ = stream.write_all(
@@ -1410,7 +2330,9 @@ fn handle_connection<S: Read + Write>(
                 let _ = stream.flush();
                 return;
             }
-            auth::AuthResult::MissingOrInvalidAuth => {
+            auth::AuthResult::MissingOrInvalidAuth
+            | auth::AuthResult::Expired
+            | auth::AuthResult::BadSignature(_) => {
                 respond_plain(stream, "401 Unauthorized", 86, ERR_UNAUTHORIZED);
                 let _ = stream.flush();
                 return;
@@ -1432,7 +2354,9 @@ fn handle_connection<S: Read + Write>(
                 let _ = stream.flush();
                 return;
             }
-            auth::AuthResult::MissingOrInvalidAuth => {
+            auth::AuthResult::MissingOrInvalidAuth
+            | auth::AuthResult::Expired
+            | auth::AuthResult::BadSignature(_) => {
                 respond_plain(stream, "401 Unauthorized", 86, ERR_UNAUTHORIZED);
                 let _ = stream.flush();
                 return;
@@ -1445,20 +2369,21 @@ fn handle_connection<S: Read + Write>(
         }
     }
 
-    let (authorized, mut proto_v2) = match auth_res {
-        auth::AuthResult::Authorized { proto } => (true, matches!(proto, auth::Proto::V2)),
+    let (grant, mut proto_v2) = match auth_res {
+        auth::AuthResult::Authorized { proto, grant } => (grant, matches!(proto, auth::Proto::V2)),
         auth::AuthResult::MissingOrInvalidProto => {
             respond_plain(stream, "426 Upgrade Required", 86, ERR_UNSUPPORTED_PROTO);
             let _ = stream.flush();
             return;
         }
-        auth::AuthResult::MissingOrInvalidAuth => {
+        auth::AuthResult::MissingOrInvalidAuth
+        | auth::AuthResult::Expired
+        | auth::AuthResult::BadSignature(_) => {
             respond_plain(stream, "401 Unauthorized", 86, ERR_UNAUTHORIZED);
             let _ = stream.flush();
             return;
         }
     };
-    debug_assert!(authorized);
     // Env override: AIFO_PROXY_PROTO=1 forces buffered (v1), =2 forces streaming (v2)
     if let Ok(v) = std_env::var("AIFO_PROXY_PROTO") {
         match v.trim() {
@@ -1472,19 +2397,128 @@ fn handle_connection<S: Read + Write>(
         }
     }
 
+    // Replay mode: answer from the recorded transcript; nothing runs and no sidecar is needed.
+    if let Some(replay) = ctx.replay.as_deref() {
+        let chunked = proto_v2 && !req.headers.contains_key("upgrade");
+        serve_replay(
+            stream,
+            replay,
+            (&tool, &argv, &cwd),
+            &exec_id,
+            chunked,
+            verbose,
+        );
+        return;
+    }
+
     // Route to sidecar kind and enforce allowlist
     let selected_kind = {
         let mut cache = tool_cache.lock().unwrap_or_else(|e| e.into_inner());
         select_kind_for_tool(session, &tool, timeout_secs, &mut cache)
     };
     let kind = selected_kind.as_str();
+    if !grant.allows_kind(kind) {
+        let reason = format!("token not valid for sidecar kind {kind}");
+        ctx.audit.denied(
+            &tool,
+            &redact_argv_for_logs(&argv),
+            &cwd,
+            "token.kinds",
+            &reason,
+        );
+        respond_plain(
+            stream,
+            "403 Forbidden",
+            86,
+            format!("{reason}\n").as_bytes(),
+        );
+        let _ = stream.flush();
+        return;
+    }
     let allow = sidecar_allowlist(kind);
     if !allow.contains(&tool.as_str()) {
+        ctx.audit.denied(
+            &tool,
+            &redact_argv_for_logs(&argv),
+            &cwd,
+            &format!("allowlist.{kind}"),
+            "tool not allowed in this sidecar",
+        );
         respond_plain(stream, "403 Forbidden", 86, ERR_FORBIDDEN);
         let _ = stream.flush();
         return;
     }
 
+    // Declarative argument policy ([[proxy.policy.TOOL]]): deny, require flags, rewrite
+    let argv = match apply_tool_policy(&tool, tool_policy_rules(&tool), &argv) {
+        Ok(checked) => {
+            if verbose && checked != argv {
+                log_compact(&format!(
+                    "aifo-coder: proxy policy: tool={} argv rewritten to {}",
+                    tool,
+                    shell_join(&redact_argv_for_logs(&checked))
+                ));
+            }
+            checked
+        }
+        Err(denial) => {
+            if verbose {
+                log_compact(&format!(
+                    "aifo-coder: proxy policy: tool={} denied by {}",
+                    tool, denial.rule
+                ));
+            }
+            ctx.audit.denied(
+                &tool,
+                &redact_argv_for_logs(&argv),
+                &cwd,
+                &denial.rule,
+                &denial.reason,
+            );
+            respond_policy_denied(stream, &tool, &denial);
+            return;
+        }
+    };
+
+    // Approval gate: hold the request until the host user decides (the shim keeps waiting).
+    if let Some(rule) = policy_approval_rule(&tool, tool_policy_rules(&tool), &argv) {
+        let shown = shell_join(&redact_argv_for_logs(&argv));
+        let req = ApprovalRequest {
+            tool: &tool,
+            argv: &argv,
+            shown_argv: &shown,
+            cwd: &cwd,
+            rule: &rule,
+        };
+        if verbose {
+            log_compact(&format!(
+                "aifo-coder: proxy approval: tool={} waiting for the host user ({})",
+                tool, rule
+            ));
+        }
+        if let Err(reason) = ctx.approvals.check(&req) {
+            if verbose {
+                log_compact(&format!(
+                    "aifo-coder: proxy approval: tool={} refused: {}",
+                    tool, reason
+                ));
+            }
+            let denial = PolicyDenial {
+                rule,
+                reason: format!("not approved: {reason}"),
+            };
+            ctx.audit.denied(
+                &tool,
+                &redact_argv_for_logs(&argv),
+                &cwd,
+                &denial.rule,
+                &denial.reason,
+            );
+            respond_policy_denied(stream, &tool, &denial);
+            return;
+        }
+    }
+
     let name = sidecar::sidecar_container_name(kind, session);
 
     // Build OpenTelemetry span for this proxy request (after routing is known).
@@ -1522,7 +2556,7 @@ fn handle_connection<S: Read + Write>(
         ensure_rust_toolchain_warm(&ctx.runtime, &name, uidgid, verbose);
     }
 
-    let pwd = std::path::PathBuf::from(cwd);
+    let pwd = std::path::PathBuf::from(&cwd);
     // Optional hardening: detect unreadable /workspace for current uid:gid and surface a helpful hint
     if pwd.as_path() == std::path::Path::new("/workspace") {
         if let Some(hint) = workspace_access_hint(&ctx.runtime, &name, uidgid, verbose) {
@@ -1557,13 +2591,11 @@ fn handle_connection<S: Read + Write>(
     }
 
     // ExecId already determined above; reuse
-    // Register exec_id -> container
-    {
-        let mut er = exec_registry.lock().unwrap_or_else(|e| e.into_inner());
-        er.insert(exec_id.clone(), name.clone());
-    }
+    // Register exec_id -> container (also listed by GET /status)
+    let bytes_streamed =
+        exec_registry.insert(&exec_id, &name, kind, &tool, redact_argv_for_logs(&argv));
 
-    let exec_preview_args = sidecar::build_sidecar_exec_preview_with_exec_id(
+    let mut exec_preview_args = sidecar::build_sidecar_exec_preview_with_exec_id(
         &name,
         if cfg!(unix) { uidgid } else { None },
         &pwd,
@@ -1571,6 +2603,14 @@ fn handle_connection<S: Read + Write>(
         &full_args,
         Some(&exec_id),
     );
+    apply_forwarded_env(&mut exec_preview_args, forwarded_env, &tool, verbose);
+    let limits = resolve_exec_limits(ctx.exec_limits, tool_limit_rules(&tool), &tool, &argv);
+    if verbose && limits != ctx.exec_limits {
+        log_compact(&format!(
+            "aifo-coder: proxy limits: tool={} max_secs={} idle_secs={} max_output_bytes={}",
+            tool, limits.max_secs, limits.idle_secs, limits.max_output_bytes
+        ));
+    }
 
     if verbose {
         log_compact(&format!(
@@ -1579,6 +2619,29 @@ fn handle_connection<S: Read + Write>(
         ));
     }
 
+    // Interactive or stdin-fed exec: the shim asked to switch this connection to exec frames.
+    #[cfg(unix)]
+    if let Some(channel) = upgrade_requested(&req.headers) {
+        let exit = handle_upgraded_exec(
+            ctx,
+            stream,
+            &name,
+            &exec_preview_args,
+            channel,
+            req.headers.get("x-aifo-term").map(|s| s.as_str()),
+            &exec_id,
+            &bytes_streamed,
+            (&tool, kind),
+            limits,
+        );
+        finish_exec(ctx, exec_registry, &exec_id, &cwd, exit);
+        recent_signals
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&exec_id);
+        return;
+    }
+
     if proto_v2 {
         // Streaming (v2)
         if verbose {
@@ -1653,6 +2716,7 @@ fn handle_connection<S: Read + Write>(
                         .set_status(Status::error("aifo_coder_spawn_failed"));
                 }
                 log_request_result(verbose, &tool, kind, 86, &started);
+                finish_exec(ctx, exec_registry, &exec_id, &cwd, Some(86));
                 respond_plain(stream, "500 Internal Server Error", 86, &b);
                 let _ = stream.flush();
                 return;
@@ -1666,46 +2730,18 @@ fn handle_connection<S: Read + Write>(
         }
         // Streaming log boundary: when we just wrote payload to client, next log must re-anchor with '\r'
         let mut logger = StreamLogger::new(verbose);
+        let mut recording = ctx.transcript.as_deref().map(|t| {
+            t.begin(
+                &exec_id,
+                (&tool, &redact_argv_for_logs(&argv), &cwd),
+                (kind, &name),
+                exec_env_from_preview(&exec_preview_args),
+            )
+        });
 
-        // Optional max-runtime escalation watcher
-        let done = Arc::new(AtomicBool::new(false));
-        let timed_out = Arc::new(AtomicBool::new(false));
-        if timeout_secs > 0 {
-            let done_cl = done.clone();
-            let timed_out_cl = timed_out.clone();
-            let runtime_cl = ctx.runtime.clone();
-            let container_cl = name.clone();
-            let exec_id_cl = exec_id.clone();
-            let verbose_cl = verbose;
-            std::thread::spawn(move || {
-                let mut accum: u64 = 0;
-                for (sig, dur) in [("INT", timeout_secs), ("TERM", 5), ("KILL", 5)].into_iter() {
-                    let mut secs = dur;
-                    while secs > 0 {
-                        if done_cl.load(std::sync::atomic::Ordering::SeqCst) {
-                            return;
-                        }
-                        let step = secs.min(1);
-                        std::thread::sleep(Duration::from_secs(step));
-                        secs -= step;
-                        accum = accum.saturating_add(step);
-                    }
-                    if done_cl.load(std::sync::atomic::Ordering::SeqCst) {
-                        return;
-                    }
-                    if verbose_cl {
-                        eprintln!(
-                            "\raifo-coder: max-runtime: sending {} to exec_id={} after {}s",
-                            sig, exec_id_cl, accum
-                        );
-                    }
-                    if sig == "INT" {
-                        timed_out_cl.store(true, std::sync::atomic::Ordering::SeqCst);
-                    }
-                    kill_in_container(&runtime_cl, &container_cl, &exec_id_cl, sig, verbose_cl);
-                }
-            });
-        }
+        // Runtime/idle/output limits: INT, then TERM and KILL after grace periods
+        let watch = Arc::new(ExecWatch::new());
+        watch_exec_limits(ctx, &name, &exec_id, limits, &watch);
 
         // Defer sending prelude until the first chunk is available to avoid early client disconnects
         logger.boundary_log("aifo-coder: proxy stream: deferring prelude until first chunk");
@@ -1759,6 +2795,7 @@ fn handle_connection<S: Read + Write>(
             let preview_bytes_cl = preview_bytes;
             let drop_warned_cl = drop_warned.clone();
             let dropped_count_cl = dropped_count.clone();
+            let watch_cl = watch.clone();
             std::thread::spawn(move || {
                 let mut buf = [0u8; 8192];
                 loop {
@@ -1775,43 +2812,55 @@ fn handle_connection<S: Read + Write>(
                                     n, prev
                                 ));
                             }
-                            let chunk = buf[..n].to_vec();
-                            match txo {
-                                TxKind::Unbounded(ref s) => {
-                                    let _ = s.send(chunk);
-                                }
-                                TxKind::Bounded(ref s) => {
-                                    // Best-effort small backoff attempts before dropping under backpressure
-                                    let mut msg = chunk;
-                                    let mut attempts = 0usize;
-                                    loop {
-                                        match s.try_send(msg) {
-                                            Ok(()) => break,
-                                            Err(std::sync::mpsc::TrySendError::Full(c)) => {
-                                                attempts += 1;
-                                                if attempts <= 2 {
-                                                    std::thread::sleep(
-                                                        std::time::Duration::from_millis(5),
-                                                    );
-                                                    msg = c;
-                                                    continue;
-                                                }
-                                                if !drop_warned_cl
-                                                    .swap(true, std::sync::atomic::Ordering::SeqCst)
-                                                {
-                                                    log_compact(
-                                                        "aifo-coder: proxy stream: dropping output (backpressure)",
+                            // Past max_output_bytes: send what fits and the marker, then keep
+                            // draining until the limit watcher has stopped the exec.
+                            let keep = watch_cl.output(n, limits.max_output_bytes);
+                            let mut chunks = Vec::new();
+                            if keep > 0 {
+                                chunks.push(buf[..keep].to_vec());
+                            }
+                            if keep < n && watch_cl.trip(LimitHit::Output) {
+                                chunks.push(LimitHit::Output.marker(&limits).into_bytes());
+                            }
+                            for chunk in chunks {
+                                match txo {
+                                    TxKind::Unbounded(ref s) => {
+                                        let _ = s.send(chunk);
+                                    }
+                                    TxKind::Bounded(ref s) => {
+                                        // Best-effort small backoff attempts before dropping under backpressure
+                                        let mut msg = chunk;
+                                        let mut attempts = 0usize;
+                                        loop {
+                                            match s.try_send(msg) {
+                                                Ok(()) => break,
+                                                Err(std::sync::mpsc::TrySendError::Full(c)) => {
+                                                    attempts += 1;
+                                                    if attempts <= 2 {
+                                                        std::thread::sleep(
+                                                            std::time::Duration::from_millis(5),
+                                                        );
+                                                        msg = c;
+                                                        continue;
+                                                    }
+                                                    if !drop_warned_cl.swap(
+                                                        true,
+                                                        std::sync::atomic::Ordering::SeqCst,
+                                                    ) {
+                                                        log_compact(
+                                                            "aifo-coder: proxy stream: dropping output (backpressure)",
+                                                        );
+                                                    }
+                                                    let _ = dropped_count_cl.fetch_add(
+                                                        1,
+                                                        std::sync::atomic::Ordering::SeqCst,
                                                     );
+                                                    break;
                                                 }
-                                                let _ = dropped_count_cl.fetch_add(
-                                                    1,
-                                                    std::sync::atomic::Ordering::SeqCst,
-                                                );
-                                                break;
+                                                Err(
+                                                    std::sync::mpsc::TrySendError::Disconnected(_c),
+                                                ) => break,
                                             }
-                                            Err(std::sync::mpsc::TrySendError::Disconnected(
-                                                _c,
-                                            )) => break,
                                         }
                                     }
                                 }
@@ -1827,7 +2876,7 @@ fn handle_connection<S: Read + Write>(
         // Stream until EOF or write error
         #[allow(unused_assignments)]
         let mut write_failed = false;
-        let mut timeout_chunk_emitted = false;
+        let mut limit_marker_emitted = false;
         #[allow(unused_assignments)]
         let mut prelude_sent = false;
         let mut wrote_any_chunk = false;
@@ -1838,8 +2887,12 @@ fn handle_connection<S: Read + Write>(
         let mut total_bytes: usize = 0;
         let mut chunk_count_log: usize = 0;
         loop {
-            // Emit timeout chunk once when INT has been sent
-            if !timeout_chunk_emitted && timed_out.load(std::sync::atomic::Ordering::SeqCst) {
+            // Emit the timeout marker once when INT has been sent (the stdout reader queues the
+            // output-limit marker itself, after the last bytes that fit)
+            let timeout_hit = watch
+                .hit()
+                .filter(|h| h.is_timeout() && !limit_marker_emitted);
+            if let Some(hit) = timeout_hit {
                 // Ensure prelude is sent before emitting any chunk
                 if !prelude_sent {
                     if let Err(e) = respond_chunked_prelude(stream, Some(&exec_id)) {
@@ -1860,8 +2913,12 @@ fn handle_connection<S: Read + Write>(
                     let _ = prelude_sent;
                     logger.boundary_log("aifo-coder: proxy stream: prelude sent");
                 }
-                let _ = respond_chunked_write_chunk(stream, b"aifo-coder proxy timeout\n");
-                timeout_chunk_emitted = true;
+                let marker = hit.marker(&limits);
+                let _ = respond_chunked_write_chunk(stream, marker.as_bytes());
+                if let Some(r) = recording.as_mut() {
+                    r.chunk(marker.as_bytes());
+                }
+                limit_marker_emitted = true;
             }
             match rx.recv_timeout(Duration::from_millis(200)) {
                 Ok(chunk) => {
@@ -1919,8 +2976,12 @@ fn handle_connection<S: Read + Write>(
                         }
                         break;
                     } else {
+                        if let Some(r) = recording.as_mut() {
+                            r.chunk(&chunk);
+                        }
                         wrote_any_chunk = true;
                         total_bytes = total_bytes.saturating_add(chunk.len());
+                        bytes_streamed.fetch_add(chunk.len() as u64, Ordering::Relaxed);
                         chunk_count_log = chunk_count_log.saturating_add(1);
                         logger.set_boundary();
                     }
@@ -2018,10 +3079,10 @@ fn handle_connection<S: Read + Write>(
             let _ = child.kill();
             let _ = child.wait();
             // Mark watcher done and remove from registry
-            done.store(true, std::sync::atomic::Ordering::SeqCst);
-            {
-                let mut er = exec_registry.lock().unwrap_or_else(|e| e.into_inner());
-                let _ = er.remove(&exec_id);
+            watch.finish();
+            finish_exec(ctx, exec_registry, &exec_id, &cwd, None);
+            if let Some(r) = recording.take() {
+                r.finish(None);
             }
             {
                 let mut rs = recent_signals.lock().unwrap_or_else(|e| e.into_inner());
@@ -2030,20 +3091,25 @@ fn handle_connection<S: Read + Write>(
             return;
         }
 
-        // Emit timeout chunk late if not already sent
-        if !timeout_chunk_emitted && timed_out.load(std::sync::atomic::Ordering::SeqCst) {
-            let _ = respond_chunked_write_chunk(stream, b"aifo-coder proxy timeout\n");
-        }
-
         let mut code = child.wait().ok().and_then(|s| s.code()).unwrap_or(1);
-        if timeout_secs > 0 && timed_out.load(std::sync::atomic::Ordering::SeqCst) {
-            code = 124;
+        // Mark watcher done; a tripped limit reports its own exit code
+        watch.finish();
+        let hit = watch.hit();
+        if let Some(hit) = hit {
+            code = hit.exit_code();
         }
-        // Mark watcher done and remove from registry
-        done.store(true, std::sync::atomic::Ordering::SeqCst);
-        {
-            let mut er = exec_registry.lock().unwrap_or_else(|e| e.into_inner());
-            let _ = er.remove(&exec_id);
+        // Emit the timeout marker late if the exec ended before the loop saw the limit trip
+        let late_marker = hit
+            .filter(|h| h.is_timeout() && !limit_marker_emitted)
+            .map(|h| h.marker(&limits));
+        if let Some(r) = recording.as_mut() {
+            if let Some(m) = late_marker.as_deref() {
+                r.chunk(m.as_bytes());
+            }
+        }
+        finish_exec(ctx, exec_registry, &exec_id, &cwd, Some(code));
+        if let Some(r) = recording.take() {
+            r.finish(Some(code));
         }
         {
             let mut rs = recent_signals.lock().unwrap_or_else(|e| e.into_inner());
@@ -2056,8 +3122,7 @@ fn handle_connection<S: Read + Write>(
             use opentelemetry::trace::{Status, TraceContextExt};
             use tracing_opentelemetry::OpenTelemetrySpanExt;
             let secs = started.elapsed().as_secs_f64();
-            let result = if timeout_secs > 0 && timed_out.load(std::sync::atomic::Ordering::SeqCst)
-            {
+            let result = if hit.is_some_and(LimitHit::is_timeout) {
                 "timeout"
             } else if code == 0 {
                 "ok"
@@ -2103,6 +3168,9 @@ fn handle_connection<S: Read + Write>(
                 logger.boundary_log("aifo-coder: proxy stream: prelude sent");
             }
         }
+        if let Some(m) = late_marker.as_deref() {
+            let _ = respond_chunked_write_chunk(stream, m.as_bytes());
+        }
         if let Err(e) = respond_chunked_trailer(stream, code) {
             if !drop_warned.swap(true, std::sync::atomic::Ordering::SeqCst) {
                 logger.boundary_log("aifo-coder: proxy stream: dropping output (backpressure)");
@@ -2136,6 +3204,14 @@ fn handle_connection<S: Read + Write>(
         log_stderr_and_file("aifo-coder: proxy exec: proto=v1 (buffered)");
     }
     let started = std::time::Instant::now();
+    let recording = ctx.transcript.as_deref().map(|t| {
+        t.begin(
+            &exec_id,
+            (&tool, &redact_argv_for_logs(&argv), &cwd),
+            (kind, &name),
+            exec_env_from_preview(&exec_preview_args),
+        )
+    });
 
     let spawn_args = build_exec_args_with_wrapper(&name, &exec_preview_args, false);
     let mut cmd = Command::new(&ctx.runtime);
@@ -2158,51 +3234,19 @@ fn handle_connection<S: Read + Write>(
                     .set_status(Status::error("aifo_coder_spawn_failed"));
             }
             log_request_result(verbose, &tool, kind, 86, &started);
+            finish_exec(ctx, exec_registry, &exec_id, &cwd, Some(86));
+            if let Some(r) = recording {
+                r.finish(Some(86));
+            }
             respond_plain(stream, "500 Internal Server Error", 86, &b);
             let _ = stream.flush();
             return;
         }
     };
 
-    // Optional max-runtime escalation watcher
-    let done = Arc::new(AtomicBool::new(false));
-    let timed_out = Arc::new(AtomicBool::new(false));
-    if timeout_secs > 0 {
-        let done_cl = done.clone();
-        let timed_out_cl = timed_out.clone();
-        let runtime_cl = ctx.runtime.clone();
-        let container_cl = name.clone();
-        let exec_id_cl = exec_id.clone();
-        let verbose_cl = verbose;
-        std::thread::spawn(move || {
-            let mut accum: u64 = 0;
-            for (sig, dur) in [("INT", timeout_secs), ("TERM", 5), ("KILL", 5)].into_iter() {
-                let mut secs = dur;
-                while secs > 0 {
-                    if done_cl.load(std::sync::atomic::Ordering::SeqCst) {
-                        return;
-                    }
-                    let step = secs.min(1);
-                    std::thread::sleep(Duration::from_secs(step));
-                    secs -= step;
-                    accum = accum.saturating_add(step);
-                }
-                if done_cl.load(std::sync::atomic::Ordering::SeqCst) {
-                    return;
-                }
-                if verbose_cl {
-                    eprintln!(
-                        "\raifo-coder: max-runtime: sending {} to exec_id={} after {}s",
-                        sig, exec_id_cl, accum
-                    );
-                }
-                if sig == "INT" {
-                    timed_out_cl.store(true, std::sync::atomic::Ordering::SeqCst);
-                }
-                kill_in_container(&runtime_cl, &container_cl, &exec_id_cl, sig, verbose_cl);
-            }
-        });
-    }
+    // Runtime/idle/output limits: INT, then TERM and KILL after grace periods
+    let watch = Arc::new(ExecWatch::new());
+    watch_exec_limits(ctx, &name, &exec_id, limits, &watch);
 
     // Drain stdout/stderr concurrently
     let out_buf = std::sync::Arc::new(std::sync::Mutex::new(Vec::<u8>::new()));
@@ -2211,14 +3255,21 @@ fn handle_connection<S: Read + Write>(
     let mut h_out = None;
     if let Some(mut so) = child.stdout.take() {
         let out_buf_cl = out_buf.clone();
+        let bytes_cl = bytes_streamed.clone();
+        let watch_cl = watch.clone();
         h_out = Some(std::thread::spawn(move || {
             let mut buf = [0u8; 8192];
             loop {
                 match so.read(&mut buf) {
                     Ok(0) => break,
                     Ok(n) => {
+                        bytes_cl.fetch_add(n as u64, Ordering::Relaxed);
+                        let keep = watch_cl.output(n, limits.max_output_bytes);
+                        if keep < n {
+                            watch_cl.trip(LimitHit::Output);
+                        }
                         if let Ok(mut w) = out_buf_cl.lock() {
-                            w.extend_from_slice(&buf[..n]);
+                            w.extend_from_slice(&buf[..keep]);
                         }
                     }
                     Err(_) => break,
@@ -2230,14 +3281,21 @@ fn handle_connection<S: Read + Write>(
     let mut h_err = None;
     if let Some(mut se) = child.stderr.take() {
         let err_buf_cl = err_buf.clone();
+        let bytes_cl = bytes_streamed.clone();
+        let watch_cl = watch.clone();
         h_err = Some(std::thread::spawn(move || {
             let mut buf = [0u8; 8192];
             loop {
                 match se.read(&mut buf) {
                     Ok(0) => break,
                     Ok(n) => {
+                        bytes_cl.fetch_add(n as u64, Ordering::Relaxed);
+                        let keep = watch_cl.output(n, limits.max_output_bytes);
+                        if keep < n {
+                            watch_cl.trip(LimitHit::Output);
+                        }
                         if let Ok(mut w) = err_buf_cl.lock() {
-                            w.extend_from_slice(&buf[..n]);
+                            w.extend_from_slice(&buf[..keep]);
                         }
                     }
                     Err(_) => break,
@@ -2248,7 +3306,8 @@ fn handle_connection<S: Read + Write>(
 
     // Wait for child without hard timeout; join output threads
     let st = child.wait();
-    done.store(true, std::sync::atomic::Ordering::SeqCst);
+    watch.finish();
+    let hit = watch.hit();
     let final_code: i32 = st.ok().and_then(|s| s.code()).unwrap_or(1);
 
     if let Some(h) = h_out {
@@ -2267,24 +3326,31 @@ fn handle_connection<S: Read + Write>(
             body_bytes.extend_from_slice(&err);
         }
     }
-
-    {
-        let mut er = exec_registry.lock().unwrap_or_else(|e| e.into_inner());
-        let _ = er.remove(&exec_id);
+    // A tripped limit reports its own exit code; timeouts answer 504 with only the marker
+    let code = hit.map(LimitHit::exit_code).unwrap_or(final_code);
+    match hit {
+        Some(h) if h.is_timeout() => body_bytes = h.marker(&limits).into_bytes(),
+        Some(h) => body_bytes.extend_from_slice(h.marker(&limits).as_bytes()),
+        None => {}
     }
+
+    finish_exec(ctx, exec_registry, &exec_id, &cwd, Some(code));
     {
         let mut rs = recent_signals.lock().unwrap_or_else(|e| e.into_inner());
         let _ = rs.remove(&exec_id);
     }
-    let code = final_code;
     log_request_result(verbose, &tool, kind, code, &started);
+    if let Some(mut r) = recording {
+        r.chunk(&body_bytes);
+        r.finish(Some(code));
+    }
 
     #[cfg(feature = "otel")]
     {
         use opentelemetry::trace::{Status, TraceContextExt};
         use tracing_opentelemetry::OpenTelemetrySpanExt;
         let secs = started.elapsed().as_secs_f64();
-        let result = if timeout_secs > 0 && timed_out.load(std::sync::atomic::Ordering::SeqCst) {
+        let result = if hit.is_some_and(LimitHit::is_timeout) {
             "timeout"
         } else if code == 0 {
             "ok"
@@ -2302,9 +3368,8 @@ fn handle_connection<S: Read + Write>(
         crate::telemetry::metrics::record_proxy_exec_duration(&tool, secs);
         crate::telemetry::metrics::record_proxy_request(&tool, result);
     }
-    // If watcher timed out (on initial INT), map to 504 with exit code 124
-    if timeout_secs > 0 && timed_out.load(std::sync::atomic::Ordering::SeqCst) {
-        respond_plain(stream, "504 Gateway Timeout", 124, b"timeout\n");
+    if hit.is_some_and(LimitHit::is_timeout) {
+        respond_plain(stream, "504 Gateway Timeout", code, &body_bytes);
         let _ = stream.flush();
         return;
     }
@@ -2320,7 +3385,7 @@ fn handle_connection<S: Read + Write>(
 
 fn is_tool_allowed_any_sidecar(tool: &str) -> bool {
     let tl = tool.to_ascii_lowercase();
-    ["rust", "node", "python", "c-cpp", "go"]
+    SIDECAR_KINDS
         .iter()
         .any(|k| sidecar_allowlist(k).contains(&tl.as_str()))
 }
@@ -2419,4 +3484,357 @@ mod tests {
             script
         );
     }
+
+    #[test]
+    fn test_build_pty_exec_args_allocates_tty_and_forwards_sane_term() {
+        let container = "tc-container";
+        let preview: Vec<String> = vec![
+            "docker".into(),
+            "exec".into(),
+            "-w".into(),
+            "/workspace".into(),
+            container.into(),
+            "python3".into(),
+        ];
+        let out = build_pty_exec_args(container, &preview, Some("xterm-256color"));
+        assert_eq!(&out[..5], ["exec", "-e", "TERM=xterm-256color", "-i", "-t"]);
+        let script = &out[out.iter().position(|s| s == "-c").unwrap() + 1];
+        assert!(
+            script.contains(r#"printf "%s\n" "$$" > "$d/pgid""#),
+            "{script}"
+        );
+        assert!(!script.contains("setsid"), "{script}");
+        assert_eq!(out.last().map(String::as_str), Some("python3"));
+
+        let out = build_pty_exec_args(container, &preview, Some("xterm; rm -rf /"));
+        assert!(!out.iter().any(|s| s.starts_with("TERM=")), "{out:?}");
+    }
+
+    #[test]
+    fn test_apply_forwarded_env_adds_filtered_vars_before_container() {
+        let container = "tc-container";
+        let mut preview: Vec<String> = vec![
+            "docker".into(),
+            "exec".into(),
+            "-e".into(),
+            "RUSTFLAGS=-Clinker=clang".into(),
+            container.into(),
+            "cargo".into(),
+            "build".into(),
+        ];
+        let requested = vec![
+            ("RUST_LOG".to_string(), "debug".to_string()),
+            ("RUSTFLAGS".to_string(), "-g".to_string()),
+            ("GITHUB_TOKEN".to_string(), "ghp_x".to_string()),
+            ("PATH".to_string(), "/evil".to_string()),
+        ];
+        apply_forwarded_env(&mut preview, requested, "cargo", false);
+        assert_eq!(
+            preview,
+            [
+                "docker",
+                "exec",
+                "-e",
+                "RUST_LOG=debug",
+                "-e",
+                "RUSTFLAGS=-Clinker=clang",
+                container,
+                "cargo",
+                "build"
+            ]
+        );
+        let (prefix, user) = split_exec_preview(container, &preview);
+        assert!(prefix.contains(&"RUST_LOG=debug".to_string()), "{prefix:?}");
+        assert_eq!(user, ["cargo", "build"]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_build_stdin_exec_args_keeps_stdin_for_backgrounded_command() {
+        let container = "tc-container";
+        let preview: Vec<String> = vec![
+            "docker".into(),
+            "exec".into(),
+            container.into(),
+            "tr".into(),
+            "a-z".into(),
+            "A-Z".into(),
+        ];
+        let out = build_stdin_exec_args(container, &preview);
+        assert_eq!(&out[..2], ["exec", "-i"]);
+        assert!(!out.iter().any(|a| a == "-t"), "{out:?}");
+
+        // Run the wrapper locally: without the fd 3 hand-off the backgrounded command would read
+        // /dev/null instead of the piped input.
+        let td = tempfile::tempdir().expect("tmpdir");
+        let sh_at = out.iter().position(|a| a == "sh").unwrap();
+        let mut child = Command::new("sh")
+            .args(&out[sh_at + 1..])
+            .env("HOME", td.path())
+            .env("AIFO_EXEC_ID", "stdin-test")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("spawn wrapper");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(b"piped input\n")
+            .unwrap();
+        let res = child.wait_with_output().expect("wait wrapper");
+        assert_eq!(String::from_utf8_lossy(&res.stdout), "PIPED INPUT\n");
+        assert!(!td.path().join(".aifo-exec/stdin-test").exists());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_stdin_relay_pipes_input_and_propagates_eof() {
+        use std::os::unix::net::UnixStream;
+        let (mut server, mut client) = UnixStream::pair().expect("socketpair");
+        let bytes = Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let relay = std::thread::spawn(move || {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c")
+                .arg("test -t 0 || echo no-tty; tr a-z A-Z; echo eof-seen; exit 5");
+            exec_relay(
+                &mut server,
+                cmd,
+                ExecChannel::Stdin,
+                b"UPGRADED\n",
+                &bytes,
+                None,
+                &Arc::new(ExecWatch::new()),
+                ExecLimits::default(),
+            )
+        });
+
+        let mut hdr = [0u8; 9];
+        client.read_exact(&mut hdr).unwrap();
+        assert_eq!(&hdr, b"UPGRADED\n");
+        pty_write_frame(&mut client, PTY_FRAME_STDIN, b"first ").unwrap();
+        pty_write_frame(
+            &mut client,
+            PTY_FRAME_RESIZE,
+            &crate::pty_resize_payload(90, 20),
+        )
+        .unwrap();
+        pty_write_frame(&mut client, PTY_FRAME_STDIN, b"second\n").unwrap();
+        pty_write_frame(&mut client, PTY_FRAME_STDIN_EOF, b"").unwrap();
+
+        client
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let mut frames = PtyFrameReader::new();
+        let mut output = Vec::new();
+        let mut exit = None;
+        while let Some((kind, payload)) = frames.read_frame(&mut client).unwrap() {
+            match kind {
+                PTY_FRAME_OUTPUT => output.extend_from_slice(&payload),
+                PTY_FRAME_EXIT => {
+                    exit = Some(i32::from_be_bytes(payload[..4].try_into().unwrap()));
+                    break;
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(
+            String::from_utf8_lossy(&output),
+            "no-tty\nFIRST SECOND\neof-seen\n"
+        );
+        assert_eq!(exit, Some(5));
+        assert_eq!(relay.join().unwrap().unwrap(), RelayOutcome::Exited(5));
+    }
+
+    /// Connection without a second handle, like TLS.
+    #[cfg(target_os = "linux")]
+    struct Unsplittable(std::os::unix::net::UnixStream);
+
+    #[cfg(target_os = "linux")]
+    impl Read for Unsplittable {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    impl Write for Unsplittable {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            self.0.flush()
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    impl ProxyStream for Unsplittable {
+        fn set_deadlines(&self, read: Option<Duration>, write: Option<Duration>) {
+            self.0.set_deadlines(read, write);
+        }
+
+        fn try_clone_stream(&self) -> io::Result<Self> {
+            Err(io::Error::from(io::ErrorKind::Unsupported))
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_stdin_relay_over_a_single_stream() {
+        use std::os::unix::net::UnixStream;
+        let (server, mut client) = UnixStream::pair().expect("socketpair");
+        let bytes = Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let relay = std::thread::spawn(move || {
+            let mut cmd = Command::new("sh");
+            // More output than the queue holds while the client is still sending input.
+            cmd.arg("-c")
+                .arg("yes | head -c 1000000; tr a-z A-Z; exit 7");
+            exec_relay(
+                &mut Unsplittable(server),
+                cmd,
+                ExecChannel::Stdin,
+                b"UPGRADED\n",
+                &bytes,
+                None,
+                &Arc::new(ExecWatch::new()),
+                ExecLimits::default(),
+            )
+        });
+
+        let mut hdr = [0u8; 9];
+        client.read_exact(&mut hdr).unwrap();
+        assert_eq!(&hdr, b"UPGRADED\n");
+        pty_write_frame(&mut client, PTY_FRAME_STDIN, b"tail\n").unwrap();
+        pty_write_frame(&mut client, PTY_FRAME_STDIN_EOF, b"").unwrap();
+
+        client
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let mut frames = PtyFrameReader::new();
+        let mut output = Vec::new();
+        let mut exit = None;
+        while let Some((kind, payload)) = frames.read_frame(&mut client).unwrap() {
+            match kind {
+                PTY_FRAME_OUTPUT => output.extend_from_slice(&payload),
+                PTY_FRAME_EXIT => {
+                    exit = Some(i32::from_be_bytes(payload[..4].try_into().unwrap()));
+                    break;
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(output.len(), 1_000_005);
+        assert!(output.ends_with(b"y\nTAIL\n"));
+        assert_eq!(exit, Some(7));
+        assert_eq!(relay.join().unwrap().unwrap(), RelayOutcome::Exited(7));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_stdin_relay_truncates_output_at_the_limit() {
+        use std::os::unix::net::UnixStream;
+        let (mut server, mut client) = UnixStream::pair().expect("socketpair");
+        let bytes = Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let limits = ExecLimits {
+            max_output_bytes: 100,
+            ..ExecLimits::default()
+        };
+        let relay = std::thread::spawn(move || {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c").arg("yes | head -c 5000");
+            exec_relay(
+                &mut server,
+                cmd,
+                ExecChannel::Stdin,
+                b"UPGRADED\n",
+                &bytes,
+                None,
+                &Arc::new(ExecWatch::new()),
+                limits,
+            )
+        });
+
+        let mut hdr = [0u8; 9];
+        client.read_exact(&mut hdr).unwrap();
+        pty_write_frame(&mut client, PTY_FRAME_STDIN_EOF, b"").unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let mut frames = PtyFrameReader::new();
+        let mut output = Vec::new();
+        let mut exit = None;
+        while let Some((kind, payload)) = frames.read_frame(&mut client).unwrap() {
+            match kind {
+                PTY_FRAME_OUTPUT => output.extend_from_slice(&payload),
+                PTY_FRAME_EXIT => {
+                    exit = Some(i32::from_be_bytes(payload[..4].try_into().unwrap()));
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let text = String::from_utf8_lossy(&output);
+        assert_eq!(
+            text,
+            format!("{}{}", "y\n".repeat(50), LimitHit::Output.marker(&limits))
+        );
+        assert_eq!(exit, Some(crate::toolchain::limits::EXIT_OUTPUT_LIMIT));
+        assert_eq!(
+            relay.join().unwrap().unwrap(),
+            RelayOutcome::Exited(crate::toolchain::limits::EXIT_OUTPUT_LIMIT)
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_pty_relay_runs_command_on_a_terminal() {
+        use std::os::unix::net::UnixStream;
+        let (mut server, mut client) = UnixStream::pair().expect("socketpair");
+        let bytes = Arc::new(std::sync::atomic::AtomicU64::new(0));
+        let bytes_cl = bytes.clone();
+        let relay = std::thread::spawn(move || {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c")
+                .arg(r#"stty size; test -t 0 && echo tty-ok; read l; echo "got:$l"; exit 3"#);
+            exec_relay(
+                &mut server,
+                cmd,
+                ExecChannel::Pty((100, 30)),
+                b"UPGRADED\n",
+                &bytes_cl,
+                None,
+                &Arc::new(ExecWatch::new()),
+                ExecLimits::default(),
+            )
+        });
+
+        let mut hdr = [0u8; 9];
+        client.read_exact(&mut hdr).unwrap();
+        assert_eq!(&hdr, b"UPGRADED\n");
+        pty_write_frame(&mut client, PTY_FRAME_STDIN, b"hello\r").unwrap();
+
+        client
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let mut frames = PtyFrameReader::new();
+        let mut output = Vec::new();
+        let mut exit = None;
+        while let Some((kind, payload)) = frames.read_frame(&mut client).unwrap() {
+            match kind {
+                PTY_FRAME_OUTPUT => output.extend_from_slice(&payload),
+                PTY_FRAME_EXIT => {
+                    exit = Some(i32::from_be_bytes(payload[..4].try_into().unwrap()));
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let text = String::from_utf8_lossy(&output);
+        assert!(text.contains("30 100"), "{text}");
+        assert!(text.contains("tty-ok"), "{text}");
+        assert!(text.contains("got:hello"), "{text}");
+        assert_eq!(exit, Some(3));
+        assert_eq!(relay.join().unwrap().unwrap(), RelayOutcome::Exited(3));
+        assert!(bytes.load(Ordering::Relaxed) >= output.len() as u64);
+    }
 }
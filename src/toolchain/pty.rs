@@ -0,0 +1,192 @@
+/*!
+Interactive (PTY) exec protocol: framing shared by the shim and the proxy.
+
+A shim whose stdin and stdout are terminals sends the usual POST /exec with
+`Connection: Upgrade`, `Upgrade: aifo-pty` and `X-Aifo-Tty-Size: <cols>x<rows>`. A proxy that
+can allocate a pseudo-terminal answers `101 Switching Protocols` and both sides then exchange
+frames on the same connection: one type byte, a big-endian u32 payload length, the payload.
+
+- shim → proxy: PTY_FRAME_STDIN (raw terminal input), PTY_FRAME_RESIZE (cols u16, rows u16),
+  PTY_FRAME_STDIN_EOF.
+- proxy → shim: PTY_FRAME_OUTPUT (terminal output), PTY_FRAME_EXIT (exit code, i32) as the last
+  frame.
+
+A shim whose stdin is redirected from a pipe or file (and not a terminal) uses the same frames
+with `Upgrade: aifo-stdin`: the proxy runs `docker exec -i` on plain pipes, PTY_FRAME_STDIN carries
+the piped bytes and PTY_FRAME_STDIN_EOF closes the command's stdin. Resize frames are ignored.
+
+Any other response means the proxy did not switch; it is a regular v1/v2 response.
+*/
+
+use std::io::{self, Read, Write};
+
+/// Upgrade token negotiated for interactive execs.
+pub const PTY_UPGRADE: &str = "aifo-pty";
+/// Upgrade token negotiated for execs fed from the shim's redirected stdin.
+pub const STDIN_UPGRADE: &str = "aifo-stdin";
+/// Request header carrying the initial terminal size as `<cols>x<rows>`.
+pub const PTY_SIZE_HEADER: &str = "X-Aifo-Tty-Size";
+
+pub const PTY_FRAME_STDIN: u8 = b'i';
+pub const PTY_FRAME_RESIZE: u8 = b'w';
+pub const PTY_FRAME_STDIN_EOF: u8 = b'e';
+pub const PTY_FRAME_OUTPUT: u8 = b'o';
+pub const PTY_FRAME_EXIT: u8 = b'x';
+
+/// Largest payload accepted from the peer.
+pub const PTY_MAX_FRAME: usize = 1024 * 1024;
+
+/// Write one frame.
+pub fn pty_write_frame<W: Write + ?Sized>(w: &mut W, kind: u8, payload: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(payload.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "pty frame too large"))?;
+    let mut frame = Vec::with_capacity(5 + payload.len());
+    frame.push(kind);
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(payload);
+    w.write_all(&frame)?;
+    w.flush()
+}
+
+/// Payload of a resize frame.
+pub fn pty_resize_payload(cols: u16, rows: u16) -> [u8; 4] {
+    let c = cols.to_be_bytes();
+    let r = rows.to_be_bytes();
+    [c[0], c[1], r[0], r[1]]
+}
+
+/// Parse a resize frame payload into (cols, rows).
+pub fn pty_parse_resize(payload: &[u8]) -> Option<(u16, u16)> {
+    if payload.len() != 4 {
+        return None;
+    }
+    let cols = u16::from_be_bytes([payload[0], payload[1]]);
+    let rows = u16::from_be_bytes([payload[2], payload[3]]);
+    (cols > 0 && rows > 0).then_some((cols, rows))
+}
+
+/// Parse an `X-Aifo-Tty-Size` value (`<cols>x<rows>`).
+pub fn pty_parse_size_header(value: &str) -> Option<(u16, u16)> {
+    let (c, r) = value.trim().split_once(['x', 'X'])?;
+    let cols = c.trim().parse::<u16>().ok()?;
+    let rows = r.trim().parse::<u16>().ok()?;
+    (cols > 0 && rows > 0).then_some((cols, rows))
+}
+
+/// Incremental frame decoder that keeps partial frames across read timeouts.
+#[derive(Debug, Default)]
+pub struct PtyFrameReader {
+    buf: Vec<u8>,
+}
+
+impl PtyFrameReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Seed the decoder with bytes already read past the HTTP headers.
+    pub fn with_initial(bytes: &[u8]) -> Self {
+        PtyFrameReader {
+            buf: bytes.to_vec(),
+        }
+    }
+
+    fn take_frame(&mut self) -> io::Result<Option<(u8, Vec<u8>)>> {
+        if self.buf.len() < 5 {
+            return Ok(None);
+        }
+        let len = u32::from_be_bytes([self.buf[1], self.buf[2], self.buf[3], self.buf[4]]) as usize;
+        if len > PTY_MAX_FRAME {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "pty frame exceeds size limit",
+            ));
+        }
+        if self.buf.len() < 5 + len {
+            return Ok(None);
+        }
+        let kind = self.buf[0];
+        let payload = self.buf[5..5 + len].to_vec();
+        self.buf.drain(..5 + len);
+        Ok(Some((kind, payload)))
+    }
+
+    /// Read the next frame; Ok(None) on a clean EOF between frames.
+    ///
+    /// Read timeouts surface as errors (WouldBlock/TimedOut) without losing buffered bytes, so
+    /// callers can poll other work and call again.
+    pub fn read_frame<R: Read + ?Sized>(&mut self, r: &mut R) -> io::Result<Option<(u8, Vec<u8>)>> {
+        let mut tmp = [0u8; 8192];
+        loop {
+            if let Some(frame) = self.take_frame()? {
+                return Ok(Some(frame));
+            }
+            match r.read(&mut tmp) {
+                Ok(0) if self.buf.is_empty() => return Ok(None),
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "connection closed inside a pty frame",
+                    ))
+                }
+                Ok(n) => self.buf.extend_from_slice(&tmp[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pty_frames_roundtrip_across_partial_reads() {
+        let mut wire = Vec::new();
+        pty_write_frame(&mut wire, PTY_FRAME_STDIN, b"ls\r").unwrap();
+        pty_write_frame(&mut wire, PTY_FRAME_RESIZE, &pty_resize_payload(120, 40)).unwrap();
+        pty_write_frame(&mut wire, PTY_FRAME_STDIN_EOF, b"").unwrap();
+
+        // Deliver one byte per read to exercise reassembly.
+        struct Trickle(Vec<u8>, usize);
+        impl Read for Trickle {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.1 >= self.0.len() {
+                    return Ok(0);
+                }
+                buf[0] = self.0[self.1];
+                self.1 += 1;
+                Ok(1)
+            }
+        }
+        let mut src = Trickle(wire, 0);
+        let mut reader = PtyFrameReader::new();
+        assert_eq!(
+            reader.read_frame(&mut src).unwrap(),
+            Some((PTY_FRAME_STDIN, b"ls\r".to_vec()))
+        );
+        let (kind, payload) = reader.read_frame(&mut src).unwrap().unwrap();
+        assert_eq!(kind, PTY_FRAME_RESIZE);
+        assert_eq!(pty_parse_resize(&payload), Some((120, 40)));
+        assert_eq!(
+            reader.read_frame(&mut src).unwrap(),
+            Some((PTY_FRAME_STDIN_EOF, Vec::new()))
+        );
+        assert_eq!(reader.read_frame(&mut src).unwrap(), None);
+    }
+
+    #[test]
+    fn test_pty_size_header_and_oversized_frame() {
+        assert_eq!(pty_parse_size_header("80x24"), Some((80, 24)));
+        assert_eq!(pty_parse_size_header(" 200X50 "), Some((200, 50)));
+        assert_eq!(pty_parse_size_header("0x24"), None);
+        assert_eq!(pty_parse_size_header("wide"), None);
+
+        let mut bad = vec![PTY_FRAME_STDIN];
+        bad.extend_from_slice(&((PTY_MAX_FRAME as u32) + 1).to_be_bytes());
+        let mut reader = PtyFrameReader::with_initial(&bad);
+        let err = reader.read_frame(&mut io::empty()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -9,7 +9,6 @@ use std::env as std_env;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
 use std::time::Duration;
 
 #[cfg(feature = "otel")]
@@ -20,7 +19,10 @@ use nix::unistd::{getgid, getuid};
 
 use crate::apparmor::{desired_apparmor_profile, docker_supports_apparmor};
 use crate::ToolchainError;
-use crate::{container_runtime_path, shell_join, ShellScript};
+use crate::{
+    container_runtime, container_runtime_kind, runtime_for_program, shell_join, ContainerRuntime,
+    RuntimeIo, ShellScript,
+};
 
 use super::env::{
     apply_passthrough_envs, apply_rust_common_env, apply_rust_linker_flags_if_set, push_env,
@@ -97,6 +99,23 @@ pub fn set_session_network_env(name: &str, managed: bool, create: bool, source:
     );
 }
 
+/// Mark the session network as internal: created with `--internal`, so it has no route out.
+pub fn set_session_network_internal(internal: bool) {
+    std_env::set_var(
+        "AIFO_SESSION_NETWORK_INTERNAL",
+        if internal { "1" } else { "0" },
+    );
+}
+
+/// True when NAME is the session network and it must be internal (egress allowlist).
+fn session_network_is_internal(name: &str) -> bool {
+    std_env::var("AIFO_SESSION_NETWORK_INTERNAL")
+        .ok()
+        .as_deref()
+        == Some("1")
+        && std_env::var("AIFO_SESSION_NETWORK").ok().as_deref() == Some(name)
+}
+
 #[cfg_attr(
     feature = "otel",
     instrument(
@@ -106,51 +125,41 @@ pub fn set_session_network_env(name: &str, managed: bool, create: bool, source:
     )
 )]
 pub fn ensure_network_exists(runtime: &Path, name: &str, verbose: bool) -> bool {
+    ensure_network_exists_with(runtime_for_program(runtime).as_ref(), name, verbose)
+}
+
+/// Create network NAME through RT unless it already exists; true when it exists afterwards.
+pub fn ensure_network_exists_with(rt: &dyn ContainerRuntime, name: &str, verbose: bool) -> bool {
     let use_err = crate::color_enabled_stderr();
     // Fast path: already exists
-    let exists = Command::new(runtime)
-        .arg("network")
-        .arg("inspect")
-        .arg(name)
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-        .map(|s| s.success())
-        .unwrap_or(false);
-    if exists {
+    if rt.network_exists(name) {
         return true;
     }
 
     // Create the network (best-effort)
+    let internal = session_network_is_internal(name);
     if verbose {
-        let args = vec![
+        let mut args = vec![
             "docker".to_string(),
             "network".to_string(),
             "create".to_string(),
-            name.to_string(),
         ];
+        if internal {
+            args.push("--internal".to_string());
+        }
+        args.push(name.to_string());
         let preview = crate::preview_from_args(&args);
         crate::log_info_stderr(use_err, &format!("aifo-coder: docker: {}", preview));
     }
-    let mut cmd = Command::new(runtime);
-    cmd.arg("network").arg("create").arg(name);
-    if !verbose {
-        cmd.stdout(Stdio::null()).stderr(Stdio::null());
+    if internal {
+        let _ = rt.network_create_internal(name, quiet_unless(verbose));
+    } else {
+        let _ = rt.network_create(name, quiet_unless(verbose));
     }
-    let _ = cmd.status();
 
     // Verify with brief retries to absorb races between concurrent creators
     for _ in 0..20 {
-        let ok = Command::new(runtime)
-            .arg("network")
-            .arg("inspect")
-            .arg(name)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .map(|s| s.success())
-            .unwrap_or(false);
-        if ok {
+        if rt.network_exists(name) {
             return true;
         }
         std::thread::sleep(Duration::from_millis(50));
@@ -158,35 +167,21 @@ pub fn ensure_network_exists(runtime: &Path, name: &str, verbose: bool) -> bool
     false
 }
 
+/// Remove network NAME through RT when it exists (best-effort).
 #[cfg_attr(
     feature = "otel",
     instrument(
         level = "debug",
-        skip(runtime),
+        skip(rt),
         fields(aifo_coder_network = %name, aifo_coder_verbose = %verbose)
     )
 )]
-pub(crate) fn remove_network(runtime: &Path, name: &str, verbose: bool) {
+pub fn remove_network_with(rt: &dyn ContainerRuntime, name: &str, verbose: bool) {
     let use_err = crate::color_enabled_stderr();
     // Only attempt removal if network exists to avoid noisy errors
-    let exists = Command::new(runtime)
-        .arg("network")
-        .arg("inspect")
-        .arg(name)
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-        .map(|s| s.success())
-        .unwrap_or(false);
-    if !exists {
+    if !rt.network_exists(name) {
         return;
     }
-
-    let mut cmd = Command::new(runtime);
-    cmd.arg("network").arg("rm").arg(name);
-    if !verbose {
-        cmd.stdout(Stdio::null()).stderr(Stdio::null());
-    }
     if verbose {
         let args = vec![
             "docker".to_string(),
@@ -197,7 +192,16 @@ pub(crate) fn remove_network(runtime: &Path, name: &str, verbose: bool) {
         let preview = crate::preview_from_args(&args);
         crate::log_info_stderr(use_err, &format!("aifo-coder: docker: {}", preview));
     }
-    let _ = cmd.status();
+    let _ = rt.network_remove(name, quiet_unless(verbose));
+}
+
+/// Inherit engine output in verbose mode, otherwise discard it.
+fn quiet_unless(verbose: bool) -> RuntimeIo {
+    if verbose {
+        RuntimeIo::Inherit
+    } else {
+        RuntimeIo::Quiet
+    }
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -225,9 +229,9 @@ pub fn build_sidecar_run_preview_with_overrides(
         args.push(net.to_string());
     }
     if let Some((uid, gid)) = uidgid {
-        args.push("--user".to_string());
-        args.push(format!("{uid}:{gid}"));
+        args.extend(container_runtime_kind().user_flags(uid, gid));
     }
+    args.extend(crate::toolchain_resource_limits(kind).run_flags());
     // mounts
     push_mount(&mut args, &format!("{}:/workspace", pwd.display()));
 
@@ -508,6 +512,12 @@ pub fn build_sidecar_run_preview_with_overrides(
             args.push(format!("apparmor={profile}"));
         }
     }
+    args.extend(crate::hardened_run_flags(uidgid));
+    args.extend(crate::seccomp_run_flags(Some(kind)));
+    // Egress allowlist: package downloads go through the host-side proxy.
+    for (k, v) in crate::egress_proxy_env() {
+        push_env(&mut args, &k, &v);
+    }
 
     // Linux connectivity for sidecars (optional; typically only the agent needs host-gateway).
     // Enable via AIFO_TOOLEEXEC_ADD_HOST=1 for troubleshooting if required.
@@ -526,12 +536,11 @@ pub fn build_sidecar_run_preview_with_overrides(
 }
 
 fn node_overlay_state_and_guard(
-    runtime: &Path,
+    rt: &dyn ContainerRuntime,
     container_name: &str,
     verbose: bool,
 ) -> io::Result<bool> {
     let use_err = crate::color_enabled_stderr();
-    let mut cmd = Command::new(runtime);
     let script = ShellScript::new()
         .extend([
             "set -e".to_string(),
@@ -548,18 +557,13 @@ fn node_overlay_state_and_guard(
     if let Err(msg) = crate::validate_docker_exec_sh_login_script(&script) {
         return Err(io::Error::other(msg));
     }
-    cmd.arg("exec")
-        .arg(container_name)
-        .arg("sh")
-        .arg("-lc")
-        .arg(&script)
-        .stdout(Stdio::piped())
-        .stderr(if verbose {
-            Stdio::inherit()
-        } else {
-            Stdio::null()
-        });
-    let out = cmd.output().map_err(|e| {
+    let exec_args = vec![
+        container_name.to_string(),
+        "sh".to_string(),
+        "-lc".to_string(),
+        script,
+    ];
+    let out = rt.exec(&exec_args, RuntimeIo::Capture).map_err(|e| {
         io::Error::new(
             e.kind(),
             crate::display_for_toolchain_error(&ToolchainError::Message(format!(
@@ -567,8 +571,10 @@ fn node_overlay_state_and_guard(
             ))),
         )
     })?;
-    let s = String::from_utf8_lossy(&out.stdout);
-    let trimmed = s.trim();
+    if verbose && !out.stderr.is_empty() {
+        eprint!("{}", out.stderr);
+    }
+    let trimmed = out.stdout.trim();
     match trimmed {
         "empty" => Ok(true),
         "nonempty" => Ok(false),
@@ -588,12 +594,11 @@ fn node_overlay_state_and_guard(
 }
 
 fn ensure_node_overlay_and_install(
-    runtime: &Path,
+    rt: &dyn ContainerRuntime,
     container_name: &str,
     verbose: bool,
 ) -> io::Result<()> {
     let use_err = crate::color_enabled_stderr();
-    let mut cmd = Command::new(runtime);
     let script = ShellScript::new()
         .extend([
             "set -e".to_string(),
@@ -611,28 +616,19 @@ fn ensure_node_overlay_and_install(
     if let Err(msg) = crate::validate_docker_exec_sh_login_script(&script) {
         return Err(io::Error::other(msg));
     }
-    cmd.arg("exec")
-        .arg(container_name)
-        .arg("sh")
-        .arg("-lc")
-        .arg(&script)
-        .stdout(if verbose {
-            Stdio::inherit()
-        } else {
-            Stdio::null()
-        })
-        .stderr(if verbose {
-            Stdio::inherit()
-        } else {
-            Stdio::null()
-        });
-    let status = cmd.status()?;
+    let exec_args = vec![
+        container_name.to_string(),
+        "sh".to_string(),
+        "-lc".to_string(),
+        script,
+    ];
+    let status = rt.exec(&exec_args, quiet_unless(verbose))?;
     if !status.success() && verbose {
         crate::log_warn_stderr(
             use_err,
             &format!(
                 "aifo-coder: warning: pnpm bootstrap in node sidecar exited with status {:?}",
-                status.code()
+                status.code
             ),
         );
     }
@@ -849,9 +845,52 @@ pub(crate) fn build_sidecar_exec_preview_with_exec_id(
     args
 }
 
+/// Outcome of `start_sidecar_if_absent`.
+enum SidecarStart {
+    /// A container with the name was already running (e.g. started by another pane).
+    Existing,
+    /// Started now, or concurrently by a peer.
+    Started,
+    /// `run` failed and the container did not appear; carries the engine exit code.
+    Failed(Option<i32>),
+}
+
+/// Start the sidecar from RUN_ARGS (a `docker run ...` preview vector) unless NAME exists.
+fn start_sidecar_if_absent(
+    rt: &dyn ContainerRuntime,
+    name: &str,
+    run_args: &[String],
+    verbose: bool,
+) -> io::Result<SidecarStart> {
+    if rt.inspect(name) {
+        return Ok(SidecarStart::Existing);
+    }
+    let status = rt
+        .invoke(&run_args[1..], quiet_unless(verbose))
+        .map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                crate::display_for_toolchain_error(&ToolchainError::Message(format!(
+                    "failed to start sidecar: {e}"
+                ))),
+            )
+        })?;
+    if status.success() {
+        return Ok(SidecarStart::Started);
+    }
+    // Race-safe fallback: consider success if the container exists now (started by a peer)
+    for _ in 0..5 {
+        if rt.inspect(name) {
+            return Ok(SidecarStart::Started);
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    Ok(SidecarStart::Failed(status.code))
+}
+
 /// Choose/create the session network and return its name (or None to omit --network).
 pub(crate) fn choose_session_network(
-    runtime: &Path,
+    rt: &dyn ContainerRuntime,
     session_id: &str,
     verbose: bool,
     skip_creation: bool,
@@ -864,20 +903,11 @@ pub(crate) fn choose_session_network(
         if skip_creation {
             return Some(net);
         }
-        let exists = Command::new(runtime)
-            .arg("network")
-            .arg("inspect")
-            .arg(&net.name)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .map(|s| s.success())
-            .unwrap_or(false);
-        if exists {
+        if rt.network_exists(&net.name) {
             return Some(net);
         }
         if net.create_if_missing {
-            if ensure_network_exists(runtime, &net.name, verbose) {
+            if ensure_network_exists_with(rt, &net.name, verbose) {
                 set_session_network_env(&net.name, net.managed, net.create_if_missing, "generated");
                 return Some(SessionNetwork {
                     name: net.name,
@@ -1041,7 +1071,8 @@ pub fn toolchain_run(
     verbose: bool,
     dry_run: bool,
 ) -> io::Result<i32> {
-    let runtime: std::path::PathBuf = container_runtime_path()?;
+    let rt = container_runtime()?;
+    let rt = rt.as_ref();
     let use_err = crate::color_enabled_stderr();
     let pwd = {
         let p = std_env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
@@ -1074,7 +1105,7 @@ pub fn toolchain_run(
             create_if_missing: true,
         })
     } else {
-        choose_session_network(&runtime, &session_id, verbose, false)
+        choose_session_network(rt, &session_id, verbose, false)
     };
     let name = sidecar_container_name(sidecar_kind.as_str(), &session_id);
 
@@ -1102,7 +1133,7 @@ pub fn toolchain_run(
         // Phase 5: initialize named cargo volumes ownership (best-effort) before starting sidecar
         if sidecar_kind == "rust" && !no_cache {
             init_rust_named_volumes_if_needed(
-                &runtime,
+                rt,
                 &image,
                 &run_preview_args,
                 if cfg!(unix) { Some((uid, gid)) } else { None },
@@ -1120,14 +1151,14 @@ pub fn toolchain_run(
         // Phase 5: initialize node cache and node_modules overlay volumes ownership (best-effort)
         if sidecar_kind == "node" && !no_cache {
             init_node_cache_volume_if_needed(
-                &runtime,
+                rt,
                 &image,
                 &run_preview_args,
                 if cfg!(unix) { Some((uid, gid)) } else { None },
                 verbose,
             );
             super::mounts::init_node_modules_volume_if_needed(
-                &runtime,
+                rt,
                 &image,
                 &run_preview_args,
                 if cfg!(unix) { Some((uid, gid)) } else { None },
@@ -1135,62 +1166,25 @@ pub fn toolchain_run(
             );
         }
         // If a sidecar with this name already exists, reuse it (another pane may have started it)
-        let exists = Command::new(&runtime)
-            .arg("inspect")
-            .arg(&name)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .map(|s| s.success())
-            .unwrap_or(false);
-        if !exists {
-            let mut run_cmd = Command::new(&runtime);
-            for a in &run_preview_args[1..] {
-                run_cmd.arg(a);
-            }
-            if !verbose {
-                run_cmd.stdout(Stdio::null()).stderr(Stdio::null());
-            }
-            let status = run_cmd.status().map_err(|e| {
-                io::Error::new(
-                    e.kind(),
-                    crate::display_for_toolchain_error(&ToolchainError::Message(format!(
-                        "failed to start sidecar: {e}"
-                    ))),
-                )
-            })?;
-            if !status.success() {
-                // Race-safe fallback: consider success if the container exists now (started by a peer)
-                let mut exists_after = false;
-                for _ in 0..5 {
-                    exists_after = Command::new(&runtime)
-                        .arg("inspect")
-                        .arg(&name)
-                        .stdout(Stdio::null())
-                        .stderr(Stdio::null())
-                        .status()
-                        .map(|s| s.success())
-                        .unwrap_or(false);
-                    if exists_after {
-                        break;
-                    }
-                    std::thread::sleep(Duration::from_millis(100));
-                }
-                if !exists_after {
-                    return Err(io::Error::other(crate::display_for_toolchain_error(
-                        &ToolchainError::Message(format!(
-                            "sidecar container failed to start (exit: {:?})",
-                            status.code()
-                        )),
-                    )));
-                }
+        let started = match start_sidecar_if_absent(rt, &name, &run_preview_args, verbose)? {
+            SidecarStart::Existing => false,
+            SidecarStart::Started => true,
+            SidecarStart::Failed(code) => {
+                return Err(io::Error::other(crate::display_for_toolchain_error(
+                    &ToolchainError::Message(format!(
+                        "sidecar container failed to start (exit: {:?})",
+                        code
+                    )),
+                )));
             }
+        };
+        if started {
             // Node overlay/bootstrap: if node sidecar was just created, ensure per-OS node_modules
             // overlay is initialized, sentinel is present, and lockfile changes trigger installs.
             if sidecar_kind == "node" {
-                match node_overlay_state_and_guard(&runtime, &name, verbose) {
+                match node_overlay_state_and_guard(rt, &name, verbose) {
                     Ok(_need_install) => {
-                        let _ = ensure_node_overlay_and_install(&runtime, &name, verbose);
+                        let _ = ensure_node_overlay_and_install(rt, &name, verbose);
                     }
                     Err(_) => {
                         return Err(io::Error::other(crate::display_for_toolchain_error(
@@ -1222,19 +1216,17 @@ pub fn toolchain_run(
 
     if !dry_run {
         let _started = std::time::Instant::now();
-        let mut exec_cmd = Command::new(&runtime);
-        for a in &exec_preview_args[1..] {
-            exec_cmd.arg(a);
-        }
-        let status = exec_cmd.status().map_err(|e| {
-            io::Error::new(
-                e.kind(),
-                crate::display_for_toolchain_error(&ToolchainError::Message(format!(
-                    "failed to exec in sidecar: {e}"
-                ))),
-            )
-        })?;
-        exit_code = status.code().unwrap_or(1);
+        let status = rt
+            .invoke(&exec_preview_args[1..], RuntimeIo::Inherit)
+            .map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    crate::display_for_toolchain_error(&ToolchainError::Message(format!(
+                        "failed to exec in sidecar: {e}"
+                    ))),
+                )
+            })?;
+        exit_code = status.code.unwrap_or(1);
 
         #[cfg(feature = "otel")]
         {
@@ -1254,12 +1246,7 @@ pub fn toolchain_run(
 
     // Cleanup: stop sidecar and remove network (best-effort)
     if !dry_run {
-        let mut stop_cmd = Command::new(&runtime);
-        stop_cmd.arg("stop").arg(&name);
-        if !verbose {
-            stop_cmd.stdout(Stdio::null()).stderr(Stdio::null());
-        }
-        let _ = stop_cmd.status();
+        let _ = rt.stop(&name, None, quiet_unless(verbose));
 
         #[cfg(feature = "otel")]
         {
@@ -1268,7 +1255,7 @@ pub fn toolchain_run(
 
         if let Some(net_name) = net_for_run {
             if net_name.managed {
-                remove_network(&runtime, &net_name.name, verbose);
+                remove_network_with(rt, &net_name.name, verbose);
             }
         }
     }
@@ -1299,7 +1286,8 @@ pub fn toolchain_start_session(
     no_cache: bool,
     verbose: bool,
 ) -> io::Result<String> {
-    let runtime: std::path::PathBuf = container_runtime_path()?;
+    let rt = container_runtime()?;
+    let rt = rt.as_ref();
     let use_err = crate::color_enabled_stderr();
     let pwd = {
         let p = std_env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
@@ -1317,7 +1305,7 @@ pub fn toolchain_start_session(
         .ok()
         .filter(|s| !s.trim().is_empty())
         .unwrap_or_else(super::create_session_id);
-    let net_for_run = choose_session_network(&runtime, &session_id, verbose, false);
+    let net_for_run = choose_session_network(rt, &session_id, verbose, false);
 
     let apparmor_profile = desired_apparmor_profile();
     for k in kinds {
@@ -1358,14 +1346,14 @@ pub fn toolchain_start_session(
         // Phase 5: initialize node cache and node_modules overlay volumes ownership (best-effort)
         if kind == "node" && !no_cache {
             init_node_cache_volume_if_needed(
-                &runtime,
+                rt,
                 &image,
                 &args,
                 if cfg!(unix) { Some((uid, gid)) } else { None },
                 verbose,
             );
             super::mounts::init_node_modules_volume_if_needed(
-                &runtime,
+                rt,
                 &image,
                 &args,
                 if cfg!(unix) { Some((uid, gid)) } else { None },
@@ -1373,70 +1361,25 @@ pub fn toolchain_start_session(
             );
         }
         // If a sidecar with this name already exists, reuse it (another pane may have started it)
-        let exists = Command::new(&runtime)
-            .arg("inspect")
-            .arg(&name)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .map(|s| s.success())
-            .unwrap_or(false);
-        if !exists {
-            let mut run_cmd = Command::new(&runtime);
-            for a in &args[1..] {
-                run_cmd.arg(a);
-            }
-            if !verbose {
-                run_cmd.stdout(Stdio::null()).stderr(Stdio::null());
-            }
-            let st = run_cmd.status().map_err(|e| {
-                io::Error::new(
-                    e.kind(),
-                    crate::display_for_toolchain_error(&ToolchainError::Message(format!(
-                        "failed to start sidecar: {e}"
-                    ))),
-                )
-            })?;
-            if !st.success() {
-                // Race-safe fallback: if the container exists now, proceed; otherwise fail
-                let mut exists_after = false;
-                for _ in 0..5 {
-                    exists_after = Command::new(&runtime)
-                        .arg("inspect")
-                        .arg(&name)
-                        .stdout(Stdio::null())
-                        .stderr(Stdio::null())
-                        .status()
-                        .map(|s| s.success())
-                        .unwrap_or(false);
-                    if exists_after {
-                        break;
-                    }
-                    std::thread::sleep(Duration::from_millis(100));
-                }
-                if !exists_after {
-                    #[cfg(feature = "otel")]
-                    {
-                        use opentelemetry::trace::{Status, TraceContextExt};
-                        use tracing_opentelemetry::OpenTelemetrySpanExt;
-                        let cx = tracing::Span::current().context();
-                        cx.span()
-                            .set_status(Status::error("aifo_coder_sidecar_start_failed"));
-                    }
-                    return Err(io::Error::other(crate::display_for_toolchain_error(
-                        &ToolchainError::Message(
-                            "failed to start one or more sidecars".to_string(),
-                        ),
-                    )));
-                }
+        if let SidecarStart::Failed(_) = start_sidecar_if_absent(rt, &name, &args, verbose)? {
+            #[cfg(feature = "otel")]
+            {
+                use opentelemetry::trace::{Status, TraceContextExt};
+                use tracing_opentelemetry::OpenTelemetrySpanExt;
+                let cx = tracing::Span::current().context();
+                cx.span()
+                    .set_status(Status::error("aifo_coder_sidecar_start_failed"));
             }
+            return Err(io::Error::other(crate::display_for_toolchain_error(
+                &ToolchainError::Message("failed to start one or more sidecars".to_string()),
+            )));
         }
 
         // Node overlay/bootstrap for sessions: ensure per-OS node_modules overlay and lock hash.
         if kind == "node" {
-            match node_overlay_state_and_guard(&runtime, &name, verbose) {
+            match node_overlay_state_and_guard(rt, &name, verbose) {
                 Ok(_need_install) => {
-                    let _ = ensure_node_overlay_and_install(&runtime, &name, verbose);
+                    let _ = ensure_node_overlay_and_install(rt, &name, verbose);
                 }
                 Err(_) => {
                     return Err(io::Error::other(crate::display_for_toolchain_error(
@@ -1458,47 +1401,37 @@ pub fn toolchain_start_session(
 
 /// Cleanup sidecars and network for a session id (best-effort).
 pub fn toolchain_cleanup_session(session_id: &str, verbose: bool) {
-    let runtime = match container_runtime_path() {
-        Ok(p) => p,
+    let rt = match container_runtime() {
+        Ok(rt) => rt,
         Err(_) => return,
     };
+    toolchain_cleanup_session_with(rt.as_ref(), session_id, verbose);
+}
+
+/// Cleanup sidecars and network for a session id through RT (best-effort).
+pub fn toolchain_cleanup_session_with(rt: &dyn ContainerRuntime, session_id: &str, verbose: bool) {
     let use_err = crate::color_enabled_stderr();
     let kinds = ["rust", "node", "python", "c-cpp", "go"];
     for k in kinds {
         let name = sidecar_container_name(k, session_id);
         // Only attempt stop when container exists to avoid noisy daemon errors
-        let exists = Command::new(&runtime)
-            .arg("inspect")
-            .arg(&name)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .map(|s| s.success())
-            .unwrap_or(false);
-        if exists {
+        if rt.inspect(&name) {
             if verbose {
                 crate::log_info_stderr(
                     use_err,
                     &format!("aifo-coder: docker: docker stop {}", name),
                 );
             }
-            let _ = Command::new(&runtime)
-                .arg("stop")
-                .arg("--time")
-                .arg("1")
-                .arg(&name)
-                .stdout(Stdio::null())
-                .stderr(Stdio::null())
-                .status();
+            let _ = rt.stop(&name, Some(1), RuntimeIo::Quiet);
         }
     }
     let net = sidecar_network_name(session_id);
     if let Some(net) = session_network_from_env() {
         if net.managed {
-            remove_network(&runtime, &net.name, verbose);
+            remove_network_with(rt, &net.name, verbose);
         }
     } else {
-        remove_network(&runtime, &net, verbose);
+        remove_network_with(rt, &net, verbose);
     }
 
     // Best-effort cleanup of unix socket directory (Linux, unix transport)
@@ -1535,7 +1468,13 @@ pub fn toolchain_purge_volume_names() -> &'static [&'static str] {
     )
 )]
 pub fn toolchain_purge_caches(verbose: bool) -> io::Result<()> {
-    let runtime = container_runtime_path()?;
+    let rt = container_runtime()?;
+    toolchain_purge_caches_with(rt.as_ref(), verbose);
+    Ok(())
+}
+
+/// Purge the toolchain cache volumes through RT (best-effort).
+pub fn toolchain_purge_caches_with(rt: &dyn ContainerRuntime, verbose: bool) {
     let use_err = crate::color_enabled_stderr();
     // Phase 7: Purge caches
     // Include consolidated Node cache volume; retain legacy npm cache for back-compat cleanup.
@@ -1547,16 +1486,8 @@ pub fn toolchain_purge_caches(verbose: bool) -> io::Result<()> {
                 &format!("aifo-coder: docker: docker volume rm -f {}", v),
             );
         }
-        let _ = Command::new(&runtime)
-            .arg("volume")
-            .arg("rm")
-            .arg("-f")
-            .arg(v)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status();
+        let _ = rt.volume_remove(v, RuntimeIo::Quiet);
     }
-    Ok(())
 }
 
 /// Bootstrap: install a global typescript in the node sidecar (best-effort).
@@ -1573,7 +1504,7 @@ pub fn toolchain_purge_caches(verbose: bool) -> io::Result<()> {
     )
 )]
 pub fn toolchain_bootstrap_typescript_global(session_id: &str, verbose: bool) -> io::Result<()> {
-    let runtime = container_runtime_path()?;
+    let rt = container_runtime()?;
     let use_err = crate::color_enabled_stderr();
     let name = sidecar_container_name("node", session_id);
 
@@ -1604,13 +1535,6 @@ pub fn toolchain_bootstrap_typescript_global(session_id: &str, verbose: bool) ->
         );
     }
 
-    let mut cmd = Command::new(&runtime);
-    for a in &args[1..] {
-        cmd.arg(a);
-    }
-    if !verbose {
-        cmd.stdout(Stdio::null()).stderr(Stdio::null());
-    }
-    let _ = cmd.status();
+    let _ = rt.invoke(&args[1..], quiet_unless(verbose));
     Ok(())
 }
@@ -0,0 +1,181 @@
+/*!
+In-flight exec registry and the proxy's /health and /status documents.
+
+- ExecRegistry maps exec ids to the sidecar running them (used by /signal) together with the
+  tool, redacted argv, start time, signals received and a byte counter updated while output is
+  streamed. Removing an exec hands the entry back for the audit log.
+- health_json/status_json render the GET /health and GET /status bodies.
+*/
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use super::sidecar;
+
+/// Sidecar kinds the proxy can route to.
+pub(crate) const SIDECAR_KINDS: [&str; 5] = ["rust", "node", "python", "c-cpp", "go"];
+
+/// One exec currently running in a sidecar.
+pub(crate) struct ExecEntry {
+    pub container: String,
+    pub tool: String,
+    pub argv: Vec<String>,
+    pub kind: String,
+    pub started: SystemTime,
+    pub bytes: Arc<AtomicU64>,
+    /// Signals forwarded via /signal, in order.
+    pub signals: Vec<String>,
+}
+
+/// Execs in flight for one proxy, keyed by exec id.
+#[derive(Default)]
+pub(crate) struct ExecRegistry {
+    inner: Mutex<HashMap<String, ExecEntry>>,
+}
+
+impl ExecRegistry {
+    /// Record EXEC_ID and return its streamed-bytes counter. ARGV must already be redacted.
+    pub(crate) fn insert(
+        &self,
+        exec_id: &str,
+        container: &str,
+        kind: &str,
+        tool: &str,
+        argv: Vec<String>,
+    ) -> Arc<AtomicU64> {
+        let bytes = Arc::new(AtomicU64::new(0));
+        let entry = ExecEntry {
+            container: container.to_string(),
+            tool: tool.to_string(),
+            argv,
+            kind: kind.to_string(),
+            started: SystemTime::now(),
+            bytes: bytes.clone(),
+            signals: Vec::new(),
+        };
+        self.lock().insert(exec_id.to_string(), entry);
+        bytes
+    }
+
+    /// Forget EXEC_ID and return its entry (None when it was never registered).
+    pub(crate) fn remove(&self, exec_id: &str) -> Option<ExecEntry> {
+        self.lock().remove(exec_id)
+    }
+
+    /// Note a signal forwarded to EXEC_ID.
+    pub(crate) fn record_signal(&self, exec_id: &str, sig: &str) {
+        if let Some(e) = self.lock().get_mut(exec_id) {
+            e.signals.push(sig.to_string());
+        }
+    }
+
+    /// Container and sidecar kind running EXEC_ID, if it is still in flight.
+    pub(crate) fn container(&self, exec_id: &str) -> Option<(String, String)> {
+        self.lock()
+            .get(exec_id)
+            .map(|e| (e.container.clone(), e.kind.clone()))
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, ExecEntry>> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// JSON array of in-flight execs, oldest first.
+    fn to_json(&self) -> serde_json::Value {
+        let now = SystemTime::now();
+        let map = self.lock();
+        let mut entries: Vec<(&String, &ExecEntry)> = map.iter().collect();
+        entries.sort_by(|a, b| a.1.started.cmp(&b.1.started).then(a.0.cmp(b.0)));
+        let list = entries
+            .into_iter()
+            .map(|(id, e)| {
+                serde_json::json!({
+                    "exec_id": id,
+                    "tool": e.tool,
+                    "argv": e.argv,
+                    "sidecar": e.container,
+                    "kind": e.kind,
+                    "started_at": unix_secs(e.started),
+                    "elapsed_secs": now.duration_since(e.started).unwrap_or_default().as_secs(),
+                    "bytes_streamed": e.bytes.load(Ordering::Relaxed),
+                })
+            })
+            .collect();
+        serde_json::Value::Array(list)
+    }
+}
+
+fn unix_secs(t: SystemTime) -> u64 {
+    t.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Body of GET /health.
+pub(crate) fn health_json(uptime: Duration, registry: &ExecRegistry) -> String {
+    serde_json::json!({
+        "status": "ok",
+        "version": env!("CARGO_PKG_VERSION"),
+        "uptime_secs": uptime.as_secs(),
+        "in_flight": registry.len(),
+    })
+    .to_string()
+}
+
+/// Body of GET /status: health fields plus running sidecars and every in-flight exec.
+pub(crate) fn status_json(uptime: Duration, session: &str, registry: &ExecRegistry) -> String {
+    let sidecars: Vec<serde_json::Value> = SIDECAR_KINDS
+        .iter()
+        .filter_map(|kind| {
+            let name = sidecar::sidecar_container_name(kind, session);
+            super::container_exists(&name).then(|| serde_json::json!({"kind": kind, "name": name}))
+        })
+        .collect();
+    serde_json::json!({
+        "status": "ok",
+        "version": env!("CARGO_PKG_VERSION"),
+        "session": session,
+        "uptime_secs": uptime.as_secs(),
+        "sidecars": sidecars,
+        "execs": registry.to_json(),
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_tracks_bytes_and_orders_by_start() {
+        let reg = ExecRegistry::default();
+        let first = reg.insert("a", "aifo-tc-rust-s", "rust", "cargo", vec!["build".into()]);
+        std::thread::sleep(Duration::from_millis(5));
+        let _second = reg.insert("b", "aifo-tc-node-s", "node", "npm", vec![]);
+        first.fetch_add(42, Ordering::Relaxed);
+        assert_eq!(
+            reg.container("a"),
+            Some(("aifo-tc-rust-s".to_string(), "rust".to_string()))
+        );
+
+        let v = reg.to_json();
+        let execs = v.as_array().unwrap();
+        assert_eq!(execs.len(), 2);
+        assert_eq!(execs[0]["exec_id"], "a");
+        assert_eq!(execs[0]["bytes_streamed"], 42);
+        assert_eq!(execs[0]["argv"], serde_json::json!(["build"]));
+        assert_eq!(execs[1]["sidecar"], "aifo-tc-node-s");
+
+        reg.record_signal("a", "INT");
+        let done = reg.remove("a").expect("entry");
+        assert_eq!(done.signals, vec!["INT".to_string()]);
+        assert!(reg.container("a").is_none());
+        assert_eq!(reg.len(), 1);
+    }
+}
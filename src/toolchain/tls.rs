@@ -0,0 +1,433 @@
+/*!
+Mutual TLS for the TCP toolexec proxy.
+
+With AIFO_TOOLEEXEC_TLS=1 the launcher generates an ephemeral session CA and issues a server
+certificate (for localhost, 127.0.0.1, host.docker.internal, the bind host and
+AIFO_EGRESS_GATEWAY) and a client certificate from it. The proxy then speaks HTTPS and refuses
+clients without a certificate of that CA. The CA key and the server key stay in memory; only
+ca.pem, client.pem and client-key.pem are written to a private per-session directory
+(`$XDG_STATE_HOME/aifo-coder/tls/<session>`, exported as AIFO_TOOLEEXEC_TLS_DIR), which is
+mounted read-only into the agent container and removed when the proxy stops.
+
+The unix socket transport is unaffected. Upgraded (PTY and stdin) execs are not offered over
+TLS; the shim falls back to streaming execs.
+*/
+
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rcgen::{
+    BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, Issuer, KeyPair,
+    KeyUsagePurpose,
+};
+use rustls::crypto::CryptoProvider;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection};
+
+use super::audit::{aifo_state_dir, create_private_dir};
+use super::pool::{ProxyListener, ProxyStream};
+
+/// Session CA certificate, trusted by the shim to verify the proxy.
+pub const TLS_CA_FILE: &str = "ca.pem";
+/// Client certificate the shim presents to the proxy.
+pub const TLS_CLIENT_CERT_FILE: &str = "client.pem";
+/// Private key of the client certificate.
+pub const TLS_CLIENT_KEY_FILE: &str = "client-key.pem";
+
+/// Validity of the session certificates; they are regenerated on every proxy start.
+const CERT_VALIDITY_DAYS: i64 = 30;
+
+/// Directory holding the client-side TLS files of SESSION.
+pub fn toolexec_tls_dir(session: &str) -> PathBuf {
+    aifo_state_dir().join("tls").join(session)
+}
+
+/// Session CA and the server and client certificates issued from it, PEM encoded.
+pub struct TlsMaterial {
+    pub ca_cert: String,
+    pub server_cert: String,
+    pub server_key: String,
+    pub client_cert: String,
+    pub client_key: String,
+}
+
+impl TlsMaterial {
+    /// Generate a fresh CA and certificates; SERVER_NAMES become the server certificate's
+    /// subject alternative names (DNS names or IP addresses).
+    pub fn generate(server_names: &[String]) -> Result<Self, String> {
+        let err = |e: rcgen::Error| format!("certificate generation failed: {e}");
+        let now = time::OffsetDateTime::now_utc();
+        let validity = |p: &mut CertificateParams| {
+            p.not_before = now - time::Duration::hours(1);
+            p.not_after = now + time::Duration::days(CERT_VALIDITY_DAYS);
+        };
+
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).map_err(err)?;
+        ca_params
+            .distinguished_name
+            .push(DnType::CommonName, "aifo-coder toolexec session CA");
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+        ca_params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+        validity(&mut ca_params);
+        let ca_key = KeyPair::generate().map_err(err)?;
+        let ca_cert = ca_params.self_signed(&ca_key).map_err(err)?;
+        let issuer = Issuer::new(ca_params, ca_key);
+
+        let mut server_params = CertificateParams::new(server_names.to_vec()).map_err(err)?;
+        server_params
+            .distinguished_name
+            .push(DnType::CommonName, "aifo-coder toolexec proxy");
+        server_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        validity(&mut server_params);
+        let server_key = KeyPair::generate().map_err(err)?;
+        let server_cert = server_params.signed_by(&server_key, &issuer).map_err(err)?;
+
+        let mut client_params = CertificateParams::new(Vec::<String>::new()).map_err(err)?;
+        client_params
+            .distinguished_name
+            .push(DnType::CommonName, "aifo-shim");
+        client_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        validity(&mut client_params);
+        let client_key = KeyPair::generate().map_err(err)?;
+        let client_cert = client_params.signed_by(&client_key, &issuer).map_err(err)?;
+
+        Ok(TlsMaterial {
+            ca_cert: ca_cert.pem(),
+            server_cert: server_cert.pem(),
+            server_key: server_key.serialize_pem(),
+            client_cert: client_cert.pem(),
+            client_key: client_key.serialize_pem(),
+        })
+    }
+
+    /// Server configuration that requires a client certificate issued by the session CA.
+    pub fn server_config(&self) -> Result<Arc<ServerConfig>, String> {
+        let provider = provider();
+        let verifier = WebPkiClientVerifier::builder_with_provider(
+            Arc::new(root_store(&self.ca_cert)?),
+            provider.clone(),
+        )
+        .build()
+        .map_err(|e| format!("client verifier: {e}"))?;
+        let config = ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|e| e.to_string())?
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(
+                parse_certs(&self.server_cert)?,
+                parse_key(&self.server_key)?,
+            )
+            .map_err(|e| format!("server certificate: {e}"))?;
+        Ok(Arc::new(config))
+    }
+
+    /// Write the CA certificate and the client certificate and key to DIR (created 0700,
+    /// files 0600).
+    pub fn write_client_files(&self, dir: &Path) -> io::Result<()> {
+        create_private_dir(dir)?;
+        for (name, pem) in [
+            (TLS_CA_FILE, &self.ca_cert),
+            (TLS_CLIENT_CERT_FILE, &self.client_cert),
+            (TLS_CLIENT_KEY_FILE, &self.client_key),
+        ] {
+            let mut opts = fs::OpenOptions::new();
+            opts.create(true).write(true).truncate(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::OpenOptionsExt;
+                opts.mode(0o600);
+            }
+            opts.open(dir.join(name))?.write_all(pem.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+fn provider() -> Arc<CryptoProvider> {
+    Arc::new(rustls::crypto::ring::default_provider())
+}
+
+fn parse_certs(pem: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = CertificateDer::pem_slice_iter(pem.as_bytes())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("invalid certificate PEM: {e}"))?;
+    if certs.is_empty() {
+        return Err("no certificate in PEM".to_string());
+    }
+    Ok(certs)
+}
+
+fn parse_key(pem: &str) -> Result<PrivateKeyDer<'static>, String> {
+    PrivateKeyDer::from_pem_slice(pem.as_bytes()).map_err(|e| format!("invalid key PEM: {e}"))
+}
+
+fn root_store(ca_pem: &str) -> Result<RootCertStore, String> {
+    let mut roots = RootCertStore::empty();
+    for cert in parse_certs(ca_pem)? {
+        roots
+            .add(cert)
+            .map_err(|e| format!("invalid CA certificate: {e}"))?;
+    }
+    Ok(roots)
+}
+
+/// Client configuration from the files in DIR: trusts ca.pem only and, with CLIENT_AUTH,
+/// presents client.pem/client-key.pem.
+pub fn toolexec_tls_client_config(
+    dir: &Path,
+    client_auth: bool,
+) -> Result<Arc<ClientConfig>, String> {
+    let read = |name: &str| {
+        fs::read_to_string(dir.join(name)).map_err(|e| format!("{}: {e}", dir.join(name).display()))
+    };
+    let builder = ClientConfig::builder_with_provider(provider())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_root_certificates(root_store(&read(TLS_CA_FILE)?)?);
+    let config = if client_auth {
+        builder
+            .with_client_auth_cert(
+                parse_certs(&read(TLS_CLIENT_CERT_FILE)?)?,
+                parse_key(&read(TLS_CLIENT_KEY_FILE)?)?,
+            )
+            .map_err(|e| format!("client certificate: {e}"))?
+    } else {
+        builder.with_no_client_auth()
+    };
+    Ok(Arc::new(config))
+}
+
+/// Client connection to the TLS proxy.
+pub struct ToolexecTlsStream(rustls::StreamOwned<ClientConnection, TcpStream>);
+
+/// Connect to HOST:PORT and verify the proxy against the session CA in DIR, presenting the
+/// client certificate. The handshake completes before this returns.
+pub fn toolexec_tls_connect(host: &str, port: u16, dir: &Path) -> io::Result<ToolexecTlsStream> {
+    let config = toolexec_tls_client_config(dir, true).map_err(io::Error::other)?;
+    let name = ServerName::try_from(host.to_string())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let conn = ClientConnection::new(config, name).map_err(io::Error::other)?;
+    let sock = TcpStream::connect((host, port))?;
+    let mut stream = rustls::StreamOwned::new(conn, sock);
+    while stream.conn.is_handshaking() {
+        stream.conn.complete_io(&mut stream.sock)?;
+    }
+    Ok(ToolexecTlsStream(stream))
+}
+
+impl ToolexecTlsStream {
+    pub fn set_read_timeout(&self, d: Option<Duration>) -> io::Result<()> {
+        self.0.sock.set_read_timeout(d)
+    }
+
+    pub fn set_write_timeout(&self, d: Option<Duration>) -> io::Result<()> {
+        self.0.sock.set_write_timeout(d)
+    }
+
+    /// Send close_notify and shut the socket down.
+    pub fn shutdown(&mut self) {
+        self.0.conn.send_close_notify();
+        let _ = self.0.conn.complete_io(&mut self.0.sock);
+        let _ = self.0.sock.shutdown(std::net::Shutdown::Both);
+    }
+}
+
+impl Read for ToolexecTlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl Write for ToolexecTlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Server names for the proxy certificate: loopback, the container-side host names and BIND_HOST.
+pub(crate) fn proxy_server_names(bind_host: &str) -> Vec<String> {
+    let mut names: Vec<String> = ["localhost", "127.0.0.1", "host.docker.internal"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let gateway = env::var("AIFO_EGRESS_GATEWAY").unwrap_or_default();
+    for extra in [bind_host, gateway.trim()] {
+        if !extra.is_empty()
+            && extra != "0.0.0.0"
+            && extra != "::"
+            && !names.iter().any(|n| n == extra)
+        {
+            names.push(extra.to_string());
+        }
+    }
+    names
+}
+
+/// TCP listener whose connections are wrapped in server-side TLS.
+pub(crate) struct TlsListener {
+    pub(crate) inner: TcpListener,
+    pub(crate) config: Arc<ServerConfig>,
+}
+
+/// Server side of a TLS proxy connection; the handshake runs on the worker thread at first I/O.
+pub(crate) struct TlsServerStream {
+    inner: rustls::StreamOwned<ServerConnection, TcpStream>,
+    /// Set once a TLS error (failed handshake, bad record) was read; rustls would otherwise
+    /// wait for the peer again on the next write.
+    failed: bool,
+}
+
+impl ProxyListener for TlsListener {
+    type Stream = TlsServerStream;
+
+    fn accept_stream(&self) -> io::Result<TlsServerStream> {
+        let sock = self.inner.accept_stream()?;
+        let conn = ServerConnection::new(self.config.clone()).map_err(io::Error::other)?;
+        Ok(TlsServerStream {
+            inner: rustls::StreamOwned::new(conn, sock),
+            failed: false,
+        })
+    }
+
+    fn wait_pending(&self, timeout: Duration) {
+        self.inner.wait_pending(timeout);
+    }
+}
+
+impl ProxyStream for TlsServerStream {
+    fn set_deadlines(&self, read: Option<Duration>, write: Option<Duration>) {
+        self.inner.sock.set_deadlines(read, write);
+    }
+
+    fn try_clone_stream(&self) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "TLS connections cannot be split",
+        ))
+    }
+}
+
+impl TlsServerStream {
+    fn check_usable(&self) -> io::Result<()> {
+        if self.failed {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "TLS connection failed",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Read for TlsServerStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.check_usable()?;
+        let res = self.inner.read(buf);
+        if res
+            .as_ref()
+            .is_err_and(|e| e.kind() == io::ErrorKind::InvalidData)
+        {
+            self.failed = true;
+        }
+        res
+    }
+}
+
+impl Write for TlsServerStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check_usable()?;
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.check_usable()?;
+        self.inner.flush()
+    }
+}
+
+impl Drop for TlsServerStream {
+    // close_notify lets the client tell a complete response from a truncated one.
+    fn drop(&mut self) {
+        if !self.failed && !self.inner.conn.is_handshaking() {
+            self.inner.conn.send_close_notify();
+            let _ = self.inner.conn.complete_io(&mut self.inner.sock);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handshake(server: Arc<ServerConfig>, client: Arc<ClientConfig>) -> Result<(), String> {
+        let mut s = ServerConnection::new(server).unwrap();
+        let mut c =
+            ClientConnection::new(client, ServerName::try_from("localhost").unwrap()).unwrap();
+        // Shuttle records in memory until both sides are done or one fails.
+        for _ in 0..16 {
+            let mut buf = Vec::new();
+            c.write_tls(&mut buf).unwrap();
+            s.read_tls(&mut &buf[..]).unwrap();
+            s.process_new_packets().map_err(|e| e.to_string())?;
+            let mut buf = Vec::new();
+            s.write_tls(&mut buf).unwrap();
+            c.read_tls(&mut &buf[..]).unwrap();
+            c.process_new_packets().map_err(|e| e.to_string())?;
+            if !s.is_handshaking() && !c.is_handshaking() && !c.wants_write() {
+                return Ok(());
+            }
+        }
+        Err("handshake did not finish".to_string())
+    }
+
+    #[test]
+    fn test_session_ca_issues_mutually_trusted_certificates() {
+        let td = tempfile::tempdir().unwrap();
+        let material = TlsMaterial::generate(&proxy_server_names("127.0.0.1")).unwrap();
+        material.write_client_files(td.path()).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(td.path().join(TLS_CLIENT_KEY_FILE))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let server = material.server_config().unwrap();
+        let client = toolexec_tls_client_config(td.path(), true).unwrap();
+        handshake(server.clone(), client).expect("client with certificate is accepted");
+
+        let anonymous = toolexec_tls_client_config(td.path(), false).unwrap();
+        assert!(handshake(server, anonymous).is_err());
+    }
+
+    #[test]
+    fn test_client_rejects_proxy_of_another_session() {
+        let td = tempfile::tempdir().unwrap();
+        let ours = TlsMaterial::generate(&proxy_server_names("127.0.0.1")).unwrap();
+        ours.write_client_files(td.path()).unwrap();
+        let other = TlsMaterial::generate(&proxy_server_names("127.0.0.1")).unwrap();
+        let client = toolexec_tls_client_config(td.path(), true).unwrap();
+        let err = handshake(other.server_config().unwrap(), client).unwrap_err();
+        assert!(err.contains("certificate"), "{err}");
+    }
+
+    #[test]
+    fn test_proxy_server_names_skip_wildcard_bind_hosts() {
+        let names = proxy_server_names("0.0.0.0");
+        assert_eq!(names, ["localhost", "127.0.0.1", "host.docker.internal"]);
+        assert!(proxy_server_names("192.168.1.20").contains(&"192.168.1.20".to_string()));
+    }
+}
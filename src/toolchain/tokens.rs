@@ -0,0 +1,620 @@
+/*!
+Scoped, expiring proxy tokens and signed requests.
+
+A scoped token is `aifo1.<claims>.<mac>`: claims is the hex of a JSON object
+`{"id","iat","exp","scopes","kinds"}` and mac the hex HMAC-SHA256 of `aifo1.<claims>` under a
+key that never leaves the proxy. exp 0 never expires; empty kinds allow every sidecar kind.
+Scopes: exec, notify, signal, status (GET /health and /status) and token (POST /token).
+
+The agent receives a scoped token when AIFO_TOOLEEXEC_TOKEN_TTL_SECS, AIFO_TOOLEEXEC_TOKEN_SCOPES
+or AIFO_TOOLEEXEC_TOKEN_KINDS is set; otherwise it gets the legacy static session token, which
+keeps every scope and never expires (protocol v2 clients are unaffected).
+
+POST /token trades a token for a fresh one: the same grant with a new lifetime (rotation), or
+a narrower one when `scope`/`kind` fields are given. A rotated-out token keeps working for
+ROTATION_GRACE_SECS, so shims that share a cached token and rotate it concurrently (`make -j`)
+all succeed instead of racing the first rotation. Tokens issued there always expire (after
+ISSUED_TOKEN_TTL_SECS when no lifetime is configured), so the revoked set can be pruned by
+expiry; only the agent's own token may be revoked without one.
+
+Signed requests carry X-Aifo-Timestamp (unix seconds), X-Aifo-Nonce and X-Aifo-Signature, the
+hex HMAC-SHA256 of "METHOD\nTARGET\nTIMESTAMP\nNONCE\nUPGRADE\nEXEC_ID\n" followed by the body.
+TARGET is the request target as sent (path and query); UPGRADE and EXEC_ID are the Upgrade and
+X-Aifo-Exec-Id header values, empty when absent, since they change what runs. The key is the
+session signing key (AIFO_TOOLEEXEC_SIGNING_KEY in the agent), not the bearer token: it never
+appears in a request, so a captured Authorization header is not enough to sign. A nonce is
+accepted once; timestamps more than 5 minutes off are refused. Signatures are checked when
+present and required when AIFO_TOOLEEXEC_REQUIRE_SIGNED=1.
+*/
+
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::OnceCell;
+use ring::hmac;
+use serde_json::{json, Value};
+
+use super::http::{HttpRequest, Method};
+use super::random_token;
+use super::transcript::{hex_decode, hex_encode};
+
+/// Variable carrying the session signing key to the agent's shims.
+pub const TOOLEXEC_SIGNING_KEY_ENV: &str = "AIFO_TOOLEEXEC_SIGNING_KEY";
+
+const TOKEN_PREFIX: &str = "aifo1.";
+const SIGNATURE_MAX_SKEW_SECS: u64 = 300;
+const MAX_NONCE_LEN: usize = 128;
+/// How long a rotated-out token stays valid.
+pub(crate) const ROTATION_GRACE_SECS: u64 = 30;
+/// Lifetime of tokens from POST /token when AIFO_TOOLEEXEC_TOKEN_TTL_SECS is 0.
+const ISSUED_TOKEN_TTL_SECS: u64 = 3600;
+
+/// What a proxy token may be used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenScope {
+    Exec,
+    Notify,
+    Signal,
+    Status,
+    Token,
+}
+
+impl TokenScope {
+    pub const ALL: [TokenScope; 5] = [
+        TokenScope::Exec,
+        TokenScope::Notify,
+        TokenScope::Signal,
+        TokenScope::Status,
+        TokenScope::Token,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TokenScope::Exec => "exec",
+            TokenScope::Notify => "notify",
+            TokenScope::Signal => "signal",
+            TokenScope::Status => "status",
+            TokenScope::Token => "token",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|sc| sc.as_str().eq_ignore_ascii_case(s.trim()))
+    }
+}
+
+/// Unverified claims of a scoped token (what the shim needs to decide on rotation).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenClaims {
+    pub id: String,
+    /// Issue time, unix seconds.
+    pub iat: u64,
+    /// Expiry, unix seconds; 0 never expires.
+    pub exp: u64,
+    pub scopes: Vec<TokenScope>,
+    /// Sidecar kinds exec may route to; empty allows all.
+    pub kinds: Vec<String>,
+}
+
+impl TokenClaims {
+    /// True once two thirds of the lifetime have passed (never for non-expiring tokens).
+    pub fn needs_rotation(&self, now: u64) -> bool {
+        self.exp > 0 && now >= self.iat + self.exp.saturating_sub(self.iat) * 2 / 3
+    }
+
+    pub(crate) fn allows(&self, scope: TokenScope) -> bool {
+        self.scopes.contains(&scope)
+    }
+
+    pub(crate) fn allows_kind(&self, kind: &str) -> bool {
+        self.kinds.is_empty() || self.kinds.iter().any(|k| k == kind)
+    }
+
+    fn to_hex(&self) -> String {
+        let scopes: Vec<&str> = self.scopes.iter().map(|s| s.as_str()).collect();
+        let claims = json!({
+            "id": self.id,
+            "iat": self.iat,
+            "exp": self.exp,
+            "scopes": scopes,
+            "kinds": self.kinds,
+        });
+        hex_encode(claims.to_string().as_bytes())
+    }
+}
+
+/// Claims of a scoped token without checking its signature; None for legacy or malformed tokens.
+pub fn token_claims(token: &str) -> Option<TokenClaims> {
+    let (claims_hex, _mac) = token.strip_prefix(TOKEN_PREFIX)?.split_once('.')?;
+    let v: Value = serde_json::from_slice(&hex_decode(claims_hex)?).ok()?;
+    let strings = |k: &str| -> Option<Vec<String>> {
+        v.get(k)?
+            .as_array()?
+            .iter()
+            .map(|s| s.as_str().map(String::from))
+            .collect()
+    };
+    Some(TokenClaims {
+        id: v.get("id")?.as_str()?.to_string(),
+        iat: v.get("iat")?.as_u64()?,
+        exp: v.get("exp")?.as_u64()?,
+        scopes: strings("scopes")?
+            .iter()
+            .map(|s| TokenScope::parse(s))
+            .collect::<Option<_>>()?,
+        kinds: strings("kinds")?,
+    })
+}
+
+static SIGNING_KEY: OnceCell<String> = OnceCell::new();
+
+/// Request-signing key of this launcher session; every proxy of the process verifies with it.
+pub fn toolexec_signing_key() -> &'static str {
+    SIGNING_KEY.get_or_init(|| format!("{}{}", random_token(), random_token()))
+}
+
+/// Request headers covered by the signature; empty strings for headers the request lacks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SignedHeaders<'a> {
+    /// Upgrade (aifo-pty, aifo-stdin).
+    pub upgrade: &'a str,
+    /// X-Aifo-Exec-Id.
+    pub exec_id: &'a str,
+}
+
+/// Hex HMAC-SHA256 of a request under the signing KEY (see the module docs for the string).
+pub fn toolexec_request_signature(
+    key: &str,
+    method: &str,
+    target: &str,
+    timestamp: u64,
+    nonce: &str,
+    headers: SignedHeaders,
+    body: &[u8],
+) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key.as_bytes());
+    let msg = signed_message(method, target, timestamp, nonce, headers, body);
+    hex_encode(hmac::sign(&key, &msg).as_ref())
+}
+
+/// X-Aifo-Timestamp, X-Aifo-Nonce and X-Aifo-Signature for a POST of BODY to TARGET carrying
+/// HEADERS, signed with the session signing KEY.
+pub fn toolexec_signature_headers(
+    key: &str,
+    target: &str,
+    headers: SignedHeaders,
+    body: &[u8],
+) -> [(&'static str, String); 3] {
+    let ts = unix_now();
+    let nonce = random_token();
+    let sig = toolexec_request_signature(key, "POST", target, ts, &nonce, headers, body);
+    [
+        ("X-Aifo-Timestamp", ts.to_string()),
+        ("X-Aifo-Nonce", nonce),
+        ("X-Aifo-Signature", sig),
+    ]
+}
+
+fn signed_message(
+    method: &str,
+    target: &str,
+    timestamp: u64,
+    nonce: &str,
+    headers: SignedHeaders,
+    body: &[u8],
+) -> Vec<u8> {
+    let SignedHeaders { upgrade, exec_id } = headers;
+    let mut msg =
+        format!("{method}\n{target}\n{timestamp}\n{nonce}\n{upgrade}\n{exec_id}\n").into_bytes();
+    msg.extend_from_slice(body);
+    msg
+}
+
+/// Secret comparison whose time does not depend on where the inputs differ.
+pub(crate) fn secrets_equal(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Why a bearer token was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenError {
+    Invalid,
+    Expired,
+}
+
+/// Issues and verifies the tokens of one proxy instance.
+pub(crate) struct TokenAuthority {
+    key: hmac::Key,
+    /// Session signing key for X-Aifo-Signature (see `toolexec_signing_key`).
+    signing_key: hmac::Key,
+    /// Static session token with every scope; None when the agent got a scoped token.
+    legacy: Option<String>,
+    /// Lifetime of issued tokens (0: no expiry).
+    ttl_secs: u64,
+    require_signed: bool,
+    /// Accepted nonces and their timestamps, pruned once outside the skew window.
+    nonces: Mutex<HashMap<String, u64>>,
+    /// Ids of rotated-away tokens: (refused from, expiry), pruned once expired.
+    revoked: Mutex<HashMap<String, (u64, u64)>>,
+}
+
+impl TokenAuthority {
+    /// Authority configured by AIFO_TOOLEEXEC_TOKEN_* and the token to hand to the agent.
+    pub(crate) fn from_env() -> Result<(Self, String), String> {
+        let mut secret = [0u8; 32];
+        getrandom::getrandom(&mut secret).map_err(|e| format!("secure RNG failed: {e}"))?;
+        let ttl_secs = env::var("AIFO_TOOLEEXEC_TOKEN_TTL_SECS")
+            .ok()
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+        let list = |name: &str| -> Vec<String> {
+            env::var(name)
+                .unwrap_or_default()
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        };
+        let scope_names = list("AIFO_TOOLEEXEC_TOKEN_SCOPES");
+        let kinds = list("AIFO_TOOLEEXEC_TOKEN_KINDS");
+        let scopes = if scope_names.is_empty() {
+            TokenScope::ALL.to_vec()
+        } else {
+            scope_names
+                .iter()
+                .map(|s| {
+                    TokenScope::parse(s).ok_or_else(|| {
+                        format!(
+                            "unknown token scope '{s}' in AIFO_TOOLEEXEC_TOKEN_SCOPES (expected exec, notify, signal, status or token)"
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        let scoped = ttl_secs > 0 || !scope_names.is_empty() || !kinds.is_empty();
+        let mut authority = TokenAuthority {
+            key: hmac::Key::new(hmac::HMAC_SHA256, &secret),
+            signing_key: hmac::Key::new(hmac::HMAC_SHA256, toolexec_signing_key().as_bytes()),
+            legacy: None,
+            ttl_secs,
+            require_signed: env::var("AIFO_TOOLEEXEC_REQUIRE_SIGNED").ok().as_deref() == Some("1"),
+            nonces: Mutex::new(HashMap::new()),
+            revoked: Mutex::new(HashMap::new()),
+        };
+        let agent_token = if scoped {
+            authority.issue(scopes, kinds, ttl_secs)
+        } else {
+            let token = random_token();
+            authority.legacy = Some(token.clone());
+            token
+        };
+        Ok((authority, agent_token))
+    }
+
+    /// Lifetime of a token from POST /token: REQUESTED (0 or None: the default) capped by the
+    /// configured TTL, and never unlimited.
+    pub(crate) fn issued_ttl(&self, requested: Option<u64>) -> u64 {
+        let requested = requested.filter(|&n| n > 0);
+        match self.ttl_secs {
+            0 => requested.unwrap_or(ISSUED_TOKEN_TTL_SECS),
+            max => requested.map_or(max, |n| n.min(max)),
+        }
+    }
+
+    /// New signed token; TTL 0 never expires.
+    pub(crate) fn issue(&self, scopes: Vec<TokenScope>, kinds: Vec<String>, ttl: u64) -> String {
+        self.issue_until(scopes, kinds, ttl, 0)
+    }
+
+    /// New signed token expiring no later than MAX_EXP (0: no cap); TTL 0 never expires.
+    pub(crate) fn issue_until(
+        &self,
+        scopes: Vec<TokenScope>,
+        kinds: Vec<String>,
+        ttl: u64,
+        max_exp: u64,
+    ) -> String {
+        let iat = unix_now();
+        let exp = match (if ttl > 0 { iat + ttl } else { 0 }, max_exp) {
+            (exp, 0) => exp,
+            (0, cap) => cap,
+            (exp, cap) => exp.min(cap),
+        };
+        let claims = TokenClaims {
+            id: random_token(),
+            iat,
+            exp,
+            scopes,
+            kinds,
+        };
+        let body = format!("{TOKEN_PREFIX}{}", claims.to_hex());
+        let mac = hex_encode(hmac::sign(&self.key, body.as_bytes()).as_ref());
+        format!("{body}.{mac}")
+    }
+
+    /// Grant of TOKEN: every scope for the legacy token, the signed claims otherwise.
+    pub(crate) fn verify(&self, token: &str) -> Result<TokenClaims, TokenError> {
+        if let Some(legacy) = &self.legacy {
+            if secrets_equal(token, legacy) {
+                return Ok(TokenClaims {
+                    id: String::new(),
+                    iat: 0,
+                    exp: 0,
+                    scopes: TokenScope::ALL.to_vec(),
+                    kinds: Vec::new(),
+                });
+            }
+        }
+        let (body, mac_hex) = token.rsplit_once('.').ok_or(TokenError::Invalid)?;
+        let mac = hex_decode(mac_hex).ok_or(TokenError::Invalid)?;
+        hmac::verify(&self.key, body.as_bytes(), &mac).map_err(|_| TokenError::Invalid)?;
+        let claims = token_claims(token).ok_or(TokenError::Invalid)?;
+        let now = unix_now();
+        if claims.exp > 0 && now >= claims.exp {
+            return Err(TokenError::Expired);
+        }
+        let revoked = self.revoked.lock().unwrap_or_else(|e| e.into_inner());
+        if revoked
+            .get(&claims.id)
+            .is_some_and(|(from, _)| now >= *from)
+        {
+            return Err(TokenError::Invalid);
+        }
+        Ok(claims)
+    }
+
+    /// Refuse the token with CLAIMS once GRACE_SECS have passed (after a rotation); the legacy
+    /// token stays. Revoking again does not move the first deadline.
+    pub(crate) fn revoke(&self, claims: &TokenClaims, grace_secs: u64) {
+        if claims.id.is_empty() {
+            return;
+        }
+        let now = unix_now();
+        let mut revoked = self.revoked.lock().unwrap_or_else(|e| e.into_inner());
+        revoked.retain(|_, (_, exp)| *exp == 0 || *exp > now);
+        revoked
+            .entry(claims.id.clone())
+            .or_insert((now + grace_secs, claims.exp));
+    }
+
+    /// Check the request signature; unsigned requests pass unless required.
+    pub(crate) fn check_signature(&self, req: &HttpRequest) -> Result<(), String> {
+        let header = |k: &str| req.headers.get(k).map(|v| v.trim());
+        let Some(sig_hex) = header("x-aifo-signature") else {
+            return if self.require_signed {
+                Err("request signature required".to_string())
+            } else {
+                Ok(())
+            };
+        };
+        let ts = header("x-aifo-timestamp")
+            .and_then(|v| v.parse::<u64>().ok())
+            .ok_or("missing or invalid X-Aifo-Timestamp")?;
+        let now = unix_now();
+        if now.abs_diff(ts) > SIGNATURE_MAX_SKEW_SECS {
+            return Err("stale request signature".to_string());
+        }
+        let nonce = header("x-aifo-nonce")
+            .filter(|n| !n.is_empty() && n.len() <= MAX_NONCE_LEN)
+            .ok_or("missing or invalid X-Aifo-Nonce")?;
+        let method = match &req.method {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Other(m) => m.as_str(),
+        };
+        let sig = hex_decode(sig_hex).ok_or("invalid request signature")?;
+        let signed = SignedHeaders {
+            upgrade: header("upgrade").unwrap_or_default(),
+            exec_id: header("x-aifo-exec-id").unwrap_or_default(),
+        };
+        let msg = signed_message(method, &req.target, ts, nonce, signed, &req.body);
+        hmac::verify(&self.signing_key, &msg, &sig).map_err(|_| "invalid request signature")?;
+        let mut seen = self.nonces.lock().unwrap_or_else(|e| e.into_inner());
+        seen.retain(|_, t| now.abs_diff(*t) <= SIGNATURE_MAX_SKEW_SECS);
+        if seen.insert(nonce.to_string(), ts).is_some() {
+            return Err("replayed request (nonce already used)".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authority(legacy: Option<&str>) -> TokenAuthority {
+        TokenAuthority {
+            key: hmac::Key::new(hmac::HMAC_SHA256, b"test-key"),
+            signing_key: hmac::Key::new(hmac::HMAC_SHA256, b"sign-key"),
+            legacy: legacy.map(String::from),
+            ttl_secs: 60,
+            require_signed: false,
+            nonces: Mutex::new(HashMap::new()),
+            revoked: Mutex::new(HashMap::new()),
+        }
+    }
+
+    #[test]
+    fn test_issued_tokens_verify_and_keep_their_grant() {
+        let auth = authority(Some("legacy"));
+        let tok = auth.issue(vec![TokenScope::Exec], vec!["rust".into()], 60);
+        let claims = auth.verify(&tok).expect("valid");
+        assert!(claims.allows(TokenScope::Exec) && !claims.allows(TokenScope::Notify));
+        assert!(claims.allows_kind("rust") && !claims.allows_kind("node"));
+        assert_eq!(claims.exp, claims.iat + 60);
+        assert_eq!(token_claims(&tok), Some(claims));
+
+        let full = auth.verify("legacy").expect("legacy token");
+        assert!(TokenScope::ALL.iter().all(|s| full.allows(*s)) && full.allows_kind("node"));
+        for near in ["legacz", "legac", "legacyy", ""] {
+            assert_eq!(auth.verify(near), Err(TokenError::Invalid));
+        }
+
+        // Tampered claims, foreign keys and garbage are rejected.
+        let widened = tok.replacen(
+            &tok[6..tok.rfind('.').unwrap()],
+            &hex_encode(br#"{"id":"x","iat":0,"exp":0,"scopes":["exec","token"],"kinds":[]}"#),
+            1,
+        );
+        assert_eq!(auth.verify(&widened), Err(TokenError::Invalid));
+        let other = TokenAuthority {
+            key: hmac::Key::new(hmac::HMAC_SHA256, b"other-key"),
+            ..authority(None)
+        };
+        assert_eq!(other.verify(&tok), Err(TokenError::Invalid));
+        assert_eq!(other.verify("legacy"), Err(TokenError::Invalid));
+    }
+
+    #[test]
+    fn test_expiry_and_rotation_window() {
+        let auth = authority(None);
+        let claims = TokenClaims {
+            id: "x".into(),
+            iat: 1_000,
+            exp: 1_090,
+            scopes: vec![TokenScope::Exec],
+            kinds: vec![],
+        };
+        assert!(!claims.needs_rotation(1_059) && claims.needs_rotation(1_060));
+        let body = format!("{TOKEN_PREFIX}{}", claims.to_hex());
+        let mac = hex_encode(hmac::sign(&auth.key, body.as_bytes()).as_ref());
+        assert_eq!(
+            auth.verify(&format!("{body}.{mac}")),
+            Err(TokenError::Expired)
+        );
+        let forever = auth.issue(vec![TokenScope::Status], vec![], 0);
+        assert!(!token_claims(&forever).unwrap().needs_rotation(u64::MAX));
+
+        // A rotation never outlives the old token, which stops verifying.
+        let old = auth.issue(vec![TokenScope::Exec], vec![], 30);
+        let old_claims = auth.verify(&old).expect("valid");
+        for ttl in [0, 60, 10] {
+            let new = auth.issue_until(vec![TokenScope::Exec], vec![], ttl, old_claims.exp);
+            let exp = auth.verify(&new).expect("valid").exp;
+            assert!(exp > 0 && exp <= old_claims.exp, "ttl {ttl}: exp {exp}");
+        }
+        auth.revoke(&old_claims, 0);
+        assert_eq!(auth.verify(&old), Err(TokenError::Invalid));
+
+        // Within the grace period a rotated-out token still works, and rotating it again (a
+        // concurrent shim) does not extend the deadline.
+        let shared = auth.issue(vec![TokenScope::Exec], vec![], 30);
+        let shared_claims = auth.verify(&shared).expect("valid");
+        auth.revoke(&shared_claims, ROTATION_GRACE_SECS);
+        assert!(auth.verify(&shared).is_ok());
+        auth.revoke(&shared_claims, 3_600);
+        {
+            let revoked = auth.revoked.lock().unwrap();
+            assert!(revoked[&shared_claims.id].0 <= unix_now() + ROTATION_GRACE_SECS);
+        }
+
+        // Issued tokens always expire, so revoked entries are pruned once they have.
+        assert_eq!(auth.issued_ttl(None), 60);
+        assert_eq!(auth.issued_ttl(Some(600)), 60);
+        let unlimited = TokenAuthority {
+            ttl_secs: 0,
+            ..authority(None)
+        };
+        assert_eq!(unlimited.issued_ttl(Some(0)), ISSUED_TOKEN_TTL_SECS);
+        assert_eq!(unlimited.issued_ttl(Some(5)), 5);
+        let expired = TokenClaims {
+            id: "gone".into(),
+            ..claims.clone()
+        };
+        auth.revoke(&expired, 0);
+        auth.revoke(&old_claims, 0);
+        assert!(!auth.revoked.lock().unwrap().contains_key("gone"));
+    }
+
+    #[test]
+    fn test_signed_requests_reject_tampering_and_replay() {
+        let auth = authority(None);
+        let body = b"tool=cargo&arg=build".to_vec();
+        let mut headers = HashMap::new();
+        let signed = SignedHeaders {
+            exec_id: "exec-1",
+            ..SignedHeaders::default()
+        };
+        for (k, v) in toolexec_signature_headers("sign-key", "/exec", signed, &body) {
+            headers.insert(k.to_ascii_lowercase(), v);
+        }
+        headers.insert("x-aifo-exec-id".into(), "exec-1".into());
+        let req = HttpRequest {
+            method: Method::Post,
+            path_lc: "/exec".into(),
+            target: "/exec".into(),
+            query: vec![],
+            headers,
+            body,
+        };
+        assert_eq!(auth.check_signature(&req), Ok(()));
+        assert!(auth
+            .check_signature(&req)
+            .unwrap_err()
+            .contains("nonce already used"));
+
+        let mut tampered = req.clone();
+        tampered.body = b"tool=cargo&arg=publish".to_vec();
+        tampered
+            .headers
+            .insert("x-aifo-nonce".into(), "fresh".into());
+        assert_eq!(
+            auth.check_signature(&tampered),
+            Err("invalid request signature".to_string())
+        );
+
+        // The query is merged into the exec form, and the Upgrade and exec id headers change
+        // what runs: all of them are covered.
+        let mut fresh = req.clone();
+        fresh.headers.insert("x-aifo-nonce".into(), "fresh".into());
+        let mut with_query = fresh.clone();
+        with_query.target = "/exec?arg=--release".into();
+        with_query.query = vec![("arg".into(), "--release".into())];
+        let mut upgraded = fresh.clone();
+        upgraded.headers.insert("upgrade".into(), "aifo-pty".into());
+        let mut other_exec = fresh.clone();
+        other_exec
+            .headers
+            .insert("x-aifo-exec-id".into(), "exec-2".into());
+        for tampered in [with_query, upgraded, other_exec] {
+            assert_eq!(
+                auth.check_signature(&tampered),
+                Err("invalid request signature".to_string())
+            );
+        }
+
+        // Knowing the bearer token is not enough: it is not the signing key.
+        let mut token_signed = req.clone();
+        for (k, v) in toolexec_signature_headers("tok", "/exec", signed, &req.body) {
+            token_signed.headers.insert(k.to_ascii_lowercase(), v);
+        }
+        assert_eq!(
+            auth.check_signature(&token_signed),
+            Err("invalid request signature".to_string())
+        );
+
+        let mut unsigned = req.clone();
+        unsigned.headers.clear();
+        assert_eq!(auth.check_signature(&unsigned), Ok(()));
+        let strict = TokenAuthority {
+            require_signed: true,
+            ..authority(None)
+        };
+        assert!(strict.check_signature(&unsigned).is_err());
+    }
+}
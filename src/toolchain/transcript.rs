@@ -0,0 +1,395 @@
+/*!
+Record-and-replay transcripts of proxied tool executions.
+
+Recording (AIFO_TOOLEEXEC_RECORD=1, or a file path) appends one JSON line per finished exec to
+`$XDG_STATE_HOME/aifo-coder/transcripts/<session>.jsonl` (or the given file):
+- exec_id, tool, argv (redacted), cwd, kind, sidecar, env (the `-e` variables of docker exec)
+- chunks: output as streamed, each `{"t_ms", "text"}` (or `"hex"` when not UTF-8), with t_ms
+  counted from the start of the exec
+- exit_code (null when the client disconnected), duration_ms and truncated (set once the
+  output exceeded AIFO_TOOLEEXEC_RECORD_MAX_BYTES, default 8 MiB)
+
+Buffered (v1) and streaming (v2) execs are recorded; interactive PTY and stdin sessions are not.
+
+Replay (AIFO_TOOLEEXEC_REPLAY=FILE) answers /exec from a transcript without sidecars: the next
+unused exec with the same tool, redacted argv and cwd is served (the last one again once all
+were used). AIFO_TOOLEEXEC_REPLAY_TIMING=1 reproduces the recorded delays between chunks.
+*/
+
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde_json::{json, Map, Value};
+
+use super::audit::{aifo_state_dir, create_private_dir, rfc3339_utc};
+
+const DEFAULT_RECORD_MAX_BYTES: usize = 8 * 1024 * 1024;
+
+/// Default transcript file of SESSION.
+pub fn transcript_path(session: &str) -> PathBuf {
+    aifo_state_dir()
+        .join("transcripts")
+        .join(format!("{session}.jsonl"))
+}
+
+pub(crate) fn hex_encode(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub(crate) fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// `-e NAME=VALUE` pairs of a docker exec preview; values of secret-looking names are masked.
+pub(crate) fn exec_env_from_preview(preview: &[String]) -> Map<String, Value> {
+    let mut env = Map::new();
+    for pair in preview.windows(2).filter(|w| w[0] == "-e") {
+        let Some((name, value)) = pair[1].split_once('=') else {
+            continue;
+        };
+        let upper = name.to_ascii_uppercase();
+        let secret = ["TOKEN", "SECRET", "PASSWORD", "KEY"]
+            .iter()
+            .any(|s| upper.contains(s));
+        let value = if secret { "***" } else { value };
+        env.insert(name.to_string(), Value::String(value.to_string()));
+    }
+    env
+}
+
+/// Transcript recorder of one proxy instance.
+pub(crate) struct TranscriptWriter {
+    path: PathBuf,
+    session: String,
+    max_bytes: usize,
+}
+
+impl TranscriptWriter {
+    /// Recorder configured by AIFO_TOOLEEXEC_RECORD, if enabled.
+    pub(crate) fn from_env(session: &str, verbose: bool) -> Option<Self> {
+        let v = env::var("AIFO_TOOLEEXEC_RECORD").ok()?;
+        let v = v.trim();
+        let path = match v.to_ascii_lowercase().as_str() {
+            "" | "0" | "false" | "no" | "off" => return None,
+            "1" | "true" | "yes" | "on" => transcript_path(session),
+            _ => PathBuf::from(v),
+        };
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            if let Err(e) = create_private_dir(dir) {
+                crate::log_warn_stderr(
+                    crate::color_enabled_stderr(),
+                    &format!(
+                        "aifo-coder: warning: exec recording disabled: cannot create {}: {}",
+                        dir.display(),
+                        e
+                    ),
+                );
+                return None;
+            }
+        }
+        if verbose {
+            eprintln!("aifo-coder: proxy recording execs to {}", path.display());
+        }
+        let max_bytes = env::var("AIFO_TOOLEEXEC_RECORD_MAX_BYTES")
+            .ok()
+            .and_then(|s| s.trim().parse::<usize>().ok())
+            .unwrap_or(DEFAULT_RECORD_MAX_BYTES);
+        Some(TranscriptWriter {
+            path,
+            session: session.to_string(),
+            max_bytes,
+        })
+    }
+
+    /// Start recording one exec; ARGV must already be redacted.
+    pub(crate) fn begin(
+        &self,
+        exec_id: &str,
+        (tool, argv, cwd): (&str, &[String], &str),
+        (kind, sidecar): (&str, &str),
+        env: Map<String, Value>,
+    ) -> ExecRecording<'_> {
+        ExecRecording {
+            writer: self,
+            started: Instant::now(),
+            head: json!({
+                "ts": rfc3339_utc(SystemTime::now()),
+                "session": self.session,
+                "exec_id": exec_id,
+                "tool": tool,
+                "argv": argv,
+                "cwd": cwd,
+                "kind": kind,
+                "sidecar": sidecar,
+                "env": env,
+            }),
+            chunks: Vec::new(),
+            bytes: 0,
+            truncated: false,
+        }
+    }
+
+    fn append(&self, record: &Value) {
+        let mut line = record.to_string();
+        line.push('\n');
+        let mut opts = fs::OpenOptions::new();
+        opts.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            opts.mode(0o600);
+        }
+        let _ = opts
+            .open(&self.path)
+            .and_then(|mut f| f.write_all(line.as_bytes()));
+    }
+}
+
+/// Output of one exec being recorded; written by finish().
+pub(crate) struct ExecRecording<'a> {
+    writer: &'a TranscriptWriter,
+    started: Instant,
+    head: Value,
+    chunks: Vec<Value>,
+    bytes: usize,
+    truncated: bool,
+}
+
+impl ExecRecording<'_> {
+    pub(crate) fn chunk(&mut self, data: &[u8]) {
+        if self.truncated || data.is_empty() {
+            return;
+        }
+        let room = self.writer.max_bytes.saturating_sub(self.bytes);
+        let data = if data.len() > room {
+            self.truncated = true;
+            &data[..room]
+        } else {
+            data
+        };
+        self.bytes += data.len();
+        let t_ms = u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX);
+        self.chunks.push(match std::str::from_utf8(data) {
+            Ok(text) => json!({"t_ms": t_ms, "text": text}),
+            Err(_) => json!({"t_ms": t_ms, "hex": hex_encode(data)}),
+        });
+    }
+
+    /// Write the transcript line; EXIT is None when the client disconnected.
+    pub(crate) fn finish(mut self, exit: Option<i32>) {
+        let duration_ms = u64::try_from(self.started.elapsed().as_millis()).unwrap_or(u64::MAX);
+        if let Some(obj) = self.head.as_object_mut() {
+            obj.insert(
+                "chunks".into(),
+                Value::Array(std::mem::take(&mut self.chunks)),
+            );
+            obj.insert("exit_code".into(), json!(exit));
+            obj.insert("duration_ms".into(), json!(duration_ms));
+            obj.insert("truncated".into(), json!(self.truncated));
+        }
+        self.writer.append(&self.head);
+    }
+}
+
+/// One exec loaded from a transcript.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RecordedExec {
+    pub tool: String,
+    pub argv: Vec<String>,
+    pub cwd: String,
+    /// (milliseconds since start, bytes)
+    pub chunks: Vec<(u64, Vec<u8>)>,
+    /// Recorded exit code; 1 when the original client disconnected.
+    pub exit_code: i32,
+}
+
+impl RecordedExec {
+    fn from_json(v: &Value) -> Option<Self> {
+        let strings = |k: &str| -> Vec<String> {
+            v.get(k)
+                .and_then(Value::as_array)
+                .map(|a| {
+                    a.iter()
+                        .filter_map(|s| s.as_str().map(String::from))
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+        let chunks = v
+            .get("chunks")
+            .and_then(Value::as_array)
+            .map(|a| {
+                a.iter()
+                    .filter_map(|c| {
+                        let t = c.get("t_ms").and_then(Value::as_u64).unwrap_or(0);
+                        let data = match (c.get("text"), c.get("hex")) {
+                            (Some(Value::String(s)), _) => s.clone().into_bytes(),
+                            (_, Some(Value::String(h))) => hex_decode(h)?,
+                            _ => return None,
+                        };
+                        Some((t, data))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Some(RecordedExec {
+            tool: v.get("tool")?.as_str()?.to_string(),
+            argv: strings("argv"),
+            cwd: v
+                .get("cwd")
+                .and_then(Value::as_str)
+                .unwrap_or("/workspace")
+                .to_string(),
+            chunks,
+            exit_code: v
+                .get("exit_code")
+                .and_then(Value::as_i64)
+                .and_then(|c| i32::try_from(c).ok())
+                .unwrap_or(1),
+        })
+    }
+
+    /// All recorded output concatenated.
+    pub(crate) fn body(&self) -> Vec<u8> {
+        self.chunks.iter().flat_map(|(_, d)| d.clone()).collect()
+    }
+}
+
+/// Transcript served by a replay proxy.
+pub(crate) struct Replay {
+    execs: Vec<RecordedExec>,
+    used: Mutex<Vec<bool>>,
+    /// Sleep to reproduce the recorded chunk timing.
+    pub timing: bool,
+}
+
+impl Replay {
+    /// Replay configured by AIFO_TOOLEEXEC_REPLAY, if set; a missing or empty file is an error.
+    pub(crate) fn from_env() -> io::Result<Option<Self>> {
+        let Some(path) = env::var_os("AIFO_TOOLEEXEC_REPLAY").filter(|v| !v.is_empty()) else {
+            return Ok(None);
+        };
+        let path = PathBuf::from(path);
+        let file = fs::File::open(&path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("cannot open replay transcript {}: {e}", path.display()),
+            )
+        })?;
+        let replay = Replay::parse(io::BufReader::new(file));
+        if replay.execs.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("replay transcript {} has no execs", path.display()),
+            ));
+        }
+        Ok(Some(replay))
+    }
+
+    fn parse<R: BufRead>(reader: R) -> Self {
+        let execs: Vec<RecordedExec> = reader
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|l| serde_json::from_str::<Value>(&l).ok())
+            .filter_map(|v| RecordedExec::from_json(&v))
+            .collect();
+        Replay {
+            used: Mutex::new(vec![false; execs.len()]),
+            execs,
+            timing: env::var("AIFO_TOOLEEXEC_REPLAY_TIMING").ok().as_deref() == Some("1"),
+        }
+    }
+
+    /// Next recorded exec of TOOL with ARGV (redacted) in CWD.
+    pub(crate) fn take(&self, tool: &str, argv: &[String], cwd: &str) -> Option<&RecordedExec> {
+        let mut used = self.used.lock().unwrap_or_else(|e| e.into_inner());
+        let matching: Vec<usize> = self
+            .execs
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.tool == tool && e.argv == argv && e.cwd == cwd)
+            .map(|(i, _)| i)
+            .collect();
+        let i = matching
+            .iter()
+            .copied()
+            .find(|&i| !used[i])
+            .or_else(|| matching.last().copied())?;
+        used[i] = true;
+        Some(&self.execs[i])
+    }
+
+    /// Delay before sending the chunk recorded at T_MS, given the time since replay started.
+    pub(crate) fn delay(&self, t_ms: u64, elapsed: Duration) -> Duration {
+        if !self.timing {
+            return Duration::ZERO;
+        }
+        Duration::from_millis(t_ms).saturating_sub(elapsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recording_round_trips_through_replay() {
+        let td = tempfile::tempdir().expect("tmpdir");
+        let writer = TranscriptWriter {
+            path: td.path().join("t.jsonl"),
+            session: "s".into(),
+            max_bytes: 8,
+        };
+        let argv = vec!["build".to_string()];
+        let env = exec_env_from_preview(&[
+            "-e".into(),
+            "HOME=/home/coder".into(),
+            "-e".into(),
+            "NPM_TOKEN=abc".into(),
+        ]);
+        assert_eq!(env["NPM_TOKEN"], "***");
+        let mut rec = writer.begin("e1", ("cargo", &argv, "/workspace"), ("rust", "tc"), env);
+        rec.chunk(b"ok\n");
+        rec.chunk(&[0xff, b'x']);
+        rec.chunk(b"0123456789");
+        rec.finish(Some(3));
+        let mut rec = writer.begin(
+            "e2",
+            ("cargo", &argv, "/workspace"),
+            ("rust", "tc"),
+            Map::new(),
+        );
+        rec.chunk(b"second\n");
+        rec.finish(Some(0));
+
+        let text = fs::read_to_string(td.path().join("t.jsonl")).unwrap();
+        let first: Value = serde_json::from_str(text.lines().next().unwrap()).unwrap();
+        assert_eq!(first["truncated"], true);
+        assert_eq!(first["env"]["HOME"], "/home/coder");
+        assert_eq!(first["chunks"][1]["hex"], "ff78");
+
+        let replay = Replay::parse(io::Cursor::new(text));
+        let a = replay.take("cargo", &argv, "/workspace").unwrap();
+        assert_eq!(a.body(), b"ok\n\xffx012".to_vec());
+        assert_eq!(a.exit_code, 3);
+        let b = replay.take("cargo", &argv, "/workspace").unwrap();
+        assert_eq!(b.body(), b"second\n".to_vec());
+        // Exhausted: the last match is served again; other requests have no match.
+        assert_eq!(
+            replay.take("cargo", &argv, "/workspace").unwrap().exit_code,
+            0
+        );
+        assert!(replay.take("cargo", &[], "/workspace").is_none());
+        assert!(replay.take("cargo", &argv, "/workspace/sub").is_none());
+    }
+}
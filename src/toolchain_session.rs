@@ -568,7 +568,11 @@ impl ToolchainSession {
             }
         }
 
-        // Start proxy
+        // Start proxy. The agent's shims sign every request, so the launcher refuses unsigned
+        // ones unless AIFO_TOOLEEXEC_REQUIRE_SIGNED=0 opts out.
+        if std::env::var_os("AIFO_TOOLEEXEC_REQUIRE_SIGNED").is_none() {
+            std::env::set_var("AIFO_TOOLEEXEC_REQUIRE_SIGNED", "1");
+        }
         let (url, token, flag, handle) = match aifo_coder::toolexec_start_proxy(&sid, cli.verbose) {
             Ok(t) => t,
             Err(e) => {
@@ -581,11 +585,17 @@ impl ToolchainSession {
                 return Err(e);
             }
         };
-        // Use loopback URL on host for tests, but rewrite to host.docker.internal for agent container env
-        let url_for_env = if url.starts_with("http://127.0.0.1:") {
-            url.replacen("http://127.0.0.1", "http://host.docker.internal", 1)
-        } else {
-            url.clone()
+        // Use loopback URL on host for tests, but rewrite to host.docker.internal for agent container env;
+        // on the egress allowlist's internal network the gateway is the only reachable host address.
+        let egress_gateway = std::env::var("AIFO_EGRESS_GATEWAY")
+            .ok()
+            .filter(|s| !s.trim().is_empty());
+        let loopback =
+            url.starts_with("http://127.0.0.1:") || url.starts_with("https://127.0.0.1:");
+        let url_for_env = match (loopback, egress_gateway) {
+            (true, Some(gw)) => url.replacen("127.0.0.1", &gw, 1),
+            (true, None) => url.replacen("127.0.0.1", "host.docker.internal", 1),
+            (false, _) => url.clone(),
         };
         std::env::set_var("AIFO_TOOLEEXEC_URL", &url_for_env);
         std::env::set_var("AIFO_TOOLEEXEC_TOKEN", &token);
@@ -230,13 +230,16 @@ pub fn warn_if_missing_llm_credentials(interactive_block: bool) -> bool {
     };
 
     let mut missing: Vec<&str> = Vec::new();
-    if !is_set_nonempty("AIFO_API_KEY") {
+    // A configured key helper supplies AIFO_API_KEY (it only runs outside dry-run).
+    if !is_set_nonempty("AIFO_API_KEY") && aifo_coder::llm_api_key_helper().is_none() {
         missing.push("AIFO_API_KEY");
     }
     if !is_set_nonempty("AIFO_API_BASE") {
         missing.push("AIFO_API_BASE");
     }
-    if !is_set_nonempty("AIFO_API_VERSION") {
+    // Only providers with versioned APIs (Azure OpenAI) need AIFO_API_VERSION.
+    let needs_version = aifo_coder::llm_selected_provider().map_or(true, |p| p.uses_version());
+    if needs_version && !is_set_nonempty("AIFO_API_VERSION") {
         missing.push("AIFO_API_VERSION");
     }
 
@@ -265,7 +268,7 @@ pub fn warn_if_missing_llm_credentials(interactive_block: bool) -> bool {
     // Guidance
     aifo_coder::log_warn_stderr(
         use_err,
-        "Warning: set them in your shell or .env file, or run: aifo-coder doctor",
+        "Warning: set them in your shell or .env file, configure [llm] api_key_command, or run: aifo-coder doctor",
     );
     eprintln!();
 
@@ -28,7 +28,7 @@ fn e2e_dev_tool_routing_make_rust_only_tcp_v2() {
     }
     let rust_image = std::env::var("AIFO_CODER_TEST_RUST_IMAGE")
         .unwrap_or_else(|_| "aifo-coder-toolchain-rust:latest".to_string());
-    if !support::docker_image_present(&runtime.as_path(), &rust_image) {
+    if !support::docker_image_present(runtime.as_path(), &rust_image) {
         eprintln!("skipping: rust image '{}' not present locally", rust_image);
         return;
     }
@@ -364,7 +364,7 @@ fn e2e_dev_tool_routing_make_cpp_only_tcp_v2() {
     }
     let cpp_image = std::env::var("AIFO_CODER_TEST_CPP_IMAGE")
         .unwrap_or_else(|_| "aifo-coder-toolchain-cpp:latest".to_string());
-    if !support::docker_image_present(&runtime.as_path(), &cpp_image) {
+    if !support::docker_image_present(runtime.as_path(), &cpp_image) {
         eprintln!("skipping: c-cpp image '{}' not present locally", cpp_image);
         return;
     }
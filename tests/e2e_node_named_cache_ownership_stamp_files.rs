@@ -14,7 +14,7 @@ fn e2e_node_named_cache_ownership_stamp_files() {
 
     // Start node sidecar and run a harmless command to init cache
     let image = support::default_node_test_image();
-    let img_ok = support::docker_image_present(&rt.as_path(), &image);
+    let img_ok = support::docker_image_present(rt.as_path(), &image);
     if !img_ok {
         eprintln!("skipping: node image '{}' not present locally", image);
         return;
@@ -26,8 +26,7 @@ fn e2e_node_named_cache_ownership_stamp_files() {
         aifo_coder::toolchain_start_session(&kinds, &overrides, false, true).expect("sidecar");
     // Best-effort: run a simple command to trigger cache init
     let (_cmd, preview) =
-        aifo_coder::build_docker_cmd("node", &vec!["--version".into()], &image, None)
-            .expect("preview");
+        aifo_coder::build_docker_cmd("node", &["--version".into()], &image, None).expect("preview");
     eprintln!("preview: {}", preview);
 
     // Inspect named cache volume for stamp file
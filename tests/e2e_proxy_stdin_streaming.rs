@@ -0,0 +1,145 @@
+mod support;
+use support::urlencode;
+
+/// Run TOOL with ARGS over `Upgrade: aifo-stdin`, feeding INPUT then EOF; returns (exit, output).
+#[cfg(unix)]
+fn exec_with_stdin(
+    port: u16,
+    token: &str,
+    tool: &str,
+    args: &[&str],
+    input: &[u8],
+) -> (i32, String) {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::time::Duration;
+
+    let mut stream =
+        TcpStream::connect(("127.0.0.1", port)).expect("connect 127.0.0.1:<port> failed");
+    let mut body = format!("tool={}&cwd={}", urlencode(tool), urlencode("."));
+    for a in args {
+        body.push_str(&format!("&arg={}", urlencode(a)));
+    }
+    let req = format!(
+        "POST /exec HTTP/1.1\r\nHost: host.docker.internal\r\nAuthorization: Bearer {}\r\nX-Aifo-Proto: 1\r\nConnection: Upgrade\r\nUpgrade: {}\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\n\r\n{}",
+        token,
+        aifo_coder::STDIN_UPGRADE,
+        body.len(),
+        body
+    );
+    stream.write_all(req.as_bytes()).expect("write failed");
+    stream
+        .set_read_timeout(Some(Duration::from_secs(60)))
+        .expect("read timeout");
+
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while aifo_coder::find_header_end(&head).is_none() {
+        stream.read_exact(&mut byte).expect("read response header");
+        head.push(byte[0]);
+    }
+    let head = String::from_utf8_lossy(&head).to_string();
+    assert!(
+        head.starts_with("HTTP/1.1 101 "),
+        "expected 101 Switching Protocols, got:\n{head}"
+    );
+
+    // Several frames so the tool sees a stream rather than one write.
+    for piece in input.chunks(7) {
+        aifo_coder::pty_write_frame(&mut stream, aifo_coder::PTY_FRAME_STDIN, piece)
+            .expect("write stdin frame");
+    }
+    aifo_coder::pty_write_frame(&mut stream, aifo_coder::PTY_FRAME_STDIN_EOF, b"")
+        .expect("write eof frame");
+
+    let mut frames = aifo_coder::PtyFrameReader::new();
+    let mut output = Vec::new();
+    let mut code = None;
+    while let Some((kind, payload)) = frames.read_frame(&mut stream).expect("read frame") {
+        match kind {
+            aifo_coder::PTY_FRAME_OUTPUT => output.extend_from_slice(&payload),
+            aifo_coder::PTY_FRAME_EXIT => {
+                code = Some(i32::from_be_bytes(payload[..4].try_into().unwrap()));
+                break;
+            }
+            _ => {}
+        }
+    }
+    (
+        code.expect("missing exit frame"),
+        String::from_utf8_lossy(&output).to_string(),
+    )
+}
+
+#[cfg(unix)]
+#[ignore]
+#[test]
+fn e2e_proxy_stdin_streaming_pipes_input_and_eof_to_tool() {
+    // Skip if docker isn't available on this host
+    if aifo_coder::container_runtime_path().is_err() {
+        eprintln!("skipping: docker not found in PATH");
+        return;
+    }
+    std::env::remove_var("AIFO_TOOLEEXEC_USE_UNIX");
+
+    // Ensure Docker daemon reachable and python image present locally (avoid pulls)
+    let runtime = aifo_coder::container_runtime_path().expect("runtime");
+    let ok = std::process::Command::new(&runtime)
+        .arg("ps")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+    if !ok {
+        eprintln!("skipping: Docker daemon not reachable");
+        return;
+    }
+    let py_image = support::default_python_test_image();
+    if !support::docker_image_present(runtime.as_path(), &py_image) {
+        eprintln!("skipping: python image '{}' not present locally", py_image);
+        return;
+    }
+
+    let kinds = vec!["python".to_string()];
+    let overrides: Vec<(String, String)> = vec![("python".to_string(), py_image)];
+    let verbose = true;
+    let sid = aifo_coder::toolchain_start_session(&kinds, &overrides, false, verbose)
+        .expect("start python sidecar");
+    let (url, token, flag, handle) =
+        aifo_coder::toolexec_start_proxy(&sid, verbose).expect("start proxy");
+    let port = support::port_from_http_url(&url);
+
+    // `cat data | python3 script`: the script only finishes once it sees EOF.
+    let (code, out) = exec_with_stdin(
+        port,
+        &token,
+        "python3",
+        &[
+            "-c",
+            "import sys; d = sys.stdin.read(); print(d.upper(), end=''); print('lines=%d' % d.count(chr(10))); sys.exit(4)",
+        ],
+        b"alpha\nbeta\ngamma\n",
+    );
+    assert_eq!(code, 4, "unexpected exit code; output:\n{out}");
+    assert!(out.contains("ALPHA\nBETA\nGAMMA\n"), "output:\n{out}");
+    assert!(out.contains("lines=3"), "output:\n{out}");
+
+    // Immediate EOF: the tool sees empty stdin instead of waiting forever.
+    let (code, out) = exec_with_stdin(
+        port,
+        &token,
+        "python3",
+        &[
+            "-c",
+            "import sys; print('len=%d' % len(sys.stdin.buffer.read()))",
+        ],
+        b"",
+    );
+    assert_eq!(code, 0, "output:\n{out}");
+    assert!(out.contains("len=0"), "output:\n{out}");
+
+    flag.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+    aifo_coder::toolchain_cleanup_session(&sid, verbose);
+}
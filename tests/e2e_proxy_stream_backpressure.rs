@@ -35,7 +35,7 @@ fn e2e_proxy_v2_backpressure_emits_drop_warning_and_counter() {
         return;
     }
     let node_image = support::default_node_test_image();
-    if !support::docker_image_present(&runtime.as_path(), &node_image) {
+    if !support::docker_image_present(runtime.as_path(), &node_image) {
         eprintln!("skipping: node image '{}' not present locally", node_image);
         return;
     }
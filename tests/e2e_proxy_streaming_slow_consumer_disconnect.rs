@@ -12,7 +12,7 @@ fn e2e_proxy_streaming_slow_consumer_disconnect() {
     // Use node sidecar (skip if image not present locally to avoid pulling)
     let rt = aifo_coder::container_runtime_path().expect("runtime");
     let node_image = support::default_node_test_image();
-    let img_ok = support::docker_image_present(&rt.as_path(), &node_image);
+    let img_ok = support::docker_image_present(rt.as_path(), &node_image);
     if !img_ok {
         eprintln!("skipping: node image '{}' not present locally", node_image);
         return;
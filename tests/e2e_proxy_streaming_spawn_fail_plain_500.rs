@@ -44,7 +44,7 @@ fn e2e_streaming_spawn_fail_plain_500() {
         return;
     }
     let py_image = support::default_python_test_image();
-    if !support::docker_image_present(&runtime.as_path(), &py_image) {
+    if !support::docker_image_present(runtime.as_path(), &py_image) {
         eprintln!("skipping: python image '{}' not present locally", py_image);
         return;
     }
@@ -31,12 +31,12 @@ fn e2e_proxy_unix_socket_rust_and_node() {
     }
     let rust_image = std::env::var("AIFO_CODER_TEST_RUST_IMAGE")
         .unwrap_or_else(|_| "aifo-coder-toolchain-rust:latest".to_string());
-    if !support::docker_image_present(&runtime.as_path(), &rust_image) {
+    if !support::docker_image_present(runtime.as_path(), &rust_image) {
         eprintln!("skipping: rust image '{}' not present locally", rust_image);
         return;
     }
     let node_image = support::default_node_test_image();
-    if !support::docker_image_present(&runtime.as_path(), &node_image) {
+    if !support::docker_image_present(runtime.as_path(), &node_image) {
         eprintln!("skipping: node image '{}' not present locally", node_image);
         return;
     }
@@ -35,7 +35,7 @@ fn e2e_python_venv_activation_path_precedence_tcp_v2() {
         return;
     }
     let py_image = support::default_python_test_image();
-    if !support::docker_image_present(&runtime.as_path(), &py_image) {
+    if !support::docker_image_present(runtime.as_path(), &py_image) {
         eprintln!("skipping: python image '{}' not present locally", py_image);
         return;
     }
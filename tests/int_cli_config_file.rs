@@ -0,0 +1,69 @@
+use std::process::Command;
+
+fn run_images(user_cfg: &std::path::Path, flavor_env: Option<&str>) -> String {
+    let bin = env!("CARGO_BIN_EXE_aifo-coder");
+    let mut cmd = Command::new(bin);
+    cmd.arg("images")
+        .env("AIFO_CODER_USER_CONFIG", user_cfg)
+        .env(
+            "AIFO_CODER_SYSTEM_CONFIG",
+            user_cfg.with_extension("absent"),
+        )
+        .env_remove("AIFO_CODER_IMAGE_FLAVOR")
+        .env_remove("AIFO_CODER_NO_CONFIG");
+    if let Some(f) = flavor_env {
+        cmd.env("AIFO_CODER_IMAGE_FLAVOR", f);
+    }
+    let out = cmd.output().expect("failed to run aifo-coder images");
+    assert!(
+        out.status.success(),
+        "aifo-coder images exited non-zero: {:?}\nstderr:\n{}",
+        out.status.code(),
+        String::from_utf8_lossy(&out.stderr)
+    );
+    format!(
+        "{}\n{}",
+        String::from_utf8_lossy(&out.stdout),
+        String::from_utf8_lossy(&out.stderr)
+    )
+}
+
+#[test]
+fn int_test_cli_user_config_sets_flavor_and_env_wins() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let cfg = td.path().join("config.toml");
+    std::fs::write(&cfg, "[image]\nflavor = \"slim\"\n").unwrap();
+
+    let all = run_images(&cfg, None);
+    assert!(
+        all.contains("-slim:"),
+        "expected slim images from config file, got:\n{all}"
+    );
+
+    let all = run_images(&cfg, Some("full"));
+    assert!(
+        !all.contains("-slim:"),
+        "env AIFO_CODER_IMAGE_FLAVOR must override the config file, got:\n{all}"
+    );
+}
+
+#[test]
+fn int_test_cli_invalid_config_file_fails_fast() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let cfg = td.path().join("config.toml");
+    std::fs::write(&cfg, "[image]\nunknown_knob = 1\n").unwrap();
+
+    let bin = env!("CARGO_BIN_EXE_aifo-coder");
+    let out = Command::new(bin)
+        .arg("images")
+        .env("AIFO_CODER_USER_CONFIG", &cfg)
+        .env_remove("AIFO_CODER_NO_CONFIG")
+        .output()
+        .expect("failed to run aifo-coder images");
+    assert_eq!(out.status.code(), Some(1));
+    let err = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        err.contains("invalid config file") && err.contains("unknown_knob"),
+        "stderr should explain the invalid config file:\n{err}"
+    );
+}
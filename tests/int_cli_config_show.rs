@@ -0,0 +1,84 @@
+use std::process::Command;
+
+fn config_json(td: &std::path::Path, args: &[&str], env: &[(&str, &str)]) -> serde_json::Value {
+    let bin = env!("CARGO_BIN_EXE_aifo-coder");
+    let mut cmd = Command::new(bin);
+    cmd.arg("config")
+        .args(args)
+        .current_dir(td)
+        .env("AIFO_CODER_USER_CONFIG", td.join("config.toml"))
+        .env("AIFO_CODER_SYSTEM_CONFIG", td.join("absent.toml"))
+        .env_remove("AIFO_CODER_NO_CONFIG")
+        .env_remove("AIFO_TOOLEEXEC_MAX_SECS")
+        .env_remove("AIFO_TOOLEEXEC_MAX_CONNECTIONS");
+    for (k, v) in env {
+        cmd.env(k, v);
+    }
+    let out = cmd.output().expect("failed to run aifo-coder config");
+    assert!(
+        out.status.success(),
+        "aifo-coder config exited non-zero: {:?}\nstderr:\n{}",
+        out.status.code(),
+        String::from_utf8_lossy(&out.stderr)
+    );
+    serde_json::from_slice(&out.stdout).expect("stdout must be JSON")
+}
+
+fn setting<'a>(doc: &'a serde_json::Value, key: &str) -> &'a serde_json::Value {
+    doc["settings"]
+        .as_array()
+        .expect("settings array")
+        .iter()
+        .find(|s| s["key"] == key)
+        .unwrap_or_else(|| panic!("missing setting {key} in {doc}"))
+}
+
+#[test]
+fn int_test_cli_config_show_json_reports_sources() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let cfg = td.path().join("config.toml");
+    std::fs::write(
+        &cfg,
+        "[proxy]\nmax_secs = 42\n[fork]\ninclude_dirty = true\n",
+    )
+    .unwrap();
+    let user_label = format!("user:{}", cfg.display());
+
+    let doc = config_json(td.path(), &["show", "--json"], &[]);
+    assert_eq!(doc["files"][0], cfg.display().to_string());
+    let max = setting(&doc, "proxy.max_secs");
+    assert_eq!(max["value"], "42");
+    assert_eq!(max["source"], user_label);
+    assert_eq!(max["env"], "AIFO_TOOLEEXEC_MAX_SECS");
+    assert_eq!(setting(&doc, "proxy.max_connections")["source"], "default");
+    assert_eq!(setting(&doc, "fork.include_dirty")["source"], user_label);
+
+    let doc = config_json(
+        td.path(),
+        &["show", "--json"],
+        &[("AIFO_TOOLEEXEC_MAX_SECS", "7")],
+    );
+    let max = setting(&doc, "proxy.max_secs");
+    assert_eq!(max["value"], "7");
+    assert_eq!(max["source"], "env");
+}
+
+#[test]
+fn int_test_cli_config_explain_lists_layers() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let cfg = td.path().join("config.toml");
+    std::fs::write(&cfg, "[proxy]\nmax_secs = 42\n").unwrap();
+
+    let doc = config_json(td.path(), &["explain", "proxy.max_secs", "--json"], &[]);
+    assert_eq!(doc["value"], "42");
+    assert_eq!(doc["default"], "300");
+    assert_eq!(doc["layers"][0]["value"], "42");
+
+    let bin = env!("CARGO_BIN_EXE_aifo-coder");
+    let out = Command::new(bin)
+        .args(["config", "explain", "no.such.key"])
+        .env("AIFO_CODER_USER_CONFIG", &cfg)
+        .output()
+        .expect("run");
+    assert_eq!(out.status.code(), Some(1));
+}
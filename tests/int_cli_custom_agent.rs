@@ -0,0 +1,57 @@
+use std::process::Command;
+
+fn aifo(td: &std::path::Path, args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_aifo-coder"))
+        .args(args)
+        .current_dir(td)
+        .env("HOME", td)
+        .env("AIFO_CODER_USER_CONFIG", td.join("config.toml"))
+        .env("AIFO_CODER_SYSTEM_CONFIG", td.join("absent.toml"))
+        .env("AIFO_CODER_SUPPRESS_LLM_WARNING", "1")
+        .env("HOST_AGENT_KEY", "k-123")
+        .env_remove("AIFO_CODER_NO_CONFIG")
+        .env_remove("AIFO_CODER_IMAGE")
+        .output()
+        .expect("run aifo-coder")
+}
+
+#[test]
+fn int_test_cli_custom_agent_dry_run_uses_config_definition() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    std::fs::create_dir_all(td.path().join(".my-agent")).unwrap();
+    std::fs::write(td.path().join(".my-agent").join("settings.json"), "{}").unwrap();
+    std::fs::write(
+        td.path().join("config.toml"),
+        concat!(
+            "[agents.my-agent]\nimage = \"example.com/my-agent:1\"\n",
+            "bin = \"/opt/my/bin/my-agent\"\nruntime = \"node\"\n",
+            "config_dirs = [\"~/.my-agent\"]\nenv = { MY_AGENT_MODE = \"strict\" }\n",
+            "env_from = { MY_AGENT_KEY = \"HOST_AGENT_KEY\" }\n",
+        ),
+    )
+    .unwrap();
+
+    let out = aifo(
+        td.path(),
+        &["--dry-run", "run", "my-agent", "--", "--version"],
+    );
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "non-zero exit:\n{stderr}");
+    for needle in [
+        "example.com/my-agent:1",
+        "/opt/my/bin/my-agent --version",
+        "AIFO_AGENT_NAME=my-agent",
+        "AIFO_SHIM_SMART_NODE=1",
+        "MY_AGENT_MODE=strict",
+        "MY_AGENT_KEY=k-123",
+        "/home/coder/.aifo-config-host/my-agent:ro",
+    ] {
+        assert!(stderr.contains(needle), "missing {needle} in:\n{stderr}");
+    }
+
+    let out = aifo(td.path(), &["--dry-run", "run", "other-agent"]);
+    assert_eq!(out.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("unknown agent 'other-agent'"), "{stderr}");
+    assert!(stderr.contains("my-agent"), "{stderr}");
+}
@@ -7,7 +7,7 @@ fn docker_present() -> bool {
 
 fn image_present(img: &str) -> bool {
     if let Ok(rt) = aifo_coder::container_runtime_path() {
-        return support::docker_image_present(&rt.as_path(), img);
+        return support::docker_image_present(rt.as_path(), img);
     }
     false
 }
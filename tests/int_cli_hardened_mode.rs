@@ -0,0 +1,72 @@
+use std::process::Command;
+
+fn aifo(td: &std::path::Path, args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_aifo-coder"))
+        .args(args)
+        .current_dir(td)
+        .env("HOME", td)
+        .env("AIFO_CODER_USER_CONFIG", td.join("config.toml"))
+        .env("AIFO_CODER_SYSTEM_CONFIG", td.join("absent.toml"))
+        .env("AIFO_CODER_SUPPRESS_LLM_WARNING", "1")
+        .env_remove("AIFO_CODER_NO_CONFIG")
+        .env_remove("AIFO_CODER_HARDENED")
+        .env_remove("AIFO_CODER_CAP_ADD")
+        .output()
+        .expect("run aifo-coder")
+}
+
+#[test]
+fn int_test_cli_hardened_flag_in_dry_run_preview() {
+    let td = tempfile::tempdir().expect("tmpdir");
+
+    let out = aifo(td.path(), &["--dry-run", "aider"]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "non-zero exit:\n{stderr}");
+    assert!(
+        !stderr.contains("--cap-drop") && !stderr.contains("--read-only"),
+        "hardened mode must be opt-in:\n{stderr}"
+    );
+
+    let out = aifo(td.path(), &["--dry-run", "--hardened", "aider"]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "non-zero exit:\n{stderr}");
+    for needle in [
+        "--cap-drop ALL --cap-add CHOWN --cap-add DAC_OVERRIDE --cap-add FOWNER",
+        "--security-opt no-new-privileges --read-only",
+        "--tmpfs '/tmp:rw,nosuid,nodev,mode=1777'",
+        "--tmpfs '/home/coder:rw,nosuid,nodev,mode=1777'",
+        "hardened=yes",
+    ] {
+        assert!(stderr.contains(needle), "missing {needle:?}:\n{stderr}");
+    }
+}
+
+#[test]
+fn int_test_cli_hardened_mode_from_config() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    std::fs::write(
+        td.path().join("config.toml"),
+        "[security]\nhardened = true\ncap_add = [\"cap_net_bind_service\"]\n",
+    )
+    .unwrap();
+    let out = aifo(td.path(), &["--dry-run", "aider"]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "non-zero exit:\n{stderr}");
+    assert!(
+        stderr.contains("--cap-drop ALL --cap-add NET_BIND_SERVICE --security-opt"),
+        "config cap_add should replace the default add-back list:\n{stderr}"
+    );
+
+    std::fs::write(
+        td.path().join("config.toml"),
+        "[security]\ncap_add = [\"not a cap\"]\n",
+    )
+    .unwrap();
+    let out = aifo(td.path(), &["--dry-run", "aider"]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(!out.status.success(), "invalid capability must fail");
+    assert!(
+        stderr.contains("[security]") && stderr.contains("invalid capability"),
+        "error should name the section:\n{stderr}"
+    );
+}
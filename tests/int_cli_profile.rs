@@ -0,0 +1,84 @@
+use std::process::Command;
+
+#[test]
+fn int_test_cli_profile_supplies_agent_and_flags() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let cfg = td.path().join("config.toml");
+    std::fs::write(
+        &cfg,
+        concat!(
+            "[profiles.backend]\nagent = \"aider\"\nflavor = \"slim\"\n",
+            "[profiles.backend.env]\nPROFILE_MARKER = \"from-profile\"\n",
+        ),
+    )
+    .unwrap();
+
+    let bin = env!("CARGO_BIN_EXE_aifo-coder");
+    let out = Command::new(bin)
+        .args(["--profile", "backend", "--dry-run", "--", "--version"])
+        .current_dir(td.path())
+        .env("AIFO_CODER_USER_CONFIG", &cfg)
+        .env("AIFO_CODER_SYSTEM_CONFIG", td.path().join("absent.toml"))
+        .env("AIFO_CODER_SUPPRESS_LLM_WARNING", "1")
+        .env_remove("AIFO_CODER_NO_CONFIG")
+        .env_remove("AIFO_CODER_IMAGE_FLAVOR")
+        .env_remove("AIFO_ENV_PROFILE_MARKER")
+        .output()
+        .expect("run aifo-coder");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "non-zero exit:\n{stderr}");
+    assert!(
+        stderr.contains("aifo-coder-aider-slim:"),
+        "profile agent and flavor expected in dry-run:\n{stderr}"
+    );
+    assert!(stderr.contains("PROFILE_MARKER=from-profile"), "{stderr}");
+    assert!(stderr.contains("aider --version"), "{stderr}");
+
+    // config show attributes profile env entries and profile booleans (false included).
+    std::fs::write(
+        &cfg,
+        concat!(
+            "[network]\nisolate = true\n",
+            "[profiles.backend]\nagent = \"aider\"\nnetwork_isolate = false\n",
+            "[profiles.backend.env]\nPROFILE_MARKER = \"from-profile\"\n",
+        ),
+    )
+    .unwrap();
+    let out = Command::new(bin)
+        .args(["--profile", "backend", "config", "show"])
+        .current_dir(td.path())
+        .env("AIFO_CODER_USER_CONFIG", &cfg)
+        .env("AIFO_CODER_SYSTEM_CONFIG", td.path().join("absent.toml"))
+        .env_remove("AIFO_CODER_NO_CONFIG")
+        .env_remove("AIFO_ENV_PROFILE_MARKER")
+        .output()
+        .expect("run aifo-coder");
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    let line = |key: &str| {
+        stdout
+            .lines()
+            .find(|l| l.split_whitespace().next() == Some(key))
+            .unwrap_or_default()
+            .to_string()
+    };
+    let isolate = line("network.isolate");
+    assert!(
+        isolate.contains(" 0 ") && isolate.ends_with("[profile:backend]"),
+        "{stdout}"
+    );
+    let marker = line("env.PROFILE_MARKER");
+    assert!(
+        marker.contains("from-profile") && marker.ends_with("[profile:backend]"),
+        "{stdout}"
+    );
+
+    let out = Command::new(bin)
+        .args(["--profile", "nope", "--dry-run", "aider"])
+        .current_dir(td.path())
+        .env("AIFO_CODER_USER_CONFIG", &cfg)
+        .env("AIFO_CODER_SYSTEM_CONFIG", td.path().join("absent.toml"))
+        .output()
+        .expect("run aifo-coder");
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("unknown profile 'nope'"));
+}
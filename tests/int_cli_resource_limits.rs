@@ -0,0 +1,64 @@
+use std::process::Command;
+
+fn aifo(td: &std::path::Path, args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_aifo-coder"))
+        .args(args)
+        .current_dir(td)
+        .env("HOME", td)
+        .env("AIFO_CODER_USER_CONFIG", td.join("config.toml"))
+        .env("AIFO_CODER_SYSTEM_CONFIG", td.join("absent.toml"))
+        .env("AIFO_CODER_SUPPRESS_LLM_WARNING", "1")
+        .env_remove("AIFO_CODER_NO_CONFIG")
+        .env_remove("AIFO_CODER_CPUS")
+        .env_remove("AIFO_CODER_MEMORY")
+        .env_remove("AIFO_CODER_PIDS_LIMIT")
+        .env_remove("AIFO_CODER_ULIMITS")
+        .output()
+        .expect("run aifo-coder")
+}
+
+#[test]
+fn int_test_cli_resource_limits_in_dry_run_preview() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    std::fs::write(
+        td.path().join("config.toml"),
+        concat!(
+            "[limits.agent]\ncpus = 4\nmemory = \"6g\"\npids = 1024\n",
+            "[limits.agents.aider]\ncpus = 2\n",
+        ),
+    )
+    .unwrap();
+
+    let out = aifo(td.path(), &["--dry-run", "aider"]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "non-zero exit:\n{stderr}");
+    assert!(
+        stderr.contains("--cpus 2 --memory 6g --pids-limit 1024"),
+        "per-agent config should override [limits.agent]:\n{stderr}"
+    );
+    assert!(
+        stderr.contains("Limits: cpus=2 memory=6g pids=1024"),
+        "banner should show limits:\n{stderr}"
+    );
+
+    let out = aifo(
+        td.path(),
+        &[
+            "--dry-run",
+            "--memory",
+            "2g",
+            "--ulimit",
+            "nofile=1024:2048",
+            "aider",
+        ],
+    );
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "non-zero exit:\n{stderr}");
+    assert!(
+        stderr.contains("--cpus 2 --memory 2g --pids-limit 1024 --ulimit nofile=1024:2048"),
+        "CLI flags should override config:\n{stderr}"
+    );
+
+    let out = aifo(td.path(), &["--dry-run", "--pids-limit", "0", "aider"]);
+    assert!(!out.status.success());
+}
@@ -0,0 +1,98 @@
+use std::process::Command;
+
+fn aifo(td: &std::path::Path, args: &[&str], envs: &[(&str, &str)]) -> std::process::Output {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_aifo-coder"));
+    cmd.args(args)
+        .current_dir(td)
+        .env("HOME", td)
+        .env("TMPDIR", td)
+        .env("AIFO_CODER_USER_CONFIG", td.join("config.toml"))
+        .env("AIFO_CODER_SYSTEM_CONFIG", td.join("absent.toml"))
+        .env("AIFO_CODER_SUPPRESS_LLM_WARNING", "1")
+        .env_remove("AIFO_CODER_NO_CONFIG")
+        .env_remove("AIFO_CODER_SECCOMP");
+    for (k, v) in envs {
+        cmd.env(k, v);
+    }
+    cmd.output().expect("run aifo-coder")
+}
+
+#[test]
+fn int_test_agent_preview_uses_generated_seccomp_profile() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let out = aifo(td.path(), &["--dry-run", "aider"], &[]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "non-zero exit:\n{stderr}");
+
+    let marker = "--security-opt seccomp=";
+    let start = stderr.find(marker).expect("seccomp flag in preview") + marker.len();
+    let path = stderr[start..].split_whitespace().next().unwrap();
+    let path = std::path::Path::new(path.trim_matches('\''));
+    assert!(
+        path.starts_with(td.path()),
+        "profile should be a temp file: {path:?}"
+    );
+    let written = std::fs::read_to_string(path).expect("profile file");
+    assert_eq!(written, aifo_coder::seccomp_profile_json(false));
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600, "profile must be private");
+    }
+
+    let out = aifo(
+        td.path(),
+        &["--dry-run", "aider"],
+        &[("AIFO_CODER_SECCOMP", "engine")],
+    );
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(out.status.success(), "non-zero exit:\n{stderr}");
+    assert!(
+        !stderr.contains("seccomp="),
+        "engine mode passes no flag:\n{stderr}"
+    );
+}
+
+#[test]
+fn int_test_security_export_seccomp() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    let out = aifo(td.path(), &["security", "export-seccomp"], &[]);
+    assert!(out.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout),
+        aifo_coder::seccomp_profile_json(false)
+    );
+
+    let dest = td.path().join("relaxed.json");
+    let out = aifo(
+        td.path(),
+        &[
+            "security",
+            "export-seccomp",
+            "--relaxed",
+            "-o",
+            dest.to_str().unwrap(),
+        ],
+        &[],
+    );
+    assert!(out.status.success());
+    assert_eq!(
+        std::fs::read_to_string(&dest).unwrap(),
+        aifo_coder::seccomp_profile_json(true)
+    );
+}
+
+#[test]
+fn int_test_config_rejects_unknown_seccomp_mode() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    std::fs::write(
+        td.path().join("config.toml"),
+        "[security]\nseccomp = \"strict\"\n",
+    )
+    .unwrap();
+    let out = aifo(td.path(), &["--dry-run", "aider"], &[]);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(!out.status.success(), "invalid mode must fail:\n{stderr}");
+    assert!(stderr.contains("invalid seccomp mode 'strict'"), "{stderr}");
+}
@@ -1,4 +1,4 @@
-//// ignore-tidy-linelength
+// ignore-tidy-linelength
 
 use std::env;
 use std::fs;
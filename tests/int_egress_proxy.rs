@@ -0,0 +1,164 @@
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+/// Upstream that answers one connection: echoes a line for CONNECT, or the request line for HTTP.
+fn spawn_upstream() -> (u16, std::thread::JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind upstream");
+    let port = listener.local_addr().unwrap().port();
+    let h = std::thread::spawn(move || {
+        let (mut s, _) = listener.accept().expect("accept");
+        s.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut buf = [0u8; 4096];
+        let n = s.read(&mut buf).unwrap_or(0);
+        let got = String::from_utf8_lossy(&buf[..n]).to_string();
+        let first = got.lines().next().unwrap_or_default().to_string();
+        let _ = writeln!(s, "upstream saw: {first}");
+        got
+    });
+    (port, h)
+}
+
+fn start_proxy(
+    allow: &[&str],
+    log: &std::path::Path,
+) -> (
+    u16,
+    std::sync::Arc<std::sync::atomic::AtomicBool>,
+    std::thread::JoinHandle<()>,
+) {
+    aifo_coder::egress_start_proxy(
+        "127.0.0.1",
+        allow.iter().map(|s| s.to_string()).collect(),
+        Some(log.to_path_buf()),
+        false,
+    )
+    .expect("start egress proxy")
+}
+
+fn roundtrip(port: u16, request: &str) -> String {
+    let mut s = TcpStream::connect(("127.0.0.1", port)).expect("connect proxy");
+    s.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    s.write_all(request.as_bytes()).unwrap();
+    let mut out = String::new();
+    let _ = s.read_to_string(&mut out);
+    out
+}
+
+#[test]
+fn int_test_egress_proxy_allows_connect_and_http_to_allowlisted_hosts() {
+    let td = tempfile::tempdir().unwrap();
+    let log = td.path().join("egress.log");
+    let (port, flag, handle) = start_proxy(&["127.0.0.1"], &log);
+
+    let (tunnel, up_h) = spawn_upstream();
+    let up = tunnel;
+    let out = roundtrip(
+        port,
+        &format!("CONNECT 127.0.0.1:{up} HTTP/1.1\r\nHost: 127.0.0.1:{up}\r\n\r\nhello tunnel\n"),
+    );
+    assert!(out.starts_with("HTTP/1.1 200"), "{out}");
+    assert!(out.contains("upstream saw: hello tunnel"), "{out}");
+    assert_eq!(up_h.join().unwrap(), "hello tunnel\n");
+
+    let (up, up_h) = spawn_upstream();
+    let out = roundtrip(
+        port,
+        &format!(
+            "GET http://127.0.0.1:{up}/simple/pkg?x=1 HTTP/1.1\r\nHost: 127.0.0.1:{up}\r\nProxy-Connection: keep-alive\r\n\r\n"
+        ),
+    );
+    assert!(
+        out.contains("upstream saw: GET /simple/pkg?x=1 HTTP/1.1"),
+        "{out}"
+    );
+    let seen = up_h.join().unwrap();
+    assert!(
+        !seen.to_ascii_lowercase().contains("proxy-connection"),
+        "{seen}"
+    );
+    assert!(seen.contains("Connection: close"), "{seen}");
+
+    flag.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+    let text = std::fs::read_to_string(&log).expect("egress log");
+    assert!(
+        text.contains(&format!(" allow CONNECT 127.0.0.1:{tunnel}\n")),
+        "{text}"
+    );
+    assert!(
+        text.contains(&format!(" allow GET 127.0.0.1:{up}\n")),
+        "{text}"
+    );
+}
+
+#[test]
+fn int_test_egress_proxy_denies_other_hosts_and_logs() {
+    let td = tempfile::tempdir().unwrap();
+    let log = td.path().join("egress.log");
+    let (port, flag, handle) = start_proxy(&["*.example.com"], &log);
+
+    let out = roundtrip(
+        port,
+        "CONNECT evil.test:443 HTTP/1.1\r\nHost: evil.test:443\r\n\r\n",
+    );
+    assert!(out.starts_with("HTTP/1.1 403"), "{out}");
+    assert!(
+        out.contains("egress to evil.test is not in the allowlist"),
+        "{out}"
+    );
+
+    let out = roundtrip(
+        port,
+        "GET http://example.com/ HTTP/1.1\r\nHost: example.com\r\n\r\n",
+    );
+    assert!(out.starts_with("HTTP/1.1 403"), "{out}");
+
+    let out = roundtrip(port, "GET /relative HTTP/1.1\r\nHost: x\r\n\r\n");
+    assert!(out.starts_with("HTTP/1.1 400"), "{out}");
+
+    flag.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+    let text = std::fs::read_to_string(&log).expect("egress log");
+    assert!(text.contains(" deny CONNECT evil.test:443"), "{text}");
+    assert!(text.contains(" deny GET example.com:80"), "{text}");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&log).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600);
+    }
+}
+
+#[test]
+fn int_test_egress_allowlist_dry_run_uses_internal_session_network() {
+    let td = tempfile::tempdir().unwrap();
+    let home = td.path();
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_aifo-coder"))
+        .args([
+            "--dry-run",
+            "--egress-allowlist",
+            "--egress-allow",
+            "github.com",
+            "aider",
+            "--",
+            "--version",
+        ])
+        .env("HOME", home)
+        .env("AIFO_CODER_USER_CONFIG", home.join("user.toml"))
+        .env("AIFO_CODER_SYSTEM_CONFIG", home.join("system.toml"))
+        .env("AIFO_API_BASE", "https://llm.example.net/v1")
+        .env("AIFO_CODER_SUPPRESS_LLM_WARNING", "1")
+        .env_remove("AIFO_SESSION_NETWORK")
+        .output()
+        .expect("run aifo-coder");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains(
+            "would route egress through the allowlist proxy on internal network aifo-egress-"
+        ),
+        "{stderr}"
+    );
+    assert!(stderr.contains("github.com, llm.example.net"), "{stderr}");
+    assert!(stderr.contains("--network aifo-egress-"), "{stderr}");
+}
@@ -1,11 +1,11 @@
 use std::fs;
-use std::path::PathBuf;
+use std::path::Path;
 mod support;
 use support::{have_git, init_repo_with_default_user};
 
 // Helper: initialize a minimal git repo with one commit
-fn init_repo(dir: &PathBuf) {
-    let _ = init_repo_with_default_user(dir.as_path());
+fn init_repo(dir: &Path) {
+    let _ = init_repo_with_default_user(dir);
 }
 
 #[test]
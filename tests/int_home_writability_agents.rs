@@ -118,7 +118,7 @@ mod int_home_writability_agents {
         let mut script_path = std::env::temp_dir();
         script_path.push(format!(
             "aifo-writability-{}.sh",
-            image.replace(':', "_").replace('/', "_")
+            image.replace([':', '/'], "_")
         ));
         std::fs::write(&script_path, &script).expect("write writability script");
 
@@ -0,0 +1,102 @@
+use std::process::Command;
+
+fn helper(command: &str) -> aifo_coder::ApiKeyHelper {
+    aifo_coder::ApiKeyHelper {
+        provider: None,
+        command: command.to_string(),
+    }
+}
+
+#[test]
+fn int_test_api_key_helper_returns_first_line() {
+    if cfg!(windows) {
+        eprintln!("skipping: helper commands below use sh syntax");
+        return;
+    }
+    let key = aifo_coder::llm_run_api_key_helper(&helper("printf 'sk-from-helper\\nuser: me\\n'"))
+        .expect("helper should succeed");
+    assert_eq!(key, "sk-from-helper");
+}
+
+#[test]
+fn int_test_api_key_helper_errors_name_the_command() {
+    if cfg!(windows) {
+        eprintln!("skipping: helper commands below use sh syntax");
+        return;
+    }
+    let err = aifo_coder::llm_run_api_key_helper(&helper("exit 3")).unwrap_err();
+    assert!(
+        err.contains("api_key_command `exit 3` failed (exit status 3)"),
+        "{err}"
+    );
+
+    let err = aifo_coder::llm_run_api_key_helper(&aifo_coder::ApiKeyHelper {
+        provider: Some("azure".to_string()),
+        command: "printf ''".to_string(),
+    })
+    .unwrap_err();
+    assert!(
+        err.contains("[llm.providers.azure] api_key_command `printf ''` printed no key"),
+        "{err}"
+    );
+}
+
+fn run_aider(cfg: &std::path::Path, dry_run: bool) -> std::process::Output {
+    let home = cfg.parent().unwrap();
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_aifo-coder"));
+    if dry_run {
+        cmd.arg("--dry-run");
+    }
+    cmd.args(["aider", "--", "--version"])
+        .env("HOME", home)
+        .env("AIFO_CODER_USER_CONFIG", cfg)
+        .env("AIFO_CODER_SYSTEM_CONFIG", home.join("system.toml"))
+        .env("AIFO_API_BASE", "https://llm.example.net/v1")
+        .env("AIFO_API_VERSION", "2024-10-21")
+        .env_remove("AIFO_API_KEY")
+        .env_remove("AIFO_API_KEY_COMMAND")
+        .env_remove("AIFO_CODER_LLM_PROVIDER")
+        .env_remove("AIFO_CODER_SUPPRESS_LLM_WARNING")
+        .env_remove("AIFO_CODER_NO_CONFIG");
+    cmd.output().expect("run aifo-coder")
+}
+
+#[test]
+fn int_test_api_key_helper_provider_entry_wins_and_failure_aborts() {
+    if cfg!(windows) {
+        eprintln!("skipping: helper commands below use sh syntax");
+        return;
+    }
+    let td = tempfile::tempdir().expect("tmpdir");
+    let cfg = td.path().join("config.toml");
+    std::fs::write(
+        &cfg,
+        concat!(
+            "[llm]\n",
+            "provider = \"azure\"\n",
+            "api_key_command = \"printf sk-default\"\n",
+            "[llm.providers.azure]\n",
+            "api_key_command = \"exit 7\"\n"
+        ),
+    )
+    .unwrap();
+
+    let out = run_aider(&cfg, false);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert_eq!(out.status.code(), Some(1), "{stderr}");
+    assert!(
+        stderr.contains("[llm.providers.azure] api_key_command `exit 7` failed (exit status 7)"),
+        "{stderr}"
+    );
+
+    let out = run_aider(&cfg, true);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains("would run [llm.providers.azure] api_key_command `exit 7`"),
+        "{stderr}"
+    );
+    assert!(
+        !stderr.contains("Missing: AIFO_API_KEY"),
+        "a configured helper counts as a key source: {stderr}"
+    );
+}
@@ -0,0 +1,287 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+const REAL_KEY: &str = "sk-real-host-key";
+const SESSION_KEY: &str = "aifo-session-0123456789abcdef0123456789abcdef";
+
+/// Mock LLM upstream: records one request (head and body) and answers with a fixed JSON body.
+fn spawn_upstream(reply: &'static str) -> (u16, std::thread::JoinHandle<(String, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind upstream");
+    let port = listener.local_addr().unwrap().port();
+    let h = std::thread::spawn(move || {
+        let (s, _) = listener.accept().expect("accept");
+        s.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut reader = BufReader::new(s.try_clone().unwrap());
+        let mut head = String::new();
+        let mut len = 0usize;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some(v) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                len = v.trim().parse().unwrap();
+            }
+            head.push_str(&line);
+        }
+        let mut body = vec![0u8; len];
+        reader.read_exact(&mut body).unwrap();
+        let mut s = s;
+        write!(
+            s,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            reply.len(),
+            reply
+        )
+        .unwrap();
+        (head, String::from_utf8(body).unwrap())
+    });
+    (port, h)
+}
+
+fn start_gateway(
+    upstream_port: u16,
+    log: &std::path::Path,
+) -> (
+    u16,
+    std::sync::Arc<std::sync::atomic::AtomicBool>,
+    std::thread::JoinHandle<()>,
+) {
+    let cfg = aifo_coder::LlmGatewayConfig {
+        upstream: url::Url::parse(&format!("http://127.0.0.1:{upstream_port}/openai/v1")).unwrap(),
+        api_key: REAL_KEY.to_string(),
+        session_key: SESSION_KEY.to_string(),
+        log: Some(log.to_path_buf()),
+        verbose: false,
+    };
+    assert_eq!(
+        cfg.container_base_url("host.docker.internal", 4000),
+        "http://host.docker.internal:4000/openai/v1"
+    );
+    aifo_coder::llm_gateway_start("127.0.0.1", cfg).expect("start gateway")
+}
+
+fn roundtrip(port: u16, request: &str) -> String {
+    let mut s = TcpStream::connect(("127.0.0.1", port)).expect("connect gateway");
+    s.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    s.write_all(request.as_bytes()).unwrap();
+    let mut out = String::new();
+    let _ = s.read_to_string(&mut out);
+    out
+}
+
+#[test]
+fn int_test_llm_gateway_swaps_session_key_for_real_key() {
+    let td = tempfile::tempdir().unwrap();
+    let log = td.path().join("gateway.log");
+    let (up, up_h) = spawn_upstream("{\"id\":\"chatcmpl-1\"}");
+    let (port, flag, handle) = start_gateway(up, &log);
+
+    let body = "{\"model\":\"gpt-4o\",\"messages\":[]}";
+    let out = roundtrip(
+        port,
+        &format!(
+            concat!(
+                "POST /openai/v1/chat/completions?api-version=2024-10-21 HTTP/1.1\r\n",
+                "Host: host.docker.internal:{}\r\n",
+                "Authorization: Bearer {}\r\n",
+                "Content-Type: application/json\r\n",
+                "Content-Length: {}\r\n\r\n{}"
+            ),
+            port,
+            SESSION_KEY,
+            body.len(),
+            body
+        ),
+    );
+    assert!(out.starts_with("HTTP/1.1 200"), "{out}");
+    assert!(out.ends_with("{\"id\":\"chatcmpl-1\"}"), "{out}");
+
+    let (head, seen_body) = up_h.join().unwrap();
+    assert!(
+        head.starts_with("POST /openai/v1/chat/completions?api-version=2024-10-21 HTTP/1.1"),
+        "{head}"
+    );
+    assert!(
+        head.to_ascii_lowercase()
+            .contains(&format!("authorization: bearer {REAL_KEY}")),
+        "{head}"
+    );
+    assert!(!head.contains(SESSION_KEY), "{head}");
+    assert!(
+        head.to_ascii_lowercase()
+            .contains(&format!("host: 127.0.0.1:{up}")),
+        "{head}"
+    );
+    assert_eq!(seen_body, body);
+
+    flag.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+    let text = std::fs::read_to_string(&log).expect("gateway log");
+    assert!(
+        text.contains(&format!(
+            " POST /openai/v1/chat/completions 200 req={} resp=19 ",
+            body.len()
+        )),
+        "{text}"
+    );
+    assert!(
+        !text.contains("api-version"),
+        "query strings stay out of the log: {text}"
+    );
+    assert!(
+        !text.contains(REAL_KEY) && !text.contains(SESSION_KEY),
+        "{text}"
+    );
+}
+
+#[test]
+fn int_test_llm_gateway_replaces_api_key_header_and_decodes_chunked_body() {
+    let td = tempfile::tempdir().unwrap();
+    let log = td.path().join("gateway.log");
+    let (up, up_h) = spawn_upstream("{}");
+    let (port, flag, handle) = start_gateway(up, &log);
+
+    let out = roundtrip(
+        port,
+        &format!(
+            concat!(
+                "POST /openai/v1/embeddings HTTP/1.1\r\n",
+                "api-key: {}\r\n",
+                "Transfer-Encoding: chunked\r\n\r\n",
+                "5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n"
+            ),
+            SESSION_KEY
+        ),
+    );
+    assert!(out.starts_with("HTTP/1.1 200"), "{out}");
+    let (head, seen_body) = up_h.join().unwrap();
+    assert!(
+        head.to_ascii_lowercase()
+            .contains(&format!("api-key: {REAL_KEY}")),
+        "{head}"
+    );
+    assert!(
+        !head.to_ascii_lowercase().contains("transfer-encoding"),
+        "{head}"
+    );
+    assert_eq!(seen_body, "hello world");
+
+    flag.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+}
+
+#[test]
+fn int_test_llm_gateway_rejects_requests_without_session_key() {
+    let td = tempfile::tempdir().unwrap();
+    let log = td.path().join("gateway.log");
+    // Nothing listens here: rejected requests must never reach the upstream.
+    let (port, flag, handle) = start_gateway(9, &log);
+
+    for auth in [
+        String::new(),
+        format!("Authorization: Bearer {REAL_KEY}\r\n"),
+        format!("Authorization: Basic {SESSION_KEY}\r\n"),
+        "x-api-key: aifo-session-wrong\r\n".to_string(),
+    ] {
+        let out = roundtrip(
+            port,
+            &format!("GET /openai/v1/models HTTP/1.1\r\n{auth}\r\n"),
+        );
+        assert!(out.starts_with("HTTP/1.1 401"), "{auth:?}: {out}");
+        assert!(out.contains("missing or invalid session key"), "{out}");
+    }
+
+    flag.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+    let text = std::fs::read_to_string(&log).expect("gateway log");
+    assert_eq!(
+        text.matches(" GET /openai/v1/models 401 ").count(),
+        4,
+        "{text}"
+    );
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&log).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600);
+    }
+}
+
+#[test]
+fn int_test_llm_gateway_env_keeps_real_key_out_of_agent_env() {
+    if aifo_coder::container_runtime_path().is_err() {
+        eprintln!("skipping: docker not found in PATH");
+        return;
+    }
+    std::env::set_var("AIFO_API_KEY", REAL_KEY);
+    std::env::set_var("AIFO_API_BASE", "https://res.openai.azure.com/openai");
+    std::env::set_var(
+        aifo_coder::LLM_GATEWAY_URL_ENV,
+        "http://host.docker.internal:40111/openai",
+    );
+    std::env::set_var(aifo_coder::LLM_GATEWAY_KEY_ENV, SESSION_KEY);
+    let args = vec!["--version".to_string()];
+    let built = aifo_coder::build_docker_cmd("aider", &args, "alpine:3.20", None);
+    for k in [
+        "AIFO_API_KEY",
+        "AIFO_API_BASE",
+        aifo_coder::LLM_GATEWAY_URL_ENV,
+        aifo_coder::LLM_GATEWAY_KEY_ENV,
+    ] {
+        std::env::remove_var(k);
+    }
+    let (_cmd, preview) = built.expect("build_docker_cmd failed");
+
+    assert!(!preview.contains(REAL_KEY), "real key leaked: {preview}");
+    assert!(!preview.contains("AZURE_RESOURCE_NAME"), "{preview}");
+    for expected in [
+        format!("OPENAI_API_KEY={SESSION_KEY}"),
+        format!("AIFO_API_KEY={SESSION_KEY}"),
+        "OPENAI_BASE_URL=http://host.docker.internal:40111/openai".to_string(),
+        "AIFO_API_BASE=http://host.docker.internal:40111/openai".to_string(),
+    ] {
+        assert!(preview.contains(&expected), "missing {expected}: {preview}");
+    }
+}
+
+#[test]
+fn int_test_llm_gateway_dry_run_and_missing_key() {
+    let td = tempfile::tempdir().unwrap();
+    let home = td.path();
+    let run = |key: Option<&str>| {
+        let mut cmd = std::process::Command::new(env!("CARGO_BIN_EXE_aifo-coder"));
+        cmd.args(["--dry-run", "--llm-gateway", "aider", "--", "--version"])
+            .env("HOME", home)
+            .env("AIFO_CODER_USER_CONFIG", home.join("user.toml"))
+            .env("AIFO_CODER_SYSTEM_CONFIG", home.join("system.toml"))
+            .env("AIFO_API_BASE", "https://llm.example.net/v1")
+            .env("AIFO_CODER_SUPPRESS_LLM_WARNING", "1")
+            .env_remove("AIFO_LLM_GATEWAY_URL")
+            .env_remove("AIFO_LLM_GATEWAY_KEY");
+        match key {
+            Some(k) => cmd.env("AIFO_API_KEY", k),
+            None => cmd.env_remove("AIFO_API_KEY"),
+        };
+        cmd.output().expect("run aifo-coder")
+    };
+
+    let out = run(Some(REAL_KEY));
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains(
+            "would route LLM requests for https://llm.example.net through the credential gateway"
+        ),
+        "{stderr}"
+    );
+
+    let out = run(None);
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert_eq!(out.status.code(), Some(1), "{stderr}");
+    assert!(
+        stderr.contains("the LLM gateway needs AIFO_API_KEY"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,106 @@
+mod support;
+
+#[cfg(unix)]
+#[test]
+fn int_proxy_approval_holds_requests_for_the_approval_command() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Skip if docker isn't available on this host (proxy requires docker CLI path for runtime)
+    if aifo_coder::container_runtime_path().is_err() {
+        eprintln!("skipping: docker not found in PATH");
+        return;
+    }
+    let td = tempfile::tempdir().expect("tmpdir");
+    let bindir = td.path().join("bin");
+    std::fs::create_dir_all(&bindir).expect("mkdir bin");
+
+    // Stub approver: logs each prompt, denies anything mentioning "evil", else allows for the session.
+    let log = td.path().join("prompts.log");
+    let approver = bindir.join("approve");
+    std::fs::write(
+        &approver,
+        format!(
+            "#!/bin/sh\nprintf '%s|%s|%s\\n' \"$AIFO_APPROVAL_TOOL\" \"$AIFO_APPROVAL_ARGV\" \"$1\" >> '{}'\ncase \"$AIFO_APPROVAL_ARGV\" in *evil*) echo deny ;; *) echo always ;; esac\n",
+            log.display()
+        ),
+    )
+    .expect("write approver");
+    std::fs::set_permissions(&approver, std::fs::Permissions::from_mode(0o755))
+        .expect("chmod approver");
+    let notif_cfg = td.path().join("aider.yml");
+    std::fs::write(
+        &notif_cfg,
+        format!(
+            "notifications-command: [\"{}\", \"{{args}}\"]\n",
+            approver.display()
+        ),
+    )
+    .expect("write notifications config");
+
+    let system = td.path().join("system.toml");
+    std::fs::write(
+        &system,
+        r#"
+[[proxy.policy.npm]]
+subcommands = ["install"]
+approve = true
+"#,
+    )
+    .expect("write config");
+    let _env_guard = support::notifications_allow_test_exec_from(&bindir)
+        .set(
+            "AIFO_NOTIFICATIONS_CONFIG",
+            notif_cfg.to_string_lossy().to_string(),
+        )
+        .set(
+            "AIFO_CODER_SYSTEM_CONFIG",
+            system.to_string_lossy().to_string(),
+        )
+        .set(
+            "AIFO_CODER_USER_CONFIG",
+            td.path().join("absent.toml").to_string_lossy().to_string(),
+        )
+        .set("AIFO_TOOLEEXEC_APPROVAL", "command")
+        .remove("AIFO_CODER_NO_CONFIG")
+        .remove("AIFO_TOOLEEXEC_USE_UNIX");
+    let resolved = aifo_coder::config_init().expect("load config");
+    assert!(resolved.config.proxy.policy["npm"][0].approve);
+
+    let (url, token, running, handle) =
+        aifo_coder::toolexec_start_proxy("unit-test-session", false).expect("start proxy");
+    let port = support::port_from_http_url(&url);
+    let prompts = || std::fs::read_to_string(&log).unwrap_or_default();
+
+    let resp = support::http_send_raw(
+        port,
+        &support::exec_request(&token, "npm", &["install", "evil"]),
+    );
+    assert!(resp.starts_with("HTTP/1.1 403 Forbidden"), "got:\n{resp}");
+    assert!(
+        resp.contains("X-Aifo-Policy-Rule: proxy.policy.npm[0]"),
+        "got:\n{resp}"
+    );
+    assert!(
+        resp.contains("X-Aifo-Policy-Reason: not approved: denied by the host user"),
+        "got:\n{resp}"
+    );
+    let first = prompts();
+    assert!(first.starts_with("npm|install evil|"), "prompts:\n{first}");
+    assert!(first.contains("cwd /workspace"), "prompts:\n{first}");
+
+    // Approved ("always"): continues to routing (no sidecar runs here), then skips the prompt.
+    for _ in 0..2 {
+        let resp = support::http_send_raw(
+            port,
+            &support::exec_request(&token, "npm", &["install", "lodash"]),
+        );
+        assert!(!resp.starts_with("HTTP/1.1 403"), "got:\n{resp}");
+    }
+    // Invocations no approve rule matches are never held.
+    let resp = support::http_send_raw(port, &support::exec_request(&token, "npm", &["test"]));
+    assert!(!resp.starts_with("HTTP/1.1 403"), "got:\n{resp}");
+    assert_eq!(prompts().lines().count(), 2, "prompts:\n{}", prompts());
+
+    running.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+}
@@ -0,0 +1,137 @@
+mod support;
+
+fn post(token: &str, path: &str, body: &str) -> String {
+    format!(
+        "POST {path} HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {token}\r\nX-Aifo-Proto: 2\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+#[cfg(unix)]
+#[test]
+fn int_proxy_audit_records_denials_and_notifications() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Skip if docker isn't available on this host (proxy requires docker CLI path for runtime)
+    if aifo_coder::container_runtime_path().is_err() {
+        eprintln!("skipping: docker not found in PATH");
+        return;
+    }
+    let td = tempfile::tempdir().expect("tmpdir");
+    let audit_dir = td.path().join("audit");
+    let bindir = td.path().join("bin");
+    std::fs::create_dir_all(&bindir).expect("mkdir bin");
+    let say = bindir.join("say");
+    std::fs::write(&say, "#!/bin/sh\necho said\n").expect("write say");
+    std::fs::set_permissions(&say, std::fs::Permissions::from_mode(0o755)).expect("chmod say");
+    let notif_cfg = td.path().join("aider.yml");
+    std::fs::write(
+        &notif_cfg,
+        format!(
+            "notifications-command: [\"{}\",\"--title\",\"AIFO\"]\n",
+            say.display()
+        ),
+    )
+    .expect("write notifications config");
+    let system = td.path().join("system.toml");
+    std::fs::write(
+        &system,
+        "[[proxy.policy.cargo]]\nsubcommands = [\"publish\"]\ndeny = true\nmessage = \"no publishing\"\n",
+    )
+    .expect("write config");
+
+    let _env_guard = support::notifications_allow_test_exec_from(&bindir)
+        .set(
+            "AIFO_NOTIFICATIONS_CONFIG",
+            notif_cfg.to_string_lossy().to_string(),
+        )
+        .set(
+            "AIFO_CODER_SYSTEM_CONFIG",
+            system.to_string_lossy().to_string(),
+        )
+        .set(
+            "AIFO_CODER_USER_CONFIG",
+            td.path().join("absent.toml").to_string_lossy().to_string(),
+        )
+        .set(
+            "AIFO_TOOLEEXEC_AUDIT_DIR",
+            audit_dir.to_string_lossy().to_string(),
+        )
+        .set("AIFO_AGENT_NAME", "aider")
+        .remove("AIFO_TOOLEEXEC_AUDIT")
+        .remove("AIFO_CODER_NO_CONFIG")
+        .remove("AIFO_TOOLEEXEC_USE_UNIX");
+    aifo_coder::config_init().expect("load config");
+
+    let session = "audit-test-session";
+    let (url, token, running, handle) =
+        aifo_coder::toolexec_start_proxy(session, false).expect("start proxy");
+    let port = support::port_from_http_url(&url);
+
+    let resp = support::http_send_raw(
+        port,
+        &post(
+            &token,
+            "/exec",
+            "tool=cargo&cwd=%2Fworkspace&arg=publish&arg=--token&arg=s3cr3t",
+        ),
+    );
+    assert!(resp.starts_with("HTTP/1.1 403"), "got:\n{resp}");
+    let resp = support::http_send_raw(
+        port,
+        &post(&token, "/notify", "cmd=say&arg=--title&arg=AIFO"),
+    );
+    assert!(resp.contains("200 OK"), "got:\n{resp}");
+    running.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+
+    let path = aifo_coder::audit_log_path(session);
+    assert_eq!(path, audit_dir.join("audit-test-session.jsonl"));
+    let mode = std::fs::metadata(&path)
+        .expect("audit file")
+        .permissions()
+        .mode()
+        & 0o777;
+    assert_eq!(mode, 0o600, "audit log must be private");
+    let records = aifo_coder::audit_read(session).expect("read audit log");
+    assert_eq!(records.len(), 2, "records: {records:?}");
+
+    let denied = &records[0];
+    assert_eq!(denied["event"], "denied");
+    assert_eq!(denied["session"], session);
+    assert_eq!(denied["agent"], "aider");
+    assert_eq!(denied["tool"], "cargo");
+    assert_eq!(denied["cwd"], "/workspace");
+    assert_eq!(denied["rule"], "proxy.policy.cargo[0]");
+    assert_eq!(denied["reason"], "no publishing");
+    let argv = denied["argv"].to_string();
+    assert!(!argv.contains("s3cr3t"), "argv must be redacted: {argv}");
+    assert!(denied["ts"].as_str().is_some_and(|t| t.ends_with('Z')));
+
+    let notify = &records[1];
+    assert_eq!(notify["event"], "notify");
+    assert_eq!(notify["cmd"], "say");
+    assert_eq!(notify["argv"], serde_json::json!(["--title", "AIFO"]));
+    assert_eq!(notify["exit_code"], 0);
+
+    // The CLI reads the same file back, filtered.
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_aifo-coder"))
+        .args(["audit", "show", "--session", session, "--tool", "cargo"])
+        .env("AIFO_TOOLEEXEC_AUDIT_DIR", &audit_dir)
+        .env("AIFO_CODER_NO_CONFIG", "1")
+        .env("AIFO_CODER_SUPPRESS_LLM_WARNING", "1")
+        .output()
+        .expect("run aifo-coder");
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert!(
+        out.status.success(),
+        "stderr:\n{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_eq!(stdout.lines().count(), 1, "stdout:\n{stdout}");
+    assert!(
+        stdout.contains("denied  cargo publish --token")
+            && stdout.contains("proxy.policy.cargo[0]: no publishing"),
+        "stdout:\n{stdout}"
+    );
+}
@@ -0,0 +1,220 @@
+mod support;
+
+// A stand-in container runtime: `docker exec [opts] CONTAINER CMD...` runs CMD on the host with
+// the -e variables; /home/coder (pgid files of the exec wrapper) and /workspace map to temp dirs.
+const FAKE_DOCKER: &str = r#"#!/bin/sh
+case "$1" in
+  exec) shift ;;
+  *) exit 0 ;;
+esac
+host() { printf '%s' "$1" | sed -e "s#/home/coder#$AIFO_TEST_FAKE_ROOT/home#g" -e "s#/workspace#$AIFO_TEST_FAKE_ROOT/workspace#g"; }
+while [ $# -gt 0 ]; do
+  case "$1" in
+    -e) export "$2"; shift 2 ;;
+    -w) cd "$(host "$2")" || exit 126; shift 2 ;;
+    -u) shift 2 ;;
+    -i|-t) shift ;;
+    *) break ;;
+  esac
+done
+shift
+export HOME="$AIFO_TEST_FAKE_ROOT/home"
+for a in "$@"; do
+  shift
+  set -- "$@" "$(host "$a")"
+done
+exec "$@"
+"#;
+
+fn exec_request(token: &str, proto: &str, script: &str) -> String {
+    let body = format!("tool=python3&cwd=.&arg={}", support::urlencode(script));
+    format!(
+        "POST /exec HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {}\r\nX-Aifo-Proto: {}\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        token,
+        proto,
+        body.len(),
+        body
+    )
+}
+
+/// Send an upgrade REQ, close the exec's stdin after the 101 and collect the head and frames.
+fn upgraded_exec(port: u16, req: &str) -> (String, Vec<(u8, Vec<u8>)>) {
+    use std::io::{Read, Write};
+    let mut s = std::net::TcpStream::connect(("127.0.0.1", port)).expect("connect");
+    s.set_read_timeout(Some(std::time::Duration::from_secs(30)))
+        .unwrap();
+    s.write_all(req.as_bytes()).unwrap();
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while aifo_coder::find_header_end(&head).is_none() && s.read_exact(&mut byte).is_ok() {
+        head.push(byte[0]);
+    }
+    let head = String::from_utf8_lossy(&head).into_owned();
+    // Frames may only follow the 101; the request itself carries no stdin.
+    let _ = s.write_all(&[b'e', 0, 0, 0, 0]);
+    let mut resp = Vec::new();
+    let _ = s.read_to_end(&mut resp);
+    let mut frames = Vec::new();
+    let mut rest = &resp[..];
+    while rest.len() >= 5 {
+        let len = u32::from_be_bytes(rest[1..5].try_into().unwrap()) as usize;
+        let Some(payload) = rest.get(5..5 + len) else {
+            break;
+        };
+        frames.push((rest[0], payload.to_vec()));
+        rest = &rest[5 + len..];
+    }
+    (head, frames)
+}
+
+#[cfg(unix)]
+#[test]
+fn int_proxy_exec_limits_stop_execs_with_markers_and_exit_codes() {
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, Instant};
+
+    let login_python = std::process::Command::new("sh")
+        .args(["-lc", "command -v python3"])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false);
+    if !login_python {
+        eprintln!("skipping: python3 not found");
+        return;
+    }
+    let td = tempfile::tempdir().expect("tmpdir");
+    let bin = td.path().join("bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    std::fs::create_dir_all(td.path().join("home")).unwrap();
+    std::fs::create_dir_all(td.path().join("workspace")).unwrap();
+    let docker = bin.join("docker");
+    std::fs::write(&docker, FAKE_DOCKER).unwrap();
+    std::fs::set_permissions(&docker, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let script = |name: &str, src: &str| {
+        let p = td.path().join(name);
+        std::fs::write(&p, src).unwrap();
+        p.to_string_lossy().into_owned()
+    };
+    let idle = script(
+        "idle.py",
+        "import time\nprint('working', flush=True)\ntime.sleep(60)\n",
+    );
+    let flood = script(
+        "flood.py",
+        "import sys\nwhile True:\n    sys.stdout.write('x' * 99 + '\\n')\n",
+    );
+    let slow = script("slow.py", "import time\ntime.sleep(60)\n");
+
+    let system = td.path().join("system.toml");
+    std::fs::write(
+        &system,
+        format!(
+            r#"
+[[proxy.limits.python3]]
+subcommands = ["{idle}"]
+idle_secs = 1
+
+[[proxy.limits.python3]]
+subcommands = ["{flood}"]
+max_output_bytes = 1000
+"#
+        ),
+    )
+    .expect("write config");
+    let path = format!(
+        "{}:{}",
+        bin.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    let _env_guard = support::EnvGuard::new()
+        .set("PATH", path)
+        .set(
+            "AIFO_TEST_FAKE_ROOT",
+            td.path().to_string_lossy().to_string(),
+        )
+        .set(
+            "AIFO_CODER_SYSTEM_CONFIG",
+            system.to_string_lossy().to_string(),
+        )
+        .set(
+            "AIFO_CODER_USER_CONFIG",
+            td.path().join("absent.toml").to_string_lossy().to_string(),
+        )
+        .set("AIFO_TOOLEEXEC_MAX_SECS", "2")
+        .set("AIFO_TOOLEEXEC_AUDIT", "0")
+        .set("XDG_STATE_HOME", td.path().to_string_lossy().to_string())
+        .remove("AIFO_CODER_NO_CONFIG")
+        .remove("AIFO_CODER_CONTAINER_RUNTIME")
+        .remove("AIFO_TOOLEEXEC_IDLE_SECS")
+        .remove("AIFO_TOOLEEXEC_MAX_OUTPUT_BYTES")
+        .remove("AIFO_TOOLEEXEC_TIMEOUT_SECS")
+        .remove("AIFO_TOOLEEXEC_TLS")
+        .remove("AIFO_TOOLEEXEC_REPLAY")
+        .remove("AIFO_TOOLEEXEC_USE_UNIX");
+    let resolved = aifo_coder::config_init().expect("load config");
+    assert_eq!(resolved.config.proxy.limits["python3"].len(), 2);
+
+    let (url, token, running, handle) =
+        aifo_coder::toolexec_start_proxy("limits-test-session", false).expect("start proxy");
+    let port = support::port_from_http_url(&url);
+
+    // Idle limit from the rule: INT stops the exec long before its own sleep ends.
+    let t0 = Instant::now();
+    let resp = support::http_send_raw(port, &exec_request(&token, "2", &idle));
+    assert!(t0.elapsed() < Duration::from_secs(20), "exec not stopped");
+    assert!(resp.contains("working"), "got:\n{resp}");
+    assert!(
+        resp.contains("aifo-coder proxy timeout: no output for 1s"),
+        "got:\n{resp}"
+    );
+    assert!(resp.contains("X-Exit-Code: 123"), "got:\n{resp}");
+
+    // Output limit: truncated at max_output_bytes with a marker, then stopped.
+    let resp = support::http_send_raw(port, &exec_request(&token, "2", &flood));
+    assert!(
+        resp.contains("aifo-coder proxy: output truncated after 1000 bytes"),
+        "got:\n{resp}"
+    );
+    assert!(resp.matches('x').count() <= 1000, "got:\n{resp}");
+    assert!(resp.contains("X-Exit-Code: 122"), "got:\n{resp}");
+
+    // Proxy-wide max runtime for everything else, buffered: 504 with exit code 124.
+    let resp = support::http_send_raw(port, &exec_request(&token, "1", &slow));
+    assert!(
+        resp.starts_with("HTTP/1.1 504 Gateway Timeout"),
+        "got:\n{resp}"
+    );
+    assert!(resp.contains("X-Exit-Code: 124"), "got:\n{resp}");
+    assert!(
+        resp.contains("aifo-coder proxy timeout: exceeded 2s"),
+        "got:\n{resp}"
+    );
+
+    // Upgraded (piped stdin) execs get the same max runtime, marker and exit code.
+    let body = format!("tool=python3&cwd=.&arg={}", support::urlencode(&slow));
+    let req = format!(
+        "POST /exec HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {token}\r\nX-Aifo-Proto: 1\r\nConnection: Upgrade\r\nUpgrade: aifo-stdin\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    let t0 = Instant::now();
+    let (head, frames) = upgraded_exec(port, &req);
+    assert!(t0.elapsed() < Duration::from_secs(20), "exec not stopped");
+    assert!(head.starts_with("HTTP/1.1 101"), "got:\n{head}");
+    let output: Vec<u8> = frames
+        .iter()
+        .filter(|(kind, _)| *kind == b'o')
+        .flat_map(|(_, payload)| payload.clone())
+        .collect();
+    let output = String::from_utf8_lossy(&output);
+    assert!(
+        output.contains("aifo-coder proxy timeout: exceeded 2s"),
+        "got:\n{output}"
+    );
+    let exit = frames.last().expect("exit frame");
+    assert_eq!(exit.0, b'x');
+    assert_eq!(i32::from_be_bytes(exit.1[..4].try_into().unwrap()), 124);
+
+    running.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+}
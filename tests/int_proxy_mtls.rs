@@ -0,0 +1,110 @@
+mod support;
+
+use std::io::{Read, Write};
+
+fn health_request(token: &str) -> String {
+    format!(
+        "GET /health HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {token}\r\nConnection: close\r\n\r\n"
+    )
+}
+
+#[cfg(unix)]
+#[test]
+fn int_proxy_mtls_requires_session_client_certificate() {
+    // Skip if docker isn't available on this host (proxy requires docker CLI path for runtime)
+    if aifo_coder::container_runtime_path().is_err() {
+        eprintln!("skipping: docker not found in PATH");
+        return;
+    }
+    let td = tempfile::tempdir().expect("tmpdir");
+    let notify_cfg = td.path().join("no-such-aider.conf.yml");
+    let _env_guard = support::EnvGuard::new()
+        .set("AIFO_TOOLEEXEC_TLS", "1")
+        .set("AIFO_TOOLEEXEC_AUDIT", "0")
+        .set("XDG_STATE_HOME", td.path().to_str().unwrap())
+        .set("AIFO_NOTIFICATIONS_CONFIG", notify_cfg.to_str().unwrap())
+        .remove("AIFO_TOOLEEXEC_TLS_DIR")
+        .remove("AIFO_TOOLEEXEC_TOKEN_TTL_SECS")
+        .remove("AIFO_TOOLEEXEC_TOKEN_SCOPES")
+        .remove("AIFO_TOOLEEXEC_TOKEN_KINDS")
+        .remove("AIFO_TOOLEEXEC_REQUIRE_SIGNED")
+        .remove("AIFO_TOOLEEXEC_REPLAY")
+        .remove("AIFO_TOOLEEXEC_USE_UNIX");
+
+    let (url, token, running, handle) =
+        aifo_coder::toolexec_start_proxy("mtls-test-session", false).expect("start proxy");
+    assert!(url.starts_with("https://127.0.0.1:"), "url: {url}");
+    let port = support::port_from_http_url(&url);
+
+    // Only the client side is written, privately, and exported for the agent mount.
+    let dir = aifo_coder::toolexec_tls_dir("mtls-test-session");
+    assert_eq!(
+        std::env::var("AIFO_TOOLEEXEC_TLS_DIR").ok().as_deref(),
+        dir.to_str()
+    );
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+    let mut files: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .flatten()
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+    assert_eq!(files, ["ca.pem", "client-key.pem", "client.pem"]);
+
+    // A shim-style client with the session certificate is served.
+    let mut s = aifo_coder::toolexec_tls_connect("127.0.0.1", port, &dir).expect("tls connect");
+    s.write_all(health_request(&token).as_bytes()).unwrap();
+    let mut resp = String::new();
+    s.read_to_string(&mut resp).expect("clean TLS close");
+    assert!(resp.starts_with("HTTP/1.1 200"), "got:\n{resp}");
+
+    // Plain HTTP is not answered.
+    let resp = support::http_send_raw(port, &health_request(&token));
+    assert!(!resp.contains("HTTP/1.1 200"), "got:\n{resp}");
+
+    // A client that trusts the CA but has no client certificate is refused.
+    let config = aifo_coder::toolexec_tls_client_config(&dir, false).expect("client config");
+    let name = rustls::pki_types::ServerName::try_from("127.0.0.1").unwrap();
+    let conn = rustls::ClientConnection::new(config, name).unwrap();
+    let sock = std::net::TcpStream::connect(("127.0.0.1", port)).unwrap();
+    sock.set_read_timeout(Some(std::time::Duration::from_secs(5)))
+        .unwrap();
+    let mut tls = rustls::StreamOwned::new(conn, sock);
+    let _ = tls.write_all(health_request(&token).as_bytes());
+    let mut buf = Vec::new();
+    assert!(
+        tls.read_to_end(&mut buf).is_err() && buf.is_empty(),
+        "anonymous client must not get a response"
+    );
+
+    // The shim speaks mutual TLS natively: a notify without a notifications config gets the
+    // proxy's error rather than a connection failure.
+    let say = td.path().join("say");
+    std::os::unix::fs::symlink(env!("CARGO_BIN_EXE_aifo-shim"), &say).expect("symlink shim");
+    let out = std::process::Command::new(&say)
+        .arg("hi")
+        .env("AIFO_TOOLEEXEC_URL", &url)
+        .env("AIFO_TOOLEEXEC_TOKEN", &token)
+        .env("AIFO_TOOLEEXEC_TLS_DIR", &dir)
+        .env("AIFO_SHIM_NOTIFY_ASYNC", "0")
+        .env("AIFO_SHIM_NATIVE_HTTP", "1")
+        .env("HOME", td.path())
+        .stdin(std::process::Stdio::null())
+        .output()
+        .expect("run shim");
+    let text =
+        String::from_utf8_lossy(&out.stdout).to_string() + &String::from_utf8_lossy(&out.stderr);
+    assert_eq!(out.status.code(), Some(86), "shim output:\n{text}");
+    assert!(
+        text.contains("cannot read") && text.contains("no-such-aider.conf.yml"),
+        "shim output:\n{text}"
+    );
+
+    running.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+    assert!(!dir.exists(), "TLS dir removed when the proxy stops");
+}
@@ -0,0 +1,82 @@
+mod support;
+use support::urlencode;
+
+#[cfg(target_os = "linux")]
+#[test]
+fn int_proxy_mtls_streams_piped_stdin_to_the_tool() {
+    use std::io::{Read, Write};
+    use std::time::Duration;
+
+    if !support::have_login_python3() {
+        eprintln!("skipping: python3 not found");
+        return;
+    }
+    let td = tempfile::tempdir().expect("tmpdir");
+    let _env_guard = support::fake_docker_env(td.path())
+        .set("AIFO_TOOLEEXEC_TLS", "1")
+        .set("AIFO_TOOLEEXEC_AUDIT", "0")
+        .set("AIFO_CODER_NO_CONFIG", "1")
+        .set("XDG_STATE_HOME", td.path().to_str().unwrap())
+        .remove("AIFO_TOOLEEXEC_TLS_DIR")
+        .remove("AIFO_TOOLEEXEC_TOKEN_TTL_SECS")
+        .remove("AIFO_TOOLEEXEC_TOKEN_SCOPES")
+        .remove("AIFO_TOOLEEXEC_TOKEN_KINDS")
+        .remove("AIFO_TOOLEEXEC_REQUIRE_SIGNED")
+        .remove("AIFO_TOOLEEXEC_REPLAY")
+        .remove("AIFO_CODER_CONTAINER_RUNTIME")
+        .remove("AIFO_TOOLEEXEC_USE_UNIX");
+
+    let (url, token, running, handle) =
+        aifo_coder::toolexec_start_proxy("mtls-stdin-session", false).expect("start proxy");
+    assert!(url.starts_with("https://127.0.0.1:"), "url: {url}");
+    let port = support::port_from_http_url(&url);
+    let dir = aifo_coder::toolexec_tls_dir("mtls-stdin-session");
+
+    // Like the shim over https: one TLS stream carries stdin frames and output frames.
+    let mut s = aifo_coder::toolexec_tls_connect("127.0.0.1", port, &dir).expect("tls connect");
+    let body = format!(
+        "tool=python3&cwd=.&arg=-c&arg={}",
+        urlencode("import sys; sys.stdout.write(sys.stdin.read().upper()); sys.exit(4)")
+    );
+    let req = format!(
+        "POST /exec HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {token}\r\nX-Aifo-Proto: 1\r\nConnection: Upgrade\r\nUpgrade: {}\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\n\r\n{body}",
+        aifo_coder::STDIN_UPGRADE,
+        body.len()
+    );
+    s.write_all(req.as_bytes()).expect("write request");
+    s.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while aifo_coder::find_header_end(&head).is_none() {
+        s.read_exact(&mut byte).expect("read response header");
+        head.push(byte[0]);
+    }
+    let head = String::from_utf8_lossy(&head).to_string();
+    assert!(head.starts_with("HTTP/1.1 101 "), "got:\n{head}");
+
+    for piece in ["piped over ", "mutual tls\n"] {
+        aifo_coder::pty_write_frame(&mut s, aifo_coder::PTY_FRAME_STDIN, piece.as_bytes())
+            .expect("write stdin frame");
+    }
+    aifo_coder::pty_write_frame(&mut s, aifo_coder::PTY_FRAME_STDIN_EOF, b"")
+        .expect("write eof frame");
+
+    let mut frames = aifo_coder::PtyFrameReader::new();
+    let mut output = Vec::new();
+    let mut code = None;
+    while let Some((kind, payload)) = frames.read_frame(&mut s).expect("read frame") {
+        match kind {
+            aifo_coder::PTY_FRAME_OUTPUT => output.extend_from_slice(&payload),
+            aifo_coder::PTY_FRAME_EXIT => {
+                code = Some(i32::from_be_bytes(payload[..4].try_into().unwrap()));
+                break;
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(String::from_utf8_lossy(&output), "PIPED OVER MUTUAL TLS\n");
+    assert_eq!(code, Some(4));
+
+    running.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+}
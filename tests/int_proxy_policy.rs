@@ -0,0 +1,72 @@
+mod support;
+
+#[test]
+fn int_proxy_policy_denies_with_structured_403_before_exec() {
+    // Skip if docker isn't available on this host (proxy requires docker CLI path for runtime)
+    if aifo_coder::container_runtime_path().is_err() {
+        eprintln!("skipping: docker not found in PATH");
+        return;
+    }
+    let td = tempfile::tempdir().expect("tmpdir");
+    let system = td.path().join("system.toml");
+    std::fs::write(
+        &system,
+        r#"
+[[proxy.policy.cargo]]
+subcommands = ["publish"]
+deny = true
+message = "publishing crates from agents is disabled"
+
+[[proxy.policy.cargo]]
+subcommands = ["build"]
+require = ["--locked"]
+"#,
+    )
+    .expect("write config");
+    let _env_guard = support::EnvGuard::new()
+        .set(
+            "AIFO_CODER_SYSTEM_CONFIG",
+            system.to_string_lossy().to_string(),
+        )
+        .set(
+            "AIFO_CODER_USER_CONFIG",
+            td.path().join("absent.toml").to_string_lossy().to_string(),
+        )
+        .remove("AIFO_CODER_NO_CONFIG")
+        .remove("AIFO_TOOLEEXEC_USE_UNIX");
+    let resolved = aifo_coder::config_init().expect("load config");
+    assert_eq!(resolved.config.proxy.policy["cargo"].len(), 2);
+
+    let (url, token, running, handle) =
+        aifo_coder::toolexec_start_proxy("unit-test-session", false).expect("start proxy");
+    let port = support::port_from_http_url(&url);
+
+    let resp = support::http_send_raw(
+        port,
+        &support::exec_request(&token, "cargo", &["--quiet", "publish", "--dry-run"]),
+    );
+    assert!(resp.starts_with("HTTP/1.1 403 Forbidden"), "got:\n{resp}");
+    let lower = resp.to_ascii_lowercase();
+    assert!(lower.contains("x-exit-code: 86"), "got:\n{resp}");
+    assert!(lower.contains("x-aifo-policy: denied"), "got:\n{resp}");
+    assert!(
+        resp.contains("X-Aifo-Policy-Rule: proxy.policy.cargo[0]"),
+        "got:\n{resp}"
+    );
+    assert!(
+        resp.contains("X-Aifo-Policy-Reason: publishing crates from agents is disabled"),
+        "got:\n{resp}"
+    );
+    assert!(
+        resp.contains("cargo blocked by proxy policy proxy.policy.cargo[0]"),
+        "got:\n{resp}"
+    );
+
+    // Allowed invocations pass the policy and continue to routing (no sidecar runs here).
+    let resp = support::http_send_raw(port, &support::exec_request(&token, "cargo", &["build"]));
+    assert!(!resp.starts_with("HTTP/1.1 403"), "got:\n{resp}");
+    assert!(!resp.contains("X-Aifo-Policy"), "got:\n{resp}");
+
+    running.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+}
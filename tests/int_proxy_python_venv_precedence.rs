@@ -37,7 +37,7 @@ fn int_proxy_python_venv_precedence() {
     let kinds = vec!["python".to_string()];
     let image = support::default_python_test_image();
     let rt = aifo_coder::container_runtime_path().expect("runtime");
-    let present = support::docker_image_present(&rt.as_path(), &image);
+    let present = support::docker_image_present(rt.as_path(), &image);
     if !present {
         eprintln!("skipping: test image not present locally: {}", image);
         return;
@@ -0,0 +1,80 @@
+mod support;
+
+fn exec(token: &str, proto: &str, body: &str) -> String {
+    format!(
+        "POST /exec HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {token}\r\nX-Aifo-Proto: {proto}\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+#[cfg(unix)]
+#[test]
+fn int_proxy_replay_serves_recorded_execs() {
+    // Skip if docker isn't available on this host (proxy requires docker CLI path for runtime)
+    if aifo_coder::container_runtime_path().is_err() {
+        eprintln!("skipping: docker not found in PATH");
+        return;
+    }
+    let td = tempfile::tempdir().expect("tmpdir");
+    let transcript = td.path().join("transcript.jsonl");
+    let recorded = serde_json::json!({
+        "exec_id": "rec-1",
+        "tool": "cargo",
+        "argv": ["build"],
+        "cwd": "/workspace",
+        "chunks": [{"t_ms": 0, "text": "Compiling demo\n"}, {"t_ms": 5, "hex": "6f6b0a"}],
+        "exit_code": 3,
+    });
+    std::fs::write(&transcript, format!("{recorded}\n")).expect("write transcript");
+
+    let _env_guard = support::EnvGuard::new()
+        .set(
+            "AIFO_TOOLEEXEC_REPLAY",
+            transcript.to_string_lossy().to_string(),
+        )
+        .remove("AIFO_TOOLEEXEC_REPLAY_TIMING")
+        .remove("AIFO_TOOLEEXEC_RECORD")
+        .remove("AIFO_TOOLEEXEC_USE_UNIX")
+        .set("AIFO_TOOLEEXEC_AUDIT", "0");
+
+    let (url, token, running, handle) =
+        aifo_coder::toolexec_start_proxy("replay-test-session", false).expect("start proxy");
+    let port = support::port_from_http_url(&url);
+
+    // v2: streamed as recorded, exit code in the trailer.
+    let resp = support::http_send_raw(
+        port,
+        &exec(&token, "2", "tool=cargo&cwd=%2Fworkspace&arg=build"),
+    );
+    assert!(resp.starts_with("HTTP/1.1 200"), "got:\n{resp}");
+    assert!(
+        resp.to_ascii_lowercase()
+            .contains("transfer-encoding: chunked"),
+        "got:\n{resp}"
+    );
+    assert!(
+        resp.contains("Compiling demo\n") && resp.contains("ok\n"),
+        "got:\n{resp}"
+    );
+    assert!(resp.contains("X-Exit-Code: 3"), "got:\n{resp}");
+
+    // v1: buffered body with the exit code header.
+    let resp = support::http_send_raw(
+        port,
+        &exec(&token, "1", "tool=cargo&cwd=%2Fworkspace&arg=build"),
+    );
+    assert!(resp.starts_with("HTTP/1.1 200"), "got:\n{resp}");
+    assert!(resp.contains("X-Exit-Code: 3"), "got:\n{resp}");
+    assert!(resp.ends_with("Compiling demo\nok\n"), "got:\n{resp}");
+
+    // Anything not in the transcript is refused instead of run.
+    let resp = support::http_send_raw(
+        port,
+        &exec(&token, "2", "tool=cargo&cwd=%2Fworkspace&arg=test"),
+    );
+    assert!(resp.starts_with("HTTP/1.1 404"), "got:\n{resp}");
+    assert!(resp.contains("replay: no recorded exec"), "got:\n{resp}");
+
+    running.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+}
@@ -0,0 +1,145 @@
+mod support;
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+fn read_response(mut stream: TcpStream) -> String {
+    stream
+        .set_read_timeout(Some(Duration::from_secs(10)))
+        .expect("read timeout");
+    let mut buf = Vec::new();
+    let _ = stream.read_to_end(&mut buf);
+    String::from_utf8_lossy(&buf).to_string()
+}
+
+#[test]
+fn int_proxy_saturated_returns_503_and_stops_promptly() {
+    // Skip if docker isn't available on this host (proxy requires docker CLI path for runtime)
+    if aifo_coder::container_runtime_path().is_err() {
+        eprintln!("skipping: docker not found in PATH");
+        return;
+    }
+    let _env_guard = support::EnvGuard::new()
+        .set("AIFO_TOOLEEXEC_MAX_CONNECTIONS", "1")
+        .remove("AIFO_TOOLEEXEC_USE_UNIX");
+
+    let (url, _token, running, handle) =
+        aifo_coder::toolexec_start_proxy("unit-test-session", false).expect("start proxy");
+    let addr = format!("127.0.0.1:{}", support::port_from_http_url(&url));
+
+    // A client that never finishes its request holds the only worker.
+    let mut stalled = TcpStream::connect(&addr).expect("connect stalled");
+    stalled
+        .write_all(b"POST /exec HTTP/1.1\r\nHost: localhost\r\n")
+        .expect("write partial request");
+    std::thread::sleep(Duration::from_millis(300));
+
+    let mut second = TcpStream::connect(&addr).expect("connect second");
+    second
+        .write_all(b"GET /nope HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .expect("write request");
+    let resp = read_response(second);
+    assert!(
+        resp.starts_with("HTTP/1.1 503 Service Unavailable"),
+        "expected 503 while saturated, got:\n{resp}"
+    );
+    assert!(
+        resp.to_ascii_lowercase().contains("x-exit-code: 86"),
+        "expected X-Exit-Code: 86, got:\n{resp}"
+    );
+    assert!(resp.contains("proxy busy"), "got:\n{resp}");
+
+    // Shutdown must not wait for the stalled connection.
+    let started = Instant::now();
+    running.store(false, Ordering::SeqCst);
+    handle.join().expect("join proxy");
+    assert!(
+        started.elapsed() < Duration::from_secs(2),
+        "proxy took {:?} to stop",
+        started.elapsed()
+    );
+    drop(stalled);
+}
+
+#[test]
+fn int_proxy_slow_consumer_hits_write_deadline_and_frees_worker() {
+    if aifo_coder::container_runtime_path().is_err() {
+        eprintln!("skipping: docker not found in PATH");
+        return;
+    }
+    if !cfg!(unix) {
+        eprintln!("skipping: non-unix host for stub say");
+        return;
+    }
+
+    let td = tempfile::tempdir().expect("tmpdir");
+    let bindir = td.path().join("bin");
+    std::fs::create_dir_all(&bindir).expect("mkdir bin");
+    let say = bindir.join("say");
+    // Far more output than the socket buffers hold.
+    std::fs::write(
+        &say,
+        "#!/bin/sh\nhead -c 33554432 /dev/zero | tr '\\000' x\n",
+    )
+    .expect("write say");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&say, std::fs::Permissions::from_mode(0o755)).expect("chmod say");
+    }
+    let cfg = td.path().join("aider.yml");
+    std::fs::write(
+        &cfg,
+        format!("notifications-command: [\"{}\"]\n", say.display()),
+    )
+    .expect("write cfg");
+
+    let _env_guard = support::notifications_allow_test_exec_from(&bindir)
+        .set(
+            "AIFO_NOTIFICATIONS_CONFIG",
+            cfg.to_string_lossy().to_string(),
+        )
+        .set("AIFO_NOTIFICATIONS_NOAUTH", "1")
+        .set("AIFO_TOOLEEXEC_MAX_CONNECTIONS", "1")
+        .set("AIFO_TOOLEEXEC_WRITE_TIMEOUT_SECS", "1")
+        .remove("AIFO_TOOLEEXEC_USE_UNIX");
+
+    let (url, _token, running, handle) =
+        aifo_coder::toolexec_start_proxy("unit-test-session", false).expect("start proxy");
+    let addr = format!("127.0.0.1:{}", support::port_from_http_url(&url));
+
+    // Request a large response and never read it.
+    let body = "cmd=say";
+    let req = format!(
+        "POST /notify HTTP/1.1\r\nHost: localhost\r\nX-Aifo-Proto: 2\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    let mut slow = TcpStream::connect(&addr).expect("connect slow consumer");
+    slow.write_all(req.as_bytes()).expect("write request");
+
+    // Once the write deadline fails the stuck write, the only worker serves new connections.
+    let deadline = Instant::now() + Duration::from_secs(20);
+    let mut last = String::new();
+    while Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(250));
+        let mut probe = TcpStream::connect(&addr).expect("connect probe");
+        probe
+            .write_all(b"GET /nope HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .expect("write probe");
+        last = read_response(probe);
+        if !last.contains("503 Service Unavailable") {
+            break;
+        }
+    }
+    assert!(
+        last.starts_with("HTTP/1.1 ") && !last.contains("503 Service Unavailable"),
+        "worker still pinned by the slow consumer; last response:\n{last}"
+    );
+
+    running.store(false, Ordering::SeqCst);
+    let _ = handle.join();
+    drop(slow);
+}
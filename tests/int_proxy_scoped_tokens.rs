@@ -0,0 +1,201 @@
+mod support;
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+fn wait_until(unix_secs: u64) {
+    while now() < unix_secs {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+}
+
+fn signed_post(token: &str, path: &str, body: &str) -> String {
+    let key = aifo_coder::toolexec_signing_key();
+    let sig: String = aifo_coder::toolexec_signature_headers(
+        key,
+        path,
+        aifo_coder::SignedHeaders::default(),
+        body.as_bytes(),
+    )
+    .iter()
+    .map(|(k, v)| format!("{k}: {v}\r\n"))
+    .collect();
+    format!(
+        "POST {path} HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {token}\r\nX-Aifo-Proto: 2\r\n{sig}Content-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+fn issued_token(resp: &str) -> String {
+    assert!(resp.starts_with("HTTP/1.1 200"), "got:\n{resp}");
+    let body = resp.split("\r\n\r\n").nth(1).unwrap_or("");
+    let v: serde_json::Value = serde_json::from_str(body).expect("json body");
+    v["token"].as_str().expect("token").to_string()
+}
+
+#[cfg(unix)]
+#[test]
+fn int_proxy_scoped_tokens_expire_and_enforce_scope_and_signatures() {
+    use aifo_coder::TokenScope;
+
+    // Skip if docker isn't available on this host (proxy requires docker CLI path for runtime)
+    if aifo_coder::container_runtime_path().is_err() {
+        eprintln!("skipping: docker not found in PATH");
+        return;
+    }
+    let td = tempfile::tempdir().expect("tmpdir");
+    let _env_guard = support::EnvGuard::new()
+        .set("AIFO_TOOLEEXEC_TOKEN_TTL_SECS", "60")
+        .set("AIFO_TOOLEEXEC_REQUIRE_SIGNED", "1")
+        .set("AIFO_TOOLEEXEC_AUDIT", "0")
+        .remove("AIFO_TOOLEEXEC_TOKEN_SCOPES")
+        .remove("AIFO_TOOLEEXEC_TOKEN_KINDS")
+        .remove("AIFO_TOOLEEXEC_REPLAY")
+        .remove("AIFO_TOOLEEXEC_USE_UNIX");
+
+    let (url, token, running, handle) =
+        aifo_coder::toolexec_start_proxy("tokens-test-session", false).expect("start proxy");
+    let port = support::port_from_http_url(&url);
+
+    // The agent gets a scoped token with every scope and the configured lifetime.
+    let claims = aifo_coder::token_claims(&token).expect("scoped token");
+    assert_eq!(claims.scopes, TokenScope::ALL.to_vec());
+    assert_eq!(claims.exp, claims.iat + 60);
+
+    // Unsigned requests are refused when signatures are required.
+    let unsigned = format!(
+        "POST /token HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {token}\r\nX-Aifo-Proto: 2\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+    );
+    let resp = support::http_send_raw(port, &unsigned);
+    assert!(
+        resp.starts_with("HTTP/1.1 401") && resp.contains("request signature required"),
+        "got:\n{resp}"
+    );
+
+    // A signed request is accepted once; replaying it is refused.
+    let derive = signed_post(&token, "/token", "scope=notify&ttl_secs=2");
+    let notify_only = issued_token(&support::http_send_raw(port, &derive));
+    let resp = support::http_send_raw(port, &derive);
+    assert!(
+        resp.starts_with("HTTP/1.1 401") && resp.contains("nonce already used"),
+        "got:\n{resp}"
+    );
+    let nc = aifo_coder::token_claims(&notify_only).expect("claims");
+    assert_eq!(nc.scopes, vec![TokenScope::Notify]);
+    assert_eq!(nc.exp, nc.iat + 2);
+
+    // Scope is enforced per endpoint; a narrower token cannot widen itself.
+    for (path, body) in [
+        ("/exec", "tool=cargo&cwd=%2Fworkspace&arg=build"),
+        ("/signal", "exec_id=x&signal=INT"),
+    ] {
+        let resp = support::http_send_raw(port, &signed_post(&notify_only, path, body));
+        assert!(
+            resp.starts_with("HTTP/1.1 403") && resp.contains("token scope does not allow"),
+            "{path} got:\n{resp}"
+        );
+    }
+    let resp = support::http_send_raw(port, &signed_post(&notify_only, "/token", "scope=exec"));
+    assert!(resp.starts_with("HTTP/1.1 403"), "got:\n{resp}");
+    // GET /health is signed like every other request (nonces only need to be unique).
+    let health_calls = std::cell::Cell::new(0u32);
+    let health = |tok: &str, signed: bool| {
+        let ts = now();
+        health_calls.set(health_calls.get() + 1);
+        let nonce = format!("health-{}", health_calls.get());
+        let sig = aifo_coder::toolexec_request_signature(
+            aifo_coder::toolexec_signing_key(),
+            "GET",
+            "/health",
+            ts,
+            &nonce,
+            aifo_coder::SignedHeaders::default(),
+            b"",
+        );
+        let sig = if signed {
+            format!(
+                "X-Aifo-Timestamp: {ts}\r\nX-Aifo-Nonce: {nonce}\r\nX-Aifo-Signature: {sig}\r\n"
+            )
+        } else {
+            String::new()
+        };
+        support::http_send_raw(
+            port,
+            &format!(
+                "GET /health HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {tok}\r\n{sig}Connection: close\r\n\r\n"
+            ),
+        )
+    };
+    assert!(health(&notify_only, true).starts_with("HTTP/1.1 401"));
+    let resp = health(&token, false);
+    assert!(
+        resp.starts_with("HTTP/1.1 401") && resp.contains("request signature required"),
+        "got:\n{resp}"
+    );
+    assert!(health(&token, true).starts_with("HTTP/1.1 200"));
+
+    // Per-sidecar-kind tokens: cargo routes to the rust sidecar.
+    let node_only = issued_token(&support::http_send_raw(
+        port,
+        &signed_post(&token, "/token", "scope=exec&kind=node"),
+    ));
+    let resp = support::http_send_raw(
+        port,
+        &signed_post(&node_only, "/exec", "tool=cargo&cwd=%2Fworkspace&arg=build"),
+    );
+    assert!(
+        resp.starts_with("HTTP/1.1 403") && resp.contains("not valid for sidecar kind rust"),
+        "got:\n{resp}"
+    );
+
+    // Expired tokens are refused.
+    wait_until(nc.exp);
+    let resp = support::http_send_raw(
+        port,
+        &signed_post(&notify_only, "/notify", "cmd=say&arg=hi"),
+    );
+    assert!(
+        resp.starts_with("HTTP/1.1 401") && resp.contains("token expired"),
+        "got:\n{resp}"
+    );
+
+    // The shim rotates a token past two thirds of its lifetime and caches the new one.
+    let short = issued_token(&support::http_send_raw(
+        port,
+        &signed_post(&token, "/token", "ttl_secs=6"),
+    ));
+    let sc = aifo_coder::token_claims(&short).expect("claims");
+    wait_until(sc.iat + 4);
+    let say = td.path().join("say");
+    std::os::unix::fs::symlink(env!("CARGO_BIN_EXE_aifo-shim"), &say).expect("symlink shim");
+    let _ = std::process::Command::new(&say)
+        .arg("hi")
+        .env("AIFO_TOOLEEXEC_URL", &url)
+        .env("AIFO_TOOLEEXEC_TOKEN", &short)
+        .env("AIFO_SHIM_NOTIFY_ASYNC", "0")
+        .env("HOME", td.path())
+        .env("TMPDIR", td.path())
+        .stdin(std::process::Stdio::null())
+        .output()
+        .expect("run shim");
+    let cached = std::fs::read_to_string(td.path().join(".aifo-exec").join("token"))
+        .expect("rotated token cached");
+    let rc = aifo_coder::token_claims(cached.trim()).expect("cached claims");
+    assert_eq!(rc.scopes, sc.scopes);
+    assert!(rc.id != sc.id && rc.iat > sc.iat);
+    assert_eq!(rc.exp, sc.exp, "rotation must not extend the lifetime");
+    // The rotated-away token keeps working for concurrent shims during the grace period, but
+    // never past its own expiry.
+    let resp = support::http_send_raw(port, &signed_post(&short, "/notify", "cmd=say&arg=hi"));
+    assert!(!resp.starts_with("HTTP/1.1 401"), "got:\n{resp}");
+    wait_until(sc.exp);
+    let resp = support::http_send_raw(port, &signed_post(&short, "/notify", "cmd=say&arg=hi"));
+    assert!(resp.starts_with("HTTP/1.1 401"), "got:\n{resp}");
+
+    running.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+}
@@ -0,0 +1,90 @@
+mod support;
+
+fn post(token: &str, path: &str, body: &str) -> String {
+    format!(
+        "POST {path} HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {token}\r\nX-Aifo-Proto: 2\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+#[cfg(unix)]
+#[test]
+fn int_proxy_signal_requires_a_token_for_the_exec_sidecar_kind() {
+    use std::time::{Duration, Instant};
+
+    if !support::have_login_python3() {
+        eprintln!("skipping: python3 not found");
+        return;
+    }
+    let td = tempfile::tempdir().expect("tmpdir");
+    let _env_guard = support::fake_docker_env(td.path())
+        .set("AIFO_TOOLEEXEC_TOKEN_TTL_SECS", "60")
+        .set("AIFO_TOOLEEXEC_AUDIT", "0")
+        .set("AIFO_CODER_NO_CONFIG", "1")
+        .remove("AIFO_TOOLEEXEC_REQUIRE_SIGNED")
+        .remove("AIFO_TOOLEEXEC_TOKEN_SCOPES")
+        .remove("AIFO_TOOLEEXEC_TOKEN_KINDS")
+        .remove("AIFO_CODER_CONTAINER_RUNTIME")
+        .remove("AIFO_TOOLEEXEC_USE_UNIX")
+        .remove("AIFO_TOOLEEXEC_TLS")
+        .remove("AIFO_TOOLEEXEC_REPLAY");
+
+    let (url, token, running, handle) =
+        aifo_coder::toolexec_start_proxy("signal-kinds-session", false).expect("start proxy");
+    let port = support::port_from_http_url(&url);
+
+    // python3 runs in the python sidecar until it is signalled.
+    let body = format!(
+        "tool=python3&cwd=.&arg=-c&arg={}",
+        support::urlencode("import time; time.sleep(30)")
+    );
+    let exec = format!(
+        "POST /exec HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {token}\r\nX-Aifo-Proto: 1\r\nX-Aifo-Exec-Id: sig-kind-1\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let started = Instant::now();
+    let exec_thread = std::thread::spawn(move || support::http_send_raw(port, &exec));
+    let status = format!(
+        "GET /status HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {token}\r\nConnection: close\r\n\r\n"
+    );
+    while !support::http_send_raw(port, &status).contains("sig-kind-1") {
+        assert!(
+            started.elapsed() < Duration::from_secs(10),
+            "exec never registered"
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let issued = |body: &str| {
+        let resp = support::http_send_raw(port, &post(&token, "/token", body));
+        assert!(resp.starts_with("HTTP/1.1 200"), "got:\n{resp}");
+        let v: serde_json::Value =
+            serde_json::from_str(resp.split("\r\n\r\n").nth(1).unwrap_or("")).expect("json");
+        v["token"].as_str().expect("token").to_string()
+    };
+    let node_only = issued("scope=signal&kind=node");
+    let python_only = issued("scope=signal&kind=python");
+
+    let resp = support::http_send_raw(
+        port,
+        &post(&node_only, "/signal", "exec_id=sig-kind-1&signal=KILL"),
+    );
+    assert!(
+        resp.starts_with("HTTP/1.1 403") && resp.contains("not valid for sidecar kind python"),
+        "got:\n{resp}"
+    );
+
+    let resp = support::http_send_raw(
+        port,
+        &post(&python_only, "/signal", "exec_id=sig-kind-1&signal=KILL"),
+    );
+    assert!(resp.starts_with("HTTP/1.1 204"), "got:\n{resp}");
+    let exec_resp = exec_thread.join().expect("exec thread");
+    assert!(
+        started.elapsed() < Duration::from_secs(25),
+        "signal did not stop the exec:\n{exec_resp}"
+    );
+
+    running.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+}
@@ -0,0 +1,63 @@
+mod support;
+
+#[test]
+fn int_proxy_health_and_status_require_auth_and_return_json() {
+    // Skip if docker isn't available on this host (proxy requires docker CLI path for runtime)
+    if aifo_coder::container_runtime_path().is_err() {
+        eprintln!("skipping: docker not found in PATH");
+        return;
+    }
+    let _env_guard = support::EnvGuard::new().remove("AIFO_TOOLEEXEC_USE_UNIX");
+    let sid = "unit-test-status";
+    let (url, token, running, handle) =
+        aifo_coder::toolexec_start_proxy(sid, false).expect("start proxy");
+    let port = support::port_from_http_url(&url);
+
+    let resp = support::http_send_raw(
+        port,
+        "GET /status HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+    );
+    assert!(resp.starts_with("HTTP/1.1 401"), "got:\n{resp}");
+
+    let resp = support::http_send_raw(
+        port,
+        &format!(
+            "POST /health HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {token}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        ),
+    );
+    assert!(resp.starts_with("HTTP/1.1 405"), "got:\n{resp}");
+
+    let resp = support::http_send_raw(
+        port,
+        &format!(
+            "GET /health HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {token}\r\nConnection: close\r\n\r\n"
+        ),
+    );
+    assert!(resp.starts_with("HTTP/1.1 200 OK"), "got:\n{resp}");
+    assert!(
+        resp.contains("Content-Type: application/json"),
+        "got:\n{resp}"
+    );
+    let health: serde_json::Value =
+        serde_json::from_str(support::body_of(&resp)).expect("health json");
+    assert_eq!(health["status"], "ok");
+    assert_eq!(health["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(health["in_flight"], 0);
+
+    let resp = support::http_send_raw(
+        port,
+        &format!(
+            "GET /status HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {token}\r\nConnection: close\r\n\r\n"
+        ),
+    );
+    assert!(resp.starts_with("HTTP/1.1 200 OK"), "got:\n{resp}");
+    let status: serde_json::Value =
+        serde_json::from_str(support::body_of(&resp)).expect("status json");
+    assert_eq!(status["session"], sid);
+    assert!(status["uptime_secs"].is_u64(), "{status}");
+    assert!(status["sidecars"].is_array(), "{status}");
+    assert_eq!(status["execs"], serde_json::json!([]));
+
+    running.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+}
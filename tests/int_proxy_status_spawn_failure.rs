@@ -0,0 +1,59 @@
+mod support;
+
+#[cfg(unix)]
+#[test]
+fn int_proxy_status_drops_execs_whose_spawn_failed() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let td = tempfile::tempdir().expect("tmpdir");
+    // The proxy keeps the runtime found at start; later lookups (container checks) use PATH.
+    let start_bin = td.path().join("start-bin");
+    let later_bin = td.path().join("later-bin");
+    for dir in [&start_bin, &later_bin] {
+        std::fs::create_dir_all(dir).unwrap();
+        let docker = dir.join("docker");
+        std::fs::write(&docker, "#!/bin/sh\nexit 0\n").unwrap();
+        std::fs::set_permissions(&docker, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+    let path = std::env::var("PATH").unwrap_or_default();
+    let env_guard = support::EnvGuard::new()
+        .set("PATH", format!("{}:{path}", start_bin.display()))
+        .set("AIFO_CODER_NO_CONFIG", "1")
+        .set("AIFO_TOOLEEXEC_AUDIT", "0")
+        .remove("AIFO_CODER_CONTAINER_RUNTIME")
+        .remove("AIFO_TOOLEEXEC_REQUIRE_SIGNED")
+        .remove("AIFO_TOOLEEXEC_TLS")
+        .remove("AIFO_TOOLEEXEC_REPLAY")
+        .remove("AIFO_TOOLEEXEC_USE_UNIX");
+    let (url, token, running, handle) =
+        aifo_coder::toolexec_start_proxy("unit-test-status-spawn", false).expect("start proxy");
+    let port = support::port_from_http_url(&url);
+    std::fs::remove_file(start_bin.join("docker")).unwrap();
+    let _env_guard = env_guard.set("PATH", format!("{}:{path}", later_bin.display()));
+
+    let body = "tool=python3&cwd=.&arg=--version";
+    let resp = support::http_send_raw(
+        port,
+        &format!(
+            "POST /exec HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {token}\r\nX-Aifo-Proto: 1\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        ),
+    );
+    assert!(
+        resp.starts_with("HTTP/1.1 500") && resp.contains("X-Exit-Code: 86"),
+        "got:\n{resp}"
+    );
+
+    let resp = support::http_send_raw(
+        port,
+        &format!(
+            "GET /status HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {token}\r\nConnection: close\r\n\r\n"
+        ),
+    );
+    let status: serde_json::Value =
+        serde_json::from_str(support::body_of(&resp)).expect("status json");
+    assert_eq!(status["execs"], serde_json::json!([]), "{status}");
+
+    running.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+}
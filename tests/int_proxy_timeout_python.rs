@@ -20,7 +20,7 @@ fn int_proxy_timeout_python_sleep() {
         }
     };
     let python_image = support::default_python_test_image();
-    let img_ok = support::docker_image_present(&rt.as_path(), &python_image);
+    let img_ok = support::docker_image_present(rt.as_path(), &python_image);
     if !img_ok {
         eprintln!(
             "skipping: python image '{}' not present locally",
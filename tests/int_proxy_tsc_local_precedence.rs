@@ -37,7 +37,7 @@ fn int_proxy_tsc_prefers_local_compiler() {
     let kinds = vec!["node".to_string()];
     let image = support::default_node_test_image();
     let rt = aifo_coder::container_runtime_path().expect("runtime");
-    let present = support::docker_image_present(&rt.as_path(), &image);
+    let present = support::docker_image_present(rt.as_path(), &image);
     if !present {
         eprintln!("skipping: test image not present locally: {}", image);
         return;
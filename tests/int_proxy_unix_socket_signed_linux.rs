@@ -0,0 +1,77 @@
+mod support;
+
+#[cfg(target_os = "linux")]
+#[test]
+fn int_proxy_unix_socket_signed_exec_linux() {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+
+    if !support::have_login_python3() {
+        eprintln!("skipping: python3 not found");
+        return;
+    }
+    let td = tempfile::tempdir().expect("tmpdir");
+    let _env_guard = support::fake_docker_env(td.path())
+        .set("AIFO_TOOLEEXEC_USE_UNIX", "1")
+        .set(
+            "AIFO_TOOLEEXEC_UNIX_BASE",
+            td.path().join("run").to_string_lossy().to_string(),
+        )
+        .set("AIFO_TOOLEEXEC_REQUIRE_SIGNED", "1")
+        .set("AIFO_TOOLEEXEC_AUDIT", "0")
+        .set("AIFO_CODER_NO_CONFIG", "1")
+        .remove(aifo_coder::TOOLEXEC_SIGNING_KEY_ENV)
+        .remove("AIFO_CODER_CONTAINER_RUNTIME")
+        .remove("AIFO_TOOLEEXEC_TLS")
+        .remove("AIFO_TOOLEEXEC_REPLAY");
+
+    let (url, token, running, handle) =
+        aifo_coder::toolexec_start_proxy("unix-signed-session", false).expect("start proxy");
+    let sock = url.strip_prefix("unix://").expect("unix url").to_string();
+
+    // The shim signs with the key the launcher passes on; unix-socket proxies export it too.
+    let key = std::env::var(aifo_coder::TOOLEXEC_SIGNING_KEY_ENV).expect("signing key exported");
+    let send = |signed: bool| {
+        let body = format!(
+            "tool=python3&cwd=.&arg=-c&arg={}",
+            support::urlencode("print('signed-ok')")
+        );
+        let sig: String = if signed {
+            aifo_coder::toolexec_signature_headers(
+                &key,
+                "/exec",
+                aifo_coder::SignedHeaders::default(),
+                body.as_bytes(),
+            )
+            .iter()
+            .map(|(k, v)| format!("{k}: {v}\r\n"))
+            .collect()
+        } else {
+            String::new()
+        };
+        let req = format!(
+            "POST /exec HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {token}\r\nX-Aifo-Proto: 1\r\n{sig}Content-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        let mut s = UnixStream::connect(&sock).expect("connect unix socket");
+        s.set_read_timeout(Some(std::time::Duration::from_secs(30)))
+            .unwrap();
+        s.write_all(req.as_bytes()).unwrap();
+        let mut resp = Vec::new();
+        let _ = s.read_to_end(&mut resp);
+        String::from_utf8_lossy(&resp).into_owned()
+    };
+
+    let resp = send(false);
+    assert!(
+        resp.starts_with("HTTP/1.1 401") && resp.contains("request signature required"),
+        "got:\n{resp}"
+    );
+    let resp = send(true);
+    assert!(resp.starts_with("HTTP/1.1 200"), "got:\n{resp}");
+    assert!(resp.contains("signed-ok"), "got:\n{resp}");
+    assert!(resp.contains("X-Exit-Code: 0"), "got:\n{resp}");
+
+    running.store(false, std::sync::atomic::Ordering::SeqCst);
+    let _ = handle.join();
+}
@@ -0,0 +1,56 @@
+#[cfg(unix)]
+#[test]
+fn int_shim_forwards_allowlisted_env_and_strips_secrets() {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+    let port = listener.local_addr().unwrap().port();
+    let server = std::thread::spawn(move || {
+        let (mut s, _) = listener.accept().expect("accept");
+        s.set_read_timeout(Some(std::time::Duration::from_secs(10)))
+            .unwrap();
+        let mut req = Vec::new();
+        let mut buf = [0u8; 4096];
+        while !req.ends_with(b"0\r\n\r\n") {
+            match s.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => req.extend_from_slice(&buf[..n]),
+            }
+        }
+        let _ = s.write_all(
+            b"HTTP/1.1 200 OK\r\nX-Exit-Code: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        );
+        String::from_utf8_lossy(&req).to_string()
+    });
+
+    let td = tempfile::tempdir().expect("tmpdir");
+    let cargo = td.path().join("cargo");
+    std::os::unix::fs::symlink(env!("CARGO_BIN_EXE_aifo-shim"), &cargo).expect("symlink shim");
+    let status = std::process::Command::new(&cargo)
+        .arg("build")
+        .env(
+            "AIFO_TOOLEEXEC_URL",
+            format!("http://127.0.0.1:{port}/exec"),
+        )
+        .env("AIFO_TOOLEEXEC_TOKEN", "t")
+        .env("AIFO_TOOLEEXEC_ENV_ALLOW", "MY_APP_*")
+        .env("AIFO_TOOLEEXEC_ENV_DENY", "MY_APP_DEBUG")
+        .env("RUST_LOG", "debug")
+        .env("MY_APP_MODE", "ci fast")
+        .env("MY_APP_DEBUG", "1")
+        .env("MY_APP_TOKEN", "s3cret")
+        .env("EDITOR", "vi")
+        .env("HOME", td.path())
+        .stdin(std::process::Stdio::null())
+        .status()
+        .expect("run shim");
+    assert_eq!(status.code(), Some(0));
+
+    let req = server.join().expect("server");
+    assert!(req.contains("env=RUST_LOG%3Ddebug"), "request:\n{req}");
+    assert!(req.contains("env=MY_APP_MODE%3Dci+fast"), "request:\n{req}");
+    for absent in ["MY_APP_DEBUG", "MY_APP_TOKEN", "s3cret", "EDITOR"] {
+        assert!(!req.contains(absent), "{absent} forwarded:\n{req}");
+    }
+}
@@ -0,0 +1,102 @@
+/// Run `python` through the shim with piped stdin against a fake proxy that upgrades to
+/// aifo-stdin and never exits; SIGINT the shim once upgraded. Returns (exit code, /signal requests).
+#[cfg(target_os = "linux")]
+fn interrupt_stdin_exec(zero_on_sigint: &str) -> (Option<i32>, Vec<String>) {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::process::{Command, Stdio};
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+    let port = listener.local_addr().unwrap().port();
+    let (upgraded_tx, upgraded_rx) = mpsc::channel();
+    let (signal_tx, signal_rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut exec = None;
+        for conn in listener.incoming() {
+            let Ok(mut s) = conn else { break };
+            s.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+            let mut req = Vec::new();
+            let mut buf = [0u8; 4096];
+            while aifo_coder::find_header_end(&req).is_none() {
+                match s.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => req.extend_from_slice(&buf[..n]),
+                }
+            }
+            let req = String::from_utf8_lossy(&req).to_string();
+            if exec.is_none() {
+                let _ = s.write_all(
+                    b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: aifo-stdin\r\nConnection: Upgrade\r\n\r\n",
+                );
+                let _ = upgraded_tx.send(req);
+                // Keep the exec open; the shim has to end it.
+                exec = Some(s);
+            } else {
+                let _ = signal_tx.send(req);
+                let _ = s.write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n");
+            }
+        }
+    });
+
+    let td = tempfile::tempdir().expect("tmpdir");
+    let python = td.path().join("python");
+    std::os::unix::fs::symlink(env!("CARGO_BIN_EXE_aifo-shim"), &python).expect("symlink shim");
+    let mut child = Command::new(&python)
+        .arg("-")
+        .env(
+            "AIFO_TOOLEEXEC_URL",
+            format!("http://127.0.0.1:{port}/exec"),
+        )
+        .env("AIFO_TOOLEEXEC_TOKEN", "t")
+        .env("AIFO_SHIM_EXIT_ZERO_ON_SIGINT", zero_on_sigint)
+        .env("AIFO_SHIM_DISCONNECT_WAIT_SECS", "0")
+        .env("HOME", td.path())
+        .env("TMPDIR", td.path())
+        .env_remove("AIFO_SHIM_STDIN")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("spawn shim");
+    let head = upgraded_rx
+        .recv_timeout(Duration::from_secs(10))
+        .expect("shim never requested an exec");
+    assert!(head.contains("Upgrade: aifo-stdin"), "{head}");
+
+    let pid = nix::unistd::Pid::from_raw(child.id() as i32);
+    nix::sys::signal::kill(pid, nix::sys::signal::Signal::SIGINT).expect("SIGINT shim");
+    let started = std::time::Instant::now();
+    let status = loop {
+        if let Some(st) = child.try_wait().expect("wait shim") {
+            break st;
+        }
+        if started.elapsed() > Duration::from_secs(10) {
+            let _ = child.kill();
+            panic!("shim did not exit after SIGINT");
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    drop(child.stdin.take());
+    (status.code(), signal_rx.try_iter().collect())
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn int_shim_stdin_upgrade_sigint_honors_exit_code_setting() {
+    let (code, signals) = interrupt_stdin_exec("0");
+    assert_eq!(code, Some(130), "SIGINT maps to 130 when opted out of zero");
+    // The proxy is told through /signal (posted with curl).
+    if which::which("curl").is_ok() {
+        assert!(
+            signals
+                .iter()
+                .any(|r| r.starts_with("POST /signal ") && r.contains("X-Aifo-Proto: 2")),
+            "no /signal request: {signals:?}"
+        );
+    }
+
+    let (code, _) = interrupt_stdin_exec("1");
+    assert_eq!(code, Some(0), "default exit code after SIGINT is 0");
+}
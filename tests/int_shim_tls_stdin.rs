@@ -0,0 +1,96 @@
+/// The shim streams piped stdin over mutual TLS: a fake proxy upgrades to aifo-stdin, echoes the
+/// input upper-cased once it sees EOF and exits 4.
+#[cfg(target_os = "linux")]
+#[test]
+fn int_shim_streams_piped_stdin_over_tls() {
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::process::{Command, Stdio};
+    use std::time::Duration;
+
+    let td = tempfile::tempdir().expect("tmpdir");
+    let material =
+        aifo_coder::TlsMaterial::generate(&["127.0.0.1".to_string()]).expect("certificates");
+    let dir = td.path().join("tls");
+    material.write_client_files(&dir).expect("client files");
+    let config = material.server_config().expect("server config");
+
+    let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+    let port = listener.local_addr().unwrap().port();
+    let server = std::thread::spawn(move || {
+        let (sock, _) = listener.accept().expect("accept");
+        sock.set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        let conn = rustls::ServerConnection::new(config).unwrap();
+        let mut s = rustls::StreamOwned::new(conn, sock);
+        let mut head = Vec::new();
+        let mut byte = [0u8; 1];
+        while aifo_coder::find_header_end(&head).is_none() {
+            s.read_exact(&mut byte).expect("read request header");
+            head.push(byte[0]);
+        }
+        let head = String::from_utf8_lossy(&head).to_string();
+        let len = head
+            .lines()
+            .find_map(|l| l.strip_prefix("Content-Length: "))
+            .and_then(|v| v.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+        s.read_exact(&mut vec![0u8; len])
+            .expect("read request body");
+        s.write_all(
+            b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: aifo-stdin\r\nConnection: Upgrade\r\n\r\n",
+        )
+        .unwrap();
+        let mut frames = aifo_coder::PtyFrameReader::new();
+        let mut input = Vec::new();
+        while let Some((kind, payload)) = frames.read_frame(&mut s).expect("read frame") {
+            match kind {
+                aifo_coder::PTY_FRAME_STDIN => input.extend_from_slice(&payload),
+                aifo_coder::PTY_FRAME_STDIN_EOF => break,
+                _ => {}
+            }
+        }
+        let upper = input.to_ascii_uppercase();
+        aifo_coder::pty_write_frame(&mut s, aifo_coder::PTY_FRAME_OUTPUT, &upper).unwrap();
+        aifo_coder::pty_write_frame(&mut s, aifo_coder::PTY_FRAME_EXIT, &4i32.to_be_bytes())
+            .unwrap();
+        s.conn.send_close_notify();
+        let _ = s.flush();
+        head
+    });
+
+    let python = td.path().join("python");
+    std::os::unix::fs::symlink(env!("CARGO_BIN_EXE_aifo-shim"), &python).expect("symlink shim");
+    let mut child = Command::new(&python)
+        .arg("-")
+        .env(
+            "AIFO_TOOLEEXEC_URL",
+            format!("https://127.0.0.1:{port}/exec"),
+        )
+        .env("AIFO_TOOLEEXEC_TOKEN", "t")
+        .env("AIFO_TOOLEEXEC_TLS_DIR", &dir)
+        .env("HOME", td.path())
+        .env("TMPDIR", td.path())
+        .env_remove("AIFO_SHIM_STDIN")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn shim");
+    let mut stdin = child.stdin.take().unwrap();
+    for piece in ["piped over ", "mutual tls\n"] {
+        let _ = stdin.write_all(piece.as_bytes());
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    drop(stdin);
+    let out = child.wait_with_output().expect("wait shim");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert_eq!(
+        String::from_utf8_lossy(&out.stdout),
+        "PIPED OVER MUTUAL TLS\n",
+        "stderr:\n{stderr}"
+    );
+    assert_eq!(out.status.code(), Some(4), "stderr:\n{stderr}");
+    let head = server.join().expect("fake proxy");
+    assert!(head.contains("Upgrade: aifo-stdin"), "{head}");
+}
@@ -99,7 +99,7 @@ fn int_support_shuffle_is_deterministic() {
             }
         }
     }
-    fn shuffle_pairs(pairs: &mut Vec<(usize, usize)>, seed: u64) {
+    fn shuffle_pairs(pairs: &mut [(usize, usize)], seed: u64) {
         let mut rng = XorShift64::new(seed);
         let n = pairs.len();
         for i in (1..n).rev() {
@@ -125,12 +125,12 @@ fn int_support_shuffle_is_deterministic() {
 #[test]
 fn int_agent_check_once_per_agent() {
     // Simulate worker caching behavior with 3 agents × 2 toolchains
-    let agents = vec![
+    let agents = [
         "aider".to_string(),
         "crush".to_string(),
         "codex".to_string(),
     ];
-    let kinds = vec!["rust".to_string(), "node".to_string()];
+    let kinds = ["rust".to_string(), "node".to_string()];
     let mut worklist: Vec<(usize, usize)> = Vec::new();
     for ai in 0..agents.len() {
         for ki in 0..kinds.len() {
@@ -245,8 +245,7 @@ fn int_support_matrix_is_fully_green() {
     // Find the final Summary line
     let summary = stderr
         .lines()
-        .filter(|l| l.starts_with("Summary:"))
-        .last()
+        .rfind(|l| l.starts_with("Summary:"))
         .unwrap_or("");
     assert!(
         !summary.is_empty(),
@@ -0,0 +1,71 @@
+#[test]
+fn int_sidecar_run_preview_applies_hardened_flags() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    std::env::set_var("AIFO_CODER_HARDENED", "1");
+    std::env::set_var("AIFO_CODER_CAP_ADD", "");
+
+    let run_args = aifo_coder::build_sidecar_run_preview(
+        "tc-node-hardened",
+        Some("aifo-net-x"),
+        Some((123, 456)),
+        "node",
+        "node:22-bookworm-slim",
+        false,
+        td.path(),
+        None,
+    );
+    let preview = aifo_coder::shell_join(&run_args);
+    assert!(
+        preview.contains(" --cap-drop ALL --security-opt no-new-privileges --read-only "),
+        "empty AIFO_CODER_CAP_ADD keeps no capabilities: {preview}"
+    );
+    assert!(
+        preview.contains(
+            "--tmpfs '/tmp/runtime-123:rw,nosuid,nodev,noexec,mode=0700,uid=123,gid=456'"
+        ),
+        "XDG_RUNTIME_DIR tmpfs should belong to the mapped user: {preview}"
+    );
+    assert!(
+        preview.ends_with("node:22-bookworm-slim /bin/sleep infinity"),
+        "hardened flags must precede the image: {preview}"
+    );
+
+    std::env::remove_var("AIFO_CODER_HARDENED");
+    let run_args = aifo_coder::build_sidecar_run_preview(
+        "tc-node-plain",
+        None,
+        Some((123, 456)),
+        "node",
+        "node:22-bookworm-slim",
+        false,
+        td.path(),
+        None,
+    );
+    assert!(!run_args.iter().any(|a| a == "--read-only"));
+}
+
+#[test]
+fn int_sidecar_run_preview_relaxes_seccomp_per_kind() {
+    let td = tempfile::tempdir().expect("tmpdir");
+    std::env::set_var("AIFO_TOOLCHAIN_SECCOMP_RELAXED", "rust");
+    let profile_for = |kind: &str| {
+        let args = aifo_coder::build_sidecar_run_preview(
+            "tc-seccomp",
+            None,
+            None,
+            kind,
+            "example/toolchain:1",
+            true,
+            td.path(),
+            None,
+        );
+        let flag = args
+            .iter()
+            .find_map(|a| a.strip_prefix("seccomp="))
+            .expect("seccomp flag")
+            .to_string();
+        std::fs::read_to_string(flag).expect("profile file")
+    };
+    assert_eq!(profile_for("rust"), aifo_coder::seccomp_profile_json(true));
+    assert_eq!(profile_for("node"), aifo_coder::seccomp_profile_json(false));
+}
@@ -85,6 +85,12 @@ pub fn http_send_raw(port: u16, request: &str) -> String {
     String::from_utf8_lossy(&buf).to_string()
 }
 
+/// Body of a raw HTTP response (everything after the header block).
+#[allow(dead_code)]
+pub fn body_of(resp: &str) -> &str {
+    resp.split("\r\n\r\n").nth(1).unwrap_or("")
+}
+
 /// Return default Node image for tests, from env or fallback to node:22-bookworm-slim
 #[allow(dead_code)]
 pub fn default_node_test_image() -> String {
@@ -135,6 +141,21 @@ pub fn urlencode(s: &str) -> String {
     urlencoding::encode(s).into_owned()
 }
 
+/// Raw protocol-v2 `POST /exec` request for `tool args...` in /workspace.
+#[allow(dead_code)]
+pub fn exec_request(token: &str, tool: &str, args: &[&str]) -> String {
+    let mut body = format!("tool={}&cwd={}", urlencode(tool), "/workspace");
+    for a in args {
+        body.push_str(&format!("&arg={}", urlencode(a)));
+    }
+    format!(
+        "POST /exec HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer {}\r\nX-Aifo-Proto: 2\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        token,
+        body.len(),
+        body
+    )
+}
+
 #[allow(dead_code)]
 pub fn docker_runtime() -> Option<PathBuf> {
     aifo_coder::container_runtime_path().ok()
@@ -196,7 +217,7 @@ pub fn docker_exec_sh(runtime: &Path, name: &str, script: &str) -> (i32, String)
     match cmd.output() {
         Ok(o) => {
             let out = String::from_utf8_lossy(&o.stdout).to_string()
-                + &String::from_utf8_lossy(&o.stderr).to_string();
+                + String::from_utf8_lossy(&o.stderr).as_ref();
             (o.status.code().unwrap_or(1), out)
         }
         Err(e) => (1, format!("exec failed: {e}")),
@@ -411,6 +432,67 @@ impl Drop for EnvGuard {
     }
 }
 
+/// A stand-in container runtime: `docker exec [opts] CONTAINER CMD...` runs CMD on the host with
+/// the -e variables; /home/coder (pgid files of the exec wrapper) and /workspace map to
+/// $AIFO_TEST_FAKE_ROOT/home and /workspace. Every other docker command succeeds silently.
+#[allow(dead_code)]
+pub const FAKE_DOCKER: &str = r#"#!/bin/sh
+case "$1" in
+  exec) shift ;;
+  *) exit 0 ;;
+esac
+host() { printf '%s' "$1" | sed -e "s#/home/coder#$AIFO_TEST_FAKE_ROOT/home#g" -e "s#/workspace#$AIFO_TEST_FAKE_ROOT/workspace#g"; }
+while [ $# -gt 0 ]; do
+  case "$1" in
+    -e) export "$2"; shift 2 ;;
+    -w) cd "$(host "$2")" || exit 126; shift 2 ;;
+    -u) shift 2 ;;
+    -i|-t) shift ;;
+    *) break ;;
+  esac
+done
+shift
+export HOME="$AIFO_TEST_FAKE_ROOT/home"
+for a in "$@"; do
+  shift
+  set -- "$@" "$(host "$a")"
+done
+exec "$@"
+"#;
+
+/// Install FAKE_DOCKER under ROOT (next to home/ and workspace/) and put it first on PATH.
+#[cfg(unix)]
+#[allow(dead_code)]
+pub fn fake_docker_env(root: &Path) -> EnvGuard {
+    use std::os::unix::fs::PermissionsExt;
+    let bin = root.join("bin");
+    for dir in [bin.clone(), root.join("home"), root.join("workspace")] {
+        std::fs::create_dir_all(dir).expect("create fake docker dirs");
+    }
+    let docker = bin.join("docker");
+    std::fs::write(&docker, FAKE_DOCKER).expect("write fake docker");
+    std::fs::set_permissions(&docker, std::fs::Permissions::from_mode(0o755))
+        .expect("chmod fake docker");
+    let path = format!(
+        "{}:{}",
+        bin.display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+    EnvGuard::new()
+        .set("PATH", path)
+        .set("AIFO_TEST_FAKE_ROOT", root.to_string_lossy().to_string())
+}
+
+/// Whether a login shell finds python3 (fake-docker execs run through `sh -lc`).
+#[allow(dead_code)]
+pub fn have_login_python3() -> bool {
+    Command::new("sh")
+        .args(["-lc", "command -v python3"])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
 /// Opt into notifications safe-dir overrides for tests that execute stub binaries from temp dirs.
 #[allow(dead_code)]
 pub fn notifications_allow_test_exec_from(dir: &Path) -> EnvGuard {
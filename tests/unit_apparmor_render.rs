@@ -0,0 +1,27 @@
+#[test]
+fn unit_apparmor_render_fills_template_placeholders() {
+    let profile = aifo_coder::render_apparmor_profile("aifo-test");
+    assert!(profile.contains("profile aifo-test flags="), "{profile}");
+    assert!(profile.contains("  /workspace/** rwkmla,"));
+    assert!(profile.contains("  /home/coder/** rwkml,"));
+    assert!(profile.contains("  /home/coder/.aifo-config-host/** r,"));
+    assert!(
+        !profile.contains("__"),
+        "unrendered placeholder left in profile"
+    );
+    assert!(profile.starts_with("# Rendered by `aifo-coder apparmor render`"));
+}
+
+#[test]
+fn unit_apparmor_profile_name_validation() {
+    assert_eq!(
+        aifo_coder::parse_apparmor_profile_name(" aifo-coder.v2 "),
+        Ok("aifo-coder.v2".to_string())
+    );
+    for bad in ["", "../evil", "a/b", ".hidden", "with space"] {
+        assert!(
+            aifo_coder::parse_apparmor_profile_name(bad).is_err(),
+            "accepted {bad:?}"
+        );
+    }
+}
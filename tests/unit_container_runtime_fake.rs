@@ -0,0 +1,167 @@
+use aifo_coder::{ContainerRuntime, FakeRuntime, RuntimeIo, RuntimeKind};
+
+fn strs(v: &[&str]) -> Vec<String> {
+    v.iter().map(|s| s.to_string()).collect()
+}
+
+#[test]
+fn unit_test_fake_runtime_tracks_containers_networks_and_volumes() {
+    let rt = FakeRuntime::new().with_image("alpine:3");
+    assert!(rt.image_exists("alpine:3"));
+    assert!(!rt.image_exists("busybox"));
+    assert!(rt
+        .image_pull("busybox", RuntimeIo::Quiet)
+        .unwrap()
+        .success());
+    assert!(rt.image_exists("busybox"));
+
+    let run = strs(&[
+        "-d",
+        "--rm",
+        "--name",
+        "aifo-tc-rust-abc",
+        "-v",
+        "aifo-cargo-git:/home/coder/.cargo/git",
+        "-v",
+        "/tmp/ws:/workspace",
+        "alpine:3",
+        "sleep",
+        "infinity",
+    ]);
+    assert!(rt.run(&run, RuntimeIo::Quiet).unwrap().success());
+    assert!(rt.inspect("aifo-tc-rust-abc"));
+    let state = rt.state();
+    assert!(state.volumes.contains("aifo-cargo-git"));
+    assert_eq!(state.volumes.len(), 1, "host bind mounts are not volumes");
+
+    // One-shot (non-detached) containers do not linger.
+    let once = strs(&["--rm", "--name", "once", "alpine:3", "true"]);
+    assert!(rt.run(&once, RuntimeIo::Quiet).unwrap().success());
+    assert!(!rt.inspect("once"));
+
+    assert!(rt
+        .stop("aifo-tc-rust-abc", Some(1), RuntimeIo::Quiet)
+        .unwrap()
+        .success());
+    assert!(!rt.inspect("aifo-tc-rust-abc"));
+
+    assert!(!rt.network_exists("aifo-net-abc"));
+    assert!(rt
+        .network_create("aifo-net-abc", RuntimeIo::Quiet)
+        .unwrap()
+        .success());
+    assert!(rt.network_exists("aifo-net-abc"));
+    assert!(rt
+        .network_remove("aifo-net-abc", RuntimeIo::Quiet)
+        .unwrap()
+        .success());
+    assert!(!rt.network_exists("aifo-net-abc"));
+
+    assert_eq!(rt.calls()[0], strs(&["image", "inspect", "alpine:3"]));
+}
+
+#[test]
+fn unit_test_fake_runtime_exec_returns_scripted_result() {
+    let rt = FakeRuntime::new();
+    rt.update(|s| {
+        s.exec_code = 3;
+        s.exec_stdout = "empty\n".to_string();
+    });
+    let args = strs(&["c1", "sh", "-lc", "true"]);
+    let out = rt.exec(&args, RuntimeIo::Capture).unwrap();
+    assert_eq!(out.code, Some(3));
+    assert_eq!(out.stdout, "empty\n");
+    assert!(rt.exec(&args, RuntimeIo::Quiet).unwrap().stdout.is_empty());
+
+    rt.update(|s| s.fail_run = true);
+    let out = rt.run(&strs(&["alpine:3"]), RuntimeIo::Quiet).unwrap();
+    assert_eq!(out.code, Some(125));
+}
+
+#[test]
+fn unit_test_network_helpers_use_runtime() {
+    let rt = FakeRuntime::new().with_network("existing");
+    assert!(aifo_coder::ensure_network_exists_with(
+        &rt, "existing", false
+    ));
+    assert_eq!(rt.calls().len(), 1, "existing network is not recreated");
+
+    assert!(aifo_coder::ensure_network_exists_with(&rt, "fresh", false));
+    assert!(rt.calls().contains(&strs(&["network", "create", "fresh"])));
+
+    aifo_coder::remove_network_with(&rt, "fresh", false);
+    aifo_coder::remove_network_with(&rt, "missing", false);
+    let calls = rt.calls();
+    assert!(calls.contains(&strs(&["network", "rm", "fresh"])));
+    assert!(!calls.contains(&strs(&["network", "rm", "missing"])));
+    assert!(!rt.state().networks.contains("fresh"));
+}
+
+#[test]
+fn unit_test_internal_network_and_gateway_through_runtime() {
+    let rt = FakeRuntime::new();
+    assert!(rt
+        .network_create_internal("aifo-egress-s1", RuntimeIo::Quiet)
+        .unwrap()
+        .success());
+    assert!(rt.calls().contains(&strs(&[
+        "network",
+        "create",
+        "--internal",
+        "aifo-egress-s1"
+    ])));
+    assert!(rt.state().internal_networks.contains("aifo-egress-s1"));
+    assert_eq!(
+        rt.network_gateway("aifo-egress-s1").as_deref(),
+        Some(aifo_coder::FAKE_NETWORK_GATEWAY)
+    );
+    assert_eq!(rt.network_gateway("missing"), None);
+}
+
+#[test]
+fn unit_test_cleanup_session_stops_only_existing_sidecars() {
+    let rt = FakeRuntime::new()
+        .with_container("aifo-tc-rust-s1")
+        .with_container("aifo-tc-node-s1")
+        .with_container("aifo-tc-rust-other")
+        .with_network("aifo-net-s1");
+    aifo_coder::toolchain_cleanup_session_with(&rt, "s1", false);
+
+    let state = rt.state();
+    assert_eq!(
+        state.containers.iter().collect::<Vec<_>>(),
+        vec!["aifo-tc-rust-other"]
+    );
+    let stops: Vec<_> = state.calls.iter().filter(|c| c[0] == "stop").collect();
+    assert_eq!(stops.len(), 2);
+    assert!(stops.iter().all(|c| c[1] == "--time" && c[2] == "1"));
+}
+
+#[test]
+fn unit_test_purge_caches_removes_every_cache_volume() {
+    let rt = FakeRuntime::new();
+    aifo_coder::toolchain_purge_caches_with(&rt, false);
+    let removed: Vec<String> = rt
+        .calls()
+        .into_iter()
+        .filter(|c| c[..3] == strs(&["volume", "rm", "-f"]))
+        .map(|c| c[3].clone())
+        .collect();
+    assert_eq!(removed, aifo_coder::toolchain_purge_volume_names());
+}
+
+#[test]
+fn unit_test_runtime_kind_user_flags() {
+    assert_eq!(
+        RuntimeKind::Docker.user_flags(1000, 1000),
+        strs(&["--user", "1000:1000"])
+    );
+    assert_eq!(
+        RuntimeKind::Podman.user_flags(501, 20),
+        strs(&["--userns=keep-id", "--user", "501:20"])
+    );
+    let rt = aifo_coder::runtime_for_program(std::path::Path::new("/usr/bin/podman"));
+    assert_eq!(rt.kind(), RuntimeKind::Podman);
+    let rt = aifo_coder::runtime_for_program(std::path::Path::new("/usr/local/bin/docker"));
+    assert_eq!(rt.kind(), RuntimeKind::Docker);
+}
@@ -0,0 +1,77 @@
+#[test]
+fn unit_test_egress_host_allowed_matches_exact_and_wildcard() {
+    let allow = vec![
+        "api.openai.com".to_string(),
+        "*.example.com".to_string(),
+        ".corp.test".to_string(),
+    ];
+    assert!(aifo_coder::egress_host_allowed(&allow, "api.openai.com"));
+    assert!(aifo_coder::egress_host_allowed(&allow, "API.OpenAI.com."));
+    assert!(aifo_coder::egress_host_allowed(&allow, "a.b.example.com"));
+    assert!(aifo_coder::egress_host_allowed(&allow, "git.corp.test"));
+    assert!(!aifo_coder::egress_host_allowed(&allow, "example.com"));
+    assert!(!aifo_coder::egress_host_allowed(&allow, "badexample.com"));
+    assert!(!aifo_coder::egress_host_allowed(&allow, "openai.com"));
+    assert!(!aifo_coder::egress_host_allowed(
+        &allow,
+        "api.openai.com.evil.test"
+    ));
+}
+
+#[test]
+fn unit_test_parse_egress_host_validates_entries() {
+    assert_eq!(
+        aifo_coder::parse_egress_host(" GitHub.com ").as_deref(),
+        Ok("github.com")
+    );
+    assert_eq!(
+        aifo_coder::parse_egress_host("*.example.com").as_deref(),
+        Ok("*.example.com")
+    );
+    for bad in ["", "*.", "https://github.com", "a..b", "host:443", "a b"] {
+        assert!(aifo_coder::parse_egress_host(bad).is_err(), "{bad:?}");
+    }
+}
+
+#[test]
+fn unit_test_egress_allowlist_combines_llm_toolchains_and_extras() {
+    std::env::set_var("AIFO_API_BASE", "https://llm.example.net:8443/v1");
+    std::env::set_var(
+        "AIFO_CODER_EGRESS_ALLOW",
+        "github.com, *.corp.test,not a host",
+    );
+    let allow = aifo_coder::egress_allowlist(&["rust".to_string(), "ts".to_string()]);
+    std::env::remove_var("AIFO_API_BASE");
+    std::env::remove_var("AIFO_CODER_EGRESS_ALLOW");
+
+    for host in [
+        "llm.example.net",
+        "crates.io",
+        "static.rust-lang.org",
+        "registry.npmjs.org",
+        "github.com",
+        "*.corp.test",
+    ] {
+        assert!(allow.iter().any(|h| h == host), "{host} missing: {allow:?}");
+    }
+    assert!(!allow.iter().any(|h| h == "pypi.org"), "{allow:?}");
+    assert!(!allow.iter().any(|h| h.contains(' ')), "{allow:?}");
+    let mut sorted = allow.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(allow, sorted);
+}
+
+#[test]
+fn unit_test_egress_proxy_env_empty_until_proxy_url_set() {
+    let key = aifo_coder::EGRESS_PROXY_URL_ENV;
+    std::env::remove_var(key);
+    assert!(aifo_coder::egress_proxy_env().is_empty());
+    std::env::set_var(key, "http://10.89.0.1:40123");
+    let env = aifo_coder::egress_proxy_env();
+    std::env::remove_var(key);
+    let get = |k: &str| env.iter().find(|(n, _)| n == k).map(|(_, v)| v.as_str());
+    assert_eq!(get("HTTPS_PROXY"), Some("http://10.89.0.1:40123"));
+    assert_eq!(get("http_proxy"), Some("http://10.89.0.1:40123"));
+    assert_eq!(get("NO_PROXY"), Some("localhost,127.0.0.1,10.89.0.1"));
+}
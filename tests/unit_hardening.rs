@@ -0,0 +1,35 @@
+#[test]
+fn unit_test_capability_names_are_normalized() {
+    assert_eq!(
+        aifo_coder::parse_capability("chown").as_deref(),
+        Ok("CHOWN")
+    );
+    assert_eq!(
+        aifo_coder::parse_capability(" CAP_NET_BIND_SERVICE ").as_deref(),
+        Ok("NET_BIND_SERVICE")
+    );
+    for bad in ["", "CAP_", "net bind", "sys-admin"] {
+        assert!(aifo_coder::parse_capability(bad).is_err(), "{bad}");
+    }
+}
+
+#[test]
+fn unit_test_hardened_defaults_match_config_knobs() {
+    let caps = aifo_coder::config_knob("security.cap_add").expect("knob");
+    assert_eq!(caps.default, aifo_coder::HARDENED_CAP_ADD_DEFAULT.join(","));
+    let hardened = aifo_coder::config_knob("security.hardened").expect("knob");
+    assert_eq!(hardened.default == "1", aifo_coder::HARDENED_DEFAULT);
+}
+
+#[test]
+fn unit_test_hardened_tmpfs_mounts_include_private_runtime_dir() {
+    let mounts = aifo_coder::hardened_tmpfs_mounts(Some((1000, 1000)));
+    assert_eq!(mounts.len(), 3);
+    assert!(mounts[0].starts_with("/tmp:"));
+    assert!(mounts[1].starts_with("/home/coder:"));
+    assert_eq!(
+        mounts[2],
+        "/tmp/runtime-1000:rw,nosuid,nodev,noexec,mode=0700,uid=1000,gid=1000"
+    );
+    assert_eq!(aifo_coder::hardened_tmpfs_mounts(None).len(), 2);
+}
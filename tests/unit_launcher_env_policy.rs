@@ -74,7 +74,8 @@ fn unit_launcher_sets_smart_toggles_per_agent() {
     }
 
     // Others should not set tool toggles by default
-    for agent in ["plandex"] {
+    {
+        let agent = "plandex";
         let args = build_args(agent);
         expect_no_env_kv(&args, "AIFO_SHIM_SMART_NODE", "1");
         expect_no_env_kv(&args, "AIFO_SHIM_SMART_PYTHON", "1");
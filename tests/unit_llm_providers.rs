@@ -0,0 +1,97 @@
+fn values() -> aifo_coder::LlmValues {
+    aifo_coder::LlmValues {
+        key: Some("k-123".to_string()),
+        base: Some("https://res1.openai.azure.com/openai".to_string()),
+        version: Some("2024-10-21".to_string()),
+        derive_azure_resource: true,
+    }
+}
+
+fn names(env: &[(String, String)]) -> Vec<&str> {
+    env.iter().map(|(k, _)| k.as_str()).collect()
+}
+
+#[test]
+fn unit_test_llm_provider_azure_keeps_historical_mapping() {
+    let spec = aifo_coder::llm_provider_spec(aifo_coder::DEFAULT_LLM_PROVIDER).unwrap();
+    let env = aifo_coder::llm_provider_env(spec, "aider", &values());
+    assert!(env.contains(&("OPENAI_API_KEY".to_string(), "k-123".to_string())));
+    assert!(env.contains(&("OPENAI_API_TYPE".to_string(), "azure".to_string())));
+    assert!(env.contains(&("AZURE_RESOURCE_NAME".to_string(), "res1".to_string())));
+    assert!(env.contains(&(
+        "AZURE_OPENAI_API_VERSION".to_string(),
+        "2024-10-21".to_string()
+    )));
+
+    // OpenCode only understands the plain Azure variables.
+    let env = aifo_coder::llm_provider_env(spec, "opencode", &values());
+    assert_eq!(names(&env), vec!["AZURE_API_KEY", "AZURE_RESOURCE_NAME"]);
+}
+
+#[test]
+fn unit_test_llm_provider_opencode_behind_gateway_uses_gateway_base_url() {
+    let spec = aifo_coder::llm_provider_spec(aifo_coder::DEFAULT_LLM_PROVIDER).unwrap();
+    let gateway = aifo_coder::LlmValues {
+        key: Some("session-key".to_string()),
+        base: Some("http://host.docker.internal:41234/openai".to_string()),
+        version: Some("2024-10-21".to_string()),
+        derive_azure_resource: false,
+    };
+    let env = aifo_coder::llm_provider_env(spec, "opencode", &gateway);
+    assert_eq!(
+        names(&env),
+        vec!["AZURE_API_KEY", "OPENCODE_CONFIG_CONTENT"]
+    );
+    let cfg: serde_json::Value = serde_json::from_str(&env[1].1).expect("json config");
+    assert_eq!(
+        cfg["provider"]["azure"]["options"]["baseURL"],
+        "http://host.docker.internal:41234/openai"
+    );
+
+    // A base without the /openai suffix gets it, like the resource-derived default URL.
+    let bare = aifo_coder::LlmValues {
+        base: Some("http://host.docker.internal:41234/".to_string()),
+        ..gateway.clone()
+    };
+    let env = aifo_coder::llm_provider_env(spec, "opencode", &bare);
+    assert!(
+        env[1]
+            .1
+            .contains("\"http://host.docker.internal:41234/openai\""),
+        "{}",
+        env[1].1
+    );
+
+    // Other agents keep reading the plain variables and never get OpenCode's config.
+    let env = aifo_coder::llm_provider_env(spec, "aider", &gateway);
+    assert!(!names(&env).contains(&"OPENCODE_CONFIG_CONTENT"));
+    assert!(!names(&env).contains(&"AZURE_RESOURCE_NAME"));
+}
+
+#[test]
+fn unit_test_llm_provider_anthropic_and_missing_values() {
+    let spec = aifo_coder::llm_provider_spec("Anthropic").unwrap();
+    assert!(!spec.uses_version());
+    let env = aifo_coder::llm_provider_env(spec, "opencode", &values());
+    assert_eq!(names(&env), vec!["ANTHROPIC_API_KEY", "ANTHROPIC_BASE_URL"]);
+
+    let spec = aifo_coder::llm_provider_spec("ollama").unwrap();
+    let only_base = aifo_coder::LlmValues {
+        base: Some("http://host.docker.internal:11434".to_string()),
+        ..Default::default()
+    };
+    let env = aifo_coder::llm_provider_env(spec, "aider", &only_base);
+    assert_eq!(
+        names(&env),
+        vec!["OPENAI_BASE_URL", "OPENAI_API_BASE", "OLLAMA_API_BASE"]
+    );
+}
+
+#[test]
+fn unit_test_llm_provider_unknown_name_lists_known() {
+    let err = aifo_coder::llm_provider_spec("bedrock").unwrap_err();
+    assert!(
+        err.contains("unknown LLM provider 'bedrock'") && err.contains("azure, openai"),
+        "{err}"
+    );
+}
@@ -0,0 +1,79 @@
+use aifo_coder::ResourceLimits;
+
+#[test]
+fn unit_test_resource_limit_values_are_validated() {
+    assert_eq!(aifo_coder::parse_cpus_limit(" 1.5 ").as_deref(), Ok("1.5"));
+    assert!(aifo_coder::parse_cpus_limit("0").is_err());
+    assert!(aifo_coder::parse_cpus_limit("two").is_err());
+
+    assert_eq!(aifo_coder::parse_memory_limit("4G").as_deref(), Ok("4g"));
+    assert_eq!(
+        aifo_coder::parse_memory_limit("1048576").as_deref(),
+        Ok("1048576")
+    );
+    for bad in ["", "g", "4gb", "-1g", "4t"] {
+        assert!(aifo_coder::parse_memory_limit(bad).is_err(), "{bad}");
+    }
+
+    assert_eq!(aifo_coder::parse_pids_limit("-1"), Ok(-1));
+    assert_eq!(aifo_coder::parse_pids_limit("256"), Ok(256));
+    assert!(aifo_coder::parse_pids_limit("0").is_err());
+
+    assert!(aifo_coder::parse_ulimit("nofile=1024:2048").is_ok());
+    assert!(aifo_coder::parse_ulimit("core=-1").is_ok());
+    for bad in ["nofile", "nofile=", "NOFILE=1", "nofile=1:2:3", "nofile=a"] {
+        assert!(aifo_coder::parse_ulimit(bad).is_err(), "{bad}");
+    }
+}
+
+#[test]
+fn unit_test_resource_limits_overlay_flags_and_summary() {
+    let mut base = ResourceLimits {
+        cpus: Some("4".to_string()),
+        memory: Some("8g".to_string()),
+        pids: None,
+        ulimits: vec!["nofile=1024".to_string()],
+    };
+    base.overlay(&ResourceLimits {
+        cpus: Some("2".to_string()),
+        pids: Some(256),
+        ..Default::default()
+    });
+    assert_eq!(
+        base.run_flags(),
+        vec![
+            "--cpus",
+            "2",
+            "--memory",
+            "8g",
+            "--pids-limit",
+            "256",
+            "--ulimit",
+            "nofile=1024"
+        ]
+    );
+    assert_eq!(
+        base.summary(),
+        "cpus=2 memory=8g pids=256 ulimit=nofile=1024"
+    );
+    assert_eq!(ResourceLimits::default().summary(), "none");
+    assert!(ResourceLimits::default().run_flags().is_empty());
+}
+
+#[test]
+fn unit_test_fork_pane_defaults_divide_host() {
+    let host = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1) as f64;
+    let one = aifo_coder::fork_pane_default_limits(1);
+    let four = aifo_coder::fork_pane_default_limits(4);
+    let cpus = |l: &ResourceLimits| l.cpus.as_deref().unwrap().parse::<f64>().unwrap();
+    assert!(cpus(&one) <= host.max(0.5));
+    assert!(cpus(&four) <= (host / 4.0).max(0.5));
+    assert!(cpus(&four) >= 0.5);
+    assert_eq!(four.pids, Some(aifo_coder::FORK_PANE_PIDS_LIMIT));
+    if let (Some(m1), Some(m4)) = (one.memory.as_deref(), four.memory.as_deref()) {
+        let mib = |s: &str| s.trim_end_matches('m').parse::<u64>().unwrap();
+        assert!(mib(m4) <= mib(m1) / 4 + 256);
+    }
+}
@@ -0,0 +1,59 @@
+fn allow_list(relaxed: bool) -> Vec<String> {
+    let profile: serde_json::Value =
+        serde_json::from_str(&aifo_coder::seccomp_profile_json(relaxed)).expect("valid JSON");
+    assert_eq!(profile["defaultAction"], "SCMP_ACT_ERRNO");
+    let rules = profile["syscalls"].as_array().expect("syscalls");
+    rules
+        .iter()
+        .filter(|r| r["action"] == "SCMP_ACT_ALLOW" && r.get("args").is_none())
+        .flat_map(|r| r["names"].as_array().cloned().unwrap_or_default())
+        .filter_map(|n| n.as_str().map(str::to_string))
+        .collect()
+}
+
+#[test]
+fn unit_test_seccomp_profile_denies_agent_unneeded_syscalls() {
+    let allow = allow_list(false);
+    assert!(allow.len() > 300, "expected Docker-derived allowlist");
+    for name in ["read", "write", "execve", "openat", "futex"] {
+        assert!(allow.iter().any(|a| a == name), "{name} must be allowed");
+    }
+    for name in aifo_coder::SECCOMP_DENIED_SYSCALLS {
+        assert!(!allow.iter().any(|a| a == name), "{name} must be denied");
+    }
+    let json = aifo_coder::seccomp_profile_json(false);
+    assert!(
+        json.contains("\"clone3\""),
+        "clone3 must be answered with ENOSYS"
+    );
+}
+
+#[test]
+fn unit_test_seccomp_relaxed_profile_allows_debugging() {
+    let allow = allow_list(true);
+    for name in aifo_coder::SECCOMP_RELAXED_SYSCALLS {
+        assert!(allow.iter().any(|a| a == name), "{name} must be allowed");
+    }
+    for name in ["mount", "bpf", "keyctl", "unshare"] {
+        assert!(!allow.iter().any(|a| a == name), "{name} stays denied");
+    }
+    assert!(allow.iter().any(|a| a == "personality"));
+}
+
+#[test]
+fn unit_test_seccomp_mode_names() {
+    use aifo_coder::SeccompMode;
+    assert_eq!(
+        aifo_coder::parse_seccomp_mode("aifo"),
+        Ok(SeccompMode::Aifo)
+    );
+    assert_eq!(
+        aifo_coder::parse_seccomp_mode("Engine"),
+        Ok(SeccompMode::Engine)
+    );
+    assert_eq!(
+        aifo_coder::parse_seccomp_mode("unconfined"),
+        Ok(SeccompMode::Unconfined)
+    );
+    assert!(aifo_coder::parse_seccomp_mode("strict").is_err());
+}